@@ -0,0 +1,7 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS subject TEXT;
+
+CREATE TABLE IF NOT EXISTS dashboard_messages (
+    matrix_room_id TEXT PRIMARY KEY,
+    matrix_event_id TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
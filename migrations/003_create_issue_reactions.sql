@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS issue_reactions (
+    issue_id BIGINT NOT NULL REFERENCES issue_events (issue_id) ON DELETE CASCADE,
+    state TEXT NOT NULL,
+    reaction_event_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (issue_id, state)
+);
+
+-- Reactions used to be tracked in a single column, only for resolved issues
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'issue_events' AND column_name = 'reaction_event_id'
+    ) THEN
+        INSERT INTO issue_reactions (issue_id, state, reaction_event_id)
+        SELECT issue_id, 'resolved', reaction_event_id FROM issue_events
+        WHERE reaction_event_id IS NOT NULL
+        ON CONFLICT DO NOTHING;
+
+        ALTER TABLE issue_events DROP COLUMN reaction_event_id;
+    END IF;
+END $$;
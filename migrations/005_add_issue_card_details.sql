@@ -0,0 +1,2 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS description TEXT;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS reported_by TEXT;
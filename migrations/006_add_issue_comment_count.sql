@@ -0,0 +1 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS comment_count INTEGER NOT NULL DEFAULT 0;
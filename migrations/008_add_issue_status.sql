@@ -0,0 +1,23 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS resolved_at TIMESTAMPTZ;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS resolved_by TEXT;
+
+-- Backfill from the reactions, which used to be the only record of the state,
+-- only when adding the column: migrations run on every start
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'issue_events' AND column_name = 'status'
+    ) THEN
+        ALTER TABLE issue_events ADD COLUMN status TEXT NOT NULL DEFAULT 'open';
+
+        UPDATE issue_events e SET status = r.state
+        FROM issue_reactions r
+        WHERE r.issue_id = e.issue_id AND r.state IN ('in_progress', 'resolved');
+
+        UPDATE issue_events e SET resolved_at = r.created_at
+        FROM issue_reactions r
+        WHERE r.issue_id = e.issue_id AND r.state = 'resolved' AND e.status = 'resolved'
+          AND e.resolved_at IS NULL;
+    END IF;
+END $$;
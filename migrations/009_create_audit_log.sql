@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    actor TEXT NOT NULL,
+    action TEXT NOT NULL,
+    issue_id BIGINT,
+    details TEXT,
+    success BOOLEAN NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_issue_id_idx ON audit_log (issue_id, created_at);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS request_events (
+    request_id BIGINT PRIMARY KEY,
+    media_tmdb_id BIGINT,
+    media_type TEXT,
+    subject TEXT,
+    matrix_event_id TEXT NOT NULL,
+    matrix_room_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS request_events_matrix_event_id_idx ON request_events (matrix_event_id);
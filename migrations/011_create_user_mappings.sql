@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS user_mappings (
+    matrix_user_id TEXT PRIMARY KEY,
+    seerr_user TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS user_mappings_seerr_user_idx ON user_mappings (LOWER(seerr_user));
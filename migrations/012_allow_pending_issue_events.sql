@@ -0,0 +1,2 @@
+-- Issue rows are written before their card is sent, without an event id until then
+ALTER TABLE issue_events ALTER COLUMN matrix_event_id DROP NOT NULL;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS comment_events (
+    id BIGSERIAL PRIMARY KEY,
+    issue_id BIGINT NOT NULL REFERENCES issue_events (issue_id) ON DELETE CASCADE,
+    seerr_comment_id BIGINT UNIQUE,
+    matrix_event_id TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS comment_events_issue_id_idx ON comment_events (issue_id);
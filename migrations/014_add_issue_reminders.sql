@@ -0,0 +1,15 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS last_activity_at TIMESTAMPTZ;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS reminder_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS reminded_at TIMESTAMPTZ;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS reminders_muted BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Backfill from the latest comment, resolution or the issue creation
+UPDATE issue_events e SET last_activity_at = GREATEST(
+    e.created_at,
+    e.resolved_at,
+    (SELECT MAX(c.created_at) FROM comment_events c WHERE c.issue_id = e.issue_id)
+)
+WHERE e.last_activity_at IS NULL;
+
+ALTER TABLE issue_events ALTER COLUMN last_activity_at SET DEFAULT NOW();
+ALTER TABLE issue_events ALTER COLUMN last_activity_at SET NOT NULL;
@@ -0,0 +1,7 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS media_type TEXT;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS media_tmdb_id BIGINT;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS media_tvdb_id BIGINT;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS import_noticed_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS issue_events_media_tmdb_id_idx ON issue_events (media_tmdb_id);
+CREATE INDEX IF NOT EXISTS issue_events_media_tvdb_id_idx ON issue_events (media_tvdb_id);
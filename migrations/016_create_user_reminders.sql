@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS user_reminders (
+    id BIGSERIAL PRIMARY KEY,
+    matrix_room_id TEXT NOT NULL,
+    thread_root_event_id TEXT NOT NULL,
+    author TEXT NOT NULL,
+    message TEXT NOT NULL,
+    due_at TIMESTAMPTZ NOT NULL,
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS user_reminders_due_at_idx ON user_reminders (due_at) WHERE sent_at IS NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS acknowledged_at TIMESTAMPTZ;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS acknowledged_by TEXT;
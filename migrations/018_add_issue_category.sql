@@ -0,0 +1,3 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS category TEXT;
+
+CREATE INDEX IF NOT EXISTS issue_events_category_idx ON issue_events (category);
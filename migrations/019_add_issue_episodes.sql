@@ -0,0 +1,2 @@
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS problem_season BIGINT;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS problem_episode BIGINT;
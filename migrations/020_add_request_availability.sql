@@ -0,0 +1,4 @@
+ALTER TABLE request_events ADD COLUMN IF NOT EXISTS image TEXT;
+ALTER TABLE request_events ADD COLUMN IF NOT EXISTS available_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS request_events_available_at_idx ON request_events (available_at);
@@ -0,0 +1,12 @@
+ALTER TABLE request_events ADD COLUMN IF NOT EXISTS requested_by TEXT;
+
+CREATE TABLE IF NOT EXISTS request_votes (
+    request_id BIGINT NOT NULL,
+    matrix_user_id TEXT NOT NULL,
+    upvote BOOLEAN NOT NULL,
+    reaction_event_id TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (request_id, matrix_user_id)
+);
+
+CREATE INDEX IF NOT EXISTS request_votes_reaction_event_id_idx ON request_votes (reaction_event_id);
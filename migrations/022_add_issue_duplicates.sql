@@ -0,0 +1,2 @@
+-- Duplicates share the card of the issue they duplicate, matrix_event_id included
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS duplicate_of BIGINT;
@@ -0,0 +1,7 @@
+-- Issues being reported with `!report`, keyed by the thread the questions are asked in
+CREATE TABLE IF NOT EXISTS issue_reports (
+    thread_root_event_id TEXT PRIMARY KEY,
+    reporter TEXT NOT NULL,
+    draft JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
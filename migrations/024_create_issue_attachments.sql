@@ -0,0 +1,9 @@
+-- Images and files posted in issue threads and forwarded to Seerr, served to Seerr users by the bot
+CREATE TABLE IF NOT EXISTS issue_attachments (
+    matrix_event_id TEXT PRIMARY KEY,
+    issue_id BIGINT NOT NULL,
+    source JSONB NOT NULL,
+    mimetype TEXT,
+    filename TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
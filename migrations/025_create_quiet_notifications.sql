@@ -0,0 +1,6 @@
+-- Seerr webhooks received during quiet hours, delivered together once they are over
+CREATE TABLE IF NOT EXISTS quiet_notifications (
+    id BIGSERIAL PRIMARY KEY,
+    payload JSONB NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
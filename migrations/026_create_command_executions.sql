@@ -0,0 +1,7 @@
+-- Commands the bot ran, so a replayed sync or an edit never runs one twice
+CREATE TABLE IF NOT EXISTS command_executions (
+    matrix_event_id TEXT PRIMARY KEY,
+    command TEXT NOT NULL,
+    outcome TEXT,
+    executed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
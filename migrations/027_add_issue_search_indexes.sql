@@ -0,0 +1,6 @@
+-- Full-text search of issues and their comments for `!issues search`
+CREATE INDEX IF NOT EXISTS issue_events_search_idx ON issue_events
+    USING GIN (to_tsvector('english', coalesce(subject, '') || ' ' || coalesce(description, '')));
+
+CREATE INDEX IF NOT EXISTS comment_events_search_idx ON comment_events
+    USING GIN (to_tsvector('english', message));
@@ -0,0 +1,2 @@
+-- Home Assistant notifications are held too while the bot is paused
+ALTER TABLE quiet_notifications ADD COLUMN IF NOT EXISTS source TEXT NOT NULL DEFAULT 'seerr';
@@ -0,0 +1,18 @@
+-- Backfill from what the bot already knows, LEAST ignoring the NULLs, only
+-- when adding the column: migrations run on every start
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'issue_events' AND column_name = 'first_response_at'
+    ) THEN
+        ALTER TABLE issue_events ADD COLUMN first_response_at TIMESTAMPTZ;
+
+        UPDATE issue_events e SET first_response_at = LEAST(
+            e.acknowledged_at,
+            e.resolved_at,
+            (SELECT MIN(c.created_at) FROM comment_events c
+             WHERE c.issue_id = e.issue_id AND c.origin = 'matrix')
+        );
+    END IF;
+END $$;
@@ -0,0 +1,6 @@
+-- Numbers of the last `!requests pending` list, for `!approve 2` to find the request
+CREATE TABLE IF NOT EXISTS request_shortcuts (
+    number INTEGER PRIMARY KEY,
+    request_id BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
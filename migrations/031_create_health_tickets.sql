@@ -0,0 +1,14 @@
+-- Failing Sonarr and Radarr health checks, each with a card and thread in the room
+CREATE TABLE IF NOT EXISTS health_tickets (
+    id BIGSERIAL PRIMARY KEY,
+    source TEXT NOT NULL,
+    check_type TEXT NOT NULL,
+    message TEXT NOT NULL,
+    matrix_event_id TEXT NOT NULL,
+    opened_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    resolved_at TIMESTAMPTZ
+);
+
+-- A single open ticket per failing check
+CREATE UNIQUE INDEX IF NOT EXISTS health_tickets_open_idx
+    ON health_tickets (source, check_type) WHERE resolved_at IS NULL;
@@ -0,0 +1,16 @@
+-- Comments and resolutions sent from issue threads while Seerr was unreachable,
+-- applied in order once it is back
+CREATE TABLE IF NOT EXISTS pending_seerr_actions (
+    id BIGSERIAL PRIMARY KEY,
+    issue_id BIGINT NOT NULL,
+    action TEXT NOT NULL,
+    -- Comment as sent to Seerr, and as written in the thread
+    message TEXT,
+    comment TEXT,
+    matrix_event_id TEXT NOT NULL,
+    thread_root_event_id TEXT NOT NULL,
+    requested_by TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,10 @@
+-- Notification streams turned on or off in a room with `!bot route`, taking
+-- precedence over the room config
+CREATE TABLE IF NOT EXISTS room_routes (
+    room_id TEXT NOT NULL,
+    stream TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    updated_by TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, stream)
+);
@@ -0,0 +1,3 @@
+-- Set by an admin comment and cleared by the reporter's, for reporter follow-ups
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS awaiting_reporter_since TIMESTAMPTZ;
+ALTER TABLE issue_events ADD COLUMN IF NOT EXISTS follow_up_sent_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- When the admins were told about the item being stuck, not to tell them twice
+ALTER TABLE outbox ADD COLUMN IF NOT EXISTS escalated_at TIMESTAMPTZ;
@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use subtle::ConstantTimeEq;
+use tracing::{error, info};
+
+use crate::AppState;
+use crate::audit;
+use crate::db::{self, AuditEntry, OutboxEntry, TrackedIssue};
+use crate::issue::IssueState;
+use crate::lifecycle::{self, IssueEvent};
+use crate::metrics;
+use crate::outbox::{self, StuckItem};
+
+const DEFAULT_AUDIT_LIMIT: i64 = 100;
+const MAX_AUDIT_LIMIT: i64 = 1000;
+const DEFAULT_ISSUES_LIMIT: i64 = 100;
+const MAX_ISSUES_LIMIT: i64 = 1000;
+/// Actor recorded in the audit log for changes made through the API.
+const API_ACTOR: &str = "admin-api";
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    pub issue_id: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// `GET /admin/audit`: most recent audit log entries, optionally filtered
+/// by issue.
+pub async fn list_audit(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_AUDIT_LIMIT)
+        .clamp(1, MAX_AUDIT_LIMIT);
+    db::list_audit_entries(&state.db, query.issue_id, limit)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to list audit log: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssuesQuery {
+    /// `open`, `acknowledged`, `in_progress`, `resolved` or `reopened`.
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// `GET /admin/issues`: tracked issues, most recent first.
+pub async fn list_issues(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<IssuesQuery>,
+) -> Result<Json<Vec<TrackedIssue>>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let status = match query.status.as_deref() {
+        Some(status) => Some(IssueState::parse(status).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ISSUES_LIMIT)
+        .clamp(1, MAX_ISSUES_LIMIT);
+    db::list_tracked_issues(&state.db, status, limit)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to list issues: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// `GET /admin/outbox`: webhooks waiting to be retried.
+pub async fn list_outbox(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<OutboxEntry>>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    db::list_outbox(&state.db).await.map(Json).map_err(|e| {
+        error!("Failed to list outbox: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// `GET /admin/outbox/stuck`: webhooks over the outbox watchdog thresholds,
+/// with an excerpt of their payload.
+pub async fn list_stuck_outbox(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<StuckItem>>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    outbox::stuck(&state).await.map(Json).map_err(|e| {
+        error!("Failed to list stuck outbox items: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ResolveBody {
+    pub comment: Option<String>,
+}
+
+/// `POST /admin/issues/{id}/resolve`: comments on (optional) and resolves the
+/// issue in Seerr, like `!issues resolve`. The thread is updated by the
+/// webhooks Seerr sends back.
+pub async fn resolve_issue(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(issue_id): Path<i64>,
+    body: Option<Json<ResolveBody>>,
+) -> Result<Json<Value>, StatusCode> {
+    authorize(&state, &headers)?;
+
+    let tracked = db::get_issue_event(&state.db, issue_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to look up issue {issue_id}: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if tracked.is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let comment = body.and_then(|Json(body)| body.comment);
+    let permit = state.limiter.acquire(Some(issue_id)).await;
+    let result = resolve(&state, issue_id, comment.as_deref()).await;
+    drop(permit);
+    audit::record(
+        &state.db,
+        API_ACTOR,
+        "api.issues.resolve",
+        Some(issue_id),
+        comment.as_deref(),
+        &result,
+    )
+    .await;
+    match result {
+        Ok(()) => Ok(Json(json!({ "issue_id": issue_id, "status": "resolved" }))),
+        Err(e) => {
+            error!("Failed to resolve issue {issue_id}: {e:#}");
+            Err(StatusCode::BAD_GATEWAY)
+        }
+    }
+}
+
+async fn resolve(state: &AppState, issue_id: i64, comment: Option<&str>) -> anyhow::Result<()> {
+    if let Some(comment) = comment {
+        let result = state.seerr_client.add_comment(issue_id, comment).await;
+        audit::record(
+            &state.db,
+            API_ACTOR,
+            "seerr.add_comment",
+            Some(issue_id),
+            None,
+            &result,
+        )
+        .await;
+        result?;
+    }
+
+    let result = state.seerr_client.resolve_issue(issue_id).await;
+    audit::record(
+        &state.db,
+        API_ACTOR,
+        "seerr.resolve_issue",
+        Some(issue_id),
+        None,
+        &result,
+    )
+    .await;
+    result?;
+
+    lifecycle::apply(&state.db, issue_id, IssueEvent::Resolved, Some(API_ACTOR)).await?;
+    info!(issue_id, "Resolved issue via admin API");
+    Ok(())
+}
+
+/// `GET /admin/metrics`: Prometheus metrics, e.g. issue response times.
+pub async fn metrics(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<([(header::HeaderName, &'static str); 1], String), StatusCode> {
+    authorize(&state, &headers)?;
+
+    metrics::render(&state.db, &state.sync_health, &state.http_metrics)
+        .await
+        .map(|body| ([(header::CONTENT_TYPE, metrics::CONTENT_TYPE)], body))
+        .map_err(|e| {
+            error!("Failed to render metrics: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Admin endpoints require `Authorization: Bearer <ADMIN_API_TOKEN>` and are
+/// disabled when no token is configured.
+fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), StatusCode> {
+    let Some(expected) = &state.admin_api_token else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    if has_bearer_token(headers, expected) {
+        Ok(())
+    } else {
+        Err(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// Whether `headers` carry `Authorization: Bearer <expected>`, compared in
+/// constant time not to tell how much of a guessed token is right.
+pub(crate) fn has_bearer_token(headers: &HeaderMap, expected: &str) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| token.as_bytes().ct_eq(expected.as_bytes()).into())
+}
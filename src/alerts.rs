@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use matrix_sdk::Client;
+use tracing::warn;
+
+use crate::config::AlertConfig;
+use crate::config::PushEvent;
+use crate::maintenance::Maintenance;
+use crate::markdown;
+use crate::matrix;
+use crate::priority::Priority;
+use crate::push::Push;
+use crate::settings::LiveSettings;
+
+/// External dependency whose failures are reported to the admins. Database
+/// outages are reported by `health::watch_database` instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    Matrix,
+    Seerr,
+}
+
+impl Subsystem {
+    fn label(self) -> &'static str {
+        match self {
+            Subsystem::Matrix => "Matrix",
+            Subsystem::Seerr => "Seerr",
+        }
+    }
+
+    /// Subsystem at fault for `error`, if it is an outage rather than e.g. a
+    /// bad request. Seerr errors only count when it is unreachable or 5xx.
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        for cause in error.chain() {
+            if cause.is::<matrix_sdk::Error>() || cause.is::<matrix_sdk::HttpError>() {
+                return Some(Subsystem::Matrix);
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return e
+                    .status()
+                    .is_none_or(|status| status.is_server_error())
+                    .then_some(Subsystem::Seerr);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Default)]
+struct Tracker {
+    failures: u32,
+    alerted_at: Option<Instant>,
+}
+
+impl Tracker {
+    /// Whether this failure should be reported.
+    fn on_failure(&mut self, now: Instant, config: &AlertConfig) -> bool {
+        self.failures += 1;
+        let due = self
+            .alerted_at
+            .is_none_or(|at| now.duration_since(at) >= config.interval);
+        if self.failures >= config.threshold && due {
+            self.alerted_at = Some(now);
+            return true;
+        }
+        false
+    }
+
+    /// Whether the recovery should be reported, i.e. the failures were.
+    fn on_success(&mut self) -> bool {
+        let alerted = self.alerted_at.is_some();
+        *self = Tracker::default();
+        alerted
+    }
+}
+
+/// DMs the admins when a subsystem keeps failing, at most once per
+/// `ALERT_INTERVAL_SECS`, and once it works again.
+pub struct Alerts {
+    client: Client,
+    settings: Arc<LiveSettings>,
+    config: AlertConfig,
+    push: Option<Push>,
+    trackers: Mutex<HashMap<Subsystem, Tracker>>,
+    /// Holds the alerts back during `!maintenance`.
+    pub maintenance: Maintenance,
+}
+
+impl Alerts {
+    pub fn new(
+        client: Client,
+        settings: Arc<LiveSettings>,
+        config: AlertConfig,
+        push: Option<Push>,
+    ) -> Self {
+        Self {
+            client,
+            settings,
+            config,
+            push,
+            trackers: Mutex::new(HashMap::new()),
+            maintenance: Maintenance::default(),
+        }
+    }
+
+    /// Counts `result` as a failure of the subsystem it points at, if any.
+    pub fn observe<T>(&self, result: &anyhow::Result<T>) {
+        if let Err(e) = result
+            && let Some(subsystem) = Subsystem::of(e)
+        {
+            self.failure(subsystem, e);
+        }
+    }
+
+    pub fn failure(&self, subsystem: Subsystem, error: &anyhow::Error) {
+        let (notify, failures) = {
+            let mut trackers = self.trackers.lock().unwrap_or_else(|e| e.into_inner());
+            let tracker = trackers.entry(subsystem).or_default();
+            (
+                tracker.on_failure(Instant::now(), &self.config),
+                tracker.failures,
+            )
+        };
+        if notify {
+            let title = format!("{} is failing", subsystem.label());
+            let details = format!("{failures} errors in a row, the last one: {error:#}");
+            if self.maintenance.suppress(&title, &details) {
+                return;
+            }
+            warn!(
+                subsystem = subsystem.label(),
+                failures, "Notifying admins of repeated failures"
+            );
+            let markdown = format!("#### {} {title}\n{details}", Priority::Critical.icon());
+            self.notify(Priority::Critical.render(&markdown));
+            self.push(&title, &details);
+        }
+    }
+
+    pub fn success(&self, subsystem: Subsystem) {
+        let recovered = {
+            let mut trackers = self.trackers.lock().unwrap_or_else(|e| e.into_inner());
+            trackers
+                .get_mut(&subsystem)
+                .is_some_and(Tracker::on_success)
+        };
+        if recovered {
+            let title = format!("{} works again", subsystem.label());
+            if self.maintenance.suppress(&title, "") {
+                return;
+            }
+            self.notify(markdown::render(&format!("**✅ {title}**")));
+            self.push(&title, "");
+        }
+    }
+
+    fn notify(&self, (plain, html): (String, String)) {
+        let client = self.client.clone();
+        let admins = self.settings.get().admin_users.clone();
+        tokio::spawn(
+            async move { matrix::notify_users_html(&client, &admins, &plain, &html).await },
+        );
+    }
+
+    fn push(&self, title: &str, message: &str) {
+        if let Some(push) = &self.push {
+            push.send(PushEvent::ServiceDown, title, message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn config() -> AlertConfig {
+        AlertConfig {
+            threshold: 3,
+            interval: Duration::from_secs(3600),
+        }
+    }
+
+    #[test]
+    fn alerts_once_threshold_is_reached_then_throttles() {
+        let config = config();
+        let mut tracker = Tracker::default();
+        let start = Instant::now();
+
+        assert!(!tracker.on_failure(start, &config));
+        assert!(!tracker.on_failure(start, &config));
+        assert!(tracker.on_failure(start, &config));
+        assert!(!tracker.on_failure(start + Duration::from_secs(60), &config));
+        assert!(tracker.on_failure(start + Duration::from_secs(3600), &config));
+    }
+
+    #[test]
+    fn recovery_is_reported_only_after_an_alert() {
+        let config = config();
+        let mut tracker = Tracker::default();
+        tracker.on_failure(Instant::now(), &config);
+        assert!(!tracker.on_success());
+
+        for _ in 0..3 {
+            tracker.on_failure(Instant::now(), &config);
+        }
+        assert!(tracker.on_success());
+        assert_eq!(tracker.failures, 0);
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let error =
+            anyhow::Error::new(matrix_sdk::Error::InsufficientData).context("Failed to send");
+        assert_eq!(Subsystem::of(&error), Some(Subsystem::Matrix));
+        assert_eq!(Subsystem::of(&anyhow::anyhow!("Issue not found")), None);
+    }
+}
@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::IntoResponse;
+use matrix_sdk::Room;
+use matrix_sdk::media::{MediaFormat, MediaRequestParameters};
+use matrix_sdk::ruma::OwnedEventId;
+use matrix_sdk::ruma::events::room::MediaSource;
+use matrix_sdk::ruma::events::room::message::{MessageType, OriginalSyncRoomMessageEvent};
+use tracing::{error, info};
+
+use crate::AppState;
+use crate::audit;
+use crate::commands::{self, CommandContext};
+use crate::db::{self, CommentOrigin, IssueAttachment};
+use crate::markdown;
+use crate::matrix;
+
+/// An image, video or file posted in a thread.
+#[derive(Debug)]
+struct Attachment<'a> {
+    source: &'a MediaSource,
+    filename: &'a str,
+    caption: Option<&'a str>,
+    mimetype: Option<&'a str>,
+    is_image: bool,
+}
+
+fn attachment(msgtype: &MessageType) -> Option<Attachment<'_>> {
+    match msgtype {
+        MessageType::Image(image) => Some(Attachment {
+            source: &image.source,
+            filename: image.filename(),
+            caption: image.caption(),
+            mimetype: image.info.as_ref().and_then(|i| i.mimetype.as_deref()),
+            is_image: true,
+        }),
+        MessageType::Video(video) => Some(Attachment {
+            source: &video.source,
+            filename: video.filename(),
+            caption: video.caption(),
+            mimetype: video.info.as_ref().and_then(|i| i.mimetype.as_deref()),
+            is_image: false,
+        }),
+        MessageType::File(file) => Some(Attachment {
+            source: &file.source,
+            filename: file.filename(),
+            caption: file.caption(),
+            mimetype: file.info.as_ref().and_then(|i| i.mimetype.as_deref()),
+            is_image: false,
+        }),
+        _ => None,
+    }
+}
+
+/// Where Seerr users download the attachment posted with `event_id`.
+fn attachment_url(public_url: &str, event_id: &str) -> Result<String> {
+    let mut url = reqwest::Url::parse(public_url)?;
+    url.path_segments_mut()
+        .map_err(|_| anyhow!("BOT_PUBLIC_URL is not an http(s) URL"))?
+        .pop_if_empty()
+        .extend(["attachments", event_id]);
+    Ok(url.to_string())
+}
+
+/// Seerr comment showing the attachment, or saying where it is when the bot
+/// can't serve it.
+fn comment(attachment: &Attachment, url: Option<&str>, permalink: &str) -> String {
+    let name = markdown::escape(attachment.filename);
+    let link = match url {
+        Some(url) if attachment.is_image => format!("![{name}]({url})"),
+        Some(url) => format!("📎 [{name}]({url})"),
+        None => format!("📎 {name} was posted in [the Matrix thread]({permalink})"),
+    };
+    match attachment.caption {
+        Some(caption) => format!("{caption}\n\n{link}"),
+        None => link,
+    }
+}
+
+/// Forwards an attachment an admin or the reporter posted in an issue thread
+/// as a Seerr comment. Returns `false` when the message is not one, for
+/// other handlers to look at.
+pub async fn forward(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    root: &OwnedEventId,
+) -> Result<bool> {
+    let Some(attachment) = attachment(&event.content.msgtype) else {
+        return Ok(false);
+    };
+    let Some(issue) = db::get_issue_event_by_matrix_event_id(&ctx.db, root.as_str()).await? else {
+        return Ok(false);
+    };
+    let issue_id = issue.issue_id;
+    let sender = event.sender.as_str();
+    let seerr_user = db::get_user_mapping(&ctx.db, sender)
+        .await?
+        .map(|mapping| mapping.seerr_user);
+    if !ctx.settings.get().admin_users.contains(&event.sender) {
+        let reported_by = db::get_issue_details(&ctx.db, issue_id)
+            .await?
+            .map(|details| details.reported_by);
+        let is_reporter = matches!(
+            (&seerr_user, &reported_by),
+            (Some(seerr_user), Some(reported_by)) if seerr_user.eq_ignore_ascii_case(reported_by)
+        );
+        if !is_reporter {
+            return Ok(false);
+        }
+    }
+    let _permit = ctx.limiter.acquire(Some(issue_id)).await;
+
+    let event_id = event.event_id.as_str();
+    let stored = IssueAttachment {
+        source: serde_json::to_string(attachment.source)?,
+        mimetype: attachment.mimetype.map(str::to_string),
+        filename: attachment.filename.to_string(),
+    };
+    db::insert_issue_attachment(&ctx.db, event_id, issue_id, &stored).await?;
+
+    let url = match &ctx.state.public_url {
+        Some(public_url) => Some(attachment_url(public_url, event_id)?),
+        None => None,
+    };
+    let permalink = matrix::event_permalink(room.room_id().as_str(), event_id);
+    let comment = comment(&attachment, url.as_deref(), &permalink);
+    let message = commands::attributed_comment(&comment, sender, seerr_user.as_deref());
+    ctx.state.forwarded_comments.remember(issue_id, &message);
+    let result = ctx.seerr_client.add_comment(issue_id, &message).await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "seerr.add_comment",
+        Some(issue_id),
+        Some(attachment.filename),
+        &result,
+    )
+    .await;
+    let seerr_comment_id = result?;
+    // The attachment mirrors the comment, so its webhook isn't posted again
+    db::insert_comment_event(
+        &ctx.db,
+        issue_id,
+        seerr_comment_id,
+        event_id,
+        CommentOrigin::Matrix,
+        &comment,
+    )
+    .await?;
+    info!(
+        issue_id,
+        filename = attachment.filename,
+        "Attachment forwarded to Seerr"
+    );
+    Ok(true)
+}
+
+/// `GET /attachments/{event_id}`: an attachment forwarded to Seerr, fetched
+/// from the homeserver for Seerr users who aren't in the room.
+pub async fn serve(
+    State(state): State<Arc<AppState>>,
+    Path(event_id): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let attachment = db::get_issue_attachment(&state.db, &event_id)
+        .await
+        .map_err(|e| {
+            error!(event_id, "Failed to look up attachment: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let source: MediaSource = serde_json::from_str(&attachment.source).map_err(|e| {
+        error!(event_id, "Invalid stored media source: {e:#}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let request = MediaRequestParameters {
+        source,
+        format: MediaFormat::File,
+    };
+    let data = state
+        .room
+        .client()
+        .media()
+        .get_media_content(&request, true)
+        .await
+        .map_err(|e| {
+            error!(event_id, "Failed to download attachment: {e:#}");
+            StatusCode::BAD_GATEWAY
+        })?;
+
+    let content_type = attachment
+        .mimetype
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    // Anyone can post a file, so it is never run as a page of the bot
+    let headers = [
+        (header::CONTENT_TYPE, content_type),
+        (header::CONTENT_SECURITY_POLICY, "sandbox".to_string()),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+    ];
+    Ok((headers, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screenshot(source: &MediaSource) -> Attachment<'_> {
+        Attachment {
+            source,
+            filename: "screenshot_1.png",
+            caption: None,
+            mimetype: Some("image/png"),
+            is_image: true,
+        }
+    }
+
+    #[test]
+    fn attachment_url_escapes_the_event_id() {
+        assert_eq!(
+            attachment_url("https://bot.example.com/", "$abc/def").unwrap(),
+            "https://bot.example.com/attachments/$abc%2Fdef"
+        );
+        assert_eq!(
+            attachment_url("https://example.com/michel", "$abc").unwrap(),
+            "https://example.com/michel/attachments/$abc"
+        );
+    }
+
+    #[test]
+    fn comment_shows_images_served_by_the_bot() {
+        let source = MediaSource::Plain("mxc://example.com/abc".into());
+        let url = "https://bot.example.com/attachments/$abc";
+        assert_eq!(
+            comment(
+                &screenshot(&source),
+                Some(url),
+                "https://matrix.to/#/!r/$abc"
+            ),
+            "![screenshot\\_1\\.png](https://bot.example.com/attachments/$abc)"
+        );
+
+        let captioned = Attachment {
+            caption: Some("Green artifacts at 12:30"),
+            is_image: false,
+            ..screenshot(&source)
+        };
+        assert_eq!(
+            comment(&captioned, None, "https://matrix.to/#/!r/$abc"),
+            "Green artifacts at 12:30\n\n📎 screenshot\\_1\\.png was posted in \
+             [the Matrix thread](https://matrix.to/#/!r/$abc)"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::db;
+
+/// Records an action and its outcome in the audit log, with the error appended
+/// to `details` on failure. Failing to write the entry is only logged so it
+/// never fails the audited action.
+pub async fn record<T>(
+    pool: &PgPool,
+    actor: &str,
+    action: &str,
+    issue_id: Option<i64>,
+    details: Option<&str>,
+    outcome: &anyhow::Result<T>,
+) {
+    let details = match (details, outcome) {
+        (Some(details), Err(e)) => Some(format!("{details}: {e:#}")),
+        (None, Err(e)) => Some(format!("{e:#}")),
+        (details, Ok(_)) => details.map(str::to_string),
+    };
+    if let Err(e) = db::insert_audit_entry(
+        pool,
+        actor,
+        action,
+        issue_id,
+        details.as_deref(),
+        outcome.is_ok(),
+    )
+    .await
+    {
+        warn!(action, "Failed to write audit log entry: {e:#}");
+    }
+}
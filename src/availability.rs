@@ -0,0 +1,81 @@
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedUserId;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db::{self, TrackedRequest};
+use crate::markdown;
+use crate::matrix;
+use crate::request::RequestStatus;
+use crate::seerr::{SeerrRequest, SeerrWebhookPayload};
+use crate::webhook;
+
+/// Checks in Seerr whether approved or pending requests became available,
+/// for setups where `MEDIA_AVAILABLE` webhooks are not sent or get lost.
+pub async fn check_requests(state: &AppState) -> Result<()> {
+    let mut waiting =
+        db::list_tracked_requests(&state.db, Some(RequestStatus::Approved), None).await?;
+    waiting.extend(db::list_tracked_requests(&state.db, Some(RequestStatus::Pending), None).await?);
+
+    let mut available = 0;
+    for tracked in &waiting {
+        let Some(request) = state.seerr_client.get_request(tracked.request_id).await? else {
+            continue;
+        };
+        if request.request_status() != Some(RequestStatus::Available) {
+            continue;
+        }
+        let payload = SeerrWebhookPayload {
+            notification_type: RequestStatus::Available.notification_type().to_string(),
+            subject: subject(tracked),
+            request_id: Some(tracked.request_id.to_string()),
+            ..Default::default()
+        };
+        webhook::process_payload(state, &payload).await?;
+        notify_requester(state, tracked, &request).await;
+        available += 1;
+    }
+    info!(
+        checked = waiting.len(),
+        available, "Checked requests for availability"
+    );
+    Ok(())
+}
+
+fn subject(request: &TrackedRequest) -> String {
+    request
+        .subject
+        .clone()
+        .unwrap_or_else(|| format!("Request #{}", request.request_id))
+}
+
+/// DMs the requester when their Seerr account is linked with `!users link`.
+async fn notify_requester(state: &AppState, tracked: &TrackedRequest, request: &SeerrRequest) {
+    let Some(user) = &request.requested_by else {
+        return;
+    };
+    let names = [user.display_name.as_deref(), user.email.as_deref()];
+    for name in names.into_iter().flatten() {
+        let mapping = match db::get_user_mapping_by_seerr_user(&state.db, name).await {
+            Ok(Some(mapping)) => mapping,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!("Failed to look up the requester: {e:#}");
+                return;
+            }
+        };
+        let Ok(user_id) = OwnedUserId::try_from(mapping.matrix_user_id.as_str()) else {
+            return;
+        };
+        let markdown = format!(
+            "**🎉 {} is now available**",
+            markdown::escape(&subject(tracked))
+        );
+        if let Err(e) =
+            matrix::send_direct_markdown(&state.room.client(), &user_id, &markdown).await
+        {
+            warn!(%user_id, "Failed to notify the requester: {e:#}");
+        }
+        return;
+    }
+}
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::config::BatchConfig;
+use crate::db;
+use crate::markdown;
+use crate::matrix;
+use crate::outbox;
+use crate::request::RequestStatus;
+use crate::seerr::SeerrWebhookPayload;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notification of a request without a card, waiting to be posted with the
+/// others of its batch.
+#[derive(Debug, Clone)]
+pub struct BatchedRequest {
+    pub request_id: i64,
+    pub payload: SeerrWebhookPayload,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    /// When the recent notifications of this kind arrived.
+    arrivals: VecDeque<Instant>,
+    pending: Vec<BatchedRequest>,
+    /// When the pending notifications are posted.
+    flush_at: Option<Instant>,
+}
+
+impl Window {
+    /// Whether the notification arriving at `now` joins a batch rather than
+    /// getting a card of its own.
+    fn admit(&mut self, now: Instant, config: &BatchConfig) -> bool {
+        while self
+            .arrivals
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= config.window)
+        {
+            self.arrivals.pop_front();
+        }
+        self.arrivals.push_back(now);
+        if self.flush_at.is_none() && self.arrivals.len() < config.threshold {
+            return false;
+        }
+        self.flush_at.get_or_insert(now + config.window);
+        true
+    }
+}
+
+/// Coalesces request notifications when Seerr sends many at once, e.g. a
+/// mass import making dozens of media available, into one message per
+/// status instead of a card each.
+pub struct Batcher {
+    config: BatchConfig,
+    windows: Mutex<HashMap<RequestStatus, Window>>,
+}
+
+impl Batcher {
+    pub fn new(config: BatchConfig) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Keeps `request` for the next batch when its kind is arriving faster
+    /// than the threshold. Returns `false` when it should be posted now.
+    pub fn hold(&self, status: RequestStatus, request: BatchedRequest) -> bool {
+        self.hold_at(status, request, Instant::now())
+    }
+
+    fn hold_at(&self, status: RequestStatus, request: BatchedRequest, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        let window = windows.entry(status).or_default();
+        if !window.admit(now, &self.config) {
+            return false;
+        }
+        window.pending.push(request);
+        true
+    }
+
+    /// Batches whose window is over, or all of them with `all`.
+    fn take(&self, now: Instant, all: bool) -> Vec<(RequestStatus, Vec<BatchedRequest>)> {
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+        windows
+            .iter_mut()
+            .filter(|(_, window)| window.flush_at.is_some_and(|at| all || at <= now))
+            .map(|(status, window)| {
+                window.flush_at = None;
+                (*status, std::mem::take(&mut window.pending))
+            })
+            .collect()
+    }
+}
+
+fn heading(status: RequestStatus, count: usize) -> String {
+    match status {
+        RequestStatus::Pending => format!("⏳ {count} requests waiting for approval"),
+        RequestStatus::Approved => format!("👍 {count} requests approved"),
+        RequestStatus::Declined => format!("🚫 {count} requests declined"),
+        RequestStatus::Available => format!("🎉 {count} requests became available"),
+        RequestStatus::Failed => format!("⚠️ {count} requests failed"),
+    }
+}
+
+/// One line per request, linking to Seerr when it is reachable.
+fn render(status: RequestStatus, requests: &[(&BatchedRequest, Option<String>)]) -> String {
+    let mut markdown = format!("#### {}\n", heading(status, requests.len()));
+    for (request, seerr_url) in requests {
+        let payload = &request.payload;
+        let subject = markdown::escape(&payload.subject);
+        let title = match seerr_url {
+            Some(url) => format!("[{subject}]({url})"),
+            None => subject,
+        };
+        let requested_by = markdown::escape(payload.requested_by.as_deref().unwrap_or("unknown"));
+        markdown.push_str(&format!("- {title}, requested by {requested_by}\n"));
+    }
+    markdown
+}
+
+fn tmdb_id(payload: &SeerrWebhookPayload) -> Option<i64> {
+    payload
+        .media_tmdbid
+        .as_deref()
+        .and_then(|id| id.parse().ok())
+}
+
+/// Posts a batch as one message, which becomes the card of all its requests
+/// so their next notifications go to its thread.
+async fn post(state: &AppState, status: RequestStatus, requests: &[BatchedRequest]) -> Result<()> {
+    let lines: Vec<(&BatchedRequest, Option<String>)> = requests
+        .iter()
+        .map(|request| {
+            let url = state.seerr_client.media_url(
+                request.payload.media_type.as_deref(),
+                tmdb_id(&request.payload),
+            );
+            (request, url)
+        })
+        .collect();
+    let event_id = matrix::send_markdown(&state.room, &render(status, &lines)).await?;
+    let room_id = state.room.room_id().to_string();
+
+    for request in requests {
+        let payload = &request.payload;
+        db::insert_request_event(
+            &state.db,
+            &db::NewRequestEvent {
+                request_id: request.request_id,
+                media_tmdb_id: tmdb_id(payload),
+                media_type: payload.media_type.as_deref(),
+                subject: &payload.subject,
+                requested_by: payload.requested_by.as_deref().unwrap_or("unknown"),
+                image: payload.image.as_deref(),
+                matrix_event_id: event_id.as_str(),
+                matrix_room_id: &room_id,
+                status,
+            },
+        )
+        .await?;
+    }
+    info!(%status, count = requests.len(), %event_id, "Batched request notifications sent");
+    Ok(())
+}
+
+/// Posts the batches, handing their notifications to the outbox when that
+/// fails so they are retried like any other webhook.
+async fn post_all(state: &AppState, batches: Vec<(RequestStatus, Vec<BatchedRequest>)>) {
+    for (status, requests) in batches {
+        if requests.is_empty() {
+            continue;
+        }
+        let result = post(state, status, &requests).await;
+        state.alerts.observe(&result);
+        let Err(e) = result else {
+            continue;
+        };
+        error!(%status, "Failed to post batched notifications: {e:#}");
+        for request in &requests {
+            if let Err(e) = outbox::enqueue(state, &request.payload, &e).await {
+                error!(
+                    request_id = request.request_id,
+                    "Failed to queue batched notification in the outbox: {e:#}"
+                );
+            }
+        }
+    }
+}
+
+/// Posts the batches as their windows close, until `shutdown` is cancelled.
+pub async fn run(state: Arc<AppState>, shutdown: CancellationToken) {
+    let Some(batcher) = &state.batcher else {
+        return;
+    };
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+        post_all(&state, batcher.take(Instant::now(), false)).await;
+    }
+}
+
+/// Posts the batches still open, on shutdown.
+pub async fn flush(state: &AppState) {
+    let Some(batcher) = &state.batcher else {
+        return;
+    };
+    let batches = batcher.take(Instant::now(), true);
+    if !batches.is_empty() {
+        warn!("Posting batched notifications before their window is over");
+    }
+    post_all(state, batches).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batcher() -> Batcher {
+        Batcher::new(BatchConfig {
+            threshold: 3,
+            window: Duration::from_secs(60),
+        })
+    }
+
+    fn request(request_id: i64, subject: &str) -> BatchedRequest {
+        BatchedRequest {
+            request_id,
+            payload: SeerrWebhookPayload {
+                notification_type: "MEDIA_AVAILABLE".to_string(),
+                subject: subject.to_string(),
+                requested_by: Some("alice".to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn batches_once_the_threshold_is_reached() {
+        let batcher = batcher();
+        let start = Instant::now();
+        let available = RequestStatus::Available;
+
+        assert!(!batcher.hold_at(available, request(1, "Dune"), start));
+        assert!(!batcher.hold_at(available, request(2, "Alien"), start));
+        assert!(batcher.hold_at(available, request(3, "Heat"), start));
+        // Other statuses have their own window
+        assert!(!batcher.hold_at(RequestStatus::Failed, request(4, "Up"), start));
+        assert!(batcher.hold_at(
+            available,
+            request(5, "Jaws"),
+            start + Duration::from_secs(30)
+        ));
+
+        assert!(
+            batcher
+                .take(start + Duration::from_secs(59), false)
+                .is_empty()
+        );
+        let batches = batcher.take(start + Duration::from_secs(60), false);
+        assert_eq!(batches.len(), 1);
+        let ids: Vec<i64> = batches[0].1.iter().map(|r| r.request_id).collect();
+        assert_eq!(ids, vec![3, 5]);
+    }
+
+    #[test]
+    fn slow_notifications_are_not_batched() {
+        let batcher = batcher();
+        let start = Instant::now();
+        for n in 0..5 {
+            let at = start + Duration::from_secs(n * 40);
+            assert!(!batcher.hold_at(RequestStatus::Available, request(n as i64, "Dune"), at));
+        }
+    }
+
+    #[test]
+    fn render_lists_the_batch() {
+        let dune = request(1, "Dune");
+        let heat = request(2, "Heat");
+        let markdown = render(
+            RequestStatus::Available,
+            &[
+                (
+                    &dune,
+                    Some("https://seerr.example.com/movie/438631".to_string()),
+                ),
+                (&heat, None),
+            ],
+        );
+        assert_eq!(
+            markdown,
+            "#### 🎉 2 requests became available\n\
+             - [Dune](https://seerr.example.com/movie/438631), requested by alice\n\
+             - Heat, requested by alice\n"
+        );
+    }
+}
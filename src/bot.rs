@@ -0,0 +1,694 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::routing::{get, post};
+use matrix_sdk::Client;
+use matrix_sdk::ruma::OwnedRoomId;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+use tokio::sync::{Notify, watch};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::admin;
+use crate::alerts::Alerts;
+use crate::attachments;
+use crate::availability;
+use crate::batching::{self, Batcher};
+use crate::calendar;
+use crate::commands;
+use crate::comment_echo::ForwardedComments;
+use crate::concurrency::Limiter;
+use crate::config::Config;
+use crate::db;
+use crate::digest;
+use crate::disk::DiskMonitor;
+use crate::follow_ups;
+use crate::health;
+use crate::heartbeat::{self, SyncHealth};
+use crate::home_assistant;
+use crate::http_metrics::HttpMetrics;
+use crate::imports;
+use crate::jellyfin_client::JellyfinClient;
+use crate::maintenance;
+use crate::matrix;
+use crate::media_details::MediaDetailsCache;
+use crate::outbox;
+use crate::outgoing::OutgoingWebhooks;
+use crate::pending_actions;
+use crate::presence;
+use crate::push::Push;
+use crate::qbittorrent_client::QbittorrentClient;
+use crate::quiet_hours::{self, Pause};
+use crate::radarr_client::RadarrClient;
+use crate::reactions::CommentPrompts;
+use crate::reconcile;
+use crate::redaction;
+use crate::remind;
+use crate::reminders;
+use crate::scheduler::Scheduler;
+use crate::seerr_client::SeerrClient;
+use crate::settings::{self, LiveSettings, Settings};
+use crate::sonarr_client::SonarrClient;
+use crate::stats;
+use crate::supervisor;
+use crate::sync_loop;
+use crate::translation::Translator;
+use crate::verification;
+use crate::votes;
+use crate::webhook;
+use crate::webhook_drift;
+use crate::whats_new;
+
+/// Configures a [`Bot`] before connecting it.
+#[derive(Default)]
+pub struct BotBuilder {
+    config: Option<Config>,
+    config_path: Option<PathBuf>,
+}
+
+impl BotBuilder {
+    /// Runs with `config` instead of loading it from `config_path` and the
+    /// environment.
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Config file read when no config is given, and re-read on `SIGHUP` and
+    /// `!config reload`.
+    pub fn config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config_path = path;
+        self
+    }
+
+    /// Connects to the database and Matrix, joins the room and binds the
+    /// webhook listener. Nothing is processed until [`Bot::run`].
+    pub async fn build(self) -> Result<Bot> {
+        let config = match self.config {
+            Some(config) => config,
+            None => Config::load(self.config_path.as_deref())?,
+        };
+
+        let pool = db::connect(&config.database_url, &config.database_pool)
+            .await
+            .context("Failed to connect to PostgreSQL")?;
+        db::run_migrations(&pool).await?;
+        info!("Database connected and migrations applied");
+
+        let client = matrix::create_and_login(
+            &config.matrix_homeserver_url,
+            &config.matrix_user_id,
+            &config.matrix_auth,
+            config.matrix_store_path.as_deref(),
+        )
+        .await?;
+
+        let (room, room_id) = matrix::join_room(&client, &config.matrix_room_alias).await?;
+
+        if config.startup_self_test {
+            match matrix::self_test(&room).await {
+                Ok(()) => info!("Startup self-test passed"),
+                Err(e) => {
+                    error!(
+                        "Startup self-test failed, check the bot's power level in the room: {e:#}"
+                    )
+                }
+            }
+        }
+
+        if let Err(e) = presence::set_profile(
+            &client,
+            &pool,
+            config.bot_display_name.as_deref(),
+            config.bot_avatar_url.as_deref(),
+        )
+        .await
+        {
+            warn!("Failed to set bot profile: {e:#}");
+        }
+
+        let http_metrics = Arc::new(HttpMetrics::new(config.http_slow_call));
+        let seerr_client = SeerrClient::new(&config.seerr_api_url, &config.seerr_api_key)
+            .with_public_url(config.seerr_public_url.as_deref())
+            .with_metrics(&http_metrics);
+
+        let settings = Arc::new(LiveSettings::new(
+            Settings::from_config(&config, &[&config.matrix_room_alias, room_id.as_str()]),
+            self.config_path,
+            vec![config.matrix_room_alias.clone(), room_id.to_string()],
+        ));
+
+        let push = config.push.as_ref().map(Push::new);
+        let alerts = Arc::new(Alerts::new(
+            client.clone(),
+            settings.clone(),
+            config.alerts.clone(),
+            push.clone(),
+        ));
+
+        let command_tasks = TaskTracker::new();
+        let sonarr = config
+            .sonarr
+            .as_ref()
+            .map(|sonarr| SonarrClient::new(sonarr).with_metrics(&http_metrics));
+        let radarr = config
+            .radarr
+            .as_ref()
+            .map(|radarr| RadarrClient::new(radarr).with_metrics(&http_metrics));
+        let jellyfin = config.jellyfin.as_ref().map(JellyfinClient::new);
+        let outgoing = OutgoingWebhooks::new(config.outgoing_webhook_urls.clone());
+        let limiter = Arc::new(Limiter::new(config.max_concurrent_handlers));
+        let state = Arc::new(AppState {
+            room,
+            db: pool.clone(),
+            admin_api_token: config.admin_api_token.clone(),
+            settings: settings.clone(),
+            alerts: alerts.clone(),
+            seerr_client: seerr_client.clone(),
+            jellyfin_client: jellyfin.clone(),
+            outgoing: outgoing.clone(),
+            push,
+            home_assistant: config.home_assistant.clone(),
+            limiter: limiter.clone(),
+            translator: config.translation.as_ref().map(Translator::new),
+            public_url: config.bot_public_url.clone(),
+            batcher: config.notification_batch.clone().map(Batcher::new),
+            media_details: MediaDetailsCache::default(),
+            pause: Pause::default(),
+            release_held: Notify::new(),
+            forwarded_comments: ForwardedComments::default(),
+            comment_prompts: CommentPrompts::default(),
+            sync_health: Arc::new(SyncHealth::default()),
+            http_metrics,
+        });
+        if let Err(e) = quiet_hours::restore_pause(&state).await {
+            warn!("Failed to restore the notification pause: {e:#}");
+        }
+        if let Err(e) = maintenance::restore(&state).await {
+            warn!("Failed to restore the maintenance: {e:#}");
+        }
+        let cmd_ctx = Arc::new(commands::CommandContext {
+            db: pool.clone(),
+            seerr_client,
+            sonarr_client: sonarr.clone(),
+            radarr_client: radarr.clone(),
+            jellyfin_client: jellyfin,
+            qbittorrent_client: config.qbittorrent.as_ref().map(QbittorrentClient::new),
+            settings,
+            alerts,
+            outgoing,
+            limiter,
+            state: state.clone(),
+            tasks: command_tasks.clone(),
+        });
+
+        client.add_event_handler_context(cmd_ctx);
+        if config.features.commands {
+            client.add_event_handler(commands::on_room_message);
+            client.add_event_handler(commands::on_reaction);
+        } else {
+            info!("Commands are disabled");
+        }
+
+        if config.matrix_verification {
+            if config.matrix_store_path.is_none() {
+                warn!(
+                    "MATRIX_STORE_PATH is not set, verifications are lost with the device on restart"
+                );
+            }
+            if let Err(e) =
+                verification::bootstrap_cross_signing(&client, &config.matrix_auth).await
+            {
+                warn!("{e:#}");
+            }
+            client.add_event_handler(verification::on_to_device_request);
+            client.add_event_handler(verification::on_room_request);
+        }
+
+        client.add_event_handler_context(state.clone());
+        client.add_event_handler(redaction::on_room_redaction);
+        client.add_event_handler(votes::on_reaction);
+
+        if let Err(e) = webhook::repair_pending_issues(&state).await {
+            error!("Failed to repair pending issues: {e:#}");
+        }
+
+        let listener = TcpListener::bind(&config.webhook_listen_addr)
+            .await
+            .context("Failed to bind listener")?;
+
+        Ok(Bot {
+            config,
+            client,
+            room_id,
+            pool,
+            state,
+            sonarr,
+            radarr,
+            command_tasks,
+            listener,
+            shutdown: CancellationToken::new(),
+            stopped: watch::Sender::new(false),
+        })
+    }
+}
+
+/// Stops a running [`Bot`], e.g. on a signal or at the end of a test.
+#[derive(Debug, Clone)]
+pub struct BotHandle {
+    shutdown: CancellationToken,
+    stopped: watch::Receiver<bool>,
+}
+
+impl BotHandle {
+    /// Makes [`Bot::run`] stop syncing and accepting webhooks, then waits
+    /// until in-flight work has finished and the outbox has been flushed.
+    /// Returns right away if the bot is not running.
+    pub async fn shutdown(&self) {
+        self.shutdown.cancel();
+        let mut stopped = self.stopped.clone();
+        // An error means the bot was dropped without running
+        let _ = stopped.wait_for(|stopped| *stopped).await;
+    }
+}
+
+/// A connected bot, ready to sync and serve webhooks.
+pub struct Bot {
+    config: Config,
+    client: Client,
+    room_id: OwnedRoomId,
+    pool: PgPool,
+    state: Arc<AppState>,
+    sonarr: Option<SonarrClient>,
+    radarr: Option<RadarrClient>,
+    command_tasks: TaskTracker,
+    listener: TcpListener,
+    shutdown: CancellationToken,
+    stopped: watch::Sender<bool>,
+}
+
+impl Bot {
+    pub fn builder() -> BotBuilder {
+        BotBuilder::default()
+    }
+
+    pub fn handle(&self) -> BotHandle {
+        BotHandle {
+            shutdown: self.shutdown.clone(),
+            stopped: self.stopped.subscribe(),
+        }
+    }
+
+    pub fn state(&self) -> Arc<AppState> {
+        self.state.clone()
+    }
+
+    /// Serves webhooks, syncs with the homeserver and runs the scheduled jobs
+    /// until [`BotHandle::shutdown`] is called. The server and sync are
+    /// restarted when they fail.
+    pub async fn run(self) -> Result<()> {
+        let Bot {
+            config,
+            client,
+            room_id,
+            pool,
+            state,
+            sonarr,
+            radarr,
+            command_tasks,
+            listener,
+            shutdown,
+            stopped,
+        } = self;
+
+        let app = router(&config).with_state(state.clone());
+        info!("Webhook server listening on {}", config.webhook_listen_addr);
+
+        let addr = listener.local_addr()?;
+        let mut listener = Some(listener);
+        let server_shutdown = shutdown.clone();
+        let serve = move || {
+            let listener = listener.take();
+            let app = app.clone();
+            let shutdown = server_shutdown.clone();
+            async move {
+                // Bound again when the server is restarted
+                let listener = match listener {
+                    Some(listener) => listener,
+                    None => TcpListener::bind(addr).await?,
+                };
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(shutdown.cancelled_owned())
+                    .await
+            }
+        };
+        let server = tokio::spawn(supervisor::supervise(
+            "webhook server",
+            shutdown.clone(),
+            serve,
+        ));
+        // Webhooks are accepted again, fill the gap left while the bot was down
+        if config.catch_up_enabled
+            && let Err(e) = reconcile::catch_up(&state).await
+        {
+            error!("Failed to catch up on missed notifications: {e:#}");
+        }
+        if config.webhook_check_enabled
+            && let Err(e) = webhook_drift::check(&state, config.seerr_webhook_url.as_deref()).await
+        {
+            warn!("Failed to check the Seerr webhook settings: {e:#}");
+        }
+
+        let sync_state = state.clone();
+        let sync_client = client.clone();
+        let user_id = config.matrix_user_id.clone();
+        let auth = config.matrix_auth.clone();
+        let store_path = config.matrix_store_path.clone();
+        let sync_shutdown = shutdown.clone();
+        let run_sync = move || {
+            sync_loop::run(
+                sync_client.clone(),
+                user_id.clone(),
+                auth.clone(),
+                store_path.clone(),
+                sync_state.sync_health.clone(),
+                sync_state.alerts.clone(),
+                sync_shutdown.clone(),
+            )
+        };
+        let sync = tokio::spawn(supervisor::supervise(
+            "Matrix sync",
+            shutdown.clone(),
+            run_sync,
+        ));
+        if let Some(url) = config.heartbeat_url.clone() {
+            tokio::spawn(heartbeat::run(
+                url,
+                config.heartbeat_interval,
+                pool.clone(),
+                state.sync_health.clone(),
+                shutdown.clone(),
+            ));
+        }
+        let outbox_worker = tokio::spawn(outbox::run(state.clone(), shutdown.clone()));
+        let quiet_worker = tokio::spawn(quiet_hours::run(state.clone(), shutdown.clone()));
+        let pending_actions_worker = tokio::spawn(pending_actions::run(
+            state.clone(),
+            config.pending_actions_poll,
+            shutdown.clone(),
+        ));
+        let batch_worker = tokio::spawn(batching::run(state.clone(), shutdown.clone()));
+        tokio::spawn(presence::watch(
+            client.clone(),
+            state.seerr_client.clone(),
+            state.alerts.clone(),
+            shutdown.clone(),
+        ));
+        let scheduler = if config.features.scheduler {
+            let scheduler = schedule_jobs(&config, &state, sonarr, radarr);
+            Some(tokio::spawn(scheduler.run(shutdown.clone())))
+        } else {
+            info!("Scheduler is disabled");
+            None
+        };
+        tokio::spawn(health::watch_database(
+            pool.clone(),
+            client.clone(),
+            state.settings.clone(),
+            state.alerts.clone(),
+            shutdown.clone(),
+        ));
+        tokio::spawn(settings::reload_on_sighup(
+            state.settings.clone(),
+            shutdown.clone(),
+        ));
+        tokio::spawn(matrix::watch_membership(
+            client.clone(),
+            room_id,
+            shutdown.clone(),
+        ));
+
+        shutdown.cancelled().await;
+
+        // Stop accepting webhooks and let in-flight requests complete
+        match tokio::time::timeout(config.shutdown_timeout, server).await {
+            Ok(_) => info!("Webhook server drained"),
+            Err(_) => warn!("Timed out waiting for in-flight webhooks"),
+        }
+
+        // No new commands once sync has handled its last batch, then wait for
+        // running handlers
+        if tokio::time::timeout(config.shutdown_timeout, sync)
+            .await
+            .is_err()
+        {
+            warn!("Timed out waiting for Matrix sync to stop");
+        }
+        command_tasks.close();
+        if tokio::time::timeout(config.shutdown_timeout, command_tasks.wait())
+            .await
+            .is_err()
+        {
+            warn!("Timed out waiting for in-flight commands");
+        }
+
+        if let Some(scheduler) = scheduler
+            && tokio::time::timeout(config.shutdown_timeout, scheduler)
+                .await
+                .is_err()
+        {
+            warn!("Timed out waiting for running scheduled jobs");
+        }
+
+        if tokio::time::timeout(config.shutdown_timeout, quiet_worker)
+            .await
+            .is_err()
+        {
+            warn!("Timed out delivering held notifications");
+        }
+        if tokio::time::timeout(config.shutdown_timeout, pending_actions_worker)
+            .await
+            .is_err()
+        {
+            warn!("Timed out applying queued Seerr actions");
+        }
+        let _ = batch_worker.await;
+        batching::flush(&state).await;
+        let _ = outbox_worker.await;
+        match outbox::flush(&state).await {
+            Ok(0) => info!("Outbox flushed"),
+            Ok(remaining) => warn!(remaining, "Outbox items left for the next start"),
+            Err(e) => error!("Failed to flush outbox: {e:#}"),
+        }
+
+        if let Some(notice) = &config.shutdown_notice
+            && let Err(e) = matrix::send_markdown(&state.room, notice).await
+        {
+            warn!("Failed to send shutdown notice: {e:#}");
+        }
+
+        pool.close().await;
+        info!("Shutdown complete");
+        stopped.send_replace(true);
+
+        Ok(())
+    }
+}
+
+fn router(config: &Config) -> Router<Arc<AppState>> {
+    let mut app = Router::new()
+        .route("/admin/audit", get(admin::list_audit))
+        .route("/admin/issues", get(admin::list_issues))
+        .route("/admin/issues/{id}/resolve", post(admin::resolve_issue))
+        .route("/admin/outbox", get(admin::list_outbox))
+        .route("/admin/outbox/stuck", get(admin::list_stuck_outbox));
+    if config.features.metrics {
+        app = app.route("/admin/metrics", get(admin::metrics));
+    }
+    // Linked from the Seerr comments of attachments posted in issue threads
+    if config.bot_public_url.is_some() {
+        app = app.route("/attachments/{event_id}", get(attachments::serve));
+    }
+    if config.features.webhooks {
+        app = app.route("/webhook/seerr", post(webhook::handle_seerr_webhook));
+    } else {
+        info!("Seerr webhooks are disabled");
+    }
+    if config.features.sonarr_radarr_webhooks {
+        app = app
+            .route("/webhook/sonarr", post(imports::handle_sonarr_webhook))
+            .route("/webhook/radarr", post(imports::handle_radarr_webhook));
+    } else {
+        info!("Sonarr and Radarr webhooks are disabled");
+    }
+    if config.features.home_assistant_webhook {
+        app = app.route(
+            "/webhook/home-assistant",
+            post(home_assistant::handle_home_assistant_webhook),
+        );
+    } else {
+        info!("Home Assistant webhook is disabled");
+    }
+    app
+}
+
+/// Registers the periodic jobs enabled in `config`.
+fn schedule_jobs(
+    config: &Config,
+    state: &Arc<AppState>,
+    sonarr: Option<SonarrClient>,
+    radarr: Option<RadarrClient>,
+) -> Scheduler {
+    let mut scheduler = Scheduler::new(state.settings.clone());
+    if config.weekly_report_enabled {
+        let state = state.clone();
+        scheduler.add(
+            "weekly_report",
+            config.schedules.weekly_report.clone(),
+            move || {
+                let state = state.clone();
+                async move { stats::post_weekly_report(&state).await }
+            },
+        );
+    }
+    if config.daily_digest_enabled {
+        let state = state.clone();
+        scheduler.add(
+            "daily_digest",
+            config.schedules.daily_digest.clone(),
+            move || {
+                let state = state.clone();
+                async move { digest::post_daily_digest(&state).await }
+            },
+        );
+    }
+    if config.stale_reminders_enabled {
+        let state = state.clone();
+        let after = config.stale_issue_after;
+        scheduler.add(
+            "stale_reminders",
+            config.schedules.stale_reminders.clone(),
+            move || {
+                let state = state.clone();
+                async move { reminders::send_stale_reminders(&state, after).await }
+            },
+        );
+    }
+    if config.reconcile_enabled {
+        let state = state.clone();
+        scheduler.add("reconcile", config.schedules.reconcile.clone(), move || {
+            let state = state.clone();
+            async move { reconcile::reconcile(&state).await.map(|_| ()) }
+        });
+    }
+    if config.webhook_check_enabled {
+        let state = state.clone();
+        let expected_url = config.seerr_webhook_url.clone();
+        scheduler.add(
+            "webhook_check",
+            config.schedules.webhook_check.clone(),
+            move || {
+                let state = state.clone();
+                let expected_url = expected_url.clone();
+                async move { webhook_drift::check(&state, expected_url.as_deref()).await }
+            },
+        );
+    }
+    if config.availability_watch_enabled {
+        let state = state.clone();
+        scheduler.add(
+            "availability",
+            config.schedules.availability.clone(),
+            move || {
+                let state = state.clone();
+                async move { availability::check_requests(&state).await }
+            },
+        );
+    }
+    // Reminders are set with `!remind`
+    if config.features.commands {
+        let state = state.clone();
+        scheduler.add(
+            "user_reminders",
+            config.schedules.user_reminders.clone(),
+            move || {
+                let state = state.clone();
+                async move { remind::send_due_reminders(&state).await }
+            },
+        );
+    }
+    if config.import_auto_resolve_after.is_some() {
+        let state = state.clone();
+        scheduler.add(
+            "auto_resolve",
+            config.schedules.auto_resolve.clone(),
+            move || {
+                let state = state.clone();
+                async move { imports::auto_resolve(&state).await }
+            },
+        );
+    }
+    if config.reporter_follow_up_after.is_some() {
+        let state = state.clone();
+        scheduler.add(
+            "reporter_follow_up",
+            config.schedules.reporter_follow_up.clone(),
+            move || {
+                let state = state.clone();
+                async move { follow_ups::run(&state).await }
+            },
+        );
+    }
+    if config.outbox_watchdog_enabled {
+        let state = state.clone();
+        scheduler.add(
+            "outbox_watchdog",
+            config.schedules.outbox_watchdog.clone(),
+            move || {
+                let state = state.clone();
+                async move { outbox::watch(&state).await }
+            },
+        );
+    }
+    if config.disk_monitor_enabled {
+        let state = state.clone();
+        let monitor = Arc::new(DiskMonitor::new(
+            sonarr.clone(),
+            radarr.clone(),
+            config.disk_watch_paths.clone(),
+            config.disk_free_threshold_gb,
+        ));
+        scheduler.add(
+            "disk_monitor",
+            config.schedules.disk_monitor.clone(),
+            move || {
+                let state = state.clone();
+                let monitor = monitor.clone();
+                async move { monitor.check(&state).await }
+            },
+        );
+    }
+    if sonarr.is_some() || radarr.is_some() {
+        let state = state.clone();
+        scheduler.add("calendar", config.schedules.calendar.clone(), move || {
+            let state = state.clone();
+            let sonarr = sonarr.clone();
+            let radarr = radarr.clone();
+            async move { calendar::post_calendar(&state, sonarr.as_ref(), radarr.as_ref()).await }
+        });
+    }
+    // Rooms opt in with `whats_new` in their config
+    {
+        let state = state.clone();
+        scheduler.add("whats_new", config.schedules.whats_new.clone(), move || {
+            let state = state.clone();
+            async move { whats_new::post_whats_new(&state).await }
+        });
+    }
+    scheduler
+}
@@ -0,0 +1,207 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use tracing::info;
+
+use crate::AppState;
+use crate::markdown;
+use crate::matrix;
+use crate::radarr_client::{Movie, RadarrClient};
+use crate::room_config;
+use crate::sonarr_client::{Episode, SonarrClient};
+use crate::time_format::TimeFormat;
+
+/// One line of the calendar.
+struct Release {
+    at: DateTime<Utc>,
+    line: String,
+}
+
+fn episode_release(episode: &Episode) -> Option<Release> {
+    let series = episode
+        .series
+        .as_ref()
+        .map(|s| markdown::escape(&s.title))
+        .unwrap_or_else(|| "Unknown series".to_string());
+    let mut line = format!(
+        "{series} S{:02}E{:02}",
+        episode.season_number, episode.episode_number
+    );
+    if let Some(title) = &episode.title {
+        line.push_str(&format!(" \"{}\"", markdown::escape(title)));
+    }
+    Some(Release {
+        at: episode.air_date_utc?,
+        line,
+    })
+}
+
+/// Every release of `movie` between `start` and `end`.
+fn movie_releases(movie: &Movie, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<Release> {
+    let title = markdown::escape(&movie.title);
+    let title = match movie.year {
+        Some(year) => format!("{title} ({year})"),
+        None => title,
+    };
+    [
+        (movie.in_cinemas, "in cinemas"),
+        (movie.digital_release, "digital release"),
+        (movie.physical_release, "physical release"),
+    ]
+    .into_iter()
+    .filter_map(|(at, kind)| {
+        let at = at.filter(|at| *at >= start && *at < end)?;
+        Some(Release {
+            at,
+            line: format!("{title}, {kind}"),
+        })
+    })
+    .collect()
+}
+
+fn render(
+    episodes: &[Episode],
+    movies: &[Movie],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    time_format: &TimeFormat,
+) -> String {
+    let mut markdown = "#### 📅 Coming this week\n".to_string();
+
+    let mut sections = Vec::new();
+    let mut episodes: Vec<Release> = episodes.iter().filter_map(episode_release).collect();
+    if !episodes.is_empty() {
+        episodes.sort_by_key(|r| r.at);
+        sections.push(("📺 Episodes", episodes));
+    }
+    let mut movies: Vec<Release> = movies
+        .iter()
+        .flat_map(|movie| movie_releases(movie, start, end))
+        .collect();
+    if !movies.is_empty() {
+        movies.sort_by_key(|r| r.at);
+        sections.push(("🎬 Movies", movies));
+    }
+
+    if sections.is_empty() {
+        markdown.push_str("Nothing scheduled this week\n");
+        return markdown;
+    }
+    for (heading, releases) in sections {
+        markdown.push_str(&format!("\n**{heading}**\n"));
+        for release in releases {
+            markdown.push_str(&format!(
+                "- {}: {}\n",
+                time_format.date(release.at),
+                release.line
+            ));
+        }
+    }
+    markdown
+}
+
+/// Posts the episodes and movies coming out in the next seven days, in rooms
+/// that opted in with `calendar` in their config.
+pub async fn post_calendar(
+    state: &AppState,
+    sonarr: Option<&SonarrClient>,
+    radarr: Option<&RadarrClient>,
+) -> Result<()> {
+    let room_config = room_config::load(&state.room, &state.settings.get().room_defaults).await?;
+    if !room_config.calendar {
+        info!("Calendar not enabled for the room, skipping");
+        return Ok(());
+    }
+
+    let start = Utc::now();
+    let end = start + Duration::days(7);
+    let episodes = match sonarr {
+        Some(sonarr) => sonarr.calendar(start, end).await?,
+        None => Vec::new(),
+    };
+    let movies = match radarr {
+        Some(radarr) => radarr.calendar(start, end).await?,
+        None => Vec::new(),
+    };
+
+    let markdown = render(
+        &episodes,
+        &movies,
+        start,
+        end,
+        &state.settings.get().time_format,
+    );
+    matrix::send_markdown(&state.room, &markdown).await?;
+    info!(
+        episodes = episodes.len(),
+        movies = movies.len(),
+        "Calendar posted"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn render_lists_releases_in_the_week() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 9, 9, 0, 0).unwrap();
+        let end = start + Duration::days(7);
+        let episodes: Vec<Episode> = serde_json::from_str(
+            r#"[{"seasonNumber": 2, "episodeNumber": 5, "title": "The Return",
+                 "airDateUtc": "2026-03-11T02:00:00Z", "series": {"title": "Some Show"}}]"#,
+        )
+        .unwrap();
+        let movies: Vec<Movie> = serde_json::from_str(
+            r#"[{"title": "Some Movie", "year": 2026, "inCinemas": "2026-01-10T00:00:00Z",
+                 "digitalRelease": "2026-03-12T00:00:00Z"}]"#,
+        )
+        .unwrap();
+
+        let markdown = render(&episodes, &movies, start, end, &TimeFormat::default());
+
+        assert!(markdown.contains("- 2026-03-11: Some Show S02E05 \"The Return\""));
+        assert!(markdown.contains("- 2026-03-12: Some Movie (2026), digital release"));
+        assert!(!markdown.contains("in cinemas"));
+    }
+
+    #[test]
+    fn render_escapes_titles() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 9, 9, 0, 0).unwrap();
+        let episodes: Vec<Episode> = serde_json::from_str(
+            r#"[{"seasonNumber": 1, "episodeNumber": 2, "title": "<b>Hi</b>",
+                 "airDateUtc": "2026-03-11T02:00:00Z", "series": {"title": "*Show*"}}]"#,
+        )
+        .unwrap();
+        let movies: Vec<Movie> = serde_json::from_str(
+            r#"[{"title": "[Movie](x)", "digitalRelease": "2026-03-12T00:00:00Z"}]"#,
+        )
+        .unwrap();
+
+        let markdown = render(
+            &episodes,
+            &movies,
+            start,
+            start + Duration::days(7),
+            &TimeFormat::default(),
+        );
+
+        assert!(markdown.contains(r#"\*Show\* S01E02 "\<b\>Hi\<\/b\>""#));
+        assert!(markdown.contains(r"\[Movie\]\(x\), digital release"));
+    }
+
+    #[test]
+    fn render_empty_week() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 9, 9, 0, 0).unwrap();
+        let markdown = render(
+            &[],
+            &[],
+            start,
+            start + Duration::days(7),
+            &TimeFormat::default(),
+        );
+        assert!(markdown.contains("Nothing scheduled this week"));
+    }
+}
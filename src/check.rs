@@ -0,0 +1,139 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use matrix_sdk::Client;
+use matrix_sdk::ruma::OwnedRoomAliasId;
+
+use crate::config::{Config, MatrixAuth};
+use crate::db;
+use crate::matrix;
+use crate::seerr_client::SeerrClient;
+use crate::webhook_drift;
+
+const STEP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Checks every external dependency the bot needs and prints a report, for
+/// first-time setup and infrastructure CI. Returns whether all checks passed.
+pub async fn run(config: &Config) -> bool {
+    let mut ok = true;
+
+    ok &= report(
+        "PostgreSQL",
+        step(async {
+            let pool = db::connect(&config.database_url, &config.database_pool).await?;
+            db::ping(&pool).await?;
+            Ok("connected".to_string())
+        }),
+    )
+    .await;
+
+    let client = step(async {
+        if matches!(config.matrix_auth, MatrixAuth::LoginToken(_)) {
+            anyhow::bail!("login tokens are single use, check with a password or access token");
+        }
+        matrix::create_and_login(
+            &config.matrix_homeserver_url,
+            &config.matrix_user_id,
+            &config.matrix_auth,
+            // Leave the running bot's store and device alone
+            None,
+        )
+        .await
+    })
+    .await;
+    match client {
+        Ok(client) => {
+            let user_id = client.user_id().map(|u| u.to_string()).unwrap_or_default();
+            ok &= report("Matrix login", async {
+                Ok(format!("logged in as {user_id}"))
+            })
+            .await;
+            ok &= report(
+                "Matrix room",
+                step(resolve_room(&client, &config.matrix_room_alias)),
+            )
+            .await;
+            // Don't leave a device behind for every check run
+            if matches!(config.matrix_auth, MatrixAuth::Password(_))
+                && let Err(e) = client.logout().await
+            {
+                println!("   (failed to log out the check session: {e})");
+            }
+        }
+        Err(e) => {
+            ok &= report("Matrix login", async { Err(e) }).await;
+        }
+    }
+
+    ok &= report(
+        "Seerr",
+        step(async {
+            SeerrClient::new(&config.seerr_api_url, &config.seerr_api_key)
+                .status()
+                .await?;
+            Ok(format!("reachable at {}", config.seerr_api_url))
+        }),
+    )
+    .await;
+
+    if config.webhook_check_enabled {
+        ok &= report(
+            "Seerr webhook",
+            step(async {
+                let settings = SeerrClient::new(&config.seerr_api_url, &config.seerr_api_key)
+                    .webhook_settings()
+                    .await?;
+                let problems =
+                    webhook_drift::problems(&settings, config.seerr_webhook_url.as_deref());
+                if !problems.is_empty() {
+                    anyhow::bail!("{}", problems.join(", "));
+                }
+                Ok("enabled with the issue notification types".to_string())
+            }),
+        )
+        .await;
+    }
+
+    println!();
+    println!(
+        "{}",
+        if ok {
+            "All checks passed"
+        } else {
+            "Some checks failed"
+        }
+    );
+    ok
+}
+
+async fn resolve_room(client: &Client, room: &str) -> Result<String> {
+    if room.starts_with('!') {
+        return Ok(format!("{room} (room id, not resolved)"));
+    }
+    let alias = OwnedRoomAliasId::try_from(room).context("Invalid room alias")?;
+    let response = client
+        .resolve_room_alias(&alias)
+        .await
+        .with_context(|| format!("Failed to resolve {room}"))?;
+    Ok(format!("{room} → {}", response.room_id))
+}
+
+async fn step<T>(future: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(STEP_TIMEOUT, future)
+        .await
+        .context("timed out")?
+}
+
+async fn report(name: &str, check: impl Future<Output = Result<String>>) -> bool {
+    match check.await {
+        Ok(detail) => {
+            println!("✅ {name}: {detail}");
+            true
+        }
+        Err(e) => {
+            println!("❌ {name}: {e:#}");
+            false
+        }
+    }
+}
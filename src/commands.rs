@@ -1,32 +1,537 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
 
-use matrix_sdk::Room;
+use anyhow::Context;
 use matrix_sdk::event_handler::Ctx;
-use matrix_sdk::ruma::OwnedUserId;
+use matrix_sdk::ruma::events::reaction::OriginalSyncReactionEvent;
 use matrix_sdk::ruma::events::room::message::{OriginalSyncRoomMessageEvent, Relation};
+use matrix_sdk::ruma::{OwnedEventId, OwnedUserId};
+use matrix_sdk::{Client, Room};
 use sqlx::PgPool;
-use tracing::{error, info, warn};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, error, info, info_span, warn};
 
-use crate::db;
+use crate::AppState;
+use crate::alerts::{Alerts, Subsystem};
+use crate::attachments;
+use crate::audit;
+use crate::concurrency::Limiter;
+use crate::cross_refs;
+use crate::dashboard;
+use crate::db::{self, AuditEntry, CommentOrigin, TrackedIssue, UserMapping};
+use crate::export::{self, ExportFormat, ExportPeriod};
+use crate::issue::{IssueCategory, IssueState};
+use crate::jellyfin_client::JellyfinClient;
+use crate::lifecycle::{self, IssueEvent};
+use crate::maintenance;
+use crate::markdown;
 use crate::matrix;
+use crate::now_playing;
+use crate::outgoing::{BotEvent, OutgoingWebhooks};
+use crate::pending_actions::{self, SeerrAction};
+use crate::qbittorrent_client::QbittorrentClient;
+use crate::queue;
+use crate::quiet_hours;
+use crate::quota;
+use crate::radarr_client::RadarrClient;
+use crate::reactions::{self, ReactionAction};
+use crate::reconcile::{self, Adoption};
+use crate::remediation::{self, RadarrAction, SonarrAction};
+use crate::remind;
+use crate::report;
+use crate::request::RequestStatus;
+use crate::routing::{self, Stream};
+use crate::seerr::SeerrUser;
 use crate::seerr_client::SeerrClient;
+use crate::settings::LiveSettings;
+use crate::sonarr_client::SonarrClient;
+use crate::stats;
+use crate::time_format::TimeFormat;
+use crate::webhook;
 
 pub struct CommandContext {
     pub db: PgPool,
     pub seerr_client: SeerrClient,
-    pub admin_users: Vec<OwnedUserId>,
+    pub sonarr_client: Option<SonarrClient>,
+    pub radarr_client: Option<RadarrClient>,
+    pub jellyfin_client: Option<JellyfinClient>,
+    pub qbittorrent_client: Option<QbittorrentClient>,
+    pub settings: Arc<LiveSettings>,
+    pub alerts: Arc<Alerts>,
+    pub outgoing: OutgoingWebhooks,
+    /// Shared with the webhook handlers.
+    pub limiter: Arc<Limiter>,
+    /// For commands that post cards the way webhooks do.
+    pub state: Arc<AppState>,
+    /// Tracks in-flight handlers so shutdown can wait for them to finish.
+    pub tasks: TaskTracker,
 }
 
+const HISTORY_LIMIT: i64 = 50;
+const SEARCH_LIMIT: i64 = 20;
+const DEFAULT_ARCHIVE_LIMIT: i64 = 20;
+const MAX_ARCHIVE_LIMIT: i64 = 50;
+/// Resolution comments are cut to keep the archive table on one screen.
+const ARCHIVE_COMMENT_MAX_CHARS: usize = 60;
+const PENDING_REQUESTS_LIMIT: usize = 20;
+/// How long the numbers of a `!requests pending` list can be used.
+const REQUEST_SHORTCUT_TTL_SECS: i64 = 60 * 60;
+const DEFAULT_STATS_DAYS: i64 = 30;
+
 #[derive(Debug, PartialEq)]
 enum Command {
-    Resolve { comment: Option<String> },
+    Resolve {
+        comment: Option<String>,
+    },
+    Acknowledge,
+    ListIssues {
+        category: Option<IssueCategory>,
+    },
+    SearchIssues {
+        query: String,
+    },
+    /// `!issues archive last 20 page 2`.
+    ArchiveIssues {
+        limit: i64,
+        page: i64,
+    },
+    /// `!issues export last-quarter json`.
+    ExportIssues {
+        period: ExportPeriod,
+        format: ExportFormat,
+    },
+    LinkIssue {
+        issue_id: i64,
+    },
+    MergeIssue {
+        duplicate_id: i64,
+    },
+    History,
+    MuteReminders {
+        muted: bool,
+    },
+    Sonarr {
+        action: SonarrAction,
+    },
+    Radarr {
+        action: RadarrAction,
+    },
+    RefreshLibrary,
+    NowPlaying,
+    Queue,
+    ApproveRequest,
+    DeclineRequest,
+    ListPendingRequests,
+    /// `!approve 2` or `!decline 2`, numbered in the last pending list.
+    UpdateNumberedRequest {
+        number: i32,
+        approve: bool,
+    },
+    LinkUser {
+        matrix_user_id: String,
+        seerr_user: String,
+    },
+    UnlinkUser {
+        matrix_user_id: String,
+    },
+    ListUsers,
+    Stats {
+        days: i64,
+    },
+    ReloadConfig,
+    Pause {
+        duration: chrono::Duration,
+    },
+    Resume,
+    /// `!bot route sonarr off`, `!bot route issues here`.
+    Route {
+        stream: Stream,
+        enabled: bool,
+    },
+    ListRoutes,
+    StartMaintenance {
+        reason: String,
+    },
+    EndMaintenance,
+    Remind {
+        delay: chrono::Duration,
+        message: String,
+    },
+    ListReminders,
+    CancelReminder {
+        id: i64,
+    },
+}
+
+impl Command {
+    fn name(&self) -> &'static str {
+        match self {
+            Command::Resolve { .. } => "issues.resolve",
+            Command::Acknowledge => "issues.ack",
+            Command::ListIssues { .. } => "issues.list",
+            Command::SearchIssues { .. } => "issues.search",
+            Command::ArchiveIssues { .. } => "issues.archive",
+            Command::ExportIssues { .. } => "issues.export",
+            Command::LinkIssue { .. } => "issues.link",
+            Command::MergeIssue { .. } => "issues.merge",
+            Command::History => "issues.history",
+            Command::MuteReminders { muted: true } => "issues.mute",
+            Command::MuteReminders { muted: false } => "issues.unmute",
+            Command::Sonarr {
+                action: SonarrAction::Search,
+            } => "sonarr.search",
+            Command::Sonarr {
+                action: SonarrAction::Redownload,
+            } => "sonarr.delete-and-redownload",
+            Command::Radarr {
+                action: RadarrAction::Search,
+            } => "radarr.search",
+            Command::Radarr {
+                action: RadarrAction::Delete { .. },
+            } => "radarr.delete",
+            Command::RefreshLibrary => "library.refresh",
+            Command::NowPlaying => "nowplaying",
+            Command::Queue => "queue",
+            Command::ApproveRequest => "requests.approve",
+            Command::DeclineRequest => "requests.decline",
+            Command::ListPendingRequests => "requests.pending",
+            Command::UpdateNumberedRequest { approve: true, .. } => "requests.approve",
+            Command::UpdateNumberedRequest { approve: false, .. } => "requests.decline",
+            Command::LinkUser { .. } => "users.link",
+            Command::UnlinkUser { .. } => "users.unlink",
+            Command::ListUsers => "users.list",
+            Command::Stats { .. } => "stats",
+            Command::ReloadConfig => "config.reload",
+            Command::Pause { .. } => "bot.pause",
+            Command::Resume => "bot.resume",
+            Command::Route { .. } => "bot.route",
+            Command::ListRoutes => "bot.routes",
+            Command::StartMaintenance { .. } => "maintenance.start",
+            Command::EndMaintenance => "maintenance.end",
+            Command::Remind { .. } => "remind",
+            Command::ListReminders => "reminders.list",
+            Command::CancelReminder { .. } => "reminders.cancel",
+        }
+    }
+}
+
+/// How a message sent to the bot turned out, answered in the room so the
+/// sender is never left wondering why nothing happened.
+#[derive(Debug)]
+pub enum CommandOutcome {
+    /// Carried out, or not a command at all.
+    Success,
+    /// A command from someone who isn't an admin.
+    PermissionDenied,
+    /// A command about an issue or request sent outside of its thread.
+    NotInThread,
+    /// The thread is not the one of an issue or request the bot tracks.
+    IssueNotFound,
+    /// `!approve 2` with no request numbered 2 in the last hour's
+    /// `!requests pending`.
+    UnknownShortcut(i32),
+    /// Seerr failed or refused the call the command made.
+    SeerrError(anyhow::Error),
+    /// The command was already run, when the message is seen again.
+    AlreadyHandled,
+}
+
+impl CommandOutcome {
+    /// Outcome of a command that ran, keeping errors other than Seerr's as
+    /// failures of the bot.
+    fn of(result: anyhow::Result<()>) -> anyhow::Result<Self> {
+        match result {
+            Ok(()) => Ok(CommandOutcome::Success),
+            Err(e) if e.is::<SeerrFailed>() => Ok(CommandOutcome::SeerrError(e)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Label in the logs and `command_executed` events.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CommandOutcome::Success => "success",
+            CommandOutcome::PermissionDenied => "permission_denied",
+            CommandOutcome::NotInThread => "not_in_thread",
+            CommandOutcome::IssueNotFound => "issue_not_found",
+            CommandOutcome::UnknownShortcut(_) => "unknown_shortcut",
+            CommandOutcome::SeerrError(_) => "seerr_error",
+            CommandOutcome::AlreadyHandled => "already_handled",
+        }
+    }
+
+    /// What the sender is told, nothing when the command answered itself.
+    fn reply(&self) -> Option<String> {
+        let markdown = match self {
+            CommandOutcome::Success => return None,
+            CommandOutcome::PermissionDenied => {
+                "**🚫 Only the bot admins can run commands**".to_string()
+            }
+            CommandOutcome::NotInThread => {
+                "**⚠️ This command must be sent in the thread of a card**".to_string()
+            }
+            CommandOutcome::IssueNotFound => {
+                "**⚠️ No issue or request is tracked for this thread**".to_string()
+            }
+            CommandOutcome::UnknownShortcut(number) => format!(
+                "**⚠️ No request numbered {number}**  \nList them again with `!requests pending`"
+            ),
+            CommandOutcome::SeerrError(e) => format!(
+                "**⚠️ Seerr failed to carry out the command**  \n{}",
+                markdown::escape(&e.root_cause().to_string())
+            ),
+            CommandOutcome::AlreadyHandled => "**↩️ Already handled**".to_string(),
+        };
+        Some(markdown)
+    }
+}
+
+/// Label of a command that ran, `error` when the bot itself failed.
+fn outcome_label(outcome: &anyhow::Result<CommandOutcome>) -> &'static str {
+    outcome.as_ref().map_or("error", CommandOutcome::label)
+}
+
+/// Context marking the error of a Seerr call, for the reply to blame Seerr
+/// rather than the bot. Jellyfin, qBittorrent, Sonarr and Radarr errors go
+/// without it, reported like any other failure.
+#[derive(Debug)]
+struct SeerrFailed;
+
+impl fmt::Display for SeerrFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Seerr call failed")
+    }
+}
+
+/// Argument of a command, without the quotes it may be written in.
+fn unquote(arg: &str) -> &str {
+    let arg = arg.trim();
+    arg.strip_prefix('"')
+        .map(|inner| inner.strip_suffix('"').unwrap_or(inner))
+        .unwrap_or(arg)
+        .trim()
 }
 
 fn parse_command(body: &str) -> Option<Command> {
     let body = body.trim();
+    if let Some(rest) = body.strip_prefix("!stats") {
+        let rest = rest.trim();
+        if rest.is_empty() {
+            return Some(Command::Stats {
+                days: DEFAULT_STATS_DAYS,
+            });
+        }
+        return rest
+            .parse()
+            .ok()
+            .filter(|days| *days > 0)
+            .map(|days| Command::Stats { days });
+    }
+
+    if let Some(rest) = body.strip_prefix("!config") {
+        return (rest.trim() == "reload").then_some(Command::ReloadConfig);
+    }
+
+    if let Some(rest) = body.strip_prefix("!bot") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        return match args.as_slice() {
+            ["pause", duration] => {
+                remind::parse_delay(duration).map(|duration| Command::Pause { duration })
+            }
+            ["resume"] => Some(Command::Resume),
+            ["route"] => Some(Command::ListRoutes),
+            ["route", stream, setting] => {
+                let enabled = match *setting {
+                    "here" | "on" => true,
+                    "off" => false,
+                    _ => return None,
+                };
+                Stream::parse(stream).map(|stream| Command::Route { stream, enabled })
+            }
+            _ => None,
+        };
+    }
+
+    if let Some(rest) = body.strip_prefix("!maintenance") {
+        let rest = rest.trim();
+        if rest == "end" {
+            return Some(Command::EndMaintenance);
+        }
+        let reason = unquote(rest.strip_prefix("start")?);
+        if reason.is_empty() {
+            return None;
+        }
+        return Some(Command::StartMaintenance {
+            reason: reason.to_string(),
+        });
+    }
+
+    if let Some(rest) = body.strip_prefix("!reminders") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        return match args.as_slice() {
+            ["list"] => Some(Command::ListReminders),
+            ["cancel", id] => id
+                .trim_start_matches('#')
+                .parse()
+                .ok()
+                .map(|id| Command::CancelReminder { id }),
+            _ => None,
+        };
+    }
+
+    if let Some(rest) = body.strip_prefix("!remind ") {
+        let (delay, message) = rest.trim().split_once(char::is_whitespace)?;
+        let message = message.trim();
+        if message.is_empty() {
+            return None;
+        }
+        return remind::parse_delay(delay).map(|delay| Command::Remind {
+            delay,
+            message: message.to_string(),
+        });
+    }
+
+    if let Some(rest) = body.strip_prefix("!users") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        return match args.as_slice() {
+            ["link", matrix_user_id, seerr_user] => Some(Command::LinkUser {
+                matrix_user_id: matrix_user_id.to_string(),
+                seerr_user: seerr_user.to_string(),
+            }),
+            ["unlink", matrix_user_id] => Some(Command::UnlinkUser {
+                matrix_user_id: matrix_user_id.to_string(),
+            }),
+            ["list"] => Some(Command::ListUsers),
+            _ => None,
+        };
+    }
+
+    if let Some(rest) = body.strip_prefix("!sonarr") {
+        let action = match rest.trim() {
+            "search" => SonarrAction::Search,
+            "delete-and-redownload" => SonarrAction::Redownload,
+            _ => return None,
+        };
+        return Some(Command::Sonarr { action });
+    }
+
+    if let Some(rest) = body.strip_prefix("!radarr") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let action = match args.as_slice() {
+            ["search"] => RadarrAction::Search,
+            ["delete"] => RadarrAction::Delete { quality: None },
+            ["delete", quality] => RadarrAction::Delete {
+                quality: Some(quality.to_string()),
+            },
+            _ => return None,
+        };
+        return Some(Command::Radarr { action });
+    }
+
+    if body == "!nowplaying" {
+        return Some(Command::NowPlaying);
+    }
+    if body == "!queue" {
+        return Some(Command::Queue);
+    }
+
+    if let Some(rest) = body.strip_prefix("!library") {
+        return (rest.trim() == "refresh").then_some(Command::RefreshLibrary);
+    }
+
+    if let Some(rest) = body.strip_prefix("!requests") {
+        return match rest.trim() {
+            "approve" => Some(Command::ApproveRequest),
+            "decline" => Some(Command::DeclineRequest),
+            "pending" => Some(Command::ListPendingRequests),
+            _ => None,
+        };
+    }
+
+    let numbered = match body.split_once(char::is_whitespace) {
+        Some(("!approve", number)) => Some((true, number)),
+        Some(("!decline", number)) => Some((false, number)),
+        _ => None,
+    };
+    if let Some((approve, number)) = numbered {
+        return number
+            .trim()
+            .trim_start_matches('#')
+            .parse()
+            .ok()
+            .filter(|number| *number > 0)
+            .map(|number| Command::UpdateNumberedRequest { number, approve });
+    }
+
     let rest = body.strip_prefix("!issues")?;
     let rest = rest.trim_start();
 
+    match rest.trim_end() {
+        "ack" => return Some(Command::Acknowledge),
+        "list" => return Some(Command::ListIssues { category: None }),
+        "history" => return Some(Command::History),
+        "mute" => return Some(Command::MuteReminders { muted: true }),
+        "unmute" => return Some(Command::MuteReminders { muted: false }),
+        _ => {}
+    }
+
+    if let Some(rest) = rest.strip_prefix("link") {
+        let issue_id = rest.trim().trim_start_matches('#').parse().ok()?;
+        return Some(Command::LinkIssue { issue_id });
+    }
+
+    if let Some(rest) = rest.strip_prefix("merge") {
+        let duplicate_id = rest.trim().trim_start_matches('#').parse().ok()?;
+        return Some(Command::MergeIssue { duplicate_id });
+    }
+
+    if let Some(rest) = rest.strip_prefix("search") {
+        let query = unquote(rest);
+        if query.is_empty() {
+            return None;
+        }
+        return Some(Command::SearchIssues {
+            query: query.to_string(),
+        });
+    }
+
+    if let Some(rest) = rest.strip_prefix("archive") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let (limit, page) = match args.as_slice() {
+            [] => (DEFAULT_ARCHIVE_LIMIT, 1),
+            ["last", limit] => (limit.parse().ok()?, 1),
+            ["page", page] => (DEFAULT_ARCHIVE_LIMIT, page.parse().ok()?),
+            ["last", limit, "page", page] => (limit.parse().ok()?, page.parse().ok()?),
+            _ => return None,
+        };
+        if !(1..=MAX_ARCHIVE_LIMIT).contains(&limit) || page < 1 {
+            return None;
+        }
+        return Some(Command::ArchiveIssues { limit, page });
+    }
+
+    if let Some(rest) = rest.strip_prefix("export") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        let (period, format) = match args.as_slice() {
+            [] => (ExportPeriod::LastMonth, ExportFormat::Csv),
+            [period] => (ExportPeriod::parse(period)?, ExportFormat::Csv),
+            [period, format] => (ExportPeriod::parse(period)?, ExportFormat::parse(format)?),
+            _ => return None,
+        };
+        return Some(Command::ExportIssues { period, format });
+    }
+
+    if let Some(rest) = rest.strip_prefix("list") {
+        let args: Vec<&str> = rest.split_whitespace().collect();
+        return match args.as_slice() {
+            ["--category", category] => Some(Command::ListIssues {
+                category: Some(IssueCategory::parse(category)?),
+            }),
+            _ => None,
+        };
+    }
+
     if let Some(rest) = rest.strip_prefix("resolve") {
         let rest = rest.trim();
         if rest.is_empty() {
@@ -48,76 +553,1580 @@ fn parse_command(body: &str) -> Option<Command> {
         });
     }
 
-    None
+    None
+}
+
+pub async fn on_room_message(
+    event: OriginalSyncRoomMessageEvent,
+    room: Room,
+    ctx: Ctx<Arc<CommandContext>>,
+) {
+    if ctx.tasks.is_closed() {
+        return;
+    }
+    let _in_flight = ctx.tasks.token();
+
+    let span = info_span!("command", sender = %event.sender);
+    async {
+        let result = handle_message(&event, &room, &ctx).await;
+        if let Ok(CommandOutcome::SeerrError(e)) | Err(e) = &result
+            && let Some(subsystem) = Subsystem::of(e)
+        {
+            ctx.alerts.failure(subsystem, e);
+        }
+        let reply = match &result {
+            Ok(CommandOutcome::Success) => None,
+            Ok(outcome @ CommandOutcome::SeerrError(e)) => {
+                warn!(
+                    outcome = outcome.label(),
+                    "Seerr failed to carry out command: {e:#}"
+                );
+                outcome.reply()
+            }
+            Ok(outcome) => {
+                warn!(outcome = outcome.label(), "Command not carried out");
+                outcome.reply()
+            }
+            Err(e) => {
+                error!(outcome = "error", "Error handling command: {e:#}");
+                Some("**⚠️ Something went wrong, see the bot logs**".to_string())
+            }
+        };
+        let Some(reply) = reply else {
+            return;
+        };
+
+        let thread_root_event_id = match &event.content.relates_to {
+            Some(Relation::Thread(thread)) => Some(&thread.event_id),
+            _ => None,
+        };
+        if let Err(e) = matrix::send_long_markdown(&room, thread_root_event_id, &reply).await {
+            error!("Failed to reply to command: {e:#}");
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+async fn handle_message(
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    ctx: &CommandContext,
+) -> anyhow::Result<CommandOutcome> {
+    // Anyone in the room can report an issue, answering in the thread, mention
+    // issues like seerr#42, and reporters can post screenshots in the thread
+    // of their issue
+    if room.room_id() == ctx.state.room.room_id() {
+        if let Err(e) = cross_refs::reply(ctx, event, room).await {
+            warn!("Failed to answer issue mentions: {e:#}");
+        }
+        if let Some(query) = report::parse(event.content.body()) {
+            report::start(ctx, event, room, query).await?;
+            return Ok(CommandOutcome::Success);
+        }
+        if let Some(Relation::Thread(thread)) = &event.content.relates_to
+            && (report::answer(ctx, event, room, &thread.event_id).await?
+                || attachments::forward(ctx, event, room, &thread.event_id).await?
+                || answer_comment_prompt(ctx, event, room, &thread.event_id).await?)
+        {
+            return Ok(CommandOutcome::Success);
+        }
+    }
+
+    let Some(command) = parse_command(event.content.body()) else {
+        return Ok(CommandOutcome::Success);
+    };
+    if !ctx.settings.get().admin_users.contains(&event.sender) {
+        // The bot's own messages are never answered, not to talk to itself
+        if room.client().user_id() == Some(event.sender.as_ref()) {
+            return Ok(CommandOutcome::Success);
+        }
+        return Ok(CommandOutcome::PermissionDenied);
+    }
+
+    // Commands about an issue wait for the webhooks and commands before them
+    let issue_id = match &event.content.relates_to {
+        Some(Relation::Thread(thread)) => {
+            db::get_issue_event_by_matrix_event_id(&ctx.db, thread.event_id.as_str())
+                .await?
+                .map(|issue_event| issue_event.issue_id)
+        }
+        _ => None,
+    };
+    let _permit = ctx.limiter.acquire(issue_id).await;
+
+    // A sync replayed after losing its token, or an edit sent without the
+    // usual `* ` prefix, would otherwise run the command again
+    let command_event_id = match &event.content.relates_to {
+        Some(Relation::Replacement(replacement)) => replacement.event_id.as_str(),
+        _ => event.event_id.as_str(),
+    };
+    if !db::claim_command_execution(&ctx.db, command_event_id, command.name()).await? {
+        return Ok(CommandOutcome::AlreadyHandled);
+    }
+
+    // Let the admin know the command was seen while Seerr is being called
+    if let Err(e) = matrix::set_typing(room, true).await {
+        warn!("{e:#}");
+    }
+
+    let result = execute_command(command, event, room, ctx).await;
+
+    if let Err(e) = matrix::set_typing(room, false).await {
+        warn!("{e:#}");
+    }
+    let outcome = outcome_label(&result);
+    if let Err(e) = db::set_command_outcome(&ctx.db, command_event_id, outcome).await {
+        warn!("Failed to record command outcome: {e:#}");
+    }
+
+    let thread_root_event_id = match &event.content.relates_to {
+        Some(Relation::Thread(thread)) => Some(&thread.event_id),
+        _ => None,
+    };
+    if let Err(e) = matrix::send_read_receipt(room, &event.event_id, thread_root_event_id).await {
+        warn!("{e:#}");
+    }
+
+    result
+}
+
+async fn execute_command(
+    command: Command,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    ctx: &CommandContext,
+) -> anyhow::Result<CommandOutcome> {
+    let sender = event.sender.as_str();
+    let thread_root_event_id = match &event.content.relates_to {
+        Some(Relation::Thread(thread)) => Some(&thread.event_id),
+        _ => None,
+    };
+
+    let (issue_id, result) = match &command {
+        Command::Resolve { comment } => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let event_id = event.event_id.as_str();
+            let comment = comment.as_deref();
+            let result = resolve(ctx, sender, event_id, issue_id, comment, room, root).await;
+            (Some(issue_id), result)
+        }
+        Command::Acknowledge => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let result = acknowledge(ctx, sender, issue_id, room, root).await;
+            (Some(issue_id), result)
+        }
+        Command::ListIssues { category } => {
+            let result = list_issues(ctx, *category, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::SearchIssues { query } => {
+            let result = search_issues(ctx, query, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::ExportIssues { period, format } => {
+            let result = export_issues(ctx, *period, *format, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::ArchiveIssues { limit, page } => {
+            let result = archive_issues(ctx, *limit, *page, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::LinkIssue { issue_id } => {
+            let result = link_issue(ctx, *issue_id, room, thread_root_event_id).await;
+            (Some(*issue_id), result)
+        }
+        Command::MergeIssue { duplicate_id } => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let result = merge_issue(ctx, event, issue_id, *duplicate_id, room, root).await;
+            (Some(issue_id), result)
+        }
+        Command::History => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            (Some(issue_id), history(ctx, issue_id, room, root).await)
+        }
+        Command::MuteReminders { muted } => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let result = mute_reminders(ctx, issue_id, *muted, room, root).await;
+            (Some(issue_id), result)
+        }
+        Command::Sonarr { action } => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let result = sonarr(ctx, sender, issue_id, *action, room, root).await;
+            (Some(issue_id), result)
+        }
+        Command::Radarr { action } => {
+            let (root, issue_id) = match thread_issue(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let result = radarr(ctx, sender, issue_id, action, room, root).await;
+            (Some(issue_id), result)
+        }
+        Command::RefreshLibrary => {
+            let result = refresh_library(ctx, sender, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::NowPlaying => (None, now_playing(ctx, room, thread_root_event_id).await),
+        Command::Queue => (None, queue(ctx, room, thread_root_event_id).await),
+        Command::ApproveRequest | Command::DeclineRequest => {
+            let (root, request_id) = match thread_request(ctx, thread_root_event_id).await? {
+                Ok(found) => found,
+                Err(outcome) => return Ok(outcome),
+            };
+            let approve = command == Command::ApproveRequest;
+            let result = update_request(ctx, sender, request_id, approve, room, Some(root)).await;
+            (None, result)
+        }
+        Command::ListPendingRequests => {
+            let result = list_pending_requests(ctx, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::UpdateNumberedRequest { number, approve } => {
+            let Some(request_id) =
+                db::get_request_shortcut(&ctx.db, *number, REQUEST_SHORTCUT_TTL_SECS).await?
+            else {
+                return Ok(CommandOutcome::UnknownShortcut(*number));
+            };
+            let result = update_request(
+                ctx,
+                sender,
+                request_id,
+                *approve,
+                room,
+                thread_root_event_id,
+            )
+            .await;
+            (None, result)
+        }
+        Command::LinkUser {
+            matrix_user_id,
+            seerr_user,
+        } => {
+            let result =
+                link_user(ctx, matrix_user_id, seerr_user, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::UnlinkUser { matrix_user_id } => {
+            let result = unlink_user(ctx, matrix_user_id, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::ListUsers => (None, list_users(ctx, room, thread_root_event_id).await),
+        Command::Stats { days } => (None, stats(ctx, *days, room, thread_root_event_id).await),
+        Command::ReloadConfig => (None, reload_config(ctx, room, thread_root_event_id).await),
+        Command::Pause { duration } => {
+            let result = pause(ctx, *duration, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::Resume => (None, resume(ctx, room, thread_root_event_id).await),
+        Command::Route { stream, enabled } => {
+            let result = route(ctx, sender, *stream, *enabled, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::ListRoutes => (None, list_routes(ctx, room, thread_root_event_id).await),
+        Command::StartMaintenance { reason } => {
+            let result = start_maintenance(ctx, sender, reason, room, thread_root_event_id).await;
+            (None, result)
+        }
+        Command::EndMaintenance => (None, end_maintenance(ctx, room, thread_root_event_id).await),
+        Command::Remind { delay, message } => {
+            let root = thread_root_event_id.unwrap_or(&event.event_id);
+            (
+                None,
+                set_reminder(ctx, sender, *delay, message, room, root).await,
+            )
+        }
+        Command::ListReminders => (None, list_reminders(ctx, room, thread_root_event_id).await),
+        Command::CancelReminder { id } => {
+            let result = cancel_reminder(ctx, *id, room, thread_root_event_id).await;
+            (None, result)
+        }
+    };
+
+    audit::record(
+        &ctx.db,
+        sender,
+        &format!("command.{}", command.name()),
+        issue_id,
+        Some(event.content.body()),
+        &result,
+    )
+    .await;
+    let outcome = CommandOutcome::of(result);
+    ctx.outgoing.emit(BotEvent::CommandExecuted {
+        command: command.name().to_string(),
+        sender: sender.to_string(),
+        issue_id,
+        success: matches!(outcome, Ok(CommandOutcome::Success)),
+        outcome: outcome_label(&outcome).to_string(),
+    });
+
+    outcome
+}
+
+/// Issue whose card is the root of the thread a command was sent in.
+async fn thread_issue<'a>(
+    ctx: &CommandContext,
+    thread_root_event_id: Option<&'a OwnedEventId>,
+) -> anyhow::Result<Result<(&'a OwnedEventId, i64), CommandOutcome>> {
+    let Some(thread_root_event_id) = thread_root_event_id else {
+        return Ok(Err(CommandOutcome::NotInThread));
+    };
+    let issue_event =
+        db::get_issue_event_by_matrix_event_id(&ctx.db, thread_root_event_id.as_str()).await?;
+    Ok(issue_event
+        .map(|ev| (thread_root_event_id, ev.issue_id))
+        .ok_or(CommandOutcome::IssueNotFound))
+}
+
+/// Media request whose card is the root of the thread a command was sent in.
+async fn thread_request<'a>(
+    ctx: &CommandContext,
+    thread_root_event_id: Option<&'a OwnedEventId>,
+) -> anyhow::Result<Result<(&'a OwnedEventId, i64), CommandOutcome>> {
+    let Some(thread_root_event_id) = thread_root_event_id else {
+        return Ok(Err(CommandOutcome::NotInThread));
+    };
+    let request_event =
+        db::get_request_event_by_matrix_event_id(&ctx.db, thread_root_event_id.as_str()).await?;
+    Ok(request_event
+        .map(|ev| (thread_root_event_id, ev.request_id))
+        .ok_or(CommandOutcome::IssueNotFound))
+}
+
+/// Acknowledges an issue when an admin reacts to its card with the
+/// acknowledged emoji, like `!issues ack` in its thread, or does what the
+/// `[reactions]` section of the config file maps the emoji to.
+pub async fn on_reaction(
+    event: OriginalSyncReactionEvent,
+    room: Room,
+    client: Client,
+    ctx: Ctx<Arc<CommandContext>>,
+) {
+    if ctx.tasks.is_closed() {
+        return;
+    }
+    let _in_flight = ctx.tasks.token();
+
+    let settings = ctx.settings.get();
+    if client.user_id() == Some(event.sender.as_ref())
+        || !settings.admin_users.contains(&event.sender)
+    {
+        return;
+    }
+    let key = &event.content.relates_to.key;
+    let Some(action) =
+        reactions::action(&settings.reaction_shortcuts, &settings.reaction_emojis, key)
+    else {
+        return;
+    };
+
+    let span = info_span!("reaction", sender = %event.sender, action = action.name());
+    async {
+        let result = handle_reaction(&event, action, &room, &ctx).await;
+        ctx.alerts.observe(&result);
+        if let Err(e) = result {
+            error!("Error handling reaction: {e:#}");
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+async fn handle_reaction(
+    event: &OriginalSyncReactionEvent,
+    action: ReactionAction,
+    room: &Room,
+    ctx: &CommandContext,
+) -> anyhow::Result<()> {
+    let root = &event.content.relates_to.event_id;
+    let Some(issue_event) = db::get_issue_event_by_matrix_event_id(&ctx.db, root.as_str()).await?
+    else {
+        return Ok(());
+    };
+    let issue_id = issue_event.issue_id;
+    let _permit = ctx.limiter.acquire(Some(issue_id)).await;
+
+    let sender = event.sender.as_str();
+    let result = match action {
+        ReactionAction::Ack => acknowledge(ctx, sender, issue_id, room, root).await,
+        ReactionAction::Resolve => {
+            let event_id = event.event_id.as_str();
+            resolve(ctx, sender, event_id, issue_id, None, room, root).await
+        }
+        ReactionAction::Comment => prompt_comment(ctx, sender, room, root).await,
+        ReactionAction::Mute => mute_reminders(ctx, issue_id, true, room, root).await,
+    };
+    audit::record(
+        &ctx.db,
+        sender,
+        &format!("reaction.issues.{}", action.name()),
+        Some(issue_id),
+        Some(event.content.relates_to.key.as_str()),
+        &result,
+    )
+    .await;
+    result
+}
+
+/// Asks the admin who reacted for the comment, which their next message in
+/// the thread is.
+async fn prompt_comment(
+    ctx: &CommandContext,
+    sender: &str,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    ctx.state
+        .comment_prompts
+        .prompt(thread_root_event_id.as_str(), sender);
+    let markdown = format!(
+        "**💬 [{}](https://matrix.to/#/{sender}), your next message in this thread is sent to \
+         Seerr as a comment**",
+        markdown::escape(sender)
+    );
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// Sends the message to Seerr as a comment on the thread's issue when its
+/// sender was asked for one after reacting to the card. Commands are still
+/// commands.
+async fn answer_comment_prompt(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<bool> {
+    let comment = event.content.body();
+    let sender = event.sender.as_str();
+    if parse_command(comment).is_some()
+        || !ctx
+            .state
+            .comment_prompts
+            .take(thread_root_event_id.as_str(), sender)
+    {
+        return Ok(false);
+    }
+    let Some(issue_event) =
+        db::get_issue_event_by_matrix_event_id(&ctx.db, thread_root_event_id.as_str()).await?
+    else {
+        return Ok(false);
+    };
+    let issue_id = issue_event.issue_id;
+    let _permit = ctx.limiter.acquire(Some(issue_id)).await;
+
+    let seerr_user = db::get_user_mapping(&ctx.db, sender)
+        .await?
+        .map(|mapping| mapping.seerr_user);
+    let message = attributed_comment(comment, sender, seerr_user.as_deref());
+    ctx.state.forwarded_comments.remember(issue_id, &message);
+    let result = ctx.seerr_client.add_comment(issue_id, &message).await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "seerr.add_comment",
+        Some(issue_id),
+        Some("reaction"),
+        &result,
+    )
+    .await;
+    let seerr_comment_id = result?;
+    // The message mirrors the comment, so its webhook isn't posted again
+    db::insert_comment_event(
+        &ctx.db,
+        issue_id,
+        seerr_comment_id,
+        event.event_id.as_str(),
+        CommentOrigin::Matrix,
+        comment,
+    )
+    .await?;
+    info!(issue_id, "Comment asked with a reaction sent to Seerr");
+
+    let markdown = format!(
+        "**💬 Comment sent to Seerr**{}",
+        webhook::seerr_link(ctx.seerr_client.issue_url(issue_id).as_deref())
+    );
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(true)
+}
+
+async fn acknowledge(
+    ctx: &CommandContext,
+    sender: &str,
+    issue_id: i64,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    let Some(state) =
+        lifecycle::apply(&ctx.db, issue_id, IssueEvent::Acknowledged, Some(sender)).await?
+    else {
+        let status = db::get_issue_status(&ctx.db, issue_id)
+            .await?
+            .unwrap_or(IssueState::Open);
+        let markdown = format!("Issue {issue_id} is {status} and can't be acknowledged");
+        matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    };
+    info!(issue_id, "Acknowledged issue");
+
+    let markdown = format!(
+        "**👀 Being looked at by [{}](https://matrix.to/#/{sender})**",
+        markdown::escape(sender)
+    );
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    reactions::transition(
+        room,
+        &ctx.db,
+        &ctx.settings.get().reaction_emojis,
+        issue_id,
+        thread_root_event_id,
+        state,
+    )
+    .await?;
+    if ctx.settings.get().dashboard_enabled
+        && let Err(e) = dashboard::refresh(room, &ctx.db).await
+    {
+        warn!("Failed to refresh open issues dashboard: {e:#}");
+    }
+    Ok(())
+}
+
+/// `event_id` is the command message or the reaction asking for it.
+async fn resolve(
+    ctx: &CommandContext,
+    sender: &str,
+    event_id: &str,
+    issue_id: i64,
+    comment: Option<&str>,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    if let Some(comment_text) = comment {
+        let seerr_user = db::get_user_mapping(&ctx.db, sender)
+            .await?
+            .map(|mapping| mapping.seerr_user);
+        let message = attributed_comment(comment_text, sender, seerr_user.as_deref());
+        ctx.state.forwarded_comments.remember(issue_id, &message);
+        let result = ctx.seerr_client.add_comment(issue_id, &message).await;
+        audit::record(
+            &ctx.db,
+            sender,
+            "seerr.add_comment",
+            Some(issue_id),
+            None,
+            &result,
+        )
+        .await;
+        if let Err(e) = &result
+            && pending_actions::is_unreachable(e)
+        {
+            let comment = SeerrAction::Comment {
+                message: &message,
+                comment: comment_text,
+            };
+            let actions = [comment, SeerrAction::Resolve];
+            return queue_resolve(
+                ctx,
+                sender,
+                event_id,
+                issue_id,
+                &actions,
+                room,
+                thread_root_event_id,
+            )
+            .await;
+        }
+        let seerr_comment_id = result.context(SeerrFailed)?;
+        // The command message mirrors the comment, so its webhook isn't posted again
+        db::insert_comment_event(
+            &ctx.db,
+            issue_id,
+            seerr_comment_id,
+            event_id,
+            CommentOrigin::Matrix,
+            comment_text,
+        )
+        .await?;
+        info!(issue_id, comment = %comment_text, "Added comment to issue");
+    }
+
+    let result = ctx.seerr_client.resolve_issue(issue_id).await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "seerr.resolve_issue",
+        Some(issue_id),
+        None,
+        &result,
+    )
+    .await;
+    if let Err(e) = &result
+        && pending_actions::is_unreachable(e)
+    {
+        let actions = [SeerrAction::Resolve];
+        return queue_resolve(
+            ctx,
+            sender,
+            event_id,
+            issue_id,
+            &actions,
+            room,
+            thread_root_event_id,
+        )
+        .await;
+    }
+    result.context(SeerrFailed)?;
+
+    lifecycle::apply(&ctx.db, issue_id, IssueEvent::Resolved, Some(sender)).await?;
+    info!(issue_id, "Resolved issue via command");
+    ctx.outgoing.emit(BotEvent::IssueResolved {
+        issue_id,
+        resolved_by: sender.to_string(),
+    });
+
+    let markdown = format!(
+        "**Issue {issue_id} resolved**{}",
+        webhook::seerr_link(ctx.seerr_client.issue_url(issue_id).as_deref())
+    );
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// Keeps what `!issues resolve` couldn't tell Seerr for when it is back, and
+/// says so in the thread rather than failing.
+async fn queue_resolve(
+    ctx: &CommandContext,
+    sender: &str,
+    event_id: &str,
+    issue_id: i64,
+    actions: &[SeerrAction<'_>],
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    for action in actions {
+        pending_actions::queue(
+            &ctx.db,
+            issue_id,
+            *action,
+            event_id,
+            thread_root_event_id,
+            sender,
+        )
+        .await?;
+    }
+    let markdown =
+        format!("**⏳ Seerr is unreachable, issue {issue_id} will be resolved once it is back**");
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// Resolves `duplicate_id` in Seerr as a duplicate of the thread's issue, and
+/// moves what follows about it into this thread.
+async fn merge_issue(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    issue_id: i64,
+    duplicate_id: i64,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    let sender = event.sender.as_str();
+    if duplicate_id == issue_id {
+        let markdown = format!("Issue {issue_id} can't be merged into itself");
+        matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    }
+    let Some(duplicate) = db::get_issue_event(&ctx.db, duplicate_id).await? else {
+        let markdown =
+            format!("Issue {duplicate_id} has no card, `!issues link {duplicate_id}` it first");
+        matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    };
+    if db::get_issue_status(&ctx.db, duplicate_id).await? == Some(IssueState::Resolved) {
+        let markdown = format!("Issue {duplicate_id} is already resolved");
+        matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    }
+
+    let comment = format!("Duplicate of issue #{issue_id}");
+    let seerr_user = db::get_user_mapping(&ctx.db, sender)
+        .await?
+        .map(|mapping| mapping.seerr_user);
+    let message = attributed_comment(&comment, sender, seerr_user.as_deref());
+    ctx.state
+        .forwarded_comments
+        .remember(duplicate_id, &message);
+    let result = ctx.seerr_client.add_comment(duplicate_id, &message).await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "seerr.add_comment",
+        Some(duplicate_id),
+        None,
+        &result,
+    )
+    .await;
+    let seerr_comment_id = result.context(SeerrFailed)?;
+    db::insert_comment_event(
+        &ctx.db,
+        duplicate_id,
+        seerr_comment_id,
+        event.event_id.as_str(),
+        CommentOrigin::Matrix,
+        &comment,
+    )
+    .await?;
+
+    let result = ctx.seerr_client.resolve_issue(duplicate_id).await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "seerr.resolve_issue",
+        Some(duplicate_id),
+        None,
+        &result,
+    )
+    .await;
+    result.context(SeerrFailed)?;
+    let resolved =
+        lifecycle::apply(&ctx.db, duplicate_id, IssueEvent::Resolved, Some(sender)).await?;
+
+    // A duplicate with a card of its own says there where it went
+    if duplicate.matrix_event_id != thread_root_event_id.as_str() {
+        let own_root: OwnedEventId = duplicate.matrix_event_id.as_str().try_into()?;
+        if let Some(state) = resolved {
+            reactions::transition(
+                room,
+                &ctx.db,
+                &ctx.settings.get().reaction_emojis,
+                duplicate_id,
+                &own_root,
+                state,
+            )
+            .await?;
+        }
+        let permalink =
+            matrix::event_permalink(room.room_id().as_str(), thread_root_event_id.as_str());
+        let markdown = format!("**🔁 Merged into [issue {issue_id}]({permalink})**");
+        matrix::send_thread_markdown(room, &own_root, &markdown).await?;
+    }
+    db::mark_duplicate(
+        &ctx.db,
+        duplicate_id,
+        issue_id,
+        thread_root_event_id.as_str(),
+    )
+    .await?;
+    info!(issue_id, duplicate_id, "Merged duplicate issue");
+    ctx.outgoing.emit(BotEvent::IssueResolved {
+        issue_id: duplicate_id,
+        resolved_by: sender.to_string(),
+    });
+
+    let markdown = format!("**🔁 Issue {duplicate_id} merged into issue {issue_id}**");
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// Seerr shows every comment as posted by the bot's API key, so the comment
+/// names who wrote it in Matrix, and their Seerr user when they are linked.
+pub(crate) fn attributed_comment(comment: &str, sender: &str, seerr_user: Option<&str>) -> String {
+    match seerr_user {
+        Some(seerr_user) => format!("{comment}\n\n— {seerr_user} ({sender}) via Matrix"),
+        None => format!("{comment}\n\n— {sender} via Matrix"),
+    }
+}
+
+async fn sonarr(
+    ctx: &CommandContext,
+    sender: &str,
+    issue_id: i64,
+    action: SonarrAction,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    let markdown = match (
+        &ctx.sonarr_client,
+        ctx.seerr_client
+            .get_issue(issue_id)
+            .await
+            .context(SeerrFailed)?,
+    ) {
+        (None, _) => "**⚠️ Sonarr is not configured**".to_string(),
+        (_, None) => format!("**⚠️ Issue {issue_id} not found in Seerr**"),
+        (Some(sonarr), Some(issue)) => {
+            remediation::sonarr(&ctx.db, sonarr, sender, issue_id, &issue, action).await?
+        }
+    };
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn radarr(
+    ctx: &CommandContext,
+    sender: &str,
+    issue_id: i64,
+    action: &RadarrAction,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    let markdown = match (
+        &ctx.radarr_client,
+        ctx.seerr_client
+            .get_issue(issue_id)
+            .await
+            .context(SeerrFailed)?,
+    ) {
+        (None, _) => "**⚠️ Radarr is not configured**".to_string(),
+        (_, None) => format!("**⚠️ Issue {issue_id} not found in Seerr**"),
+        (Some(radarr), Some(issue)) => {
+            remediation::radarr(&ctx.db, radarr, sender, issue_id, &issue, action).await?
+        }
+    };
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn refresh_library(
+    ctx: &CommandContext,
+    sender: &str,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let Some(jellyfin) = &ctx.jellyfin_client else {
+        let markdown = "**⚠️ Jellyfin is not configured**";
+        matrix::send_long_markdown(room, thread_root_event_id, markdown).await?;
+        return Ok(());
+    };
+    let result = jellyfin.refresh_library().await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "jellyfin.refresh_library",
+        None,
+        None,
+        &result,
+    )
+    .await;
+    result?;
+    info!("Library refresh started via command");
+
+    let markdown = "**📚 Jellyfin library refresh started**";
+    matrix::send_long_markdown(room, thread_root_event_id, markdown).await?;
+    Ok(())
+}
+
+async fn now_playing(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let markdown = match &ctx.jellyfin_client {
+        Some(jellyfin) => now_playing::render(&jellyfin.sessions().await?),
+        None => "**⚠️ Jellyfin is not configured**".to_string(),
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn queue(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let markdown = match &ctx.qbittorrent_client {
+        Some(qbittorrent) => queue::render(&qbittorrent.downloading().await?),
+        None => "**⚠️ qBittorrent is not configured**".to_string(),
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn update_request(
+    ctx: &CommandContext,
+    sender: &str,
+    request_id: i64,
+    approve: bool,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let (result, action, status) = if approve {
+        (
+            ctx.seerr_client.approve_request(request_id).await,
+            "seerr.approve_request",
+            RequestStatus::Approved,
+        )
+    } else {
+        (
+            ctx.seerr_client.decline_request(request_id).await,
+            "seerr.decline_request",
+            RequestStatus::Declined,
+        )
+    };
+    let details = format!("request {request_id}");
+    audit::record(&ctx.db, sender, action, None, Some(&details), &result).await;
+    if let Err(e) = &result
+        && let Some(markdown) = quota::reply(e, &ctx.settings.get().time_format)
+    {
+        matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    }
+    result?;
+
+    db::set_request_status(&ctx.db, request_id, status).await?;
+    info!(request_id, %status, "Updated request via command");
+
+    let markdown = format!("**Request {request_id} {status}**");
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// A request of the `!requests pending` list.
+struct PendingRequest {
+    request_id: i64,
+    title: String,
+    requested_by: String,
+    card_event_id: Option<String>,
+}
+
+/// Lists the requests waiting for approval, numbered for `!approve 2` to
+/// work without finding their card.
+async fn list_pending_requests(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let requests = ctx
+        .seerr_client
+        .list_pending_requests()
+        .await
+        .context(SeerrFailed)?;
+
+    let mut pending = Vec::new();
+    for request in requests.into_iter().take(PENDING_REQUESTS_LIMIT) {
+        let card = db::get_request_card(&ctx.db, request.id).await?;
+        let title = match card.as_ref().and_then(|card| card.subject.clone()) {
+            Some(subject) => subject,
+            None => match &request.media {
+                Some(media) => ctx
+                    .seerr_client
+                    .media_title(media)
+                    .await
+                    .unwrap_or_else(|e| {
+                        warn!(
+                            request_id = request.id,
+                            "Failed to fetch request title: {e:#}"
+                        );
+                        format!("Request {}", request.id)
+                    }),
+                None => format!("Request {}", request.id),
+            },
+        };
+        pending.push(PendingRequest {
+            request_id: request.id,
+            title,
+            requested_by: SeerrUser::name(request.requested_by.as_ref()),
+            card_event_id: card.map(|card| card.matrix_event_id),
+        });
+    }
+
+    let request_ids: Vec<i64> = pending.iter().map(|request| request.request_id).collect();
+    db::replace_request_shortcuts(&ctx.db, &request_ids).await?;
+    let markdown = render_pending_requests(room.room_id().as_str(), &pending);
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+fn render_pending_requests(room_id: &str, requests: &[PendingRequest]) -> String {
+    if requests.is_empty() {
+        return "**📥 No request waiting for approval**".to_string();
+    }
+    let mut markdown = format!(
+        "**📥 Requests waiting for approval ({})**\n\n",
+        requests.len()
+    );
+    for (number, request) in requests.iter().enumerate() {
+        let title = markdown::escape(&request.title);
+        let title = match &request.card_event_id {
+            Some(event_id) => format!("[{title}]({})", matrix::event_permalink(room_id, event_id)),
+            None => title,
+        };
+        markdown.push_str(&format!(
+            "{}. {title}, requested by {}\n",
+            number + 1,
+            markdown::escape(&request.requested_by)
+        ));
+    }
+    markdown.push_str("\nReply `!approve 1` or `!decline 1` within the hour");
+    markdown
+}
+
+async fn link_user(
+    ctx: &CommandContext,
+    matrix_user_id: &str,
+    seerr_user: &str,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let markdown = match OwnedUserId::try_from(matrix_user_id) {
+        Ok(user_id) => {
+            db::upsert_user_mapping(&ctx.db, user_id.as_str(), seerr_user).await?;
+            info!(%user_id, seerr_user, "Linked Matrix user to Seerr user");
+            format!("**🔗 {user_id} linked to Seerr user {seerr_user}**")
+        }
+        Err(_) => format!("**⚠️ {matrix_user_id} is not a valid Matrix user ID**"),
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn unlink_user(
+    ctx: &CommandContext,
+    matrix_user_id: &str,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let markdown = if db::delete_user_mapping(&ctx.db, matrix_user_id).await? {
+        info!(matrix_user_id, "Unlinked Matrix user");
+        format!("**{matrix_user_id} unlinked**")
+    } else {
+        format!("**{matrix_user_id} is not linked to a Seerr user**")
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn reload_config(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let result = ctx.settings.reload();
+    let markdown = match &result {
+        Ok(()) => "**🔄 Configuration reloaded**".to_string(),
+        Err(e) => format!("**⚠️ Configuration not reloaded**  \n```\n{e:#}\n```"),
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    result
+}
+
+async fn pause(
+    ctx: &CommandContext,
+    duration: chrono::Duration,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let until = quiet_hours::pause(&ctx.state, duration).await?;
+    let markdown = format!(
+        "**⏸️ Notifications paused until {}**  \n\
+         They are kept and posted when the pause ends, or on `!bot resume`",
+        ctx.settings.get().time_format.datetime(until)
+    );
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn resume(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    // Replies first, the notifications held follow
+    if !quiet_hours::is_paused(&ctx.state) {
+        let markdown = "**Notifications are not paused**";
+        matrix::send_long_markdown(room, thread_root_event_id, markdown).await?;
+        return Ok(());
+    }
+    matrix::send_long_markdown(room, thread_root_event_id, "**▶️ Notifications resumed**").await?;
+    quiet_hours::resume(&ctx.state).await?;
+    Ok(())
+}
+
+async fn route(
+    ctx: &CommandContext,
+    sender: &str,
+    stream: Stream,
+    enabled: bool,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let room_id = room.room_id().as_str();
+    db::set_room_route(&ctx.db, room_id, stream.name(), enabled, sender).await?;
+    info!(%stream, enabled, room_id, "Notification stream routed");
+    let markdown = if enabled {
+        format!("**🔀 {stream} notifications are posted in this room**")
+    } else {
+        format!("**🔇 {stream} notifications are no longer posted in this room**")
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn list_routes(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let routes = db::list_room_routes(&ctx.db, room.room_id().as_str()).await?;
+    let markdown = routing::render(&routes);
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// Posts the announcement, which is the reply, unless a maintenance is
+/// already running.
+async fn start_maintenance(
+    ctx: &CommandContext,
+    sender: &str,
+    reason: &str,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    if let Some(window) = ctx.alerts.maintenance.window() {
+        let markdown = format!(
+            "**A maintenance is already running: {}**  \n\
+             End it with `!maintenance end` first",
+            markdown::escape(&window.reason)
+        );
+        matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    }
+    maintenance::start(&ctx.state, reason, sender).await?;
+    Ok(())
+}
+
+/// Posts the summary, which is the reply, when a maintenance was running.
+async fn end_maintenance(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    if !maintenance::end(&ctx.state).await? {
+        let markdown = "**No maintenance is running**";
+        matrix::send_long_markdown(room, thread_root_event_id, markdown).await?;
+    }
+    Ok(())
+}
+
+async fn list_issues(
+    ctx: &CommandContext,
+    category: Option<IssueCategory>,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let issues: Vec<TrackedIssue> = db::list_unresolved_issues(&ctx.db)
+        .await?
+        .into_iter()
+        .filter(|issue| {
+            category.is_none_or(|category| issue.category.as_deref() == Some(category.as_str()))
+        })
+        .collect();
+    let markdown = render_issues(room.room_id().as_str(), category, &issues);
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+fn render_issues(
+    room_id: &str,
+    category: Option<IssueCategory>,
+    issues: &[TrackedIssue],
+) -> String {
+    let title = match category {
+        Some(category) => format!("{} Unresolved {category} issues", category.icon()),
+        None => "📋 Unresolved issues".to_string(),
+    };
+    if issues.is_empty() {
+        return format!("**{title}**  \nNothing to do 🎉");
+    }
+
+    let mut markdown = format!("**{title} ({})**\n", issues.len());
+    for issue in issues {
+        markdown.push_str(&issue_line(room_id, issue));
+    }
+    markdown
+}
+
+/// List item of an issue, linking to its thread when it has a card.
+pub(crate) fn issue_line(room_id: &str, issue: &TrackedIssue) -> String {
+    let subject = webhook::issue_subject(
+        issue.subject.as_deref().unwrap_or("Untitled issue"),
+        issue.episodes(),
+    );
+    let subject = markdown::escape(&subject);
+    let icon = issue
+        .category
+        .as_deref()
+        .and_then(IssueCategory::parse)
+        .map(|c| format!("{} ", c.icon()))
+        .unwrap_or_default();
+    match &issue.matrix_event_id {
+        Some(event_id) => format!(
+            "- [#{}]({}) {icon}{subject} ({})\n",
+            issue.issue_id,
+            matrix::event_permalink(room_id, event_id),
+            issue.status
+        ),
+        None => format!("- #{} {icon}{subject} ({})\n", issue.issue_id, issue.status),
+    }
+}
+
+/// Issues, resolved ones included, whose subject, description or comments
+/// match `query`, to find out whether a problem was already fixed before.
+async fn search_issues(
+    ctx: &CommandContext,
+    query: &str,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let issues = db::search_issues(&ctx.db, query, SEARCH_LIMIT).await?;
+    let markdown = render_search(room.room_id().as_str(), query, &issues);
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+fn render_search(room_id: &str, query: &str, issues: &[TrackedIssue]) -> String {
+    let title = format!("🔍 Issues matching \"{}\"", markdown::escape(query));
+    if issues.is_empty() {
+        return format!("**{title}**  \nNo issue found");
+    }
+
+    let mut markdown = format!("**{title} ({})**\n", issues.len());
+    for issue in issues {
+        markdown.push_str(&issue_line(room_id, issue));
+    }
+    markdown
+}
+
+/// Resolved issues, the most recent first, with the comment they were
+/// resolved with, to find out whether a problem was already fixed.
+async fn archive_issues(
+    ctx: &CommandContext,
+    limit: i64,
+    page: i64,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let issues = db::list_resolved_issues(&ctx.db, limit, (page - 1) * limit).await?;
+    let total = db::count_resolved_issues(&ctx.db).await?;
+    let issue_ids: Vec<i64> = issues.iter().map(|issue| issue.issue_id).collect();
+    let comments: HashMap<i64, String> = db::resolution_comments(&ctx.db, &issue_ids)
+        .await?
+        .into_iter()
+        .collect();
+    let archive = Archive {
+        limit,
+        page,
+        total,
+        issues: &issues,
+        comments: &comments,
+    };
+    let time_format = ctx.settings.get().time_format;
+    let markdown = render_archive(room.room_id().as_str(), &archive, &time_format);
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+/// Uploads the issues created during `period` as a CSV or JSON file, for
+/// spreadsheets and reports.
+async fn export_issues(
+    ctx: &CommandContext,
+    period: ExportPeriod,
+    format: ExportFormat,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let now = chrono::Utc::now();
+    let issues = db::list_issues_created_since(&ctx.db, period.since(now)).await?;
+    if issues.is_empty() {
+        let markdown = format!("**📤 No issue to export for {period}**");
+        matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+        return Ok(());
+    }
+    let data = export::render(&issues, format)?;
+    let filename = format!(
+        "issues-{period}-{}.{}",
+        now.format("%Y-%m-%d"),
+        format.extension()
+    );
+    matrix::send_file(
+        room,
+        thread_root_event_id,
+        &filename,
+        &format.mimetype(),
+        data,
+    )
+    .await?;
+    info!(issues = issues.len(), %period, "Exported issues");
+    Ok(())
+}
+
+/// A page of `!issues archive`.
+struct Archive<'a> {
+    limit: i64,
+    page: i64,
+    /// Resolved issues on all pages.
+    total: i64,
+    issues: &'a [TrackedIssue],
+    /// Resolution comment of the issues that have one.
+    comments: &'a HashMap<i64, String>,
+}
+
+fn render_archive(room_id: &str, archive: &Archive, time_format: &TimeFormat) -> String {
+    if archive.total == 0 {
+        return "**📚 No resolved issue yet**".to_string();
+    }
+    let pages = (archive.total + archive.limit - 1) / archive.limit;
+    if archive.issues.is_empty() {
+        return format!(
+            "**📚 No resolved issue on page {}**  \nThere are {pages} pages of {} issues",
+            archive.page, archive.limit
+        );
+    }
+
+    let mut markdown = format!(
+        "**📚 Resolved issues, page {} of {pages}**\n\n\
+         | Issue | Resolved | By | Comment |\n\
+         |---|---|---|---|\n",
+        archive.page
+    );
+    for issue in archive.issues {
+        let subject = webhook::issue_subject(
+            issue.subject.as_deref().unwrap_or("Untitled issue"),
+            issue.episodes(),
+        );
+        let subject = markdown::escape(&subject);
+        let issue_cell = match &issue.matrix_event_id {
+            Some(event_id) => format!(
+                "[#{}]({}) {subject}",
+                issue.issue_id,
+                matrix::event_permalink(room_id, event_id)
+            ),
+            None => format!("#{} {subject}", issue.issue_id),
+        };
+        let resolved_at = issue
+            .resolved_at
+            .map(|at| time_format.date(at))
+            .unwrap_or_default();
+        let resolved_by = issue.resolved_by.as_deref().unwrap_or("Seerr");
+        let comment = archive
+            .comments
+            .get(&issue.issue_id)
+            .map(|comment| markdown::escape(&shorten_comment(comment)))
+            .unwrap_or_default();
+        markdown.push_str(&format!(
+            "| {issue_cell} | {resolved_at} | {} | {comment} |\n",
+            markdown::escape(resolved_by)
+        ));
+    }
+    if archive.page < pages {
+        markdown.push_str(&format!(
+            "\nNext page: `!issues archive last {} page {}`",
+            archive.limit,
+            archive.page + 1
+        ));
+    }
+    markdown
+}
+
+/// The comment on a single line of at most [`ARCHIVE_COMMENT_MAX_CHARS`].
+fn shorten_comment(comment: &str) -> String {
+    let line = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= ARCHIVE_COMMENT_MAX_CHARS {
+        return line;
+    }
+    let cut: String = line.chars().take(ARCHIVE_COMMENT_MAX_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+async fn link_issue(
+    ctx: &CommandContext,
+    issue_id: i64,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let markdown = match reconcile::adopt(&ctx.state, issue_id).await? {
+        Adoption::Posted => {
+            format!("**🔗 Issue {issue_id} linked**, reply in its thread to handle it")
+        }
+        Adoption::AlreadyTracked => format!("Issue {issue_id} already has a card"),
+        Adoption::Resolved => format!("Issue {issue_id} is already resolved in Seerr"),
+        Adoption::NotFound => format!("Issue {issue_id} was not found in Seerr"),
+    };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
 }
 
-pub async fn on_room_message(
-    event: OriginalSyncRoomMessageEvent,
-    room: Room,
-    ctx: Ctx<Arc<CommandContext>>,
-) {
-    if let Err(e) = handle_message(event, &room, &ctx).await {
-        error!("Error handling command: {e:#}");
-    }
+async fn list_users(
+    ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let mappings = db::list_user_mappings(&ctx.db).await?;
+    matrix::send_long_markdown(room, thread_root_event_id, &render_users(&mappings)).await?;
+    Ok(())
 }
 
-async fn handle_message(
-    event: OriginalSyncRoomMessageEvent,
+/// Reminders are posted in the thread of the command, which starts one when
+/// sent outside of a thread.
+async fn set_reminder(
+    ctx: &CommandContext,
+    sender: &str,
+    delay: chrono::Duration,
+    message: &str,
     room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    let due_at = chrono::Utc::now() + delay;
+    let id = db::insert_user_reminder(
+        &ctx.db,
+        &db::NewUserReminder {
+            matrix_room_id: room.room_id().as_str(),
+            thread_root_event_id: thread_root_event_id.as_str(),
+            author: sender,
+            message,
+            due_at,
+        },
+    )
+    .await?;
+    info!(reminder_id = id, %due_at, "Reminder set");
+
+    let markdown = format!(
+        "**⏰ Reminder #{id} set for {}**",
+        ctx.settings.get().time_format.datetime(due_at)
+    );
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
+
+async fn list_reminders(
     ctx: &CommandContext,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
 ) -> anyhow::Result<()> {
-    if !ctx.admin_users.iter().any(|u| u == &event.sender) {
-        return Ok(());
-    }
+    let reminders = db::list_pending_user_reminders(&ctx.db, room.room_id().as_str(), None).await?;
+    let markdown = remind::render_list(&reminders, &ctx.settings.get().time_format);
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
 
-    let body = event.content.body();
-    let command = match parse_command(body) {
-        Some(cmd) => cmd,
-        None => return Ok(()),
+async fn cancel_reminder(
+    ctx: &CommandContext,
+    id: i64,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let markdown = if db::cancel_user_reminder(&ctx.db, room.room_id().as_str(), id).await? {
+        info!(reminder_id = id, "Reminder cancelled");
+        format!("**Reminder #{id} cancelled**")
+    } else {
+        format!("**No pending reminder #{id}**")
     };
+    matrix::send_long_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
 
-    match command {
-        Command::Resolve { comment } => {
-            let thread_root_event_id = match &event.content.relates_to {
-                Some(Relation::Thread(thread)) => &thread.event_id,
-                _ => {
-                    warn!("!issues resolve must be sent as a thread reply");
-                    return Ok(());
-                }
-            };
+fn render_users(mappings: &[UserMapping]) -> String {
+    if mappings.is_empty() {
+        return "**👥 Linked users**  \nNo users linked yet, use `!users link @user:server seerr-user`"
+            .to_string();
+    }
 
-            let issue_event =
-                db::get_issue_event_by_matrix_event_id(&ctx.db, thread_root_event_id.as_str())
-                    .await?;
-
-            let issue_event = match issue_event {
-                Some(ev) => ev,
-                None => {
-                    warn!(
-                        event_id = %thread_root_event_id,
-                        "No issue found for thread root event"
-                    );
-                    return Ok(());
-                }
-            };
+    let mut markdown = format!("**👥 Linked users ({})**\n", mappings.len());
+    for mapping in mappings {
+        markdown.push_str(&format!(
+            "- {} → {}\n",
+            mapping.matrix_user_id, mapping.seerr_user
+        ));
+    }
+    markdown
+}
+
+async fn stats(
+    ctx: &CommandContext,
+    days: i64,
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> anyhow::Result<()> {
+    let stats = stats::collect(&ctx.db, days).await?;
+    matrix::send_long_markdown(
+        room,
+        thread_root_event_id,
+        &stats::render(&stats, &ctx.settings.get().time_format),
+    )
+    .await?;
+    Ok(())
+}
 
-            let issue_id = issue_event.issue_id;
+async fn history(
+    ctx: &CommandContext,
+    issue_id: i64,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    let entries = db::list_audit_entries(&ctx.db, Some(issue_id), HISTORY_LIMIT).await?;
+    let markdown = render_history(issue_id, &entries, &ctx.settings.get().time_format);
+    matrix::send_long_markdown(room, Some(thread_root_event_id), &markdown).await?;
+    Ok(())
+}
 
-            if let Some(ref comment_text) = comment {
-                ctx.seerr_client.add_comment(issue_id, comment_text).await?;
-                info!(issue_id, comment = %comment_text, "Added comment to issue");
-            }
+async fn mute_reminders(
+    ctx: &CommandContext,
+    issue_id: i64,
+    muted: bool,
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+) -> anyhow::Result<()> {
+    db::set_reminders_muted(&ctx.db, issue_id, muted).await?;
+    info!(issue_id, muted, "Changed stale issue reminders");
+    let markdown = if muted {
+        format!("**🔕 Reminders muted for issue {issue_id}**")
+    } else {
+        format!("**🔔 Reminders unmuted for issue {issue_id}**")
+    };
+    matrix::send_thread_markdown(room, thread_root_event_id, &markdown).await?;
+    Ok(())
+}
 
-            ctx.seerr_client.resolve_issue(issue_id).await?;
-            info!(issue_id, "Resolved issue via command");
+fn render_history(issue_id: i64, entries: &[AuditEntry], time_format: &TimeFormat) -> String {
+    if entries.is_empty() {
+        return format!("**📜 History of issue {issue_id}**  \nNothing recorded yet");
+    }
 
-            let plain = format!("Issue {issue_id} resolved");
-            let html = format!("<b>Issue {issue_id} resolved</b>");
-            matrix::send_thread_reply(room, thread_root_event_id, &plain, &html).await?;
+    let mut markdown = format!("**📜 History of issue {issue_id}**\n");
+    for entry in entries.iter().rev() {
+        let outcome = if entry.success { "" } else { " ❌" };
+        markdown.push_str(&format!(
+            "- {} `{}` by {}{outcome}",
+            time_format.datetime(entry.created_at),
+            entry.action,
+            entry.actor,
+        ));
+        if let Some(details) = &entry.details {
+            markdown.push_str(&format!(": {details}"));
         }
+        markdown.push('\n');
     }
-
-    Ok(())
+    markdown
 }
 
 #[cfg(test)]
@@ -160,6 +2169,232 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_ack() {
+        assert_eq!(parse_command("!issues ack"), Some(Command::Acknowledge));
+        assert_eq!(parse_command("!issues ack now"), None);
+    }
+
+    #[test]
+    fn attributes_comments_to_matrix_sender() {
+        assert_eq!(
+            attributed_comment("Fixed the subtitles", "@alice:localhost", None),
+            "Fixed the subtitles\n\n— @alice:localhost via Matrix"
+        );
+        assert_eq!(
+            attributed_comment("Fixed the subtitles", "@alice:localhost", Some("alice")),
+            "Fixed the subtitles\n\n— alice (@alice:localhost) via Matrix"
+        );
+    }
+
+    #[test]
+    fn parse_link_issue() {
+        assert_eq!(
+            parse_command("!issues link 57"),
+            Some(Command::LinkIssue { issue_id: 57 })
+        );
+        assert_eq!(
+            parse_command("!issues link #57"),
+            Some(Command::LinkIssue { issue_id: 57 })
+        );
+        assert_eq!(parse_command("!issues link"), None);
+        assert_eq!(parse_command("!issues link abc"), None);
+    }
+
+    #[test]
+    fn parse_merge_issue() {
+        assert_eq!(
+            parse_command("!issues merge #57"),
+            Some(Command::MergeIssue { duplicate_id: 57 })
+        );
+        assert_eq!(parse_command("!issues merge"), None);
+    }
+
+    #[test]
+    fn parse_list_issues() {
+        assert_eq!(
+            parse_command("!issues list"),
+            Some(Command::ListIssues { category: None })
+        );
+        assert_eq!(
+            parse_command("!issues list --category subtitles"),
+            Some(Command::ListIssues {
+                category: Some(IssueCategory::Subtitles),
+            })
+        );
+        assert_eq!(parse_command("!issues list --category lighting"), None);
+        assert_eq!(parse_command("!issues list subtitles"), None);
+    }
+
+    #[test]
+    fn parse_search_issues() {
+        let search = |query: &str| {
+            Some(Command::SearchIssues {
+                query: query.to_string(),
+            })
+        };
+        assert_eq!(
+            parse_command(r#"!issues search "subtitles""#),
+            search("subtitles")
+        );
+        assert_eq!(
+            parse_command("!issues search out of sync"),
+            search("out of sync")
+        );
+        assert_eq!(parse_command(r#"!issues search """#), None);
+        assert_eq!(parse_command("!issues search"), None);
+    }
+
+    #[test]
+    fn render_issues_with_categories() {
+        let issue = TrackedIssue {
+            issue_id: 42,
+            matrix_event_id: Some("$abc".to_string()),
+            subject: Some("Dune".to_string()),
+            reported_by: None,
+            category: Some("subtitles".to_string()),
+            problem_season: None,
+            problem_episode: None,
+            status: "open".to_string(),
+            comment_count: 0,
+            created_at: chrono::Utc::now(),
+            first_response_at: None,
+            resolved_at: None,
+            resolved_by: None,
+            acknowledged_by: None,
+        };
+
+        let markdown = render_issues("!room:localhost", Some(IssueCategory::Subtitles), &[issue]);
+        assert!(markdown.starts_with("**🔤 Unresolved subtitles issues (1)**"));
+        assert!(
+            markdown.contains("- [#42](https://matrix.to/#/!room:localhost/$abc) 🔤 Dune (open)")
+        );
+
+        let empty = render_issues("!room:localhost", None, &[]);
+        assert!(empty.contains("Nothing to do"));
+    }
+
+    #[test]
+    fn render_search_results() {
+        let issue = TrackedIssue {
+            issue_id: 7,
+            matrix_event_id: Some("$def".to_string()),
+            subject: Some("Alien".to_string()),
+            reported_by: None,
+            category: None,
+            problem_season: None,
+            problem_episode: None,
+            status: "resolved".to_string(),
+            comment_count: 2,
+            created_at: chrono::Utc::now(),
+            first_response_at: None,
+            resolved_at: None,
+            resolved_by: None,
+            acknowledged_by: None,
+        };
+
+        let markdown = render_search("!room:localhost", "subtitles", &[issue]);
+        assert_eq!(
+            markdown,
+            "**🔍 Issues matching \"subtitles\" (1)**\n\
+             - [#7](https://matrix.to/#/!room:localhost/$def) Alien (resolved)\n"
+        );
+
+        let empty = render_search("!room:localhost", "subtitles", &[]);
+        assert!(empty.contains("No issue found"));
+    }
+
+    #[test]
+    fn parse_archive() {
+        let archive = |limit, page| Some(Command::ArchiveIssues { limit, page });
+        assert_eq!(parse_command("!issues archive"), archive(20, 1));
+        assert_eq!(parse_command("!issues archive last 5"), archive(5, 1));
+        assert_eq!(parse_command("!issues archive page 3"), archive(20, 3));
+        assert_eq!(
+            parse_command("!issues archive last 10 page 2"),
+            archive(10, 2)
+        );
+        assert_eq!(parse_command("!issues archive last 500"), None);
+        assert_eq!(parse_command("!issues archive page 0"), None);
+    }
+
+    #[test]
+    fn parse_export() {
+        let export = |period, format| Some(Command::ExportIssues { period, format });
+        assert_eq!(
+            parse_command("!issues export"),
+            export(ExportPeriod::LastMonth, ExportFormat::Csv)
+        );
+        assert_eq!(
+            parse_command("!issues export last-quarter"),
+            export(ExportPeriod::LastQuarter, ExportFormat::Csv)
+        );
+        assert_eq!(
+            parse_command("!issues export all json"),
+            export(ExportPeriod::All, ExportFormat::Json)
+        );
+        assert_eq!(parse_command("!issues export last-quarter xml"), None);
+        assert_eq!(parse_command("!issues export yesterday"), None);
+    }
+
+    #[test]
+    fn render_archive_as_a_table() {
+        use chrono::TimeZone;
+
+        let resolved_at = chrono::Utc.with_ymd_and_hms(2026, 3, 9, 21, 0, 0).unwrap();
+        let issue = TrackedIssue {
+            issue_id: 7,
+            matrix_event_id: Some("$def".to_string()),
+            subject: Some("Dune".to_string()),
+            reported_by: None,
+            category: None,
+            problem_season: None,
+            problem_episode: None,
+            status: "resolved".to_string(),
+            comment_count: 1,
+            created_at: resolved_at,
+            first_response_at: None,
+            resolved_at: Some(resolved_at),
+            resolved_by: Some("alice".to_string()),
+            acknowledged_by: None,
+        };
+        let comments = HashMap::from([(7, "Replaced the file,\nthe audio is in sync".to_string())]);
+        let archive = Archive {
+            limit: 1,
+            page: 1,
+            total: 2,
+            issues: &[issue],
+            comments: &comments,
+        };
+        assert_eq!(
+            render_archive("!room:localhost", &archive, &TimeFormat::default()),
+            "**📚 Resolved issues, page 1 of 2**\n\n\
+             | Issue | Resolved | By | Comment |\n\
+             |---|---|---|---|\n\
+             | [#7](https://matrix.to/#/!room:localhost/$def) Dune | 2026-03-09 | alice | \
+             Replaced the file\\, the audio is in sync |\n\
+             \nNext page: `!issues archive last 1 page 2`"
+        );
+
+        let empty = Archive {
+            limit: 20,
+            page: 1,
+            total: 0,
+            issues: &[],
+            comments: &HashMap::new(),
+        };
+        assert_eq!(
+            render_archive("!room:localhost", &empty, &TimeFormat::default()),
+            "**📚 No resolved issue yet**"
+        );
+    }
+
+    #[test]
+    fn parse_config_reload() {
+        assert_eq!(parse_command("!config reload"), Some(Command::ReloadConfig));
+        assert_eq!(parse_command("!config"), None);
+    }
+
     #[test]
     fn parse_unrelated_message() {
         assert_eq!(parse_command("hello world"), None);
@@ -177,4 +2412,290 @@ mod tests {
             Some(Command::Resolve { comment: None })
         );
     }
+
+    #[test]
+    fn parse_history() {
+        assert_eq!(parse_command("!issues history"), Some(Command::History));
+        assert_eq!(parse_command("!issues history please"), None);
+    }
+
+    #[test]
+    fn parse_mute() {
+        assert_eq!(
+            parse_command("!issues mute"),
+            Some(Command::MuteReminders { muted: true })
+        );
+        assert_eq!(
+            parse_command("!issues unmute"),
+            Some(Command::MuteReminders { muted: false })
+        );
+    }
+
+    #[test]
+    fn render_history_lists_entries_oldest_first() {
+        use chrono::TimeZone;
+
+        let entries = vec![
+            AuditEntry {
+                created_at: chrono::Utc.with_ymd_and_hms(2025, 3, 2, 9, 30, 0).unwrap(),
+                actor: "@admin:localhost".to_string(),
+                action: "command.resolve".to_string(),
+                issue_id: Some(42),
+                details: Some("!issues resolve".to_string()),
+                success: true,
+            },
+            AuditEntry {
+                created_at: chrono::Utc.with_ymd_and_hms(2025, 3, 1, 8, 0, 0).unwrap(),
+                actor: "seerr".to_string(),
+                action: "webhook.ISSUE_CREATED".to_string(),
+                issue_id: Some(42),
+                details: None,
+                success: false,
+            },
+        ];
+
+        let markdown = render_history(42, &entries, &TimeFormat::default());
+        let created = markdown.find("webhook.ISSUE_CREATED").unwrap();
+        let resolved = markdown.find("command.resolve").unwrap();
+        assert!(created < resolved);
+        assert!(markdown.contains("2025-03-01 08:00 `webhook.ISSUE_CREATED` by seerr ❌"));
+        assert!(markdown.contains("by @admin:localhost: !issues resolve"));
+    }
+
+    #[test]
+    fn parse_sonarr_commands() {
+        assert_eq!(
+            parse_command("!sonarr search"),
+            Some(Command::Sonarr {
+                action: SonarrAction::Search
+            })
+        );
+        assert_eq!(
+            parse_command("!sonarr delete-and-redownload"),
+            Some(Command::Sonarr {
+                action: SonarrAction::Redownload
+            })
+        );
+        assert_eq!(parse_command("!sonarr delete"), None);
+    }
+
+    #[test]
+    fn parse_radarr_commands() {
+        assert_eq!(
+            parse_command("!radarr search"),
+            Some(Command::Radarr {
+                action: RadarrAction::Search
+            })
+        );
+        assert_eq!(
+            parse_command("!radarr delete 720p"),
+            Some(Command::Radarr {
+                action: RadarrAction::Delete {
+                    quality: Some("720p".to_string())
+                }
+            })
+        );
+        assert_eq!(
+            parse_command("!radarr delete"),
+            Some(Command::Radarr {
+                action: RadarrAction::Delete { quality: None }
+            })
+        );
+        assert_eq!(parse_command("!radarr delete 720p now"), None);
+    }
+
+    #[test]
+    fn parse_library_refresh() {
+        assert_eq!(
+            parse_command("!library refresh"),
+            Some(Command::RefreshLibrary)
+        );
+        assert_eq!(parse_command("!library"), None);
+        assert_eq!(parse_command("!nowplaying"), Some(Command::NowPlaying));
+        assert_eq!(parse_command("!queue"), Some(Command::Queue));
+    }
+
+    #[test]
+    fn parse_request_commands() {
+        assert_eq!(
+            parse_command("!requests approve"),
+            Some(Command::ApproveRequest)
+        );
+        assert_eq!(
+            parse_command(" !requests decline "),
+            Some(Command::DeclineRequest)
+        );
+        assert_eq!(parse_command("!requests delete"), None);
+    }
+
+    #[test]
+    fn parse_user_commands() {
+        assert_eq!(
+            parse_command("!users link @alice:home.lab alice@example.com"),
+            Some(Command::LinkUser {
+                matrix_user_id: "@alice:home.lab".to_string(),
+                seerr_user: "alice@example.com".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_command("!users unlink @alice:home.lab"),
+            Some(Command::UnlinkUser {
+                matrix_user_id: "@alice:home.lab".to_string(),
+            })
+        );
+        assert_eq!(parse_command("!users list"), Some(Command::ListUsers));
+        assert_eq!(parse_command("!users link @alice:home.lab"), None);
+    }
+
+    #[test]
+    fn parse_reminder_commands() {
+        assert_eq!(
+            parse_command("!remind 3d check subtitles on issue 42"),
+            Some(Command::Remind {
+                delay: chrono::Duration::days(3),
+                message: "check subtitles on issue 42".to_string(),
+            })
+        );
+        assert_eq!(parse_command("!remind 3d"), None);
+        assert_eq!(parse_command("!remind later check subtitles"), None);
+        assert_eq!(
+            parse_command("!reminders list"),
+            Some(Command::ListReminders)
+        );
+        assert_eq!(
+            parse_command("!reminders cancel #4"),
+            Some(Command::CancelReminder { id: 4 })
+        );
+        assert_eq!(parse_command("!reminders cancel"), None);
+    }
+
+    #[test]
+    fn parse_maintenance() {
+        let start = |reason: &str| {
+            Some(Command::StartMaintenance {
+                reason: reason.to_string(),
+            })
+        };
+        assert_eq!(
+            parse_command(r#"!maintenance start "Upgrading Jellyfin""#),
+            start("Upgrading Jellyfin")
+        );
+        assert_eq!(
+            parse_command("!maintenance start NAS reboot"),
+            start("NAS reboot")
+        );
+        assert_eq!(
+            parse_command("!maintenance end"),
+            Some(Command::EndMaintenance)
+        );
+        assert_eq!(parse_command("!maintenance start"), None);
+        assert_eq!(parse_command("!maintenance"), None);
+    }
+
+    #[test]
+    fn parse_pending_requests() {
+        assert_eq!(
+            parse_command("!requests pending"),
+            Some(Command::ListPendingRequests)
+        );
+        assert_eq!(
+            parse_command("!approve 2"),
+            Some(Command::UpdateNumberedRequest {
+                number: 2,
+                approve: true,
+            })
+        );
+        assert_eq!(
+            parse_command("!decline #3"),
+            Some(Command::UpdateNumberedRequest {
+                number: 3,
+                approve: false,
+            })
+        );
+        assert_eq!(parse_command("!approve 0"), None);
+        assert_eq!(parse_command("!approve all"), None);
+    }
+
+    #[test]
+    fn render_pending_requests_with_numbers() {
+        let requests = [
+            PendingRequest {
+                request_id: 12,
+                title: "Dune".to_string(),
+                requested_by: "alice".to_string(),
+                card_event_id: Some("$card:localhost".to_string()),
+            },
+            PendingRequest {
+                request_id: 15,
+                title: "Severance".to_string(),
+                requested_by: "bob".to_string(),
+                card_event_id: None,
+            },
+        ];
+        let markdown = render_pending_requests("!room:localhost", &requests);
+        assert!(markdown.starts_with("**📥 Requests waiting for approval (2)**"));
+        assert!(
+            markdown.contains("1. [Dune](https://matrix.to/#/!room:localhost/$card:localhost), ")
+        );
+        assert!(markdown.contains("2. Severance, requested by bob\n"));
+        assert!(markdown.ends_with("Reply `!approve 1` or `!decline 1` within the hour"));
+
+        let empty = render_pending_requests("!room:localhost", &[]);
+        assert_eq!(empty, "**📥 No request waiting for approval**");
+    }
+
+    #[test]
+    fn parse_route() {
+        assert_eq!(
+            parse_command("!bot route issues here"),
+            Some(Command::Route {
+                stream: Stream::Issues,
+                enabled: true,
+            })
+        );
+        assert_eq!(
+            parse_command("!bot route sonarr off"),
+            Some(Command::Route {
+                stream: Stream::Sonarr,
+                enabled: false,
+            })
+        );
+        assert_eq!(parse_command("!bot route"), Some(Command::ListRoutes));
+        assert_eq!(parse_command("!bot route lidarr off"), None);
+        assert_eq!(parse_command("!bot route sonarr maybe"), None);
+    }
+
+    #[test]
+    fn parse_pause() {
+        assert_eq!(
+            parse_command("!bot pause 2h"),
+            Some(Command::Pause {
+                duration: chrono::Duration::hours(2),
+            })
+        );
+        assert_eq!(parse_command("!bot resume"), Some(Command::Resume));
+        assert_eq!(parse_command("!bot pause"), None);
+        assert_eq!(parse_command("!bot pause soon"), None);
+    }
+
+    #[test]
+    fn parse_stats() {
+        assert_eq!(parse_command("!stats"), Some(Command::Stats { days: 30 }));
+        assert_eq!(parse_command("!stats 7"), Some(Command::Stats { days: 7 }));
+        assert_eq!(parse_command("!stats 0"), None);
+        assert_eq!(parse_command("!stats week"), None);
+    }
+    #[test]
+    fn only_seerr_failures_are_blamed_on_seerr() {
+        let error = anyhow::anyhow!("404 Not Found").context(SeerrFailed);
+        let outcome = CommandOutcome::of(Err(error));
+        assert_eq!(outcome_label(&outcome), "seerr_error");
+        let reply = outcome.unwrap().reply().unwrap();
+        assert!(reply.ends_with("404 Not Found"));
+
+        let outcome = CommandOutcome::of(Err(anyhow::anyhow!("database is down")));
+        assert_eq!(outcome_label(&outcome), "error");
+        assert_eq!(outcome_label(&CommandOutcome::of(Ok(()))), "success");
+        assert_eq!(CommandOutcome::Success.reply(), None);
+    }
 }
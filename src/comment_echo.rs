@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long after a comment is sent to Seerr its webhook is taken for the
+/// echo of it. Seerr usually fires it within a second.
+const ECHO_WINDOW: Duration = Duration::from_secs(120);
+
+/// Comments sent to Seerr from issue threads. Seerr sends them back in an
+/// `ISSUE_COMMENT` webhook right away, sometimes before telling the bot the
+/// id of the comment, and they must not be posted in the thread twice.
+#[derive(Debug, Default)]
+pub struct ForwardedComments {
+    recent: Mutex<HashMap<u64, Vec<Instant>>>,
+}
+
+impl ForwardedComments {
+    /// Call before sending the comment, its webhook may arrive before Seerr
+    /// answers.
+    pub fn remember(&self, issue_id: i64, message: &str) {
+        self.remember_at(issue_id, message, Instant::now());
+    }
+
+    /// Whether the comment of a webhook is the echo of one sent from the
+    /// thread of the issue. Each comment sent silences a single webhook.
+    pub fn is_echo(&self, issue_id: i64, message: &str) -> bool {
+        self.is_echo_at(issue_id, message, Instant::now())
+    }
+
+    fn remember_at(&self, issue_id: i64, message: &str, now: Instant) {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        expire(&mut recent, now);
+        recent.entry(key(issue_id, message)).or_default().push(now);
+    }
+
+    fn is_echo_at(&self, issue_id: i64, message: &str, now: Instant) -> bool {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        expire(&mut recent, now);
+        let key = key(issue_id, message);
+        let Some(sent) = recent.get_mut(&key) else {
+            return false;
+        };
+        sent.remove(0);
+        if sent.is_empty() {
+            recent.remove(&key);
+        }
+        true
+    }
+}
+
+fn expire(recent: &mut HashMap<u64, Vec<Instant>>, now: Instant) {
+    recent.retain(|_, sent| {
+        sent.retain(|at| now.duration_since(*at) < ECHO_WINDOW);
+        !sent.is_empty()
+    });
+}
+
+/// Seerr keeps the comment as sent, but for the surrounding whitespace.
+fn key(issue_id: i64, message: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (issue_id, message.trim()).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn echoes_are_recognized_once() {
+        let comments = ForwardedComments::default();
+        let now = Instant::now();
+        comments.remember_at(42, "Fixed, the file was replaced", now);
+
+        assert!(!comments.is_echo_at(7, "Fixed, the file was replaced", now));
+        assert!(!comments.is_echo_at(42, "Still broken", now));
+        assert!(comments.is_echo_at(42, "Fixed, the file was replaced\n", now));
+        // Someone writing the same thing in Seerr afterwards
+        assert!(!comments.is_echo_at(42, "Fixed, the file was replaced", now));
+    }
+
+    #[test]
+    fn echoes_expire() {
+        let comments = ForwardedComments::default();
+        let now = Instant::now();
+        comments.remember_at(42, "Thanks", now);
+        assert!(!comments.is_echo_at(42, "Thanks", now + ECHO_WINDOW));
+    }
+}
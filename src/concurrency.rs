@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+pub const DEFAULT_MAX_CONCURRENT: usize = 8;
+
+/// Bounds how many webhooks and commands are handled at once, and runs those
+/// about the same issue one after the other, in arrival order, so e.g. a
+/// resolve command and a comment webhook can't interleave their Seerr calls
+/// and Matrix messages.
+pub struct Limiter {
+    permits: Arc<Semaphore>,
+    issues: Mutex<HashMap<i64, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+/// Held while handling one webhook or command.
+pub struct Permit {
+    _global: OwnedSemaphorePermit,
+    _issue: Option<OwnedMutexGuard<()>>,
+}
+
+impl Limiter {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            issues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Waits for the turn of work about `issue_id`, if any, then for a free
+    /// slot.
+    pub async fn acquire(&self, issue_id: Option<i64>) -> Permit {
+        // The issue comes first so waiting on it doesn't hold a slot
+        let issue = match issue_id {
+            Some(issue_id) => Some(self.issue_lock(issue_id).lock_owned().await),
+            None => None,
+        };
+        let global = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("limiter semaphore is never closed");
+        Permit {
+            _global: global,
+            _issue: issue,
+        }
+    }
+
+    fn issue_lock(&self, issue_id: i64) -> Arc<tokio::sync::Mutex<()>> {
+        let mut issues = self.issues.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget the issues nobody is holding or waiting for
+        issues.retain(|_, lock| Arc::strong_count(lock) > 1);
+        issues.entry(issue_id).or_default().clone()
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn same_issue_runs_in_order() {
+        let limiter = Arc::new(Limiter::new(4));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let first = limiter.acquire(Some(42)).await;
+        let mut waiting = Vec::new();
+        for n in 1..=3 {
+            let limiter = limiter.clone();
+            let order = order.clone();
+            waiting.push(tokio::spawn(async move {
+                let _permit = limiter.acquire(Some(42)).await;
+                order.lock().unwrap().push(n);
+            }));
+            // Queue them up in a known order
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Other issues are not held up
+        drop(limiter.acquire(Some(7)).await);
+        assert!(order.lock().unwrap().is_empty());
+
+        drop(first);
+        for task in waiting {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn bounds_concurrent_work() {
+        let limiter = Limiter::new(1);
+        let held = limiter.acquire(None).await;
+        let blocked = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(Some(1)));
+        assert!(blocked.await.is_err());
+
+        drop(held);
+        limiter.acquire(Some(1)).await;
+    }
+}
@@ -1,39 +1,1355 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
 use anyhow::{Context, Result};
+use cron::Schedule;
+use matrix_sdk::ruma::OwnedUserId;
+
+use serde::Deserialize;
+
+use crate::concurrency;
+use crate::issue::{IssueCategory, IssueState};
+use crate::priority::Priority;
+use crate::quiet_hours::QuietHours;
+use crate::reactions::ReactionAction;
+use crate::room_config::RoomConfig;
+use crate::rules::RequestRule;
+use crate::scheduler;
+use crate::time_format::{Locale, TimeFormat};
+
+/// How the bot authenticates against the homeserver.
+#[derive(Clone)]
+pub enum MatrixAuth {
+    Password(String),
+    /// An existing access token, e.g. created through the homeserver admin API
+    /// for setups where password login is disabled.
+    AccessToken {
+        token: String,
+        device_id: Option<String>,
+    },
+    /// A one-time `m.login.token` obtained through an SSO login flow.
+    LoginToken(String),
+}
+
+impl Default for MatrixAuth {
+    fn default() -> Self {
+        MatrixAuth::Password(String::new())
+    }
+}
+
+impl MatrixAuth {
+    fn load(source: &Source) -> Self {
+        if let Some(token) = source.var("MATRIX_ACCESS_TOKEN") {
+            return MatrixAuth::AccessToken {
+                token,
+                device_id: source.var("MATRIX_DEVICE_ID"),
+            };
+        }
+        if let Some(token) = source.var("MATRIX_LOGIN_TOKEN") {
+            return MatrixAuth::LoginToken(token);
+        }
+        source
+            .var("MATRIX_PASSWORD")
+            .map(MatrixAuth::Password)
+            .unwrap_or_else(|| {
+                source.problem(
+                    "One of MATRIX_PASSWORD, MATRIX_ACCESS_TOKEN or MATRIX_LOGIN_TOKEN must be set",
+                );
+                MatrixAuth::default()
+            })
+    }
+}
+
+/// Emoji the bot reacts with on the issue card for each issue state. An empty
+/// string disables the reaction for that state.
+#[derive(Clone)]
+pub struct ReactionEmojis {
+    pub open: String,
+    /// Also what admins react with to acknowledge an issue.
+    pub acknowledged: String,
+    pub in_progress: String,
+    pub resolved: String,
+}
+
+impl ReactionEmojis {
+    pub fn for_state(&self, state: IssueState) -> &str {
+        match state {
+            IssueState::Open | IssueState::Reopened => &self.open,
+            IssueState::Acknowledged => &self.acknowledged,
+            IssueState::InProgress => &self.in_progress,
+            IssueState::Resolved => &self.resolved,
+        }
+    }
+}
+
+impl Default for ReactionEmojis {
+    fn default() -> Self {
+        Self {
+            open: "🔴".to_string(),
+            acknowledged: "👀".to_string(),
+            in_progress: "🟡".to_string(),
+            resolved: "✅".to_string(),
+        }
+    }
+}
+
+/// Subsystems that can be switched off, e.g. to run the bot as a webhook relay
+/// only. Everything but the metrics endpoint is enabled by default.
+#[derive(Clone)]
+pub struct Features {
+    pub commands: bool,
+    /// `/webhook/seerr`.
+    pub webhooks: bool,
+    /// `/webhook/sonarr` and `/webhook/radarr`.
+    pub sonarr_radarr_webhooks: bool,
+    /// `/webhook/home-assistant`.
+    pub home_assistant_webhook: bool,
+    pub scheduler: bool,
+    /// `/admin/metrics`.
+    pub metrics: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Self {
+            commands: true,
+            webhooks: true,
+            sonarr_radarr_webhooks: true,
+            home_assistant_webhook: true,
+            scheduler: true,
+            metrics: false,
+        }
+    }
+}
+
+impl Features {
+    fn load(source: &Source) -> Self {
+        let defaults = Self::default();
+        Self {
+            commands: source.flag_or("COMMANDS_ENABLED", defaults.commands),
+            webhooks: source.flag_or("WEBHOOKS_ENABLED", defaults.webhooks),
+            sonarr_radarr_webhooks: source.flag_or(
+                "SONARR_RADARR_WEBHOOKS_ENABLED",
+                defaults.sonarr_radarr_webhooks,
+            ),
+            home_assistant_webhook: source.flag_or(
+                "HOME_ASSISTANT_WEBHOOK_ENABLED",
+                defaults.home_assistant_webhook,
+            ),
+            scheduler: source.flag_or("SCHEDULER_ENABLED", defaults.scheduler),
+            metrics: source.flag_or("METRICS_ENABLED", defaults.metrics),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogRotation {
+    Hourly,
+    Daily,
+    Never,
+}
+
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// `RUST_LOG` style directives, e.g. `info,michel_bot=debug`.
+    pub filter: String,
+    /// Also write logs to this file, rotated according to `rotation`.
+    pub file: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// Error reports go to this Sentry project, with the `sentry` feature.
+    pub sentry_dsn: Option<String>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Pretty,
+            filter: "info".to_string(),
+            file: None,
+            rotation: LogRotation::Daily,
+            sentry_dsn: None,
+        }
+    }
+}
+
+impl LoggingConfig {
+    fn load(source: &Source) -> Self {
+        let defaults = Self::default();
+        Self {
+            format: source.one_of(
+                "LOG_FORMAT",
+                defaults.format,
+                &[("pretty", LogFormat::Pretty), ("json", LogFormat::Json)],
+            ),
+            filter: source
+                .optional("LOG_LEVEL")
+                .or_else(|| source.optional("RUST_LOG"))
+                .unwrap_or(defaults.filter),
+            file: source.optional("LOG_FILE").map(PathBuf::from),
+            rotation: source.one_of(
+                "LOG_ROTATION",
+                defaults.rotation,
+                &[
+                    ("hourly", LogRotation::Hourly),
+                    ("daily", LogRotation::Daily),
+                    ("never", LogRotation::Never),
+                ],
+            ),
+            sentry_dsn: source.optional("SENTRY_DSN"),
+        }
+    }
+}
+
+/// Address and API key of an optional companion service such as Sonarr.
+#[derive(Debug, Clone)]
+pub struct ServiceConfig {
+    pub url: String,
+    pub api_key: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushProvider {
+    Ntfy,
+    Gotify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TranslationProvider {
+    LibreTranslate,
+}
+
+/// Translation of Seerr comments posted in issue threads.
+#[derive(Debug, Clone)]
+pub struct TranslationConfig {
+    pub provider: TranslationProvider,
+    pub url: String,
+    pub api_key: Option<String>,
+    /// Language comments are translated into, unless the room sets its own.
+    pub language: String,
+}
+
+impl TranslationConfig {
+    /// Disabled when `TRANSLATION_URL` is unset.
+    fn load(source: &Source) -> Option<Self> {
+        let url = source.optional_url("TRANSLATION_URL")?;
+        Some(Self {
+            provider: source.one_of(
+                "TRANSLATION_PROVIDER",
+                TranslationProvider::LibreTranslate,
+                &[("libretranslate", TranslationProvider::LibreTranslate)],
+            ),
+            url,
+            api_key: source.optional("TRANSLATION_API_KEY"),
+            language: source
+                .var("TRANSLATION_LANGUAGE")
+                .unwrap_or_else(|| "en".to_string()),
+        })
+    }
+}
+
+/// Notification types mirrored to the push channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PushEvent {
+    IssueCreated,
+    ServiceDown,
+}
+
+impl PushEvent {
+    const ALL: [(&str, PushEvent); 2] = [
+        ("issue_created", PushEvent::IssueCreated),
+        ("service_down", PushEvent::ServiceDown),
+    ];
+}
+
+/// Secondary channel pinging the admins' phones through ntfy or Gotify.
+#[derive(Debug, Clone)]
+pub struct PushConfig {
+    pub provider: PushProvider,
+    /// ntfy topic URL, e.g. `https://ntfy.sh/michel`, or Gotify server URL.
+    pub url: String,
+    /// ntfy access token or Gotify application token.
+    pub token: Option<String>,
+    pub events: Vec<PushEvent>,
+}
+
+impl PushConfig {
+    /// Disabled when `PUSH_URL` is unset.
+    fn load(source: &Source) -> Option<Self> {
+        let url = source.optional_url("PUSH_URL")?;
+        let provider = source.one_of(
+            "PUSH_PROVIDER",
+            PushProvider::Ntfy,
+            &[
+                ("ntfy", PushProvider::Ntfy),
+                ("gotify", PushProvider::Gotify),
+            ],
+        );
+        let token = source.optional("PUSH_TOKEN");
+        if provider == PushProvider::Gotify && token.is_none() {
+            source.problem("PUSH_TOKEN is required with PUSH_PROVIDER=gotify");
+        }
+        let mut events = Vec::new();
+        let names = source.list("PUSH_EVENTS");
+        if names.is_empty() {
+            events = PushEvent::ALL.iter().map(|(_, event)| *event).collect();
+        }
+        for name in names {
+            match PushEvent::ALL.iter().find(|(choice, _)| *choice == name) {
+                Some((_, event)) => events.push(*event),
+                None => source.problem(format!(
+                    "PUSH_EVENTS: unknown notification type {name:?}, expected issue_created or service_down"
+                )),
+            }
+        }
+        Some(Self {
+            provider,
+            url,
+            token,
+            events,
+        })
+    }
+}
+
+/// Notifications Home Assistant posts to `/webhook/home-assistant`.
+#[derive(Debug, Clone)]
+pub struct HomeAssistantConfig {
+    /// Bearer token Home Assistant sends with its notifications.
+    pub token: String,
+    /// Markdown of the message, with `{title}`, `{message}` and the `data`
+    /// fields of the notification as placeholders.
+    pub template: String,
+}
+
+impl HomeAssistantConfig {
+    pub const DEFAULT_TEMPLATE: &str = "#### 🏠 {title}\n{message}";
+
+    /// Disabled when `HOME_ASSISTANT_TOKEN` is unset.
+    fn load(source: &Source) -> Option<Self> {
+        Some(Self {
+            token: source.optional("HOME_ASSISTANT_TOKEN")?,
+            template: source
+                .optional("HOME_ASSISTANT_TEMPLATE")
+                .unwrap_or_else(|| Self::DEFAULT_TEMPLATE.to_string()),
+        })
+    }
+}
+
+/// Address and login of a download client such as qBittorrent.
+#[derive(Debug, Clone)]
+pub struct DownloadClientConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl DownloadClientConfig {
+    /// Reads `{prefix}_URL`, `{prefix}_USERNAME` and `{prefix}_PASSWORD`, the
+    /// client being disabled when the URL is unset.
+    fn load(source: &Source, prefix: &str) -> Option<Self> {
+        let url = source.optional_url(&format!("{prefix}_URL"))?;
+        Some(Self {
+            url,
+            username: source.optional(&format!("{prefix}_USERNAME")),
+            password: source.optional(&format!("{prefix}_PASSWORD")),
+        })
+    }
+}
+
+impl ServiceConfig {
+    /// Reads `{prefix}_URL` and `{prefix}_API_KEY`, the service being disabled
+    /// when the URL is unset.
+    fn load(source: &Source, prefix: &str) -> Option<Self> {
+        let url = source.optional_url(&format!("{prefix}_URL"))?;
+        Some(Self {
+            url,
+            api_key: source.required(&format!("{prefix}_API_KEY")),
+        })
+    }
+}
+
+/// Cron schedules of the periodic jobs, in the `BOT_TIMEZONE`.
+#[derive(Clone)]
+pub struct Schedules {
+    pub weekly_report: Schedule,
+    pub daily_digest: Schedule,
+    pub calendar: Schedule,
+    pub whats_new: Schedule,
+    pub stale_reminders: Schedule,
+    pub reconcile: Schedule,
+    pub webhook_check: Schedule,
+    pub availability: Schedule,
+    pub auto_resolve: Schedule,
+    pub reporter_follow_up: Schedule,
+    pub user_reminders: Schedule,
+    pub disk_monitor: Schedule,
+    pub outbox_watchdog: Schedule,
+}
+
+impl Default for Schedules {
+    fn default() -> Self {
+        Self {
+            weekly_report: default_schedule("0 9 * * Mon"),
+            daily_digest: default_schedule("0 8 * * *"),
+            calendar: default_schedule("0 9 * * Mon"),
+            whats_new: default_schedule("0 18 * * Fri"),
+            stale_reminders: default_schedule("0 * * * *"),
+            reconcile: default_schedule("*/30 * * * *"),
+            webhook_check: default_schedule("0 */6 * * *"),
+            availability: default_schedule("0 */6 * * *"),
+            auto_resolve: default_schedule("*/15 * * * *"),
+            reporter_follow_up: default_schedule("0 * * * *"),
+            user_reminders: default_schedule("* * * * *"),
+            disk_monitor: default_schedule("*/30 * * * *"),
+            outbox_watchdog: default_schedule("*/5 * * * *"),
+        }
+    }
+}
+
+impl Schedules {
+    fn load(source: &Source) -> Self {
+        let defaults = Self::default();
+        Self {
+            weekly_report: source.schedule("SCHEDULE_WEEKLY_REPORT", defaults.weekly_report),
+            daily_digest: source.schedule("SCHEDULE_DAILY_DIGEST", defaults.daily_digest),
+            calendar: source.schedule("SCHEDULE_CALENDAR", defaults.calendar),
+            whats_new: source.schedule("SCHEDULE_WHATS_NEW", defaults.whats_new),
+            stale_reminders: source.schedule("SCHEDULE_STALE_REMINDERS", defaults.stale_reminders),
+            reconcile: source.schedule("SCHEDULE_RECONCILE", defaults.reconcile),
+            webhook_check: source.schedule("SCHEDULE_WEBHOOK_CHECK", defaults.webhook_check),
+            availability: source.schedule("SCHEDULE_AVAILABILITY", defaults.availability),
+            auto_resolve: source.schedule("SCHEDULE_AUTO_RESOLVE", defaults.auto_resolve),
+            reporter_follow_up: source
+                .schedule("SCHEDULE_REPORTER_FOLLOW_UP", defaults.reporter_follow_up),
+            user_reminders: source.schedule("SCHEDULE_USER_REMINDERS", defaults.user_reminders),
+            disk_monitor: source.schedule("SCHEDULE_DISK_MONITOR", defaults.disk_monitor),
+            outbox_watchdog: source.schedule("SCHEDULE_OUTBOX_WATCHDOG", defaults.outbox_watchdog),
+        }
+    }
+}
+
+fn default_schedule(expression: &str) -> Schedule {
+    scheduler::parse_schedule(expression).expect("valid default schedule")
+}
+
+/// When repeated failures are reported to the admins.
+#[derive(Debug, Clone)]
+pub struct AlertConfig {
+    /// Failures in a row before the admins are notified.
+    pub threshold: u32,
+    /// Minimum time between two notifications about the same subsystem.
+    pub interval: Duration,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 3,
+            interval: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl AlertConfig {
+    fn load(source: &Source) -> Self {
+        let defaults = Self::default();
+        Self {
+            threshold: source.parse("ALERT_THRESHOLD", defaults.threshold).max(1),
+            interval: source.secs("ALERT_INTERVAL_SECS", defaults.interval),
+        }
+    }
+}
+
+/// Priority of the notifications, shown on their cards.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityConfig {
+    /// Priority of issues by category, normal for the others.
+    pub issues: Vec<(IssueCategory, Priority)>,
+    /// Mention `@room` in critical notifications.
+    pub mention_room: bool,
+}
+
+impl PriorityConfig {
+    fn load(source: &Source) -> Self {
+        let mut issues = Vec::new();
+        for entry in source.list("ISSUE_PRIORITIES") {
+            let parsed = entry.split_once('=').and_then(|(category, priority)| {
+                Some((IssueCategory::parse(category)?, Priority::parse(priority)?))
+            });
+            match parsed {
+                Some(issue) => issues.push(issue),
+                None => source.problem(format!(
+                    "ISSUE_PRIORITIES: {entry:?} is not a category and priority like video=high"
+                )),
+            }
+        }
+        Self {
+            issues,
+            mention_room: source.flag("PRIORITY_MENTION_ROOM"),
+        }
+    }
+
+    pub fn of_issue(&self, category: Option<IssueCategory>) -> Priority {
+        self.issues
+            .iter()
+            .find(|(c, _)| Some(*c) == category)
+            .map_or(Priority::Normal, |(_, priority)| *priority)
+    }
+}
+
+/// When request notifications arriving together are posted as one message.
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// Notifications of the same status within `window` from which they are
+    /// batched.
+    pub threshold: usize,
+    /// How long a batch collects notifications before it is posted.
+    pub window: Duration,
+}
+
+impl BatchConfig {
+    /// Disabled when `NOTIFICATION_BATCH_THRESHOLD` is unset.
+    fn load(source: &Source) -> Option<Self> {
+        source.optional("NOTIFICATION_BATCH_THRESHOLD")?;
+        Some(Self {
+            threshold: source.parse("NOTIFICATION_BATCH_THRESHOLD", 5).max(2),
+            window: source.secs("NOTIFICATION_BATCH_WINDOW_SECS", Duration::from_secs(60)),
+        })
+    }
+}
 
+pub struct DatabasePoolConfig {
+    pub max_connections: u32,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Duration,
+}
+
+impl Default for DatabasePoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Duration::from_secs(600),
+        }
+    }
+}
+
+impl DatabasePoolConfig {
+    fn load(source: &Source) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_connections: source.parse("DATABASE_MAX_CONNECTIONS", defaults.max_connections),
+            acquire_timeout: source.secs("DATABASE_ACQUIRE_TIMEOUT_SECS", defaults.acquire_timeout),
+            idle_timeout: source.secs("DATABASE_IDLE_TIMEOUT_SECS", defaults.idle_timeout),
+        }
+    }
+}
+
+#[derive(Default)]
 pub struct Config {
     pub matrix_homeserver_url: String,
     pub matrix_user_id: String,
-    pub matrix_password: String,
+    pub matrix_auth: MatrixAuth,
     pub matrix_room_alias: String,
+    pub matrix_verification: bool,
+    /// Directory of the sqlite store keeping the session and encryption keys
+    /// across restarts.
+    pub matrix_store_path: Option<PathBuf>,
+    pub bot_display_name: Option<String>,
+    pub bot_avatar_url: Option<String>,
+    /// Where Seerr users reach the bot, to serve them forwarded attachments.
+    pub bot_public_url: Option<String>,
     pub database_url: String,
+    pub database_pool: DatabasePoolConfig,
     pub webhook_listen_addr: String,
     pub seerr_api_url: String,
     pub seerr_api_key: String,
-    pub matrix_admin_users: Vec<String>,
+    /// Used for links in messages, `seerr_api_url` being often internal.
+    pub seerr_public_url: Option<String>,
+    pub sonarr: Option<ServiceConfig>,
+    pub radarr: Option<ServiceConfig>,
+    pub jellyfin: Option<ServiceConfig>,
+    pub qbittorrent: Option<DownloadClientConfig>,
+    pub matrix_admin_users: Vec<OwnedUserId>,
+    pub admin_api_token: Option<String>,
+    pub dashboard_enabled: bool,
+    pub weekly_report_enabled: bool,
+    pub daily_digest_enabled: bool,
+    pub stale_reminders_enabled: bool,
+    pub reconcile_enabled: bool,
+    /// Check the Seerr webhook agent settings at startup and on a schedule.
+    pub webhook_check_enabled: bool,
+    /// Where Seerr should send webhooks, checked by the webhook check.
+    pub seerr_webhook_url: Option<String>,
+    pub catch_up_enabled: bool,
+    pub availability_watch_enabled: bool,
+    pub disk_monitor_enabled: bool,
+    /// Thread the grabs of Sonarr and Radarr under the matching request cards.
+    pub download_notices_enabled: bool,
+    /// Show the runtime, rating, genres and overview of the media on cards.
+    pub media_details_enabled: bool,
+    /// Post the poster and screenshots of issues in their thread.
+    pub issue_images_enabled: bool,
+    /// Post a card with a thread for each failing Sonarr or Radarr health check.
+    pub health_tickets_enabled: bool,
+    /// DM the admins about webhooks stuck in the outbox.
+    pub outbox_watchdog_enabled: bool,
+    /// Failures after which an outbox item is stuck.
+    pub outbox_stuck_attempts: i32,
+    /// Time in the outbox after which an item is stuck.
+    pub outbox_stuck_after: Duration,
+    /// Seerr notifications are held during these hours, in `BOT_TIMEZONE`.
+    pub quiet_hours: Option<QuietHours>,
+    pub notification_batch: Option<BatchConfig>,
+    pub priorities: PriorityConfig,
+    /// Local paths checked by the disk monitor, besides the Sonarr and Radarr
+    /// root folders.
+    pub disk_watch_paths: Vec<String>,
+    pub disk_free_threshold_gb: u64,
+    /// Inactivity after which an open issue gets its first reminder.
+    pub stale_issue_after: Duration,
+    /// Grace period after an import notice before the issue is resolved, never
+    /// when unset.
+    pub import_auto_resolve_after: Option<Duration>,
+    /// Time after an admin's answer before the reporter is asked to confirm
+    /// it, never when unset.
+    pub reporter_follow_up_after: Option<Duration>,
+    /// Silence after that question before the issue is resolved.
+    pub reporter_follow_up_resolve_after: Duration,
+    /// Distinct 👍 votes that approve a pending request, never without it.
+    pub request_vote_threshold: Option<usize>,
+    pub reaction_emojis: ReactionEmojis,
+    /// `[reactions]` section of the config file, what admins reacting to an
+    /// issue card with each emoji do.
+    pub reaction_shortcuts: HashMap<String, ReactionAction>,
+    pub startup_self_test: bool,
+    pub shutdown_notice: Option<String>,
+    pub shutdown_timeout: Duration,
+    pub features: Features,
+    pub schedules: Schedules,
+    pub logging: LoggingConfig,
+    pub alerts: AlertConfig,
+    /// Push monitor URL pinged while the bot is healthy.
+    pub heartbeat_url: Option<String>,
+    pub heartbeat_interval: Duration,
+    /// Webhooks and commands handled at the same time.
+    pub max_concurrent_handlers: usize,
+    /// Calls to the Seerr, Sonarr and Radarr APIs taking longer are logged.
+    pub http_slow_call: Duration,
+    /// How often actions queued while Seerr was unreachable are tried again.
+    pub pending_actions_poll: Duration,
+    /// Receivers of the bot's own events.
+    pub outgoing_webhook_urls: Vec<String>,
+    pub push: Option<PushConfig>,
+    pub home_assistant: Option<HomeAssistantConfig>,
+    pub translation: Option<TranslationConfig>,
+    pub time_format: TimeFormat,
+    /// `[[rooms]]` sections of the config file.
+    pub rooms: Vec<RoomSettings>,
+    /// `[[rules]]` sections of the config file, applied in order to new
+    /// pending requests.
+    pub rules: Vec<RequestRule>,
+}
+
+impl Config {
+    /// Config file filters for the room known under any of `names`.
+    pub fn room_defaults(&self, names: &[&str]) -> RoomConfig {
+        self.rooms
+            .iter()
+            .find(|r| names.contains(&r.room.as_str()))
+            .map(|r| r.config.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Per-room defaults from the config file. The room's own
+/// `io.michel_bot.config` state event takes precedence over these.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RoomSettings {
+    pub room: String,
+    #[serde(flatten)]
+    pub config: RoomConfig,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
-        Ok(Self {
-            matrix_homeserver_url: std::env::var("MATRIX_HOMESERVER_URL")
-                .context("MATRIX_HOMESERVER_URL must be set")?,
-            matrix_user_id: std::env::var("MATRIX_USER_ID")
-                .context("MATRIX_USER_ID must be set")?,
-            matrix_password: std::env::var("MATRIX_PASSWORD")
-                .context("MATRIX_PASSWORD must be set")?,
-            matrix_room_alias: std::env::var("MATRIX_ROOM_ALIAS")
-                .context("MATRIX_ROOM_ALIAS must be set")?,
-            database_url: std::env::var("DATABASE_URL").context("DATABASE_URL must be set")?,
-            webhook_listen_addr: std::env::var("WEBHOOK_LISTEN_ADDR")
-                .unwrap_or_else(|_| "0.0.0.0:8080".to_string()),
-            seerr_api_url: std::env::var("SEERR_API_URL").context("SEERR_API_URL must be set")?,
-            seerr_api_key: std::env::var("SEERR_API_KEY").context("SEERR_API_KEY must be set")?,
-            matrix_admin_users: std::env::var("MATRIX_ADMIN_USERS")
-                .unwrap_or_default()
-                .split(',')
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
+        Self::load(None)
+    }
+
+    /// Reads the optional TOML config file, then lets environment variables
+    /// override any value it sets. Every invalid or missing value is reported
+    /// at once rather than one per restart.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (source, sections) = match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                Source::from_toml(&content)
+                    .with_context(|| format!("Invalid config file {}", path.display()))?
+            }
+            None => (Source::default(), Sections::default()),
+        };
+        let config = Self {
+            matrix_homeserver_url: source.url("MATRIX_HOMESERVER_URL"),
+            matrix_user_id: source.required("MATRIX_USER_ID"),
+            matrix_auth: MatrixAuth::load(&source),
+            matrix_room_alias: source.required("MATRIX_ROOM_ALIAS"),
+            matrix_verification: source.flag("MATRIX_VERIFICATION"),
+            matrix_store_path: source.optional("MATRIX_STORE_PATH").map(PathBuf::from),
+            bot_display_name: source.optional("BOT_DISPLAY_NAME"),
+            bot_avatar_url: source.optional("BOT_AVATAR_URL"),
+            bot_public_url: source.optional_url("BOT_PUBLIC_URL"),
+            database_url: source.required("DATABASE_URL"),
+            database_pool: DatabasePoolConfig::load(&source),
+            webhook_listen_addr: source
+                .var("WEBHOOK_LISTEN_ADDR")
+                .unwrap_or_else(|| "0.0.0.0:8080".to_string()),
+            seerr_api_url: source.url("SEERR_API_URL"),
+            seerr_api_key: source.required("SEERR_API_KEY"),
+            seerr_public_url: source.optional_url("SEERR_PUBLIC_URL"),
+            sonarr: ServiceConfig::load(&source, "SONARR"),
+            radarr: ServiceConfig::load(&source, "RADARR"),
+            jellyfin: ServiceConfig::load(&source, "JELLYFIN"),
+            qbittorrent: DownloadClientConfig::load(&source, "QBITTORRENT"),
+            matrix_admin_users: source
+                .list("MATRIX_ADMIN_USERS")
+                .into_iter()
+                .filter_map(|user| match OwnedUserId::try_from(user.as_str()) {
+                    Ok(user_id) => Some(user_id),
+                    Err(_) => {
+                        source.problem(format!(
+                            "MATRIX_ADMIN_USERS: {user:?} is not a Matrix user id like @alice:example.org"
+                        ));
+                        None
+                    }
+                })
                 .collect(),
+            admin_api_token: source.optional("ADMIN_API_TOKEN"),
+            dashboard_enabled: source.flag("DASHBOARD_ENABLED"),
+            weekly_report_enabled: source.flag("WEEKLY_REPORT_ENABLED"),
+            daily_digest_enabled: source.flag("DAILY_DIGEST_ENABLED"),
+            stale_reminders_enabled: source.flag("STALE_REMINDERS_ENABLED"),
+            reconcile_enabled: source.flag("RECONCILE_ENABLED"),
+            webhook_check_enabled: source.flag("WEBHOOK_CHECK_ENABLED"),
+            seerr_webhook_url: source.optional_url("SEERR_WEBHOOK_URL"),
+            catch_up_enabled: source.flag_or("CATCH_UP_ENABLED", true),
+            availability_watch_enabled: source.flag("AVAILABILITY_WATCH_ENABLED"),
+            disk_monitor_enabled: source.flag("DISK_MONITOR_ENABLED"),
+            download_notices_enabled: source.flag("DOWNLOAD_NOTICES_ENABLED"),
+            media_details_enabled: source.flag("MEDIA_DETAILS_ENABLED"),
+            issue_images_enabled: source.flag("ISSUE_IMAGES_ENABLED"),
+            health_tickets_enabled: source.flag("HEALTH_TICKETS_ENABLED"),
+            outbox_watchdog_enabled: source.flag("OUTBOX_WATCHDOG_ENABLED"),
+            outbox_stuck_attempts: source.parse::<i32>("OUTBOX_STUCK_ATTEMPTS", 5).max(1),
+            outbox_stuck_after: Duration::from_secs(
+                source.parse::<u64>("OUTBOX_STUCK_AFTER_MINUTES", 60).max(1) * 60,
+            ),
+            quiet_hours: source.optional("QUIET_HOURS").and_then(|value| {
+                QuietHours::parse(&value).or_else(|| {
+                    source.problem(format!(
+                        "QUIET_HOURS: {value:?} is not a time range like 23:00-08:00"
+                    ));
+                    None
+                })
+            }),
+            disk_watch_paths: source.list("DISK_WATCH_PATHS"),
+            disk_free_threshold_gb: source.parse("DISK_FREE_THRESHOLD_GB", 50),
+            stale_issue_after: Duration::from_secs(
+                source.parse::<u64>("STALE_ISSUE_AFTER_HOURS", 72).max(1) * 3600,
+            ),
+            import_auto_resolve_after: source
+                .optional("IMPORT_AUTO_RESOLVE_AFTER_HOURS")
+                .map(|_| {
+                    Duration::from_secs(
+                        source
+                            .parse::<u64>("IMPORT_AUTO_RESOLVE_AFTER_HOURS", 48)
+                            .max(1)
+                            * 3600,
+                    )
+                }),
+            reporter_follow_up_after: source
+                .optional("REPORTER_FOLLOW_UP_AFTER_DAYS")
+                .map(|_| {
+                    Duration::from_secs(
+                        source
+                            .parse::<u64>("REPORTER_FOLLOW_UP_AFTER_DAYS", 3)
+                            .max(1)
+                            * 86400,
+                    )
+                }),
+            reporter_follow_up_resolve_after: Duration::from_secs(
+                source
+                    .parse::<u64>("REPORTER_FOLLOW_UP_RESOLVE_AFTER_DAYS", 7)
+                    .max(1)
+                    * 86400,
+            ),
+            request_vote_threshold: source
+                .optional("REQUEST_VOTE_THRESHOLD")
+                .map(|_| source.parse::<usize>("REQUEST_VOTE_THRESHOLD", 3).max(1)),
+            reaction_emojis: ReactionEmojis::load(&source),
+            reaction_shortcuts: sections.reactions,
+            startup_self_test: source.flag("STARTUP_SELF_TEST"),
+            shutdown_notice: source.optional("SHUTDOWN_NOTICE"),
+            shutdown_timeout: source.secs("SHUTDOWN_TIMEOUT_SECS", Duration::from_secs(30)),
+            features: Features::load(&source),
+            schedules: Schedules::load(&source),
+            logging: LoggingConfig::load(&source),
+            alerts: AlertConfig::load(&source),
+            notification_batch: BatchConfig::load(&source),
+            priorities: PriorityConfig::load(&source),
+            heartbeat_url: source.optional("HEARTBEAT_URL"),
+            heartbeat_interval: source.secs("HEARTBEAT_INTERVAL_SECS", Duration::from_secs(60)),
+            max_concurrent_handlers: source
+                .parse("MAX_CONCURRENT_HANDLERS", concurrency::DEFAULT_MAX_CONCURRENT)
+                .max(1),
+            http_slow_call: Duration::from_millis(source.parse("HTTP_SLOW_CALL_MS", 2000)),
+            pending_actions_poll: source.secs("PENDING_ACTIONS_POLL_SECS", Duration::from_secs(30)),
+            outgoing_webhook_urls: source.list("OUTGOING_WEBHOOK_URLS"),
+            push: PushConfig::load(&source),
+            home_assistant: HomeAssistantConfig::load(&source),
+            translation: TranslationConfig::load(&source),
+            time_format: TimeFormat {
+                timezone: source
+                    .optional("BOT_TIMEZONE")
+                    .and_then(|tz| {
+                        tz.parse()
+                            .map_err(|_| {
+                                source.problem(format!(
+                                    "BOT_TIMEZONE: {tz:?} is not a timezone like Europe/Paris"
+                                ))
+                            })
+                            .ok()
+                    })
+                    .unwrap_or(chrono_tz::Tz::UTC),
+                locale: source
+                    .optional("BOT_LOCALE")
+                    .and_then(|locale| {
+                        Locale::parse(&locale).or_else(|| {
+                            source.problem(format!(
+                                "BOT_LOCALE: {locale:?} is not a supported locale like fr_FR or en_US"
+                            ));
+                            None
+                        })
+                    })
+                    .unwrap_or(Locale::Iso),
+            },
+            rooms: sections.rooms,
+            rules: sections.rules,
+        };
+        config.validate(&source);
+        source.finish()?;
+        Ok(config)
+    }
+
+    fn validate(&self, source: &Source) {
+        if self.matrix_user_id.starts_with('@')
+            && OwnedUserId::try_from(self.matrix_user_id.as_str()).is_err()
+        {
+            source.problem(format!(
+                "MATRIX_USER_ID: {:?} is not a valid Matrix user id",
+                self.matrix_user_id
+            ));
+        }
+        if !self.matrix_room_alias.is_empty() && !self.matrix_room_alias.starts_with(['#', '!']) {
+            source.problem(format!(
+                "MATRIX_ROOM_ALIAS: {:?} must be a room alias (#room:server) or a room id (!id:server)",
+                self.matrix_room_alias
+            ));
+        }
+        let valid_addr = self
+            .webhook_listen_addr
+            .rsplit_once(':')
+            .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+        if !valid_addr {
+            source.problem(format!(
+                "WEBHOOK_LISTEN_ADDR: {:?} must be a host:port address",
+                self.webhook_listen_addr
+            ));
+        }
+        if self.disk_monitor_enabled
+            && self.sonarr.is_none()
+            && self.radarr.is_none()
+            && self.disk_watch_paths.is_empty()
+        {
+            source.problem("DISK_MONITOR_ENABLED needs SONARR_URL, RADARR_URL or DISK_WATCH_PATHS");
+        }
+        for problem in self.rules.iter().flat_map(RequestRule::problems) {
+            source.problem(problem);
+        }
+    }
+}
+
+impl ReactionEmojis {
+    fn load(source: &Source) -> Self {
+        let defaults = Self::default();
+        Self {
+            open: source.var("REACTION_OPEN").unwrap_or(defaults.open),
+            acknowledged: source
+                .var("REACTION_ACKNOWLEDGED")
+                .unwrap_or(defaults.acknowledged),
+            in_progress: source
+                .var("REACTION_IN_PROGRESS")
+                .unwrap_or(defaults.in_progress),
+            resolved: source.var("REACTION_RESOLVED").unwrap_or(defaults.resolved),
+        }
+    }
+}
+
+/// Command line: `michel-bot [check] [--config <path>]`.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// Config file path from `--config` or `MICHEL_CONFIG`.
+    pub config_path: Option<PathBuf>,
+    /// Check the environment and exit instead of running the bot.
+    pub check: bool,
+}
+
+impl Args {
+    pub fn from_env() -> Result<Self> {
+        let mut args = Self::parse(std::env::args().skip(1))?;
+        if args.config_path.is_none() {
+            args.config_path = std::env::var_os("MICHEL_CONFIG").map(PathBuf::from);
+        }
+        Ok(args)
+    }
+
+    fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--config" {
+                let path = args.next().context("--config needs a path")?;
+                parsed.config_path = Some(PathBuf::from(path));
+            } else if let Some(path) = arg.strip_prefix("--config=") {
+                parsed.config_path = Some(PathBuf::from(path));
+            } else if arg == "check" {
+                parsed.check = true;
+            } else {
+                anyhow::bail!(
+                    "Unknown argument {arg:?}, usage: michel-bot [check] [--config <path>]"
+                );
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Arrays of tables in the config file, which don't map to variables.
+#[derive(Default)]
+struct Sections {
+    rooms: Vec<RoomSettings>,
+    rules: Vec<RequestRule>,
+    reactions: HashMap<String, ReactionAction>,
+}
+
+/// Configuration values keyed by their environment variable name. Values from
+/// the config file are flattened so `[matrix] homeserver_url` and a top-level
+/// `matrix_homeserver_url` both stand for `MATRIX_HOMESERVER_URL`.
+///
+/// Any value can instead be read from a file named by `<NAME>_FILE`, which is
+/// how Docker and Kubernetes mount secrets.
+#[derive(Default)]
+struct Source {
+    file: HashMap<String, String>,
+    problems: RefCell<Vec<String>>,
+}
+
+impl Source {
+    fn from_toml(content: &str) -> Result<(Self, Sections)> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        let rooms = match table.remove("rooms") {
+            Some(rooms) => rooms.try_into().context("Invalid [[rooms]] section")?,
+            None => Vec::new(),
+        };
+        let rules = match table.remove("rules") {
+            Some(rules) => rules.try_into().context("Invalid [[rules]] section")?,
+            None => Vec::new(),
+        };
+        let reactions = match table.remove("reactions") {
+            Some(reactions) => reactions
+                .try_into()
+                .context("Invalid [reactions] section")?,
+            None => HashMap::new(),
+        };
+        let mut file = HashMap::new();
+        flatten("", table, &mut file)?;
+        Ok((
+            Self {
+                file,
+                ..Default::default()
+            },
+            Sections {
+                rooms,
+                rules,
+                reactions,
+            },
+        ))
+    }
+
+    fn problem(&self, problem: impl Into<String>) {
+        self.problems.borrow_mut().push(problem.into());
+    }
+
+    /// Fails with every problem recorded while loading.
+    fn finish(self) -> Result<()> {
+        let problems = self.problems.into_inner();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("Invalid configuration:\n  - {}", problems.join("\n  - "))
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        if let Ok(value) = std::env::var(name) {
+            return Some(value);
+        }
+        let file_var = format!("{name}_FILE");
+        if let Ok(path) = std::env::var(&file_var) {
+            return self.secret(&file_var, &path);
+        }
+        if let Some(value) = self.file.get(name) {
+            return Some(value.clone());
+        }
+        let path = self.file.get(&file_var)?;
+        self.secret(&file_var, path)
+    }
+
+    fn secret(&self, var: &str, path: &str) -> Option<String> {
+        read_secret(var, path)
+            .map_err(|e| self.problem(format!("{e:#}")))
+            .ok()
+    }
+
+    fn required(&self, name: &str) -> String {
+        self.var(name).filter(|v| !v.is_empty()).unwrap_or_else(|| {
+            self.problem(format!("{name} must be set"));
+            String::new()
         })
     }
+
+    fn optional(&self, name: &str) -> Option<String> {
+        self.var(name).filter(|s| !s.is_empty())
+    }
+
+    fn list(&self, name: &str) -> Vec<String> {
+        self.var(name)
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    fn url(&self, name: &str) -> String {
+        let value = self.required(name);
+        if !value.is_empty() {
+            self.check_url(name, &value);
+        }
+        value
+    }
+
+    fn optional_url(&self, name: &str) -> Option<String> {
+        let value = self.optional(name)?;
+        self.check_url(name, &value);
+        Some(value)
+    }
+
+    fn check_url(&self, name: &str, value: &str) {
+        match reqwest::Url::parse(value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => self.problem(format!("{name}: {value:?} is not an http(s) URL")),
+        }
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flag_or(name, false)
+    }
+
+    fn flag_or(&self, name: &str, default: bool) -> bool {
+        let Some(value) = self.var(name) else {
+            return default;
+        };
+        match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" | "" => false,
+            _ => {
+                self.problem(format!("{name} must be true or false, got {value:?}"));
+                default
+            }
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self, name: &str, default: T) -> T {
+        let Some(value) = self.var(name) else {
+            return default;
+        };
+        value.trim().parse().unwrap_or_else(|_| {
+            self.problem(format!("{name} must be a number, got {value:?}"));
+            default
+        })
+    }
+
+    fn one_of<T: Copy>(&self, name: &str, default: T, choices: &[(&str, T)]) -> T {
+        let Some(value) = self.optional(name) else {
+            return default;
+        };
+        let value = value.trim().to_lowercase();
+        match choices.iter().find(|(choice, _)| *choice == value) {
+            Some((_, choice)) => *choice,
+            None => {
+                let names: Vec<&str> = choices.iter().map(|(choice, _)| *choice).collect();
+                self.problem(format!(
+                    "{name} must be one of {}, got {value:?}",
+                    names.join(", ")
+                ));
+                default
+            }
+        }
+    }
+
+    fn schedule(&self, name: &str, default: Schedule) -> Schedule {
+        let Some(value) = self.optional(name) else {
+            return default;
+        };
+        scheduler::parse_schedule(&value).unwrap_or_else(|e| {
+            self.problem(format!("{name}: {e:#}"));
+            default
+        })
+    }
+
+    fn secs(&self, name: &str, default: Duration) -> Duration {
+        Duration::from_secs(self.parse(name, default.as_secs()))
+    }
+}
+
+/// Secret files usually end with a newline, which is not part of the value.
+fn read_secret(var: &str, path: &str) -> Result<String> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {var} ({path})"))?;
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn flatten(prefix: &str, table: toml::Table, out: &mut HashMap<String, String>) -> Result<()> {
+    for (key, value) in table {
+        let name = if prefix.is_empty() {
+            key.to_uppercase()
+        } else {
+            format!("{prefix}_{}", key.to_uppercase())
+        };
+        let value = match value {
+            toml::Value::Table(table) => {
+                flatten(&name, table, out)?;
+                continue;
+            }
+            toml::Value::String(s) => s,
+            toml::Value::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    toml::Value::String(s) => Ok(s),
+                    other => anyhow::bail!("{name} must be a list of strings, got {other}"),
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join(","),
+            other => other.to_string(),
+        };
+        out.insert(name, value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flattens_sections_to_env_names() {
+        let (source, sections) = Source::from_toml(
+            r##"
+            database_url = "postgres://localhost/michel"
+
+            [matrix]
+            homeserver_url = "https://matrix.example.org"
+            admin_users = ["@alice:example.org", "@bob:example.org"]
+
+            [database]
+            max_connections = 4
+
+            [[rooms]]
+            room = "#issues:example.org"
+            notification_types = ["ISSUE_CREATED"]
+
+            [[rules]]
+            name = "Trusted TV"
+            action = "approve"
+            media_type = "tv"
+            requested_by = ["alice"]
+
+            [reactions]
+            "✅" = "resolve"
+            "🔇" = "mute"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            source.file["MATRIX_HOMESERVER_URL"],
+            "https://matrix.example.org"
+        );
+        assert_eq!(
+            source.file["MATRIX_ADMIN_USERS"],
+            "@alice:example.org,@bob:example.org"
+        );
+        assert_eq!(source.file["DATABASE_URL"], "postgres://localhost/michel");
+        assert_eq!(source.parse("DATABASE_MAX_CONNECTIONS", 10u32), 4);
+        assert_eq!(sections.rooms.len(), 1);
+        assert!(!sections.rooms[0].config.allows("ISSUE_COMMENT"));
+        assert_eq!(sections.rules.len(), 1);
+        assert!(!source.file.contains_key("RULES"));
+        assert_eq!(sections.reactions["✅"], ReactionAction::Resolve);
+        assert!(!source.file.keys().any(|key| key.starts_with("REACTIONS")));
+    }
+
+    #[test]
+    fn reads_secrets_from_files() {
+        let path = std::env::temp_dir().join("michel-bot-test-seerr-key");
+        std::fs::write(&path, "s3cret\n").unwrap();
+        let (source, _) = Source::from_toml(&format!(
+            "[seerr]\napi_key_file = {:?}",
+            path.display().to_string()
+        ))
+        .unwrap();
+
+        let key = source.var("SEERR_API_KEY");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(key.as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn reports_every_problem_at_once() {
+        let (source, _) = Source::from_toml(
+            r#"
+            [matrix]
+            homeserver_url = "matrix.example.org"
+
+            [database]
+            max_connections = "many"
+            "#,
+        )
+        .unwrap();
+        source.url("MATRIX_HOMESERVER_URL");
+        source.required("DATABASE_URL");
+        source.parse("DATABASE_MAX_CONNECTIONS", 10u32);
+
+        let err = source.finish().unwrap_err().to_string();
+        assert!(err.contains("MATRIX_HOMESERVER_URL"), "{err}");
+        assert!(err.contains("DATABASE_URL must be set"), "{err}");
+        assert!(err.contains("DATABASE_MAX_CONNECTIONS"), "{err}");
+    }
+
+    #[test]
+    fn features_default_to_enabled_but_metrics() {
+        let (source, _) = Source::from_toml(
+            "commands_enabled = false\n\
+             sonarr_radarr_webhooks_enabled = false\n\
+             scheduler_enabled = \"maybe\"",
+        )
+        .unwrap();
+        let features = Features::load(&source);
+
+        assert!(!features.commands);
+        assert!(features.webhooks);
+        assert!(!features.sonarr_radarr_webhooks);
+        assert!(features.home_assistant_webhook);
+        assert!(features.scheduler);
+        assert!(!features.metrics);
+        assert!(
+            source
+                .finish()
+                .unwrap_err()
+                .to_string()
+                .contains("SCHEDULER_ENABLED")
+        );
+    }
+
+    #[test]
+    fn parses_logging_choices() {
+        let (source, _) =
+            Source::from_toml("[log]\nformat = \"JSON\"\nrotation = \"weekly\"").unwrap();
+        let logging = LoggingConfig::load(&source);
+
+        assert_eq!(logging.format, LogFormat::Json);
+        assert_eq!(logging.rotation, LogRotation::Daily);
+        assert!(
+            source
+                .finish()
+                .unwrap_err()
+                .to_string()
+                .contains("LOG_ROTATION")
+        );
+    }
+
+    #[test]
+    fn parses_push_events() {
+        let (source, _) = Source::from_toml(
+            "[push]\nurl = \"https://ntfy.sh/michel\"\nevents = [\"service_down\", \"typo\"]",
+        )
+        .unwrap();
+        let push = PushConfig::load(&source).unwrap();
+
+        assert_eq!(push.provider, PushProvider::Ntfy);
+        assert_eq!(push.events, vec![PushEvent::ServiceDown]);
+        assert!(source.finish().unwrap_err().to_string().contains("typo"));
+    }
+
+    #[test]
+    fn parses_issue_priorities() {
+        let (source, _) = Source::from_toml(
+            "[issue]\npriorities = [\"video=high\", \"subtitles = low\", \"smell=critical\"]",
+        )
+        .unwrap();
+        let priorities = PriorityConfig::load(&source);
+
+        assert_eq!(
+            priorities.of_issue(Some(IssueCategory::Video)),
+            Priority::High
+        );
+        assert_eq!(
+            priorities.of_issue(Some(IssueCategory::Subtitles)),
+            Priority::Low
+        );
+        assert_eq!(
+            priorities.of_issue(Some(IssueCategory::Audio)),
+            Priority::Normal
+        );
+        assert_eq!(priorities.of_issue(None), Priority::Normal);
+        assert!(source.finish().unwrap_err().to_string().contains("smell"));
+    }
+
+    #[test]
+    fn parses_args() {
+        let args = |args: &[&str]| Args::parse(args.iter().map(|a| a.to_string()));
+
+        assert_eq!(args(&[]).unwrap(), Args::default());
+        assert_eq!(
+            args(&["check", "--config", "bot.toml"]).unwrap(),
+            Args {
+                config_path: Some(PathBuf::from("bot.toml")),
+                check: true,
+            }
+        );
+        assert!(!args(&["--config=check"]).unwrap().check);
+        assert!(args(&["--verbose"]).is_err());
+    }
+
+    #[test]
+    fn rejects_non_string_lists() {
+        assert!(Source::from_toml("[matrix]\nadmin_users = [1, 2]").is_err());
+    }
 }
@@ -0,0 +1,166 @@
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::ruma::events::room::message::{
+    OriginalSyncRoomMessageEvent, Relation, RoomMessageEventContent,
+};
+use tracing::info;
+
+use crate::commands::{self, CommandContext};
+use crate::db::{self, TrackedIssue};
+use crate::matrix;
+
+const PREFIX: &str = "seerr#";
+
+/// Issues answered for a single message, not to flood the room.
+const MAX_MENTIONS: usize = 5;
+
+/// Issue ids mentioned like `seerr#42`, in the order they appear.
+pub fn mentions(body: &str) -> Vec<i64> {
+    // ASCII lowercase keeps the byte offsets of the body
+    let lower = body.to_ascii_lowercase();
+    let mut issue_ids = Vec::new();
+    for (start, _) in lower.match_indices(PREFIX) {
+        let word_start = lower[..start]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric());
+        let digits: String = lower[start + PREFIX.len()..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if word_start
+            && let Ok(issue_id) = digits.parse::<i64>()
+            && !issue_ids.contains(&issue_id)
+        {
+            issue_ids.push(issue_id);
+        }
+    }
+    issue_ids.truncate(MAX_MENTIONS);
+    issue_ids
+}
+
+/// Issues mentioned in a message, none in an edit: the message was answered
+/// when it was sent.
+fn message_mentions(content: &RoomMessageEventContent) -> Vec<i64> {
+    if let Some(Relation::Replacement(_)) = content.relates_to {
+        return Vec::new();
+    }
+    mentions(content.body())
+}
+
+/// Answers a message mentioning issues like `seerr#42` with a line about
+/// each, linking its thread and Seerr page, in the thread of the message.
+/// Issues the bot doesn't know are left out.
+pub async fn reply(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+) -> Result<()> {
+    if room.client().user_id() == Some(event.sender.as_ref()) {
+        return Ok(());
+    }
+    let mut issues = Vec::new();
+    for issue_id in message_mentions(&event.content) {
+        if let Some(issue) = db::get_tracked_issue(&ctx.db, issue_id).await? {
+            issues.push(issue);
+        }
+    }
+    if issues.is_empty() {
+        return Ok(());
+    }
+
+    let room_id = ctx.state.room.room_id().as_str();
+    let markdown = render(room_id, &issues, |issue_id| {
+        ctx.seerr_client.issue_url(issue_id)
+    });
+    let root = match &event.content.relates_to {
+        Some(Relation::Thread(thread)) => &thread.event_id,
+        _ => &event.event_id,
+    };
+    matrix::send_long_markdown(room, Some(root), &markdown).await?;
+    info!(issues = issues.len(), "Answered issue mentions");
+    Ok(())
+}
+
+fn render(
+    room_id: &str,
+    issues: &[TrackedIssue],
+    seerr_url: impl Fn(i64) -> Option<String>,
+) -> String {
+    let mut markdown = String::new();
+    for issue in issues {
+        let line = commands::issue_line(room_id, issue);
+        markdown.push_str(line.trim_end());
+        if let Some(url) = seerr_url(issue.issue_id) {
+            markdown.push_str(&format!(" · [Seerr]({url})"));
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn mentions_are_issue_ids_after_the_prefix() {
+        assert_eq!(
+            mentions("Same as seerr#42, see SEERR#7 and seerr#42"),
+            vec![42, 7]
+        );
+        assert_eq!(mentions("(seerr#3)"), vec![3]);
+        assert!(mentions("myseerr#42 or seerr# or seerr#x").is_empty());
+        assert_eq!(
+            mentions("seerr#1 seerr#2 seerr#3 seerr#4 seerr#5 seerr#6").len(),
+            MAX_MENTIONS
+        );
+    }
+
+    #[test]
+    fn edits_mention_nothing() {
+        let message: RoomMessageEventContent = serde_json::from_value(serde_json::json!({
+            "msgtype": "m.text",
+            "body": "Same as seerr#42",
+        }))
+        .unwrap();
+        assert_eq!(message_mentions(&message), vec![42]);
+
+        let edit: RoomMessageEventContent = serde_json::from_value(serde_json::json!({
+            "msgtype": "m.text",
+            "body": "* Same as seerr#42",
+            "m.new_content": { "msgtype": "m.text", "body": "Same as seerr#42" },
+            "m.relates_to": { "rel_type": "m.replace", "event_id": "$abc" },
+        }))
+        .unwrap();
+        assert!(message_mentions(&edit).is_empty());
+    }
+
+    #[test]
+    fn render_links_thread_and_seerr() {
+        let issue = TrackedIssue {
+            issue_id: 42,
+            matrix_event_id: Some("$abc".to_string()),
+            subject: Some("Dune".to_string()),
+            reported_by: None,
+            category: Some("subtitles".to_string()),
+            problem_season: None,
+            problem_episode: None,
+            status: "open".to_string(),
+            comment_count: 0,
+            created_at: Utc::now(),
+            first_response_at: None,
+            resolved_at: None,
+            resolved_by: None,
+            acknowledged_by: None,
+        };
+        let seerr_url = |id: i64| Some(format!("https://seerr.example.org/issues/{id}"));
+        assert_eq!(
+            render("!room:localhost", &[issue], seerr_url),
+            "- [#42](https://matrix.to/#/!room:localhost/$abc) 🔤 Dune (open) \
+             · [Seerr](https://seerr.example.org/issues/42)\n"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::ruma::OwnedEventId;
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::db::{self, OpenIssue};
+use crate::markdown;
+use crate::matrix;
+
+/// Lists the open issues with the state synced from Seerr and the thread
+/// commands.
+fn render(room_id: &str, issues: &[OpenIssue]) -> String {
+    if issues.is_empty() {
+        return "#### 📋 Open issues\nNo open issues 🎉".to_string();
+    }
+
+    let mut markdown = format!("#### 📋 Open issues ({})\n", issues.len());
+    for issue in issues {
+        let subject = markdown::escape(issue.subject.as_deref().unwrap_or("Untitled issue"));
+        let link = matrix::event_permalink(room_id, &issue.matrix_event_id);
+        markdown.push_str(&format!(
+            "- [#{}]({link}) {subject} · {}\n",
+            issue.issue_id,
+            issue.status.label()
+        ));
+    }
+
+    markdown
+}
+
+/// Re-renders the pinned "open issues" message of the room, posting and
+/// pinning a new one if none exists yet or the previous one can't be edited.
+pub async fn refresh(room: &Room, pool: &PgPool) -> Result<()> {
+    let room_id = room.room_id().to_string();
+    let issues = db::list_open_issue_events(pool, &room_id).await?;
+    let (plain, html) = markdown::render(&render(&room_id, &issues));
+
+    if let Some(event_id) = db::get_dashboard_event_id(pool, &room_id).await? {
+        let event_id: OwnedEventId = event_id.as_str().try_into()?;
+        match matrix::edit_html_message(room, &event_id, &plain, &html).await {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                warn!(%event_id, "Failed to edit dashboard message, posting a new one: {e:#}");
+                if let Err(e) = matrix::unpin_event(room, &event_id).await {
+                    warn!(%event_id, "Failed to unpin previous dashboard message: {e:#}");
+                }
+            }
+        }
+    }
+
+    let event_id = matrix::send_html_message(room, &plain, &html).await?;
+    matrix::pin_event(room, &event_id).await?;
+    db::set_dashboard_event_id(pool, &room_id, event_id.as_str()).await?;
+    info!(%event_id, "Dashboard message posted and pinned");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issue::IssueState;
+
+    #[test]
+    fn render_without_open_issues() {
+        let (plain, _) = markdown::render(&render("!room:localhost", &[]));
+        assert_eq!(plain, "📋 Open issues\nNo open issues 🎉");
+    }
+
+    #[test]
+    fn render_lists_issues_with_thread_links() {
+        let issues = vec![
+            OpenIssue {
+                issue_id: 42,
+                matrix_event_id: "$abc".to_string(),
+                subject: Some("Video playback problem".to_string()),
+                status: IssueState::InProgress,
+            },
+            OpenIssue {
+                issue_id: 43,
+                matrix_event_id: "$def".to_string(),
+                subject: None,
+                status: IssueState::Open,
+            },
+        ];
+
+        let (plain, html) = markdown::render(&render("!room:localhost", &issues));
+
+        assert!(plain.starts_with("📋 Open issues (2)"));
+        assert!(plain.contains(
+            "#42 (https://matrix.to/#/!room:localhost/$abc) Video playback problem · 🟡 In progress"
+        ));
+        assert!(plain.contains("Untitled issue · 🔴 Open"));
+        assert!(html.contains("<a href=\"https://matrix.to/#/!room:localhost/$abc\">#42</a>"));
+    }
+}
@@ -1,93 +1,2503 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+
+use crate::config::DatabasePoolConfig;
+use crate::issue::{IssueCategory, IssueState};
+use crate::remediation::EpisodeScope;
+use crate::request::RequestStatus;
+
+/// Timestamps are read as epoch seconds, sqlx's chrono support pulls in a
+/// second sqlite that conflicts with the matrix-sdk store.
+fn timestamp(epoch_secs: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(epoch_secs, 0).unwrap_or_default()
+}
+
+pub async fn connect(url: &str, config: &DatabasePoolConfig) -> Result<PgPool> {
+    let pool = PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
+        .connect(url)
+        .await?;
+    Ok(pool)
+}
+
+/// Cheap round trip to tell whether the database is reachable.
+pub async fn ping(pool: &PgPool) -> Result<()> {
+    sqlx::query("SELECT 1").execute(pool).await?;
+    Ok(())
+}
 
 pub async fn run_migrations(pool: &PgPool) -> Result<()> {
     sqlx::raw_sql(include_str!("../migrations/001_create_issue_events.sql"))
         .execute(pool)
         .await?;
+    sqlx::raw_sql(include_str!("../migrations/002_create_dashboard.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/003_create_issue_reactions.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/004_create_outbox.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/005_add_issue_card_details.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/006_add_issue_comment_count.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!("../migrations/007_create_bot_settings.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/008_add_issue_status.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/009_create_audit_log.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/010_create_request_events.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/011_create_user_mappings.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/012_allow_pending_issue_events.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!("../migrations/013_create_comment_events.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/014_add_issue_reminders.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/015_add_issue_media.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/016_create_user_reminders.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/017_add_issue_acknowledgement.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!("../migrations/018_add_issue_category.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/019_add_issue_episodes.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/020_add_request_availability.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!("../migrations/021_create_request_votes.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/022_add_issue_duplicates.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/023_create_issue_reports.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/024_create_issue_attachments.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/025_create_quiet_notifications.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/026_create_command_executions.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/027_add_issue_search_indexes.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/028_add_quiet_notification_source.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/029_add_issue_first_response.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/030_create_request_shortcuts.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!("../migrations/031_create_health_tickets.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!(
+        "../migrations/032_create_pending_seerr_actions.sql"
+    ))
+    .execute(pool)
+    .await?;
+    sqlx::raw_sql(include_str!("../migrations/033_create_room_routes.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/034_add_issue_follow_ups.sql"))
+        .execute(pool)
+        .await?;
+    sqlx::raw_sql(include_str!("../migrations/035_add_outbox_escalation.sql"))
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
-pub async fn insert_issue_event(
+/// Records an issue before its card is posted, so a crash between sending the
+/// card and storing its event id leaves a pending row to repair. Returns
+/// `false` when the card was already posted.
+pub async fn begin_issue_event(
     pool: &PgPool,
     issue_id: i64,
-    matrix_event_id: &str,
     matrix_room_id: &str,
+    details: &IssueDetails,
+) -> Result<bool> {
+    let (season, episode) = details.episodes.map(|e| e.numbers()).unzip();
+    let result = sqlx::query(
+        "INSERT INTO issue_events \
+             (issue_id, matrix_room_id, subject, description, reported_by, category, problem_season, problem_episode) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+         ON CONFLICT (issue_id) DO UPDATE SET matrix_room_id = $2, subject = $3, description = $4, reported_by = $5, \
+             category = $6, problem_season = $7, problem_episode = $8 \
+         WHERE issue_events.matrix_event_id IS NULL",
+    )
+    .bind(issue_id)
+    .bind(matrix_room_id)
+    .bind(&details.subject)
+    .bind(&details.description)
+    .bind(&details.reported_by)
+    .bind(details.category.map(|c| c.as_str()))
+    .bind(season)
+    .bind(episode)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn update_issue_details(
+    pool: &PgPool,
+    issue_id: i64,
+    details: &IssueDetails,
+) -> Result<()> {
+    let (season, episode) = details.episodes.map(|e| e.numbers()).unzip();
+    sqlx::query(
+        "UPDATE issue_events SET subject = $2, description = $3, reported_by = $4, category = $5, \
+             problem_season = $6, problem_episode = $7 \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .bind(&details.subject)
+    .bind(&details.description)
+    .bind(&details.reported_by)
+    .bind(details.category.map(|c| c.as_str()))
+    .bind(season)
+    .bind(episode)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Drops an issue row whose card could not be posted.
+pub async fn delete_pending_issue_event(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM issue_events WHERE issue_id = $1 AND matrix_event_id IS NULL")
+        .bind(issue_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn list_pending_issue_events(pool: &PgPool) -> Result<Vec<(i64, IssueDetails)>> {
+    let rows = sqlx::query_as::<_, IssueDetailsRow>(&format!(
+        "SELECT {ISSUE_DETAILS_COLUMNS} FROM issue_events \
+         WHERE matrix_event_id IS NULL ORDER BY created_at"
+    ))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(IssueDetails::from_row).collect())
+}
+
+/// What the issue card shows, kept so the card can be re-posted.
+pub struct IssueDetails {
+    pub subject: String,
+    pub description: String,
+    pub reported_by: String,
+    /// Unknown when the Seerr webhook template has no `issue_type`.
+    pub category: Option<IssueCategory>,
+    /// Only set for TV issues.
+    pub episodes: Option<EpisodeScope>,
+}
+
+type IssueDetailsRow = (
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Option<i64>,
+);
+
+const ISSUE_DETAILS_COLUMNS: &str =
+    "issue_id, subject, description, reported_by, category, problem_season, problem_episode";
+
+impl IssueDetails {
+    fn from_row(
+        (issue_id, subject, description, reported_by, category, season, episode): IssueDetailsRow,
+    ) -> (i64, Self) {
+        let details = Self {
+            subject: subject.unwrap_or_else(|| "Untitled issue".to_string()),
+            description: description.unwrap_or_default(),
+            reported_by: reported_by.unwrap_or_else(|| "unknown".to_string()),
+            category: category.as_deref().and_then(IssueCategory::parse),
+            episodes: EpisodeScope::from_columns(season, episode),
+        };
+        (issue_id, details)
+    }
+}
+
+pub async fn get_issue_details(pool: &PgPool, issue_id: i64) -> Result<Option<IssueDetails>> {
+    let row = sqlx::query_as::<_, IssueDetailsRow>(&format!(
+        "SELECT {ISSUE_DETAILS_COLUMNS} FROM issue_events WHERE issue_id = $1"
+    ))
+    .bind(issue_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| IssueDetails::from_row(row).1))
+}
+
+pub async fn update_issue_event_id(
+    pool: &PgPool,
+    issue_id: i64,
+    matrix_event_id: &str,
 ) -> Result<()> {
+    // Duplicates sharing the card follow it
     sqlx::query(
-        "INSERT INTO issue_events (issue_id, matrix_event_id, matrix_room_id) VALUES ($1, $2, $3)",
+        "UPDATE issue_events SET matrix_event_id = $2 WHERE issue_id = $1 OR duplicate_of = $1",
     )
     .bind(issue_id)
     .bind(matrix_event_id)
-    .bind(matrix_room_id)
     .execute(pool)
     .await?;
     Ok(())
 }
 
+/// Records `issue_id` as a duplicate of `duplicate_of`, sharing its card
+/// `matrix_event_id` from now on.
+pub async fn mark_duplicate(
+    pool: &PgPool,
+    issue_id: i64,
+    duplicate_of: i64,
+    matrix_event_id: &str,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET duplicate_of = $2, matrix_event_id = $3 WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .bind(duplicate_of)
+    .bind(matrix_event_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_duplicate_of(pool: &PgPool, issue_id: i64) -> Result<Option<i64>> {
+    let row = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT duplicate_of FROM issue_events WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(|(duplicate_of,)| duplicate_of))
+}
+
+/// The unresolved issue with its own card about the same media, other than
+/// `issue_id`.
+pub async fn find_open_issue_for_media(
+    pool: &PgPool,
+    issue_id: i64,
+    media: &IssueMedia,
+) -> Result<Option<IssueEvent>> {
+    let Some(media_type) = &media.media_type else {
+        return Ok(None);
+    };
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, matrix_room_id FROM issue_events \
+         WHERE issue_id <> $1 AND status <> 'resolved' AND matrix_event_id IS NOT NULL \
+         AND duplicate_of IS NULL AND media_type = $2 \
+         AND (media_tmdb_id = $3 OR media_tvdb_id = $4) \
+         ORDER BY issue_id LIMIT 1",
+    )
+    .bind(issue_id)
+    .bind(media_type)
+    .bind(media.tmdb_id)
+    .bind(media.tvdb_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(
+        row.map(|(issue_id, matrix_event_id, matrix_room_id)| IssueEvent {
+            issue_id,
+            matrix_event_id,
+            matrix_room_id,
+        }),
+    )
+}
+
 pub struct IssueEvent {
     pub issue_id: i64,
     pub matrix_event_id: String,
     pub matrix_room_id: String,
-    pub reaction_event_id: Option<String>,
 }
 
 pub async fn get_issue_event(pool: &PgPool, issue_id: i64) -> Result<Option<IssueEvent>> {
-    let row = sqlx::query_as::<_, (i64, String, String, Option<String>)>(
-        "SELECT issue_id, matrix_event_id, matrix_room_id, reaction_event_id FROM issue_events WHERE issue_id = $1",
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, matrix_room_id FROM issue_events \
+         WHERE issue_id = $1 AND matrix_event_id IS NOT NULL",
     )
     .bind(issue_id)
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(
-        |(issue_id, matrix_event_id, matrix_room_id, reaction_event_id)| IssueEvent {
+    Ok(
+        row.map(|(issue_id, matrix_event_id, matrix_room_id)| IssueEvent {
             issue_id,
             matrix_event_id,
             matrix_room_id,
+        }),
+    )
+}
+
+pub struct IssueReaction {
+    pub state: String,
+    pub reaction_event_id: String,
+}
+
+pub async fn list_issue_reactions(pool: &PgPool, issue_id: i64) -> Result<Vec<IssueReaction>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "SELECT state, reaction_event_id FROM issue_reactions WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(state, reaction_event_id)| IssueReaction {
+            state,
             reaction_event_id,
-        },
-    ))
+        })
+        .collect())
 }
 
-pub async fn set_reaction_event_id(
+pub async fn insert_issue_reaction(
     pool: &PgPool,
     issue_id: i64,
+    state: &str,
     reaction_event_id: &str,
 ) -> Result<()> {
-    sqlx::query("UPDATE issue_events SET reaction_event_id = $1 WHERE issue_id = $2")
-        .bind(reaction_event_id)
+    sqlx::query(
+        "INSERT INTO issue_reactions (issue_id, state, reaction_event_id) VALUES ($1, $2, $3) \
+         ON CONFLICT (issue_id, state) DO UPDATE SET reaction_event_id = $3, created_at = NOW()",
+    )
+    .bind(issue_id)
+    .bind(state)
+    .bind(reaction_event_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn clear_issue_reactions(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM issue_reactions WHERE issue_id = $1")
         .bind(issue_id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
-pub async fn clear_reaction_event_id(pool: &PgPool, issue_id: i64) -> Result<()> {
-    sqlx::query("UPDATE issue_events SET reaction_event_id = NULL WHERE issue_id = $1")
+pub async fn delete_issue_reaction(pool: &PgPool, issue_id: i64, state: &str) -> Result<()> {
+    sqlx::query("DELETE FROM issue_reactions WHERE issue_id = $1 AND state = $2")
         .bind(issue_id)
+        .bind(state)
         .execute(pool)
         .await?;
     Ok(())
 }
 
+/// The issue of a card. Duplicates share the card of the issue they
+/// duplicate, which is the one returned.
 pub async fn get_issue_event_by_matrix_event_id(
     pool: &PgPool,
     matrix_event_id: &str,
 ) -> Result<Option<IssueEvent>> {
-    let row = sqlx::query_as::<_, (i64, String, String, Option<String>)>(
-        "SELECT issue_id, matrix_event_id, matrix_room_id, reaction_event_id FROM issue_events WHERE matrix_event_id = $1",
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, matrix_room_id FROM issue_events \
+         WHERE matrix_event_id = $1 \
+         ORDER BY duplicate_of IS NOT NULL, issue_id LIMIT 1",
     )
     .bind(matrix_event_id)
     .fetch_optional(pool)
     .await?;
 
-    Ok(row.map(
-        |(issue_id, matrix_event_id, matrix_room_id, reaction_event_id)| IssueEvent {
+    Ok(
+        row.map(|(issue_id, matrix_event_id, matrix_room_id)| IssueEvent {
             issue_id,
             matrix_event_id,
             matrix_room_id,
-            reaction_event_id,
-        },
-    ))
+        }),
+    )
+}
+
+pub async fn increment_comment_count(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET comment_count = comment_count + 1, \
+             last_activity_at = NOW(), reminder_count = 0 \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_issue_status(pool: &PgPool, issue_id: i64) -> Result<Option<IssueState>> {
+    let row = sqlx::query_as::<_, (String,)>("SELECT status FROM issue_events WHERE issue_id = $1")
+        .bind(issue_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.and_then(|(status,)| IssueState::parse(&status)))
+}
+
+/// Records the new state of an issue, with who resolved it when `state` is
+/// [`IssueState::Resolved`] or acknowledged it when it is
+/// [`IssueState::Acknowledged`]. The first resolver is kept when an issue
+/// resolved by command is then reported resolved by the Seerr webhook.
+pub async fn set_issue_status(
+    pool: &PgPool,
+    issue_id: i64,
+    state: IssueState,
+    actor: Option<&str>,
+) -> Result<()> {
+    let resolved = state == IssueState::Resolved;
+    let acknowledged = state == IssueState::Acknowledged;
+    sqlx::query(
+        "UPDATE issue_events SET status = $2, last_activity_at = NOW(), reminder_count = 0, \
+             resolved_at = CASE WHEN $3 THEN COALESCE(resolved_at, NOW()) END, \
+             resolved_by = CASE WHEN $3 THEN COALESCE(resolved_by, $4) END, \
+             awaiting_reporter_since = CASE WHEN NOT $3 THEN awaiting_reporter_since END, \
+             acknowledged_at = CASE WHEN $5 THEN NOW() ELSE acknowledged_at END, \
+             acknowledged_by = CASE WHEN $5 THEN $4 ELSE acknowledged_by END, \
+             first_response_at = COALESCE(first_response_at, CASE WHEN $3 OR $5 THEN NOW() END) \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .bind(state.as_str())
+    .bind(resolved)
+    .bind(actor)
+    .bind(acknowledged)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Where a comment was first written, the other side holding the mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentOrigin {
+    Seerr,
+    Matrix,
+}
+
+impl CommentOrigin {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommentOrigin::Seerr => "seerr",
+            CommentOrigin::Matrix => "matrix",
+        }
+    }
+}
+
+pub async fn insert_comment_event(
+    pool: &PgPool,
+    issue_id: i64,
+    seerr_comment_id: Option<i64>,
+    matrix_event_id: &str,
+    origin: CommentOrigin,
+    message: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO comment_events (issue_id, seerr_comment_id, matrix_event_id, origin, message) \
+         VALUES ($1, $2, $3, $4, $5) ON CONFLICT (seerr_comment_id) DO NOTHING",
+    )
+    .bind(issue_id)
+    .bind(seerr_comment_id)
+    .bind(matrix_event_id)
+    .bind(origin.as_str())
+    .bind(message)
+    .execute(pool)
+    .await?;
+    // Comments written in the room come from the admins
+    if origin == CommentOrigin::Matrix {
+        record_first_response(pool, issue_id).await?;
+        set_awaiting_reporter(pool, issue_id, true).await?;
+    }
+    Ok(())
+}
+
+/// Marks the issue as answered, unless it already was.
+pub async fn record_first_response(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET first_response_at = NOW() \
+         WHERE issue_id = $1 AND first_response_at IS NULL",
+    )
+    .bind(issue_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Starts waiting for the reporter to confirm an admin's answer, or stops
+/// when the reporter replied. Either way a previous follow-up is forgotten.
+pub async fn set_awaiting_reporter(pool: &PgPool, issue_id: i64, awaiting: bool) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET follow_up_sent_at = NULL, \
+             awaiting_reporter_since = CASE WHEN $2 THEN NOW() END \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .bind(awaiting)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub struct FollowUpIssue {
+    pub issue_id: i64,
+    pub matrix_event_id: String,
+    pub reported_by: Option<String>,
+}
+
+/// Unresolved issues waiting for their reporter since before `before`, who
+/// wasn't asked to confirm yet.
+pub async fn list_issues_to_follow_up(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+) -> Result<Vec<FollowUpIssue>> {
+    let rows = sqlx::query_as::<_, (i64, String, Option<String>)>(
+        "SELECT issue_id, matrix_event_id, reported_by FROM issue_events \
+         WHERE status <> 'resolved' AND matrix_event_id IS NOT NULL \
+         AND awaiting_reporter_since <= to_timestamp($1) AND follow_up_sent_at IS NULL \
+         ORDER BY issue_id",
+    )
+    .bind(before.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(issue_id, matrix_event_id, reported_by)| FollowUpIssue {
+            issue_id,
+            matrix_event_id,
+            reported_by,
+        })
+        .collect())
+}
+
+pub async fn record_follow_up(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query("UPDATE issue_events SET follow_up_sent_at = NOW() WHERE issue_id = $1")
+        .bind(issue_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Unresolved issues whose reporter was asked to confirm before `before` and
+/// still hasn't replied.
+pub async fn list_issues_to_close_after_follow_up(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+) -> Result<Vec<IssueEvent>> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, matrix_room_id FROM issue_events \
+         WHERE status <> 'resolved' AND matrix_event_id IS NOT NULL \
+         AND awaiting_reporter_since IS NOT NULL AND follow_up_sent_at <= to_timestamp($1) \
+         ORDER BY issue_id",
+    )
+    .bind(before.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(issue_id, matrix_event_id, matrix_room_id)| IssueEvent {
+            issue_id,
+            matrix_event_id,
+            matrix_room_id,
+        })
+        .collect())
+}
+
+pub struct StaleIssue {
+    pub issue_id: i64,
+    pub matrix_event_id: String,
+    pub subject: Option<String>,
+    pub episodes: Option<EpisodeScope>,
+    pub last_activity_at: DateTime<Utc>,
+    pub reminded_at: Option<DateTime<Utc>>,
+    pub reminder_count: i32,
+}
+
+type StaleIssueRow = (
+    i64,
+    String,
+    Option<String>,
+    Option<i64>,
+    Option<i64>,
+    i64,
+    Option<i64>,
+    i32,
+);
+
+/// Unresolved, unmuted issues without activity since `before`.
+pub async fn list_stale_issues(pool: &PgPool, before: DateTime<Utc>) -> Result<Vec<StaleIssue>> {
+    let rows = sqlx::query_as::<_, StaleIssueRow>(
+        "SELECT issue_id, matrix_event_id, subject, problem_season, problem_episode, \
+         EXTRACT(EPOCH FROM last_activity_at)::BIGINT, \
+         EXTRACT(EPOCH FROM reminded_at)::BIGINT, reminder_count FROM issue_events \
+         WHERE status <> 'resolved' AND NOT reminders_muted AND matrix_event_id IS NOT NULL \
+         AND last_activity_at <= to_timestamp($1) ORDER BY last_activity_at",
+    )
+    .bind(before.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                issue_id,
+                matrix_event_id,
+                subject,
+                season,
+                episode,
+                last_activity_at,
+                reminded_at,
+                reminder_count,
+            )| {
+                StaleIssue {
+                    issue_id,
+                    matrix_event_id,
+                    subject,
+                    episodes: EpisodeScope::from_columns(season, episode),
+                    last_activity_at: timestamp(last_activity_at),
+                    reminded_at: reminded_at.map(timestamp),
+                    reminder_count,
+                }
+            },
+        )
+        .collect())
+}
+
+pub async fn record_reminder(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET reminder_count = reminder_count + 1, reminded_at = NOW() \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_reminders_muted(pool: &PgPool, issue_id: i64, muted: bool) -> Result<()> {
+    sqlx::query("UPDATE issue_events SET reminders_muted = $2 WHERE issue_id = $1")
+        .bind(issue_id)
+        .bind(muted)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Media an issue is about, used to match Sonarr and Radarr imports.
+#[derive(Debug, Default, PartialEq)]
+pub struct IssueMedia {
+    /// `movie` or `tv`, as in Seerr.
+    pub media_type: Option<String>,
+    pub tmdb_id: Option<i64>,
+    pub tvdb_id: Option<i64>,
+}
+
+pub async fn set_issue_media(pool: &PgPool, issue_id: i64, media: &IssueMedia) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET media_type = $2, media_tmdb_id = $3, media_tvdb_id = $4 \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .bind(&media.media_type)
+    .bind(media.tmdb_id)
+    .bind(media.tvdb_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_issue_media(pool: &PgPool, issue_id: i64) -> Result<Option<IssueMedia>> {
+    let row = sqlx::query_as::<_, (Option<String>, Option<i64>, Option<i64>)>(
+        "SELECT media_type, media_tmdb_id, media_tvdb_id FROM issue_events WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(media_type, tmdb_id, tvdb_id)| IssueMedia {
+        media_type,
+        tmdb_id,
+        tvdb_id,
+    }))
+}
+
+/// Unresolved issues about the media matching the TMDB or TVDB id, leaving
+/// out those told about an import in the last hour so a season pack gives a
+/// single notice.
+pub async fn list_issues_to_notify_of_import(
+    pool: &PgPool,
+    media_type: &str,
+    tmdb_id: Option<i64>,
+    tvdb_id: Option<i64>,
+) -> Result<Vec<IssueEvent>> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, matrix_room_id FROM issue_events \
+         WHERE status <> 'resolved' AND matrix_event_id IS NOT NULL AND media_type = $1 \
+         AND (media_tmdb_id = $2 OR media_tvdb_id = $3) \
+         AND (import_noticed_at IS NULL OR import_noticed_at < NOW() - INTERVAL '1 hour') \
+         ORDER BY issue_id",
+    )
+    .bind(media_type)
+    .bind(tmdb_id)
+    .bind(tvdb_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(issue_id, matrix_event_id, matrix_room_id)| IssueEvent {
+            issue_id,
+            matrix_event_id,
+            matrix_room_id,
+        })
+        .collect())
+}
+
+/// Records that the issue thread was told about a new import. The notice
+/// counts as activity, so anything said afterwards shows in
+/// `last_activity_at`.
+pub async fn record_import_notice(pool: &PgPool, issue_id: i64) -> Result<()> {
+    sqlx::query(
+        "UPDATE issue_events SET import_noticed_at = NOW(), last_activity_at = NOW() \
+         WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Unresolved issues told about an import before `before`, without any
+/// activity since.
+pub async fn list_issues_to_auto_resolve(
+    pool: &PgPool,
+    before: DateTime<Utc>,
+) -> Result<Vec<IssueEvent>> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, matrix_room_id FROM issue_events \
+         WHERE status <> 'resolved' AND matrix_event_id IS NOT NULL \
+         AND import_noticed_at <= to_timestamp($1) AND last_activity_at <= import_noticed_at \
+         ORDER BY issue_id",
+    )
+    .bind(before.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(issue_id, matrix_event_id, matrix_room_id)| IssueEvent {
+            issue_id,
+            matrix_event_id,
+            matrix_room_id,
+        })
+        .collect())
+}
+
+pub struct CommentEvent {
+    pub issue_id: i64,
+    pub matrix_event_id: String,
+    pub origin: CommentOrigin,
+}
+
+pub async fn get_comment_event(
+    pool: &PgPool,
+    seerr_comment_id: i64,
+) -> Result<Option<CommentEvent>> {
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT issue_id, matrix_event_id, origin FROM comment_events WHERE seerr_comment_id = $1",
+    )
+    .bind(seerr_comment_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(issue_id, matrix_event_id, origin)| CommentEvent {
+        issue_id,
+        matrix_event_id,
+        origin: if origin == "matrix" {
+            CommentOrigin::Matrix
+        } else {
+            CommentOrigin::Seerr
+        },
+    }))
+}
+
+pub async fn update_comment_message(
+    pool: &PgPool,
+    seerr_comment_id: i64,
+    message: &str,
+) -> Result<()> {
+    sqlx::query("UPDATE comment_events SET message = $2 WHERE seerr_comment_id = $1")
+        .bind(seerr_comment_id)
+        .bind(message)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_comment_event(pool: &PgPool, seerr_comment_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM comment_events WHERE seerr_comment_id = $1")
+        .bind(seerr_comment_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub struct IssueHistory {
+    pub created_at: DateTime<Utc>,
+    pub reported_by: Option<String>,
+    pub comment_count: i32,
+}
+
+pub async fn get_issue_history(pool: &PgPool, issue_id: i64) -> Result<Option<IssueHistory>> {
+    let row = sqlx::query_as::<_, (i64, Option<String>, i32)>(
+        "SELECT EXTRACT(EPOCH FROM created_at)::BIGINT, reported_by, comment_count \
+         FROM issue_events WHERE issue_id = $1",
+    )
+    .bind(issue_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(
+        row.map(|(created_at, reported_by, comment_count)| IssueHistory {
+            created_at: timestamp(created_at),
+            reported_by,
+            comment_count,
+        }),
+    )
+}
+
+#[derive(Debug, Serialize)]
+pub struct TrackedIssue {
+    pub issue_id: i64,
+    pub matrix_event_id: Option<String>,
+    pub subject: Option<String>,
+    pub reported_by: Option<String>,
+    pub category: Option<String>,
+    /// Season and episode of a TV issue, 0 meaning all of them.
+    pub problem_season: Option<i64>,
+    pub problem_episode: Option<i64>,
+    pub status: String,
+    pub comment_count: i32,
+    pub created_at: DateTime<Utc>,
+    /// First acknowledgement, resolution or comment of someone else than the
+    /// reporter.
+    pub first_response_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<String>,
+    pub acknowledged_by: Option<String>,
+}
+
+type TrackedIssueRow = (
+    i64,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    Option<i64>,
+    String,
+    i32,
+    i64,
+    Option<i64>,
+    Option<i64>,
+    Option<String>,
+    Option<String>,
+);
+
+const TRACKED_ISSUE_COLUMNS: &str = "issue_id, matrix_event_id, subject, reported_by, category, \
+     problem_season, problem_episode, status, comment_count, \
+     EXTRACT(EPOCH FROM created_at)::BIGINT, EXTRACT(EPOCH FROM first_response_at)::BIGINT, \
+     EXTRACT(EPOCH FROM resolved_at)::BIGINT, resolved_by, acknowledged_by";
+
+impl From<TrackedIssueRow> for TrackedIssue {
+    fn from(
+        (
+            issue_id,
+            matrix_event_id,
+            subject,
+            reported_by,
+            category,
+            problem_season,
+            problem_episode,
+            status,
+            comment_count,
+            created_at,
+            first_response_at,
+            resolved_at,
+            resolved_by,
+            acknowledged_by,
+        ): TrackedIssueRow,
+    ) -> Self {
+        Self {
+            issue_id,
+            matrix_event_id,
+            subject,
+            reported_by,
+            category,
+            problem_season,
+            problem_episode,
+            status,
+            comment_count,
+            created_at: timestamp(created_at),
+            first_response_at: first_response_at.map(timestamp),
+            resolved_at: resolved_at.map(timestamp),
+            resolved_by,
+            acknowledged_by,
+        }
+    }
+}
+
+impl TrackedIssue {
+    pub fn episodes(&self) -> Option<EpisodeScope> {
+        EpisodeScope::from_columns(self.problem_season, self.problem_episode)
+    }
+}
+
+pub async fn get_tracked_issue(pool: &PgPool, issue_id: i64) -> Result<Option<TrackedIssue>> {
+    let row = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events WHERE issue_id = $1"
+    ))
+    .bind(issue_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(TrackedIssue::from))
+}
+
+/// Most recently created issues first, optionally only those in `status`.
+pub async fn list_tracked_issues(
+    pool: &PgPool,
+    status: Option<IssueState>,
+    limit: i64,
+) -> Result<Vec<TrackedIssue>> {
+    let rows = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events WHERE $1::TEXT IS NULL OR status = $1 \
+         ORDER BY created_at DESC LIMIT $2"
+    ))
+    .bind(status.map(|s| s.as_str()))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(TrackedIssue::from).collect())
+}
+
+/// Issues that are open or in progress, oldest first.
+pub async fn list_unresolved_issues(pool: &PgPool) -> Result<Vec<TrackedIssue>> {
+    let rows = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events \
+         WHERE status <> 'resolved' AND matrix_event_id IS NOT NULL ORDER BY created_at"
+    ))
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(TrackedIssue::from).collect())
+}
+
+/// Issues with a card whose subject, description or comments match `query`,
+/// in Postgres `websearch_to_tsquery` syntax, most recent first.
+pub async fn search_issues(pool: &PgPool, query: &str, limit: i64) -> Result<Vec<TrackedIssue>> {
+    let rows = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events \
+         WHERE matrix_event_id IS NOT NULL AND ( \
+             to_tsvector('english', coalesce(subject, '') || ' ' || coalesce(description, '')) \
+                 @@ websearch_to_tsquery('english', $1) \
+             OR EXISTS (SELECT 1 FROM comment_events \
+                 WHERE comment_events.issue_id = issue_events.issue_id \
+                 AND to_tsvector('english', message) @@ websearch_to_tsquery('english', $1))) \
+         ORDER BY created_at DESC LIMIT $2"
+    ))
+    .bind(query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(TrackedIssue::from).collect())
+}
+
+/// Issues created since `since`, or all of them, oldest first.
+pub async fn list_issues_created_since(
+    pool: &PgPool,
+    since: Option<DateTime<Utc>>,
+) -> Result<Vec<TrackedIssue>> {
+    let rows = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events \
+         WHERE $1::FLOAT8 IS NULL OR created_at >= to_timestamp($1) ORDER BY created_at"
+    ))
+    .bind(since.map(|since| since.timestamp() as f64))
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(TrackedIssue::from).collect())
+}
+
+pub async fn list_issues_resolved_since(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<TrackedIssue>> {
+    let rows = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events \
+         WHERE status = 'resolved' AND resolved_at >= to_timestamp($1) ORDER BY resolved_at"
+    ))
+    .bind(since.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(TrackedIssue::from).collect())
+}
+
+/// Resolved issues, the most recently resolved first, skipping `offset`.
+pub async fn list_resolved_issues(
+    pool: &PgPool,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<TrackedIssue>> {
+    let rows = sqlx::query_as::<_, TrackedIssueRow>(&format!(
+        "SELECT {TRACKED_ISSUE_COLUMNS} FROM issue_events \
+         WHERE status = 'resolved' AND resolved_at IS NOT NULL \
+         ORDER BY resolved_at DESC, issue_id DESC LIMIT $1 OFFSET $2"
+    ))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(TrackedIssue::from).collect())
+}
+
+pub async fn count_resolved_issues(pool: &PgPool) -> Result<i64> {
+    let (count,) = sqlx::query_as::<_, (i64,)>(
+        "SELECT COUNT(*) FROM issue_events WHERE status = 'resolved' AND resolved_at IS NOT NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
+
+/// Last comment of each issue before it was resolved, the one `!issues
+/// resolve "comment"` sends.
+pub async fn resolution_comments(pool: &PgPool, issue_ids: &[i64]) -> Result<Vec<(i64, String)>> {
+    let rows = sqlx::query_as::<_, (i64, String)>(
+        "SELECT DISTINCT ON (c.issue_id) c.issue_id, c.message \
+         FROM comment_events c JOIN issue_events i ON i.issue_id = c.issue_id \
+         WHERE c.issue_id = ANY($1) AND c.created_at <= i.resolved_at \
+         ORDER BY c.issue_id, c.created_at DESC",
+    )
+    .bind(issue_ids)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+pub struct OpenIssue {
+    pub issue_id: i64,
+    pub matrix_event_id: String,
+    pub subject: Option<String>,
+    pub status: IssueState,
+}
+
+/// Tracked issues of a room that have not been marked as resolved yet.
+pub async fn list_open_issue_events(pool: &PgPool, matrix_room_id: &str) -> Result<Vec<OpenIssue>> {
+    let rows = sqlx::query_as::<_, (i64, String, Option<String>, String)>(
+        "SELECT issue_id, matrix_event_id, subject, status FROM issue_events \
+         WHERE matrix_room_id = $1 AND matrix_event_id IS NOT NULL AND status <> 'resolved' \
+         ORDER BY created_at",
+    )
+    .bind(matrix_room_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(issue_id, matrix_event_id, subject, status)| OpenIssue {
+            issue_id,
+            matrix_event_id,
+            subject,
+            status: IssueState::parse(&status).unwrap_or(IssueState::Open),
+        })
+        .collect())
+}
+
+pub async fn get_dashboard_event_id(pool: &PgPool, matrix_room_id: &str) -> Result<Option<String>> {
+    let row = sqlx::query_as::<_, (String,)>(
+        "SELECT matrix_event_id FROM dashboard_messages WHERE matrix_room_id = $1",
+    )
+    .bind(matrix_room_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(event_id,)| event_id))
+}
+
+pub async fn set_dashboard_event_id(
+    pool: &PgPool,
+    matrix_room_id: &str,
+    matrix_event_id: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO dashboard_messages (matrix_room_id, matrix_event_id) VALUES ($1, $2) \
+         ON CONFLICT (matrix_room_id) DO UPDATE SET matrix_event_id = $2, updated_at = NOW()",
+    )
+    .bind(matrix_room_id)
+    .bind(matrix_event_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub struct OutboxItem {
+    pub id: i64,
+    pub payload: String,
+    pub attempts: i32,
+}
+
+pub async fn enqueue_outbox(pool: &PgPool, payload: &str, error: &str) -> Result<i64> {
+    let (id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO outbox (payload, attempts, last_error, next_attempt_at) \
+         VALUES ($1::jsonb, 1, $2, NOW() + INTERVAL '30 seconds') RETURNING id",
+    )
+    .bind(payload)
+    .bind(error)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Outbox items whose retry is due, oldest first. With `include_pending`
+/// every item is returned regardless of its next attempt time.
+pub async fn list_due_outbox(
+    pool: &PgPool,
+    include_pending: bool,
+    max_attempts: i32,
+    limit: i64,
+) -> Result<Vec<OutboxItem>> {
+    let rows = sqlx::query_as::<_, (i64, String, i32)>(
+        "SELECT id, payload::text, attempts FROM outbox \
+         WHERE ($1 OR next_attempt_at <= NOW()) AND attempts < $2 ORDER BY id LIMIT $3",
+    )
+    .bind(include_pending)
+    .bind(max_attempts)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, payload, attempts)| OutboxItem {
+            id,
+            payload,
+            attempts,
+        })
+        .collect())
+}
+
+pub async fn delete_outbox(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM outbox WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Webhook held during quiet hours.
+pub struct QuietNotification {
+    pub id: i64,
+    /// Webhook the payload came in, `seerr` or `home_assistant`.
+    pub source: String,
+    pub payload: String,
+}
+
+pub async fn enqueue_quiet_notification(pool: &PgPool, source: &str, payload: &str) -> Result<i64> {
+    let (id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO quiet_notifications (source, payload) VALUES ($1, $2::jsonb) RETURNING id",
+    )
+    .bind(source)
+    .bind(payload)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Held webhooks in the order they were received.
+pub async fn list_quiet_notifications(pool: &PgPool) -> Result<Vec<QuietNotification>> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT id, source, payload::text FROM quiet_notifications ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, source, payload)| QuietNotification {
+            id,
+            source,
+            payload,
+        })
+        .collect())
+}
+
+/// Whether webhooks from `source` are held, waiting to be delivered.
+pub async fn has_quiet_notifications(pool: &PgPool, source: &str) -> Result<bool> {
+    let (held,) = sqlx::query_as::<_, (bool,)>(
+        "SELECT EXISTS (SELECT 1 FROM quiet_notifications WHERE source = $1)",
+    )
+    .bind(source)
+    .fetch_one(pool)
+    .await?;
+    Ok(held)
+}
+
+pub async fn delete_quiet_notification(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM quiet_notifications WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Records that the command sent in `matrix_event_id` is being run. Returns
+/// `false` when it already was, for it not to run again.
+pub async fn claim_command_execution(
+    pool: &PgPool,
+    matrix_event_id: &str,
+    command: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "INSERT INTO command_executions (matrix_event_id, command) VALUES ($1, $2) \
+         ON CONFLICT (matrix_event_id) DO NOTHING",
+    )
+    .bind(matrix_event_id)
+    .bind(command)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn set_command_outcome(
+    pool: &PgPool,
+    matrix_event_id: &str,
+    outcome: &str,
+) -> Result<()> {
+    sqlx::query("UPDATE command_executions SET outcome = $2 WHERE matrix_event_id = $1")
+        .bind(matrix_event_id)
+        .bind(outcome)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn record_outbox_failure(
+    pool: &PgPool,
+    id: i64,
+    error: &str,
+    retry_in_secs: i64,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE outbox SET attempts = attempts + 1, last_error = $2, \
+         next_attempt_at = NOW() + make_interval(secs => $3) WHERE id = $1",
+    )
+    .bind(id)
+    .bind(error)
+    .bind(retry_in_secs as f64)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub struct NewPendingSeerrAction<'a> {
+    pub issue_id: i64,
+    /// `comment` or `resolve`.
+    pub action: &'a str,
+    pub message: Option<&'a str>,
+    pub comment: Option<&'a str>,
+    pub matrix_event_id: &'a str,
+    pub thread_root_event_id: &'a str,
+    pub requested_by: &'a str,
+}
+
+pub async fn insert_pending_seerr_action(
+    pool: &PgPool,
+    action: &NewPendingSeerrAction<'_>,
+) -> Result<i64> {
+    let (id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO pending_seerr_actions \
+             (issue_id, action, message, comment, matrix_event_id, thread_root_event_id, requested_by) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+    )
+    .bind(action.issue_id)
+    .bind(action.action)
+    .bind(action.message)
+    .bind(action.comment)
+    .bind(action.matrix_event_id)
+    .bind(action.thread_root_event_id)
+    .bind(action.requested_by)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+pub struct PendingSeerrAction {
+    pub id: i64,
+    pub issue_id: i64,
+    pub action: String,
+    pub message: Option<String>,
+    pub comment: Option<String>,
+    pub matrix_event_id: String,
+    pub thread_root_event_id: String,
+    pub requested_by: String,
+    pub attempts: i32,
+}
+
+type PendingSeerrActionRow = (
+    i64,
+    i64,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+    String,
+    String,
+    i32,
+);
+
+/// Queued Seerr actions, in the order they were sent.
+pub async fn list_pending_seerr_actions(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<PendingSeerrAction>> {
+    let rows = sqlx::query_as::<_, PendingSeerrActionRow>(
+        "SELECT id, issue_id, action, message, comment, matrix_event_id, thread_root_event_id, \
+             requested_by, attempts \
+         FROM pending_seerr_actions ORDER BY id LIMIT $1",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                id,
+                issue_id,
+                action,
+                message,
+                comment,
+                matrix_event_id,
+                thread_root_event_id,
+                requested_by,
+                attempts,
+            )| PendingSeerrAction {
+                id,
+                issue_id,
+                action,
+                message,
+                comment,
+                matrix_event_id,
+                thread_root_event_id,
+                requested_by,
+                attempts,
+            },
+        )
+        .collect())
+}
+
+pub async fn delete_pending_seerr_action(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM pending_seerr_actions WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn record_pending_seerr_action_failure(
+    pool: &PgPool,
+    id: i64,
+    error: &str,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE pending_seerr_actions SET attempts = attempts + 1, last_error = $2 WHERE id = $1",
+    )
+    .bind(id)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub notification_type: Option<String>,
+    pub issue_id: Option<String>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+/// Every webhook still waiting in the outbox, oldest first.
+pub async fn list_outbox(pool: &PgPool) -> Result<Vec<OutboxEntry>> {
+    let rows = sqlx::query_as::<
+        _,
+        (
+            i64,
+            Option<String>,
+            Option<String>,
+            i32,
+            Option<String>,
+            i64,
+            i64,
+        ),
+    >(
+        "SELECT id, payload->>'notification_type', payload->>'issue_id', attempts, last_error, \
+         EXTRACT(EPOCH FROM created_at)::BIGINT, EXTRACT(EPOCH FROM next_attempt_at)::BIGINT \
+         FROM outbox ORDER BY created_at, id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                id,
+                notification_type,
+                issue_id,
+                attempts,
+                last_error,
+                created_at,
+                next_attempt_at,
+            )| {
+                OutboxEntry {
+                    id,
+                    notification_type,
+                    issue_id,
+                    attempts,
+                    last_error,
+                    created_at: timestamp(created_at),
+                    next_attempt_at: timestamp(next_attempt_at),
+                }
+            },
+        )
+        .collect())
+}
+
+pub struct StuckOutboxItem {
+    pub id: i64,
+    pub payload: String,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub escalated_at: Option<DateTime<Utc>>,
+}
+
+/// Outbox items that failed at least `min_attempts` times or were received
+/// before `created_before`, oldest first.
+pub async fn list_stuck_outbox(
+    pool: &PgPool,
+    min_attempts: i32,
+    created_before: DateTime<Utc>,
+) -> Result<Vec<StuckOutboxItem>> {
+    let rows = sqlx::query_as::<_, (i64, String, i32, Option<String>, i64, Option<i64>)>(
+        "SELECT id, payload::text, attempts, last_error, EXTRACT(EPOCH FROM created_at)::BIGINT, \
+         EXTRACT(EPOCH FROM escalated_at)::BIGINT FROM outbox \
+         WHERE attempts >= $1 OR created_at <= to_timestamp($2) ORDER BY created_at, id",
+    )
+    .bind(min_attempts)
+    .bind(created_before.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, payload, attempts, last_error, created_at, escalated_at)| StuckOutboxItem {
+                id,
+                payload,
+                attempts,
+                last_error,
+                created_at: timestamp(created_at),
+                escalated_at: escalated_at.map(timestamp),
+            },
+        )
+        .collect())
+}
+
+pub async fn mark_outbox_escalated(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query("UPDATE outbox SET escalated_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_setting(pool: &PgPool, key: &str) -> Result<Option<String>> {
+    let row = sqlx::query_as::<_, (String,)>("SELECT value FROM bot_settings WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.map(|(value,)| value))
+}
+
+pub async fn set_setting(pool: &PgPool, key: &str, value: &str) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO bot_settings (key, value) VALUES ($1, $2) \
+         ON CONFLICT (key) DO UPDATE SET value = $2, updated_at = NOW()",
+    )
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_setting(pool: &PgPool, key: &str) -> Result<()> {
+    sqlx::query("DELETE FROM bot_settings WHERE key = $1")
+        .bind(key)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub struct NewRequestEvent<'a> {
+    pub request_id: i64,
+    pub media_tmdb_id: Option<i64>,
+    pub media_type: Option<&'a str>,
+    pub subject: &'a str,
+    pub requested_by: &'a str,
+    /// Poster URL from the Seerr `{{image}}` field.
+    pub image: Option<&'a str>,
+    pub matrix_event_id: &'a str,
+    pub matrix_room_id: &'a str,
+    pub status: RequestStatus,
+}
+
+pub async fn insert_request_event(pool: &PgPool, event: &NewRequestEvent<'_>) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO request_events \
+         (request_id, media_tmdb_id, media_type, subject, matrix_event_id, matrix_room_id, status, \
+             image, requested_by, available_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, CASE WHEN $7 = 'available' THEN NOW() END)",
+    )
+    .bind(event.request_id)
+    .bind(event.media_tmdb_id)
+    .bind(event.media_type)
+    .bind(event.subject)
+    .bind(event.matrix_event_id)
+    .bind(event.matrix_room_id)
+    .bind(event.status.as_str())
+    .bind(event.image)
+    .bind(event.requested_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub struct RequestEvent {
+    pub request_id: i64,
+    pub matrix_event_id: String,
+    pub status: Option<RequestStatus>,
+}
+
+pub async fn get_request_event(pool: &PgPool, request_id: i64) -> Result<Option<RequestEvent>> {
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT request_id, matrix_event_id, status FROM request_events WHERE request_id = $1",
+    )
+    .bind(request_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(
+        row.map(|(request_id, matrix_event_id, status)| RequestEvent {
+            request_id,
+            matrix_event_id,
+            status: RequestStatus::parse(&status),
+        }),
+    )
+}
+
+/// Pending or approved requests for the media with this TMDB id.
+pub async fn list_waiting_requests_for_media(
+    pool: &PgPool,
+    media_type: &str,
+    tmdb_id: i64,
+) -> Result<Vec<RequestEvent>> {
+    let rows = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT request_id, matrix_event_id, status FROM request_events \
+         WHERE media_type = $1 AND media_tmdb_id = $2 AND status IN ('pending', 'approved') \
+         ORDER BY request_id",
+    )
+    .bind(media_type)
+    .bind(tmdb_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(request_id, matrix_event_id, status)| RequestEvent {
+            request_id,
+            matrix_event_id,
+            status: RequestStatus::parse(&status),
+        })
+        .collect())
+}
+
+pub async fn get_request_event_by_matrix_event_id(
+    pool: &PgPool,
+    matrix_event_id: &str,
+) -> Result<Option<RequestEvent>> {
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT request_id, matrix_event_id, status FROM request_events WHERE matrix_event_id = $1",
+    )
+    .bind(matrix_event_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(
+        row.map(|(request_id, matrix_event_id, status)| RequestEvent {
+            request_id,
+            matrix_event_id,
+            status: RequestStatus::parse(&status),
+        }),
+    )
+}
+
+pub async fn set_request_status(
+    pool: &PgPool,
+    request_id: i64,
+    status: RequestStatus,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE request_events SET status = $2, updated_at = NOW(), \
+             available_at = CASE WHEN $2 = 'available' THEN COALESCE(available_at, NOW()) \
+                 ELSE available_at END \
+         WHERE request_id = $1",
+    )
+    .bind(request_id)
+    .bind(status.as_str())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Marks a pending request approved. Returns `false` when it no longer was
+/// pending, e.g. another vote got to approve it first.
+pub async fn claim_request_approval(pool: &PgPool, request_id: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE request_events SET status = 'approved', updated_at = NOW() \
+         WHERE request_id = $1 AND status = 'pending'",
+    )
+    .bind(request_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Requested media that became available.
+pub struct AvailableMedia {
+    pub media_type: Option<String>,
+    pub media_tmdb_id: Option<i64>,
+    pub subject: Option<String>,
+    pub image: Option<String>,
+    pub available_at: DateTime<Utc>,
+}
+
+type AvailableMediaRow = (
+    Option<String>,
+    Option<i64>,
+    Option<String>,
+    Option<String>,
+    i64,
+);
+
+/// Media whose requests became available since `since`, oldest first.
+pub async fn list_available_media(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<AvailableMedia>> {
+    let rows = sqlx::query_as::<_, AvailableMediaRow>(
+        "SELECT media_type, media_tmdb_id, subject, image, EXTRACT(EPOCH FROM available_at)::BIGINT \
+         FROM request_events \
+         WHERE available_at >= to_timestamp($1) \
+         ORDER BY available_at",
+    )
+    .bind(since.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(media_type, media_tmdb_id, subject, image, available_at)| AvailableMedia {
+                media_type,
+                media_tmdb_id,
+                subject,
+                image,
+                available_at: timestamp(available_at),
+            },
+        )
+        .collect())
+}
+
+/// What the request card shows.
+pub struct RequestCard {
+    pub matrix_event_id: String,
+    pub subject: Option<String>,
+    pub requested_by: Option<String>,
+    pub media_type: Option<String>,
+    pub media_tmdb_id: Option<i64>,
+    pub status: Option<RequestStatus>,
+}
+
+type RequestCardRow = (
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i64>,
+    String,
+);
+
+pub async fn get_request_card(pool: &PgPool, request_id: i64) -> Result<Option<RequestCard>> {
+    let row = sqlx::query_as::<_, RequestCardRow>(
+        "SELECT matrix_event_id, subject, requested_by, media_type, media_tmdb_id, status \
+         FROM request_events WHERE request_id = $1",
+    )
+    .bind(request_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(
+        |(matrix_event_id, subject, requested_by, media_type, media_tmdb_id, status)| RequestCard {
+            matrix_event_id,
+            subject,
+            requested_by,
+            media_type,
+            media_tmdb_id,
+            status: RequestStatus::parse(&status),
+        },
+    ))
+}
+
+/// Records the vote of a room member on a pending request, replacing the one
+/// they cast before.
+pub async fn upsert_request_vote(
+    pool: &PgPool,
+    request_id: i64,
+    matrix_user_id: &str,
+    upvote: bool,
+    reaction_event_id: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO request_votes (request_id, matrix_user_id, upvote, reaction_event_id) \
+         VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (request_id, matrix_user_id) \
+         DO UPDATE SET upvote = $3, reaction_event_id = $4, created_at = NOW()",
+    )
+    .bind(request_id)
+    .bind(matrix_user_id)
+    .bind(upvote)
+    .bind(reaction_event_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Removes the vote cast with this reaction. Returns the request it was on,
+/// `None` when the reaction is not a current vote.
+pub async fn delete_request_vote(pool: &PgPool, reaction_event_id: &str) -> Result<Option<i64>> {
+    let row = sqlx::query_as::<_, (i64,)>(
+        "DELETE FROM request_votes WHERE reaction_event_id = $1 RETURNING request_id",
+    )
+    .bind(reaction_event_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(request_id,)| request_id))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VoteCounts {
+    pub up: i64,
+    pub down: i64,
+}
+
+pub async fn request_vote_counts(pool: &PgPool, request_id: i64) -> Result<VoteCounts> {
+    let (up, down) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*) FILTER (WHERE upvote), COUNT(*) FILTER (WHERE NOT upvote) \
+         FROM request_votes WHERE request_id = $1",
+    )
+    .bind(request_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(VoteCounts { up, down })
+}
+
+pub struct TrackedRequest {
+    pub request_id: i64,
+    pub matrix_event_id: String,
+    pub subject: Option<String>,
+    pub status: Option<RequestStatus>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Numbers the requests 1, 2, ... in order, replacing the previous list.
+pub async fn replace_request_shortcuts(pool: &PgPool, request_ids: &[i64]) -> Result<()> {
+    let numbers: Vec<i32> = (1..=request_ids.len() as i32).collect();
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM request_shortcuts")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO request_shortcuts (number, request_id) \
+         SELECT * FROM UNNEST($1::INTEGER[], $2::BIGINT[])",
+    )
+    .bind(&numbers)
+    .bind(request_ids)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Request numbered `number` in a list posted less than `max_age_secs` ago.
+pub async fn get_request_shortcut(
+    pool: &PgPool,
+    number: i32,
+    max_age_secs: i64,
+) -> Result<Option<i64>> {
+    let row = sqlx::query_as::<_, (i64,)>(
+        "SELECT request_id FROM request_shortcuts \
+         WHERE number = $1 AND created_at > NOW() - make_interval(secs => $2)",
+    )
+    .bind(number)
+    .bind(max_age_secs as f64)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(request_id,)| request_id))
+}
+
+/// A failing Sonarr or Radarr health check posted in the room.
+pub struct HealthTicket {
+    pub id: i64,
+    pub message: String,
+    pub matrix_event_id: String,
+}
+
+/// The ticket of a health check still failing.
+pub async fn get_open_health_ticket(
+    pool: &PgPool,
+    source: &str,
+    check_type: &str,
+) -> Result<Option<HealthTicket>> {
+    let row = sqlx::query_as::<_, (i64, String, String)>(
+        "SELECT id, message, matrix_event_id FROM health_tickets \
+         WHERE source = $1 AND check_type = $2 AND resolved_at IS NULL",
+    )
+    .bind(source)
+    .bind(check_type)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(id, message, matrix_event_id)| HealthTicket {
+        id,
+        message,
+        matrix_event_id,
+    }))
+}
+
+pub async fn insert_health_ticket(
+    pool: &PgPool,
+    source: &str,
+    check_type: &str,
+    message: &str,
+    matrix_event_id: &str,
+) -> Result<i64> {
+    let (id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO health_tickets (source, check_type, message, matrix_event_id) \
+         VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(source)
+    .bind(check_type)
+    .bind(message)
+    .bind(matrix_event_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+/// Keeps the last message of a check that keeps failing differently.
+pub async fn set_health_ticket_message(pool: &PgPool, id: i64, message: &str) -> Result<()> {
+    sqlx::query("UPDATE health_tickets SET message = $2 WHERE id = $1")
+        .bind(id)
+        .bind(message)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn resolve_health_ticket(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query("UPDATE health_tickets SET resolved_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether `!bot route` turned the stream on or off in the room.
+pub async fn get_room_route(pool: &PgPool, room_id: &str, stream: &str) -> Result<Option<bool>> {
+    let row = sqlx::query_as::<_, (bool,)>(
+        "SELECT enabled FROM room_routes WHERE room_id = $1 AND stream = $2",
+    )
+    .bind(room_id)
+    .bind(stream)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(enabled,)| enabled))
+}
+
+pub async fn set_room_route(
+    pool: &PgPool,
+    room_id: &str,
+    stream: &str,
+    enabled: bool,
+    updated_by: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO room_routes (room_id, stream, enabled, updated_by) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (room_id, stream) DO UPDATE SET enabled = $3, updated_by = $4, updated_at = NOW()",
+    )
+    .bind(room_id)
+    .bind(stream)
+    .bind(enabled)
+    .bind(updated_by)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Streams routed in the room, by name.
+pub async fn list_room_routes(pool: &PgPool, room_id: &str) -> Result<Vec<(String, bool)>> {
+    let rows = sqlx::query_as::<_, (String, bool)>(
+        "SELECT stream, enabled FROM room_routes WHERE room_id = $1 ORDER BY stream",
+    )
+    .bind(room_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Requests in `status`, or whose status changed since `since`, oldest first.
+pub async fn list_tracked_requests(
+    pool: &PgPool,
+    status: Option<RequestStatus>,
+    updated_since: Option<DateTime<Utc>>,
+) -> Result<Vec<TrackedRequest>> {
+    let rows = sqlx::query_as::<_, (i64, String, Option<String>, String, i64)>(
+        "SELECT request_id, matrix_event_id, subject, status, EXTRACT(EPOCH FROM created_at)::BIGINT \
+         FROM request_events \
+         WHERE ($1::TEXT IS NULL OR status = $1) \
+         AND ($2::DOUBLE PRECISION IS NULL OR updated_at >= to_timestamp($2)) \
+         ORDER BY created_at",
+    )
+    .bind(status.map(|s| s.as_str()))
+    .bind(updated_since.map(|since| since.timestamp() as f64))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(request_id, matrix_event_id, subject, status, created_at)| TrackedRequest {
+                request_id,
+                matrix_event_id,
+                subject,
+                status: RequestStatus::parse(&status),
+                created_at: timestamp(created_at),
+            },
+        )
+        .collect())
+}
+
+/// Issue being reported with `!report`, `draft` being the answers so far as
+/// JSON.
+pub struct IssueReport {
+    pub reporter: String,
+    pub draft: String,
+}
+
+pub async fn save_issue_report(
+    pool: &PgPool,
+    thread_root_event_id: &str,
+    reporter: &str,
+    draft: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO issue_reports (thread_root_event_id, reporter, draft) \
+         VALUES ($1, $2, $3::jsonb) \
+         ON CONFLICT (thread_root_event_id) DO UPDATE SET draft = $3::jsonb, updated_at = NOW()",
+    )
+    .bind(thread_root_event_id)
+    .bind(reporter)
+    .bind(draft)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// The report in the thread, unless it was left unanswered for a day.
+pub async fn get_issue_report(
+    pool: &PgPool,
+    thread_root_event_id: &str,
+) -> Result<Option<IssueReport>> {
+    let row = sqlx::query_as::<_, (String, String)>(
+        "SELECT reporter, draft::text FROM issue_reports \
+         WHERE thread_root_event_id = $1 AND updated_at > NOW() - INTERVAL '1 day'",
+    )
+    .bind(thread_root_event_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(reporter, draft)| IssueReport { reporter, draft }))
+}
+
+pub async fn delete_issue_report(pool: &PgPool, thread_root_event_id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM issue_reports WHERE thread_root_event_id = $1")
+        .bind(thread_root_event_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Attachment posted in an issue thread, `source` being its Matrix media
+/// source as JSON.
+pub struct IssueAttachment {
+    pub source: String,
+    pub mimetype: Option<String>,
+    pub filename: String,
+}
+
+pub async fn insert_issue_attachment(
+    pool: &PgPool,
+    matrix_event_id: &str,
+    issue_id: i64,
+    attachment: &IssueAttachment,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO issue_attachments (matrix_event_id, issue_id, source, mimetype, filename) \
+         VALUES ($1, $2, $3::jsonb, $4, $5) ON CONFLICT (matrix_event_id) DO NOTHING",
+    )
+    .bind(matrix_event_id)
+    .bind(issue_id)
+    .bind(&attachment.source)
+    .bind(&attachment.mimetype)
+    .bind(&attachment.filename)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_issue_attachment(
+    pool: &PgPool,
+    matrix_event_id: &str,
+) -> Result<Option<IssueAttachment>> {
+    let row = sqlx::query_as::<_, (String, Option<String>, String)>(
+        "SELECT source::text, mimetype, filename FROM issue_attachments WHERE matrix_event_id = $1",
+    )
+    .bind(matrix_event_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(source, mimetype, filename)| IssueAttachment {
+        source,
+        mimetype,
+        filename,
+    }))
+}
+
+pub struct NewUserReminder<'a> {
+    pub matrix_room_id: &'a str,
+    pub thread_root_event_id: &'a str,
+    pub author: &'a str,
+    pub message: &'a str,
+    pub due_at: DateTime<Utc>,
+}
+
+pub async fn insert_user_reminder(pool: &PgPool, reminder: &NewUserReminder<'_>) -> Result<i64> {
+    let (id,) = sqlx::query_as::<_, (i64,)>(
+        "INSERT INTO user_reminders (matrix_room_id, thread_root_event_id, author, message, due_at) \
+         VALUES ($1, $2, $3, $4, to_timestamp($5)) RETURNING id",
+    )
+    .bind(reminder.matrix_room_id)
+    .bind(reminder.thread_root_event_id)
+    .bind(reminder.author)
+    .bind(reminder.message)
+    .bind(reminder.due_at.timestamp() as f64)
+    .fetch_one(pool)
+    .await?;
+    Ok(id)
+}
+
+pub struct UserReminder {
+    pub id: i64,
+    pub thread_root_event_id: String,
+    pub author: String,
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+}
+
+/// Reminders of the room not sent yet, due before `until` when given, the
+/// earliest first.
+pub async fn list_pending_user_reminders(
+    pool: &PgPool,
+    matrix_room_id: &str,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<UserReminder>> {
+    let rows = sqlx::query_as::<_, (i64, String, String, String, i64)>(
+        "SELECT id, thread_root_event_id, author, message, EXTRACT(EPOCH FROM due_at)::BIGINT \
+         FROM user_reminders \
+         WHERE matrix_room_id = $1 AND sent_at IS NULL \
+         AND ($2::DOUBLE PRECISION IS NULL OR due_at <= to_timestamp($2)) \
+         ORDER BY due_at, id",
+    )
+    .bind(matrix_room_id)
+    .bind(until.map(|until| until.timestamp() as f64))
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, thread_root_event_id, author, message, due_at)| UserReminder {
+                id,
+                thread_root_event_id,
+                author,
+                message,
+                due_at: timestamp(due_at),
+            },
+        )
+        .collect())
+}
+
+pub async fn mark_user_reminder_sent(pool: &PgPool, id: i64) -> Result<()> {
+    sqlx::query("UPDATE user_reminders SET sent_at = NOW() WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Drops a reminder not sent yet, returns whether there was one.
+pub async fn cancel_user_reminder(pool: &PgPool, matrix_room_id: &str, id: i64) -> Result<bool> {
+    let result = sqlx::query(
+        "DELETE FROM user_reminders WHERE id = $1 AND matrix_room_id = $2 AND sent_at IS NULL",
+    )
+    .bind(id)
+    .bind(matrix_room_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub created_at: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    pub issue_id: Option<i64>,
+    pub details: Option<String>,
+    pub success: bool,
+}
+
+pub async fn insert_audit_entry(
+    pool: &PgPool,
+    actor: &str,
+    action: &str,
+    issue_id: Option<i64>,
+    details: Option<&str>,
+    success: bool,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO audit_log (actor, action, issue_id, details, success) VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(actor)
+    .bind(action)
+    .bind(issue_id)
+    .bind(details)
+    .bind(success)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Most recent audit entries first, optionally only those about `issue_id`.
+pub async fn list_audit_entries(
+    pool: &PgPool,
+    issue_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<AuditEntry>> {
+    let rows = sqlx::query_as::<_, (i64, String, String, Option<i64>, Option<String>, bool)>(
+        "SELECT EXTRACT(EPOCH FROM created_at)::BIGINT, actor, action, issue_id, details, success \
+         FROM audit_log WHERE $1::BIGINT IS NULL OR issue_id = $1 \
+         ORDER BY created_at DESC, id DESC LIMIT $2",
+    )
+    .bind(issue_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(created_at, actor, action, issue_id, details, success)| AuditEntry {
+                created_at: timestamp(created_at),
+                actor,
+                action,
+                issue_id,
+                details,
+                success,
+            },
+        )
+        .collect())
+}
+
+pub struct UserMapping {
+    pub matrix_user_id: String,
+    pub seerr_user: String,
+}
+
+pub async fn upsert_user_mapping(
+    pool: &PgPool,
+    matrix_user_id: &str,
+    seerr_user: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO user_mappings (matrix_user_id, seerr_user) VALUES ($1, $2) \
+         ON CONFLICT (matrix_user_id) DO UPDATE SET seerr_user = $2, updated_at = NOW()",
+    )
+    .bind(matrix_user_id)
+    .bind(seerr_user)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns whether a mapping was removed.
+pub async fn delete_user_mapping(pool: &PgPool, matrix_user_id: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM user_mappings WHERE matrix_user_id = $1")
+        .bind(matrix_user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn list_user_mappings(pool: &PgPool) -> Result<Vec<UserMapping>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "SELECT matrix_user_id, seerr_user FROM user_mappings ORDER BY matrix_user_id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(matrix_user_id, seerr_user)| UserMapping {
+            matrix_user_id,
+            seerr_user,
+        })
+        .collect())
+}
+
+pub async fn get_user_mapping(pool: &PgPool, matrix_user_id: &str) -> Result<Option<UserMapping>> {
+    let row = sqlx::query_as::<_, (String, String)>(
+        "SELECT matrix_user_id, seerr_user FROM user_mappings WHERE matrix_user_id = $1",
+    )
+    .bind(matrix_user_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(matrix_user_id, seerr_user)| UserMapping {
+        matrix_user_id,
+        seerr_user,
+    }))
+}
+
+/// Seerr users are matched case-insensitively, webhooks carry either their
+/// username or their email.
+pub async fn get_user_mapping_by_seerr_user(
+    pool: &PgPool,
+    seerr_user: &str,
+) -> Result<Option<UserMapping>> {
+    let row = sqlx::query_as::<_, (String, String)>(
+        "SELECT matrix_user_id, seerr_user FROM user_mappings WHERE LOWER(seerr_user) = LOWER($1)",
+    )
+    .bind(seerr_user)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(matrix_user_id, seerr_user)| UserMapping {
+        matrix_user_id,
+        seerr_user,
+    }))
+}
+
+pub struct WeeklyCount {
+    pub week_start: DateTime<Utc>,
+    pub count: i64,
+}
+
+pub async fn issues_opened_per_week(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<WeeklyCount>> {
+    let rows = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT EXTRACT(EPOCH FROM date_trunc('week', created_at))::BIGINT AS week, COUNT(*) \
+         FROM issue_events WHERE created_at >= to_timestamp($1) GROUP BY week ORDER BY week",
+    )
+    .bind(since.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(week_start, count)| WeeklyCount {
+            week_start: timestamp(week_start),
+            count,
+        })
+        .collect())
+}
+
+/// Mean time between an issue being opened and resolved, for issues resolved
+/// since `since`.
+pub async fn mean_time_to_resolution(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Option<chrono::Duration>> {
+    let (secs,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT EXTRACT(EPOCH FROM AVG(resolved_at - created_at))::BIGINT \
+         FROM issue_events WHERE resolved_at >= to_timestamp($1)",
+    )
+    .bind(since.timestamp() as f64)
+    .fetch_one(pool)
+    .await?;
+    Ok(secs.map(chrono::Duration::seconds))
+}
+
+/// Mean time between an issue being opened and first answered, for issues
+/// answered since `since`.
+pub async fn mean_time_to_first_response(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Option<chrono::Duration>> {
+    let (secs,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT EXTRACT(EPOCH FROM AVG(first_response_at - created_at))::BIGINT \
+         FROM issue_events WHERE first_response_at >= to_timestamp($1)",
+    )
+    .bind(since.timestamp() as f64)
+    .fetch_one(pool)
+    .await?;
+    Ok(secs.map(chrono::Duration::seconds))
+}
+
+/// Seconds each issue took to be first answered and to be resolved, `None`
+/// while it wasn't.
+pub async fn issue_response_times(pool: &PgPool) -> Result<Vec<(Option<f64>, Option<f64>)>> {
+    let rows = sqlx::query_as::<_, (Option<f64>, Option<f64>)>(
+        "SELECT EXTRACT(EPOCH FROM first_response_at - created_at)::DOUBLE PRECISION, \
+         EXTRACT(EPOCH FROM resolved_at - created_at)::DOUBLE PRECISION FROM issue_events",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Subjects with the most issues opened since `since`.
+pub async fn top_media_with_issues(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<(String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT subject, COUNT(*) AS issues FROM issue_events \
+         WHERE subject IS NOT NULL AND created_at >= to_timestamp($1) \
+         GROUP BY subject ORDER BY issues DESC, subject LIMIT $2",
+    )
+    .bind(since.timestamp() as f64)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Number of issues opened since `since` in each category, those without one
+/// left out.
+pub async fn issue_counts_by_category(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<(String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT category, COUNT(*) AS issues FROM issue_events \
+         WHERE category IS NOT NULL AND created_at >= to_timestamp($1) \
+         GROUP BY category ORDER BY issues DESC, category",
+    )
+    .bind(since.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Number of issues each admin resolved since `since`.
+pub async fn resolve_counts_by_user(
+    pool: &PgPool,
+    since: DateTime<Utc>,
+) -> Result<Vec<(String, i64)>> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT resolved_by, COUNT(*) AS resolved FROM issue_events \
+         WHERE resolved_by IS NOT NULL AND resolved_at >= to_timestamp($1) \
+         GROUP BY resolved_by ORDER BY resolved DESC, resolved_by",
+    )
+    .bind(since.timestamp() as f64)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
 }
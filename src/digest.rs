@@ -0,0 +1,240 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db::{self, TrackedIssue, TrackedRequest};
+use crate::markdown;
+use crate::matrix;
+use crate::request::RequestStatus;
+use crate::time_format::TimeFormat;
+use crate::webhook;
+
+/// Age groups of open issues, the upper bound in days and their heading.
+const AGE_GROUPS: [(i64, &str); 4] = [
+    (1, "Opened in the last day"),
+    (7, "Opened this week"),
+    (30, "Open for over a week"),
+    (i64::MAX, "Open for over a month"),
+];
+
+pub struct Digest {
+    pub open_issues: Vec<TrackedIssue>,
+    pub pending_requests: Vec<TrackedRequest>,
+    /// Pending requests according to Seerr, when it could be reached.
+    pub seerr_pending: Option<i64>,
+    pub resolved: Vec<TrackedIssue>,
+    pub updated_requests: Vec<TrackedRequest>,
+}
+
+pub async fn collect(state: &AppState, now: DateTime<Utc>) -> Result<Digest> {
+    let since = now - Duration::days(1);
+    let seerr_pending = match state.seerr_client.pending_request_count().await {
+        Ok(count) => Some(count),
+        Err(e) => {
+            warn!("Failed to fetch pending requests for the digest: {e:#}");
+            None
+        }
+    };
+    Ok(Digest {
+        open_issues: db::list_unresolved_issues(&state.db).await?,
+        pending_requests: db::list_tracked_requests(&state.db, Some(RequestStatus::Pending), None)
+            .await?,
+        seerr_pending,
+        resolved: db::list_issues_resolved_since(&state.db, since).await?,
+        updated_requests: db::list_tracked_requests(&state.db, None, Some(since))
+            .await?
+            .into_iter()
+            .filter(|r| r.status != Some(RequestStatus::Pending))
+            .collect(),
+    })
+}
+
+pub fn render(
+    digest: &Digest,
+    room_id: &str,
+    now: DateTime<Utc>,
+    time_format: &TimeFormat,
+) -> String {
+    let mut markdown = format!("#### ☀️ Daily digest, {}\n", time_format.date(now));
+    if digest.open_issues.is_empty()
+        && digest.pending_requests.is_empty()
+        && digest.seerr_pending.unwrap_or_default() == 0
+        && digest.resolved.is_empty()
+        && digest.updated_requests.is_empty()
+    {
+        markdown.push_str("Nothing open and nothing changed 🎉\n");
+        return markdown;
+    }
+
+    markdown.push_str(&format!(
+        "\n**Open issues ({})**\n",
+        digest.open_issues.len()
+    ));
+    if digest.open_issues.is_empty() {
+        markdown.push_str("No open issues 🎉\n");
+    }
+    let mut lower = 0;
+    for (upper, heading) in AGE_GROUPS {
+        let group: Vec<&TrackedIssue> = digest
+            .open_issues
+            .iter()
+            .filter(|issue| {
+                let age = (now - issue.created_at).num_days();
+                age >= lower && age < upper
+            })
+            .collect();
+        lower = upper;
+        if group.is_empty() {
+            continue;
+        }
+        markdown.push_str(&format!("\n*{heading}*\n"));
+        for issue in group {
+            markdown.push_str(&format!("- {}\n", issue_line(issue, room_id)));
+        }
+    }
+
+    let pending = digest
+        .seerr_pending
+        .unwrap_or(digest.pending_requests.len() as i64);
+    markdown.push_str(&format!("\n**Requests awaiting approval ({pending})**\n"));
+    for request in &digest.pending_requests {
+        markdown.push_str(&format!("- {}\n", request_line(request, room_id)));
+    }
+    let untracked = pending - digest.pending_requests.len() as i64;
+    if untracked > 0 {
+        markdown.push_str(&format!("- {untracked} more in Seerr\n"));
+    }
+
+    if !digest.resolved.is_empty() || !digest.updated_requests.is_empty() {
+        markdown.push_str("\n**Last 24 hours**\n");
+        for issue in &digest.resolved {
+            markdown.push_str(&format!("- ✅ {}\n", issue_line(issue, room_id)));
+        }
+        for request in &digest.updated_requests {
+            let label = request.status.map(|s| s.label()).unwrap_or("Updated");
+            markdown.push_str(&format!("- {label}: {}\n", request_line(request, room_id)));
+        }
+    }
+
+    markdown
+}
+
+fn issue_line(issue: &TrackedIssue, room_id: &str) -> String {
+    let subject = webhook::issue_subject(
+        issue.subject.as_deref().unwrap_or("Untitled issue"),
+        issue.episodes(),
+    );
+    let subject = markdown::escape(&subject);
+    match &issue.matrix_event_id {
+        Some(event_id) => format!(
+            "[#{}]({}) {subject}",
+            issue.issue_id,
+            matrix::event_permalink(room_id, event_id)
+        ),
+        None => format!("#{} {subject}", issue.issue_id),
+    }
+}
+
+fn request_line(request: &TrackedRequest, room_id: &str) -> String {
+    format!(
+        "[{}]({})",
+        markdown::escape(request.subject.as_deref().unwrap_or("Untitled request")),
+        matrix::event_permalink(room_id, &request.matrix_event_id)
+    )
+}
+
+/// Posts the morning summary of open issues and requests in the room.
+pub async fn post_daily_digest(state: &AppState) -> Result<()> {
+    let now = Utc::now();
+    let digest = collect(state, now).await?;
+    let markdown = render(
+        &digest,
+        state.room.room_id().as_str(),
+        now,
+        &state.settings.get().time_format,
+    );
+    matrix::send_markdown(&state.room, &markdown).await?;
+    info!("Daily digest posted");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn issue(issue_id: i64, subject: &str, created_at: DateTime<Utc>) -> TrackedIssue {
+        TrackedIssue {
+            issue_id,
+            matrix_event_id: Some(format!("$issue{issue_id}")),
+            subject: Some(subject.to_string()),
+            reported_by: None,
+            category: None,
+            problem_season: None,
+            problem_episode: None,
+            status: "open".to_string(),
+            comment_count: 0,
+            created_at,
+            first_response_at: None,
+            resolved_at: None,
+            resolved_by: None,
+            acknowledged_by: None,
+        }
+    }
+
+    fn empty() -> Digest {
+        Digest {
+            open_issues: Vec::new(),
+            pending_requests: Vec::new(),
+            seerr_pending: Some(0),
+            resolved: Vec::new(),
+            updated_requests: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn render_groups_open_issues_by_age() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 8, 0, 0).unwrap();
+        let digest = Digest {
+            open_issues: vec![
+                issue(1, "Old Movie", now - Duration::days(40)),
+                issue(2, "Recent Show", now - Duration::days(3)),
+                issue(3, "New Movie", now - Duration::hours(2)),
+            ],
+            pending_requests: vec![TrackedRequest {
+                request_id: 7,
+                matrix_event_id: "$request7".to_string(),
+                subject: Some("Requested Movie".to_string()),
+                status: Some(RequestStatus::Pending),
+                created_at: now,
+            }],
+            seerr_pending: Some(3),
+            ..empty()
+        };
+
+        let markdown = render(&digest, "!room:localhost", now, &TimeFormat::default());
+
+        assert!(markdown.contains("Daily digest, 2026-03-10"));
+        assert!(markdown.contains("**Open issues (3)**"));
+        let recent = markdown.find("*Opened in the last day*").unwrap();
+        let week = markdown.find("*Opened this week*").unwrap();
+        let month = markdown.find("*Open for over a month*").unwrap();
+        assert!(recent < week && week < month);
+        assert!(!markdown.contains("*Open for over a week*"));
+        assert!(markdown.contains("[#3](https://matrix.to/#/!room:localhost/$issue3) New Movie"));
+        assert!(markdown.contains("**Requests awaiting approval (3)**"));
+        assert!(
+            markdown.contains("[Requested Movie](https://matrix.to/#/!room:localhost/$request7)")
+        );
+        assert!(markdown.contains("2 more in Seerr"));
+    }
+
+    #[test]
+    fn render_quiet_day() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 10, 8, 0, 0).unwrap();
+        let markdown = render(&empty(), "!room:localhost", now, &TimeFormat::default());
+        assert!(markdown.contains("Nothing open and nothing changed"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::matrix;
+use crate::radarr_client::RadarrClient;
+use crate::sonarr_client::SonarrClient;
+
+const GB: u64 = 1_000_000_000;
+
+/// Root folder as returned by the Sonarr and Radarr APIs.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootFolder {
+    pub path: String,
+    pub free_space: Option<u64>,
+}
+
+/// Free space of a Sonarr or Radarr root folder, or of a local path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskSpace {
+    pub source: &'static str,
+    pub path: String,
+    pub free: u64,
+}
+
+impl DiskSpace {
+    fn key(&self) -> String {
+        format!("{}:{}", self.source, self.path)
+    }
+}
+
+/// Warns the room when a library disk runs low on space, and once it has
+/// room again.
+pub struct DiskMonitor {
+    sonarr: Option<SonarrClient>,
+    radarr: Option<RadarrClient>,
+    paths: Vec<String>,
+    threshold: u64,
+    /// Disks the room was warned about.
+    low: Mutex<HashSet<String>>,
+}
+
+impl DiskMonitor {
+    pub fn new(
+        sonarr: Option<SonarrClient>,
+        radarr: Option<RadarrClient>,
+        paths: Vec<String>,
+        threshold_gb: u64,
+    ) -> Self {
+        Self {
+            sonarr,
+            radarr,
+            paths,
+            threshold: threshold_gb * GB,
+            low: Mutex::default(),
+        }
+    }
+
+    /// Free space of every disk that could be checked.
+    async fn collect(&self) -> Result<Vec<DiskSpace>> {
+        let mut disks = Vec::new();
+        if let Some(sonarr) = &self.sonarr {
+            root_folders("Sonarr", sonarr.root_folders().await, &mut disks);
+        }
+        if let Some(radarr) = &self.radarr {
+            root_folders("Radarr", radarr.root_folders().await, &mut disks);
+        }
+        for path in &self.paths {
+            let fs_path = path.clone();
+            match tokio::task::spawn_blocking(move || fs4::available_space(fs_path)).await? {
+                Ok(free) => disks.push(DiskSpace {
+                    source: "Local",
+                    path: path.clone(),
+                    free,
+                }),
+                Err(e) => warn!(path, "Failed to read free disk space: {e}"),
+            }
+        }
+        Ok(disks)
+    }
+
+    pub async fn check(&self, state: &AppState) -> Result<()> {
+        let disks = self.collect().await?;
+        let (low, recovered) = {
+            let mut known = self.low.lock().unwrap_or_else(|e| e.into_inner());
+            changes(&mut known, &disks, self.threshold)
+        };
+        if !low.is_empty() {
+            matrix::send_markdown(&state.room, &render_low(&low, self.threshold)).await?;
+            warn!(disks = low.len(), "Low disk space reported");
+        }
+        if !recovered.is_empty() {
+            matrix::send_markdown(&state.room, &render_recovered(&recovered, self.threshold))
+                .await?;
+            info!(disks = recovered.len(), "Disk space recovered");
+        }
+        Ok(())
+    }
+}
+
+fn root_folders(
+    source: &'static str,
+    folders: Result<Vec<RootFolder>>,
+    disks: &mut Vec<DiskSpace>,
+) {
+    match folders {
+        Ok(folders) => disks.extend(folders.into_iter().filter_map(|folder| {
+            Some(DiskSpace {
+                source,
+                free: folder.free_space?,
+                path: folder.path,
+            })
+        })),
+        Err(e) => warn!("Failed to fetch {source} root folders: {e:#}"),
+    }
+}
+
+/// Disks that went below `threshold` and those back above it since the last
+/// check, `known` holding the disks already reported.
+fn changes(
+    known: &mut HashSet<String>,
+    disks: &[DiskSpace],
+    threshold: u64,
+) -> (Vec<DiskSpace>, Vec<DiskSpace>) {
+    let mut low = Vec::new();
+    let mut recovered = Vec::new();
+    for disk in disks {
+        if disk.free < threshold {
+            if known.insert(disk.key()) {
+                low.push(disk.clone());
+            }
+        } else if known.remove(&disk.key()) {
+            recovered.push(disk.clone());
+        }
+    }
+    (low, recovered)
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / GB as f64)
+}
+
+fn disk_lines(disks: &[DiskSpace]) -> String {
+    disks
+        .iter()
+        .map(|disk| {
+            format!(
+                "- {} `{}`: {} free\n",
+                disk.source,
+                disk.path,
+                format_bytes(disk.free)
+            )
+        })
+        .collect()
+}
+
+fn render_low(disks: &[DiskSpace], threshold: u64) -> String {
+    format!(
+        "#### 💾 Low disk space\nBelow {}, downloads and imports may start failing:\n{}",
+        format_bytes(threshold),
+        disk_lines(disks)
+    )
+}
+
+fn render_recovered(disks: &[DiskSpace], threshold: u64) -> String {
+    format!(
+        "**💾 Disk space back above {}**\n{}",
+        format_bytes(threshold),
+        disk_lines(disks)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk(path: &str, free_gb: u64) -> DiskSpace {
+        DiskSpace {
+            source: "Sonarr",
+            path: path.to_string(),
+            free: free_gb * GB,
+        }
+    }
+
+    #[test]
+    fn reports_each_change_once() {
+        let mut known = HashSet::new();
+        let threshold = 50 * GB;
+
+        let (low, recovered) = changes(
+            &mut known,
+            &[disk("/tv", 10), disk("/anime", 80)],
+            threshold,
+        );
+        assert_eq!(low, vec![disk("/tv", 10)]);
+        assert!(recovered.is_empty());
+
+        // Still low, already reported
+        let (low, recovered) = changes(&mut known, &[disk("/tv", 8)], threshold);
+        assert!(low.is_empty() && recovered.is_empty());
+
+        let (low, recovered) = changes(&mut known, &[disk("/tv", 120)], threshold);
+        assert!(low.is_empty());
+        assert_eq!(recovered, vec![disk("/tv", 120)]);
+    }
+
+    #[test]
+    fn render_lists_free_space() {
+        let markdown = render_low(&[disk("/tv", 12)], 50 * GB);
+        assert!(markdown.contains("Below 50.0 GB"));
+        assert!(markdown.contains("- Sonarr `/tv`: 12.0 GB free"));
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt;
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use crate::db::TrackedIssue;
+
+/// Issues exported by `!issues export`, by when they were created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportPeriod {
+    LastWeek,
+    LastMonth,
+    LastQuarter,
+    LastYear,
+    All,
+}
+
+impl ExportPeriod {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "last-week" => Some(ExportPeriod::LastWeek),
+            "last-month" => Some(ExportPeriod::LastMonth),
+            "last-quarter" => Some(ExportPeriod::LastQuarter),
+            "last-year" => Some(ExportPeriod::LastYear),
+            "all" => Some(ExportPeriod::All),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportPeriod::LastWeek => "last-week",
+            ExportPeriod::LastMonth => "last-month",
+            ExportPeriod::LastQuarter => "last-quarter",
+            ExportPeriod::LastYear => "last-year",
+            ExportPeriod::All => "all",
+        }
+    }
+
+    /// Start of the period, `None` for all issues.
+    pub fn since(self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let days = match self {
+            ExportPeriod::LastWeek => 7,
+            ExportPeriod::LastMonth => 30,
+            ExportPeriod::LastQuarter => 90,
+            ExportPeriod::LastYear => 365,
+            ExportPeriod::All => return None,
+        };
+        Some(now - chrono::Duration::days(days))
+    }
+}
+
+impl fmt::Display for ExportPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn mimetype(self) -> mime::Mime {
+        match self {
+            ExportFormat::Csv => mime::TEXT_CSV,
+            ExportFormat::Json => mime::APPLICATION_JSON,
+        }
+    }
+}
+
+/// An exported issue: what the bot tracks of it, and how long it took to
+/// answer and resolve.
+#[derive(Serialize)]
+struct ExportedIssue<'a> {
+    #[serde(flatten)]
+    issue: &'a TrackedIssue,
+    first_response_secs: Option<i64>,
+    resolution_secs: Option<i64>,
+}
+
+impl<'a> From<&'a TrackedIssue> for ExportedIssue<'a> {
+    fn from(issue: &'a TrackedIssue) -> Self {
+        Self {
+            issue,
+            first_response_secs: issue
+                .first_response_at
+                .map(|at| (at - issue.created_at).num_seconds()),
+            resolution_secs: issue
+                .resolved_at
+                .map(|at| (at - issue.created_at).num_seconds()),
+        }
+    }
+}
+
+/// The export file of `issues`.
+pub fn render(issues: &[TrackedIssue], format: ExportFormat) -> Result<Vec<u8>> {
+    let issues: Vec<ExportedIssue> = issues.iter().map(ExportedIssue::from).collect();
+    match format {
+        ExportFormat::Csv => Ok(render_csv(&issues).into_bytes()),
+        ExportFormat::Json => Ok(serde_json::to_vec_pretty(&issues)?),
+    }
+}
+
+const CSV_HEADER: &str = "issue_id,subject,reported_by,category,problem_season,problem_episode,\
+     status,comment_count,created_at,first_response_at,resolved_at,resolved_by,acknowledged_by,\
+     first_response_secs,resolution_secs";
+
+fn render_csv(issues: &[ExportedIssue]) -> String {
+    let mut csv = format!("{CSV_HEADER}\r\n");
+    for exported in issues {
+        let issue = exported.issue;
+        let fields = [
+            issue.issue_id.to_string(),
+            csv_field(issue.subject.as_deref()),
+            csv_field(issue.reported_by.as_deref()),
+            csv_field(issue.category.as_deref()),
+            optional(issue.problem_season),
+            optional(issue.problem_episode),
+            csv_field(Some(&issue.status)),
+            issue.comment_count.to_string(),
+            timestamp(Some(issue.created_at)),
+            timestamp(issue.first_response_at),
+            timestamp(issue.resolved_at),
+            csv_field(issue.resolved_by.as_deref()),
+            csv_field(issue.acknowledged_by.as_deref()),
+            optional(exported.first_response_secs),
+            optional(exported.resolution_secs),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Quotes the value when it has a separator, quote or line break in it.
+fn csv_field(value: Option<&str>) -> String {
+    let value = value.unwrap_or_default();
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn optional(value: Option<i64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn timestamp(at: Option<DateTime<Utc>>) -> String {
+    at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn issue() -> TrackedIssue {
+        let created_at = Utc.with_ymd_and_hms(2026, 9, 1, 12, 0, 0).unwrap();
+        TrackedIssue {
+            issue_id: 42,
+            matrix_event_id: Some("$abc".to_string()),
+            subject: Some("Dune, \"Part Two\"".to_string()),
+            reported_by: Some("alice".to_string()),
+            category: Some("subtitles".to_string()),
+            problem_season: None,
+            problem_episode: None,
+            status: "resolved".to_string(),
+            comment_count: 2,
+            created_at,
+            first_response_at: Some(created_at + chrono::Duration::minutes(30)),
+            resolved_at: Some(created_at + chrono::Duration::hours(2)),
+            resolved_by: Some("@bob:localhost".to_string()),
+            acknowledged_by: None,
+        }
+    }
+
+    #[test]
+    fn csv_has_timestamps_and_durations() {
+        let csv = String::from_utf8(render(&[issue()], ExportFormat::Csv).unwrap()).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(CSV_HEADER));
+        assert_eq!(
+            lines.next(),
+            Some(
+                "42,\"Dune, \"\"Part Two\"\"\",alice,subtitles,,,resolved,2,2026-09-01T12:00:00Z,\
+                 2026-09-01T12:30:00Z,2026-09-01T14:00:00Z,@bob:localhost,,1800,7200"
+            )
+        );
+    }
+
+    #[test]
+    fn json_has_durations() {
+        let json = render(&[issue()], ExportFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json[0]["issue_id"], 42);
+        assert_eq!(json[0]["category"], "subtitles");
+        assert_eq!(json[0]["resolution_secs"], 7200);
+    }
+
+    #[test]
+    fn periods_start_before_now() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            ExportPeriod::parse("last-quarter").unwrap().since(now),
+            Some(Utc.with_ymd_and_hms(2026, 7, 3, 0, 0, 0).unwrap())
+        );
+        assert_eq!(ExportPeriod::All.since(now), None);
+        assert_eq!(ExportPeriod::parse("next-week"), None);
+    }
+}
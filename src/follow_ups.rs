@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use matrix_sdk::ruma::OwnedEventId;
+use tracing::info;
+
+use crate::AppState;
+use crate::audit;
+use crate::db;
+use crate::lifecycle::{self, IssueEvent};
+use crate::markdown;
+use crate::matrix;
+use crate::stats::format_duration;
+
+/// Actor recorded in the audit log for issues resolved without the reporter's
+/// answer.
+const FOLLOW_UP_ACTOR: &str = "reporter-follow-up";
+
+const RESOLVE_NOTE: &str =
+    "Resolved automatically, the reporter didn't reply after being asked to confirm the fix";
+
+/// Asks the reporters who didn't reply to an admin's answer within
+/// `REPORTER_FOLLOW_UP_AFTER_DAYS` to confirm it, then resolves the issues of
+/// those still silent after `REPORTER_FOLLOW_UP_RESOLVE_AFTER_DAYS`. Any
+/// comment of the reporter stops both, a new answer starts over.
+pub async fn run(state: &AppState) -> Result<()> {
+    let settings = state.settings.get();
+    let Some(after) = settings.reporter_follow_up_after else {
+        return Ok(());
+    };
+    let resolve_after = settings.reporter_follow_up_resolve_after;
+
+    let before = Utc::now() - chrono::Duration::from_std(after)?;
+    for issue in db::list_issues_to_follow_up(&state.db, before).await? {
+        let reporter = match &issue.reported_by {
+            Some(reported_by) => Some(mention(state, reported_by).await?),
+            None => None,
+        };
+        let question = question(resolve_after);
+        comment(state, issue.issue_id, &question).await?;
+        let root: OwnedEventId = issue.matrix_event_id.as_str().try_into()?;
+        let markdown = render_follow_up(reporter.as_deref(), &question);
+        matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+        db::record_follow_up(&state.db, issue.issue_id).await?;
+        info!(
+            issue_id = issue.issue_id,
+            "Asked the reporter to confirm the fix"
+        );
+    }
+
+    let before = Utc::now() - chrono::Duration::from_std(resolve_after)?;
+    for issue in db::list_issues_to_close_after_follow_up(&state.db, before).await? {
+        resolve(state, &issue).await?;
+    }
+    Ok(())
+}
+
+/// Comments on the issue in Seerr, where reporters who aren't in the room
+/// read it. The comment's webhook is skipped, it isn't an answer.
+async fn comment(state: &AppState, issue_id: i64, message: &str) -> Result<()> {
+    state.forwarded_comments.remember(issue_id, message);
+    let result = state.seerr_client.add_comment(issue_id, message).await;
+    audit::record(
+        &state.db,
+        FOLLOW_UP_ACTOR,
+        "seerr.add_comment",
+        Some(issue_id),
+        None,
+        &result,
+    )
+    .await;
+    result?;
+    Ok(())
+}
+
+async fn resolve(state: &AppState, issue: &db::IssueEvent) -> Result<()> {
+    let issue_id = issue.issue_id;
+    comment(state, issue_id, RESOLVE_NOTE).await?;
+    let result = state.seerr_client.resolve_issue(issue_id).await;
+    audit::record(
+        &state.db,
+        FOLLOW_UP_ACTOR,
+        "seerr.resolve_issue",
+        Some(issue_id),
+        None,
+        &result,
+    )
+    .await;
+    result?;
+    lifecycle::apply(&state.db, issue_id, IssueEvent::Resolved, None).await?;
+
+    let root: OwnedEventId = issue.matrix_event_id.as_str().try_into()?;
+    let markdown = format!("**{RESOLVE_NOTE}**");
+    matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+    info!(issue_id, "Issue resolved without the reporter's answer");
+    Ok(())
+}
+
+/// The reporter's Matrix account when linked with `!users link`, their Seerr
+/// name otherwise.
+async fn mention(state: &AppState, reported_by: &str) -> Result<String> {
+    let mention = match db::get_user_mapping_by_seerr_user(&state.db, reported_by).await? {
+        Some(mapping) => {
+            let user_id = mapping.matrix_user_id;
+            format!("[{user_id}](https://matrix.to/#/{user_id})")
+        }
+        None => markdown::escape(reported_by),
+    };
+    Ok(mention)
+}
+
+fn question(resolve_after: Duration) -> String {
+    let resolve_after = chrono::Duration::from_std(resolve_after).unwrap_or(chrono::Duration::MAX);
+    format!(
+        "Does the answer above fix your issue? Please reply in Seerr to confirm, without any \
+         reply the issue is resolved in {}.",
+        format_duration(resolve_after)
+    )
+}
+
+fn render_follow_up(reporter: Option<&str>, question: &str) -> String {
+    match reporter {
+        Some(reporter) => format!("**🙋 {reporter}:** {question}"),
+        None => format!("**🙋** {question}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_up_mentions_the_reporter() {
+        let question = question(Duration::from_secs(7 * 86400));
+        assert_eq!(
+            question,
+            "Does the answer above fix your issue? Please reply in Seerr to confirm, without any \
+             reply the issue is resolved in 7 days."
+        );
+        let reporter = "[@bob:localhost](https://matrix.to/#/@bob:localhost)";
+        assert_eq!(
+            render_follow_up(Some(reporter), "Fixed?"),
+            "**🙋 [@bob:localhost](https://matrix.to/#/@bob:localhost):** Fixed?"
+        );
+        assert_eq!(render_follow_up(None, "Fixed?"), "**🙋** Fixed?");
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use matrix_sdk::Client;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::alerts::Alerts;
+use crate::db;
+use crate::matrix;
+use crate::priority::Priority;
+use crate::settings::LiveSettings;
+
+const DB_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Pings the database periodically and DMs the admins when it becomes
+/// unreachable and once it is back, rather than only failing on the next
+/// query a webhook or command happens to make. Held back during
+/// `!maintenance` like the other outage alerts.
+pub async fn watch_database(
+    pool: PgPool,
+    client: Client,
+    settings: Arc<LiveSettings>,
+    alerts: Arc<Alerts>,
+    shutdown: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(DB_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut healthy = true;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        match db::ping(&pool).await {
+            Ok(()) if !healthy => {
+                healthy = true;
+                info!("Database reachable again");
+                if alerts.maintenance.suppress("Database reachable again", "") {
+                    continue;
+                }
+                matrix::notify_users(
+                    &client,
+                    &settings.get().admin_users,
+                    "**✅ Database reachable again**",
+                )
+                .await;
+            }
+            Ok(()) => {}
+            Err(e) if healthy => {
+                healthy = false;
+                error!("Database unreachable: {e:#}");
+                if alerts
+                    .maintenance
+                    .suppress("Database unreachable", &format!("{e:#}"))
+                {
+                    continue;
+                }
+                let markdown = format!(
+                    "#### {} Database unreachable\nWebhooks and commands will fail until it is back: {e:#}",
+                    Priority::Critical.icon()
+                );
+                let (plain, html) = Priority::Critical.render(&markdown);
+                matrix::notify_users_html(&client, &settings.get().admin_users, &plain, &html)
+                    .await;
+            }
+            Err(e) => error!("Database still unreachable: {e:#}"),
+        }
+    }
+}
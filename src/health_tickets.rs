@@ -0,0 +1,95 @@
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedEventId;
+use tracing::info;
+
+use crate::AppState;
+use crate::db;
+use crate::imports::HealthEvent;
+use crate::markdown;
+use crate::matrix;
+
+/// Posts a card for a health check starting to fail with
+/// `HEALTH_TICKETS_ENABLED`, and follows it up in its thread until Sonarr or
+/// Radarr reports it passing again. A check failing again while its ticket is
+/// open doesn't post another card.
+pub async fn file(state: &AppState, event: &HealthEvent) -> Result<()> {
+    if !state.settings.get().health_tickets_enabled {
+        return Ok(());
+    }
+    let ticket = db::get_open_health_ticket(&state.db, event.source, &event.check_type).await?;
+    match (ticket, event.restored) {
+        (None, false) => {
+            let event_id = matrix::send_markdown(&state.room, &render_ticket(event)).await?;
+            let id = db::insert_health_ticket(
+                &state.db,
+                event.source,
+                &event.check_type,
+                &event.message,
+                event_id.as_str(),
+            )
+            .await?;
+            info!(id, source = event.source, check = %event.check_type, "Health ticket opened");
+        }
+        (Some(ticket), false) => {
+            if ticket.message == event.message {
+                return Ok(());
+            }
+            let root: OwnedEventId = ticket.matrix_event_id.as_str().try_into()?;
+            let markdown = format!("**Still failing:** {}", markdown::escape(&event.message));
+            matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+            db::set_health_ticket_message(&state.db, ticket.id, &event.message).await?;
+        }
+        (Some(ticket), true) => {
+            let root: OwnedEventId = ticket.matrix_event_id.as_str().try_into()?;
+            let markdown = format!("**✅ {} reports the check passing again**", event.source);
+            matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+            db::resolve_health_ticket(&state.db, ticket.id).await?;
+            info!(
+                id = ticket.id,
+                source = event.source,
+                "Health ticket resolved"
+            );
+        }
+        // Failing since before the tickets were enabled
+        (None, true) => {}
+    }
+    Ok(())
+}
+
+fn render_ticket(event: &HealthEvent) -> String {
+    let mut markdown = format!(
+        "#### 🩺 {} health check failing: {}\n`{}`",
+        event.source,
+        markdown::escape(&event.message),
+        event.check_type
+    );
+    if let Some(level) = &event.level {
+        markdown.push_str(&format!(", level {level}"));
+    }
+    if let Some(wiki_url) = &event.wiki_url {
+        markdown.push_str(&format!(". [How to fix it]({wiki_url})"));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticket_shows_the_check_and_how_to_fix_it() {
+        let event = HealthEvent {
+            source: "Sonarr",
+            check_type: "IndexerStatusCheck".to_string(),
+            level: Some("error".to_string()),
+            message: "All indexers are unavailable due to failures".to_string(),
+            wiki_url: Some("https://wiki.servarr.com/sonarr".to_string()),
+            restored: false,
+        };
+        assert_eq!(
+            render_ticket(&event),
+            "#### 🩺 Sonarr health check failing: All indexers are unavailable due to failures\n\
+             `IndexerStatusCheck`, level error. [How to fix it](https://wiki.servarr.com/sonarr)"
+        );
+    }
+}
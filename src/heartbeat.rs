@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::db;
+
+/// A sync older than this means the sync loop is stuck or failing. Long polls
+/// return every 30 seconds even without new events.
+const SYNC_STALE_AFTER: Duration = Duration::from_secs(120);
+
+/// Time of the last successful Matrix sync response, and how the sync loop
+/// recovered from its failures, for `/admin/metrics`.
+#[derive(Default)]
+pub struct SyncHealth {
+    last_sync: Mutex<Option<Instant>>,
+    /// Failures since the last successful sync.
+    consecutive_failures: AtomicU32,
+    failures: AtomicU64,
+    relogins: AtomicU64,
+    failed_relogins: AtomicU64,
+}
+
+impl SyncHealth {
+    /// Returns whether the sync recovered, i.e. the previous one failed.
+    pub fn mark(&self) -> bool {
+        *self.last_sync.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+        self.consecutive_failures.swap(0, Ordering::Relaxed) > 0
+    }
+
+    /// Returns the number of failures since the last successful sync.
+    pub fn record_failure(&self) -> u32 {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+        self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn record_relogin(&self, succeeded: bool) {
+        let counter = if succeeded {
+            &self.relogins
+        } else {
+            &self.failed_relogins
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    /// Successful and failed re-logins.
+    pub fn relogins(&self) -> (u64, u64) {
+        (
+            self.relogins.load(Ordering::Relaxed),
+            self.failed_relogins.load(Ordering::Relaxed),
+        )
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.last_sync
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some_and(|at| at.elapsed() < SYNC_STALE_AFTER)
+    }
+}
+
+/// Pings `url` every `interval` while sync and the database are healthy, so a
+/// push monitor (healthchecks.io, Uptime Kuma, ...) alerts once the bot stops
+/// working, even if the process is still alive.
+pub async fn run(
+    url: String,
+    interval: Duration,
+    pool: PgPool,
+    sync: Arc<SyncHealth>,
+    shutdown: CancellationToken,
+) {
+    let http = reqwest::Client::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+
+        if !sync.is_healthy() {
+            warn!("Matrix sync is stale, skipping heartbeat");
+            continue;
+        }
+        if let Err(e) = db::ping(&pool).await {
+            warn!("Database unreachable, skipping heartbeat: {e:#}");
+            continue;
+        }
+        match http
+            .get(&url)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(_) => debug!("Heartbeat sent"),
+            Err(e) => warn!("Failed to send heartbeat: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn healthy_only_after_a_sync() {
+        let health = SyncHealth::default();
+        assert!(!health.is_healthy());
+
+        assert!(!health.mark());
+        assert!(health.is_healthy());
+    }
+
+    #[test]
+    fn sync_recovers_after_failures() {
+        let health = SyncHealth::default();
+        assert_eq!(health.record_failure(), 1);
+        assert_eq!(health.record_failure(), 2);
+        assert!(health.mark());
+        assert_eq!(health.consecutive_failures(), 0);
+        assert_eq!(health.failures(), 2);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{Instrument, error, info, info_span};
+
+use crate::AppState;
+use crate::admin;
+use crate::alerts::Subsystem;
+use crate::audit;
+use crate::priority::{self, Priority};
+use crate::quiet_hours;
+
+/// Notification sent by a Home Assistant `rest_command` or automation.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HomeAssistantNotification {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub message: String,
+    /// Alertmanager style `critical`, `warning` or `info`, or a priority
+    /// name, coloring the message.
+    pub severity: Option<String>,
+    /// Extra values available to the template as `{name}`.
+    #[serde(default)]
+    pub data: HashMap<String, Value>,
+}
+
+/// `POST /webhook/home-assistant`: posts the notification in the room, or
+/// keeps it for later while the bot is paused with `!bot pause`.
+pub async fn handle_home_assistant_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(notification): Json<HomeAssistantNotification>,
+) -> StatusCode {
+    let Some(config) = &state.home_assistant else {
+        return StatusCode::NOT_FOUND;
+    };
+    if !admin::has_bearer_token(&headers, &config.token) {
+        return StatusCode::UNAUTHORIZED;
+    }
+
+    let span = info_span!("webhook", source = "home-assistant", title = %notification.title);
+    async {
+        if quiet_hours::is_paused(&state) {
+            match quiet_hours::hold_home_assistant(&state, &notification).await {
+                Ok(quiet_id) => {
+                    info!(quiet_id, "Notification held until the bot is resumed");
+                    return StatusCode::OK;
+                }
+                Err(e) => error!("Failed to hold notification, posting it now: {e:#}"),
+            }
+        }
+        match deliver(&state, &notification).await {
+            Ok(()) => StatusCode::OK,
+            Err(e) => {
+                error!("Failed to send Home Assistant notification: {e:#}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+/// Posts the notification in the room, rendered with
+/// `HOME_ASSISTANT_TEMPLATE`.
+pub async fn deliver(state: &AppState, notification: &HomeAssistantNotification) -> Result<()> {
+    let Some(config) = &state.home_assistant else {
+        return Ok(());
+    };
+    let markdown = render(&config.template, notification);
+    let priority = notification
+        .severity
+        .as_deref()
+        .map_or(Priority::Normal, Priority::from_severity);
+    let settings = state.settings.get();
+    let result = priority::send_card(&state.room, &markdown, priority, &settings.priorities).await;
+    audit::record(
+        &state.db,
+        "home-assistant",
+        "webhook.home_assistant",
+        None,
+        Some(&notification.title),
+        &result,
+    )
+    .await;
+    state.alerts.observe(&result);
+    let event_id = result?;
+    state.alerts.success(Subsystem::Matrix);
+    info!(%event_id, "Home Assistant notification sent");
+    Ok(())
+}
+
+/// Replaces `{title}`, `{message}` and `{name}` for each `data` entry in the
+/// template, in one pass so placeholders in the values are left as they are.
+/// Unknown placeholders are left as they are too.
+pub fn render(template: &str, notification: &HomeAssistantNotification) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| Some((end, placeholder(notification, &after[..end])?)));
+        match value {
+            Some((end, value)) => {
+                rendered.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                rendered.push('{');
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn placeholder(notification: &HomeAssistantNotification, name: &str) -> Option<String> {
+    match name {
+        "title" => Some(notification.title.clone()),
+        "message" => Some(notification.message.clone()),
+        _ => notification.data.get(name).map(|value| match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn renders_template_with_data() {
+        let notification: HomeAssistantNotification = serde_json::from_value(json!({
+            "title": "Washing machine",
+            "message": "Cycle finished",
+            "data": { "room": "laundry", "minutes": 92 },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            render(
+                "#### 🏠 {title}\n{message} in {room} after {minutes} min {unknown}",
+                &notification
+            ),
+            "#### 🏠 Washing machine\nCycle finished in laundry after 92 min {unknown}"
+        );
+    }
+
+    #[test]
+    fn placeholders_in_values_stay_as_sent() {
+        let notification: HomeAssistantNotification = serde_json::from_value(json!({
+            "title": "Alarm {message} {room}",
+            "message": "Door {title} opened",
+            "data": { "room": "{title}" },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            render("{title}: {message} in {room} {{title}", &notification),
+            "Alarm {message} {room}: Door {title} opened in {title} {Alarm {message} {room}"
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use tracing::warn;
+
+use crate::metrics::Histogram;
+
+/// Upper bounds of the API call latency buckets, in seconds.
+const LATENCY_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Times a GET is sent again after a connection error, a timeout, or a 502,
+/// 503 or 504 from a proxy in front of the service.
+const MAX_RETRIES: u32 = 2;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Calls slower than this are logged, unless `HTTP_SLOW_CALL_MS` says
+/// otherwise.
+const DEFAULT_SLOW_AFTER: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct EndpointKey {
+    service: &'static str,
+    method: String,
+    endpoint: &'static str,
+}
+
+impl EndpointKey {
+    fn labels(&self) -> String {
+        format!(
+            "service=\"{}\",method=\"{}\",endpoint=\"{}\"",
+            self.service, self.method, self.endpoint
+        )
+    }
+}
+
+struct EndpointStats {
+    latency: Histogram,
+    /// Calls by status code, `error` when no response came back.
+    responses: BTreeMap<String, u64>,
+    retries: u64,
+}
+
+impl Default for EndpointStats {
+    fn default() -> Self {
+        Self {
+            latency: Histogram::new(LATENCY_BUCKETS),
+            responses: BTreeMap::new(),
+            retries: 0,
+        }
+    }
+}
+
+/// Latency, status codes and retries of the calls to the Seerr, Sonarr and
+/// Radarr APIs since the bot started, by endpoint, for `/admin/metrics`.
+pub struct HttpMetrics {
+    slow_after: Duration,
+    endpoints: Mutex<BTreeMap<EndpointKey, EndpointStats>>,
+}
+
+impl Default for HttpMetrics {
+    fn default() -> Self {
+        Self::new(DEFAULT_SLOW_AFTER)
+    }
+}
+
+impl HttpMetrics {
+    /// Calls taking `slow_after` or longer are logged.
+    pub fn new(slow_after: Duration) -> Self {
+        Self {
+            slow_after,
+            endpoints: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Records the calls of the API client of `service`.
+    pub fn service(self: &Arc<Self>, service: &'static str) -> ServiceMetrics {
+        ServiceMetrics {
+            service,
+            metrics: self.clone(),
+        }
+    }
+
+    fn record(&self, key: &EndpointKey, elapsed: Duration, status: &str, retry: bool) {
+        if elapsed >= self.slow_after {
+            warn!(
+                service = key.service,
+                method = %key.method,
+                endpoint = key.endpoint,
+                status,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Slow HTTP call"
+            );
+        }
+        let mut endpoints = self.endpoints.lock().unwrap_or_else(|e| e.into_inner());
+        let stats = endpoints.entry(key.clone()).or_default();
+        stats.latency.observe(elapsed.as_secs_f64());
+        *stats.responses.entry(status.to_string()).or_default() += 1;
+        if retry {
+            stats.retries += 1;
+        }
+    }
+
+    /// Appends the metrics to `out` in the text exposition format.
+    pub fn render(&self, out: &mut String) {
+        let endpoints = self.endpoints.lock().unwrap_or_else(|e| e.into_inner());
+
+        let name = "michel_http_client_request_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Time the calls to the Seerr, Sonarr and Radarr APIs took, each retry \
+             counting as a call.\n\
+             # TYPE {name} histogram"
+        );
+        for (key, stats) in endpoints.iter() {
+            stats.latency.render_series(name, &key.labels(), out);
+        }
+
+        let name = "michel_http_client_responses_total";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Calls to the Seerr, Sonarr and Radarr APIs by status code, error when \
+             no response came back.\n\
+             # TYPE {name} counter"
+        );
+        for (key, stats) in endpoints.iter() {
+            let labels = key.labels();
+            for (status, count) in &stats.responses {
+                let _ = writeln!(out, "{name}{{{labels},status=\"{status}\"}} {count}");
+            }
+        }
+
+        let name = "michel_http_client_retries_total";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Calls sent again after a transient failure.\n\
+             # TYPE {name} counter"
+        );
+        for (key, stats) in endpoints.iter() {
+            let _ = writeln!(out, "{name}{{{}}} {}", key.labels(), stats.retries);
+        }
+    }
+}
+
+/// The metrics of a single API client.
+#[derive(Clone)]
+pub struct ServiceMetrics {
+    service: &'static str,
+    metrics: Arc<HttpMetrics>,
+}
+
+impl ServiceMetrics {
+    /// Sends the request, recording it under `endpoint`, its path with the
+    /// ids left as placeholders (e.g. `/api/v1/issue/{id}`). GETs failing
+    /// with a transient error are sent again, up to `MAX_RETRIES` times.
+    pub async fn send(
+        &self,
+        endpoint: &'static str,
+        request: RequestBuilder,
+    ) -> reqwest::Result<Response> {
+        let (client, request) = request.build_split();
+        let mut request = request?;
+        let key = EndpointKey {
+            service: self.service,
+            method: request.method().to_string(),
+            endpoint,
+        };
+        let retryable = request.method() == Method::GET;
+        let mut retries = 0;
+        loop {
+            let next = if retryable && retries < MAX_RETRIES {
+                request.try_clone()
+            } else {
+                None
+            };
+            let started = Instant::now();
+            let result = client.execute(request).await;
+            self.metrics
+                .record(&key, started.elapsed(), &status(&result), retries > 0);
+            match next {
+                Some(next) if is_transient(&result) => {
+                    retries += 1;
+                    tokio::time::sleep(RETRY_DELAY * retries).await;
+                    request = next;
+                }
+                _ => return result,
+            }
+        }
+    }
+}
+
+/// `send` recording the call in the metrics of the API client.
+pub trait SendRecorded {
+    fn send_recorded(
+        self,
+        metrics: &ServiceMetrics,
+        endpoint: &'static str,
+    ) -> impl Future<Output = reqwest::Result<Response>> + Send;
+}
+
+impl SendRecorded for RequestBuilder {
+    fn send_recorded(
+        self,
+        metrics: &ServiceMetrics,
+        endpoint: &'static str,
+    ) -> impl Future<Output = reqwest::Result<Response>> + Send {
+        metrics.send(endpoint, self)
+    }
+}
+
+fn status(result: &reqwest::Result<Response>) -> String {
+    match result {
+        Ok(response) => response.status().as_str().to_string(),
+        Err(_) => "error".to_string(),
+    }
+}
+
+fn is_transient(result: &reqwest::Result<Response>) -> bool {
+    match result {
+        Ok(response) => matches!(
+            response.status(),
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        ),
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_are_counted_by_endpoint_and_status() {
+        let metrics = HttpMetrics::default();
+        let key = EndpointKey {
+            service: "seerr",
+            method: "GET".to_string(),
+            endpoint: "/api/v1/issue/{id}",
+        };
+        metrics.record(&key, Duration::from_millis(80), "503", false);
+        metrics.record(&key, Duration::from_millis(300), "200", true);
+
+        let mut out = String::new();
+        metrics.render(&mut out);
+        let labels = "service=\"seerr\",method=\"GET\",endpoint=\"/api/v1/issue/{id}\"";
+        assert!(out.contains(&format!(
+            "\nmichel_http_client_request_duration_seconds_bucket{{{labels},le=\"0.1\"}} 1\n"
+        )));
+        assert!(out.contains(&format!(
+            "\nmichel_http_client_request_duration_seconds_count{{{labels}}} 2\n"
+        )));
+        assert!(out.contains(&format!(
+            "\nmichel_http_client_responses_total{{{labels},status=\"503\"}} 1\n"
+        )));
+        assert!(out.contains(&format!(
+            "\nmichel_http_client_retries_total{{{labels}}} 1\n"
+        )));
+    }
+}
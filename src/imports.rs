@@ -0,0 +1,519 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use chrono::Utc;
+use matrix_sdk::ruma::OwnedEventId;
+use serde::Deserialize;
+use tracing::{Instrument, error, info, info_span};
+
+use crate::AppState;
+use crate::audit;
+use crate::db;
+use crate::health_tickets;
+use crate::lifecycle::{self, IssueEvent};
+use crate::markdown;
+use crate::matrix;
+use crate::queue::format_size;
+use crate::routing::{self, Stream};
+use crate::stats::format_duration;
+
+/// Actor recorded in the audit log for issues resolved after an import.
+const AUTO_RESOLVE_ACTOR: &str = "auto-resolve";
+
+/// Sonarr webhook, only what the bot reads from it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SonarrWebhook {
+    pub event_type: String,
+    pub series: Option<SonarrSeries>,
+    #[serde(default)]
+    pub episodes: Vec<SonarrEpisode>,
+    pub release: Option<Release>,
+    #[serde(default)]
+    pub is_upgrade: bool,
+    #[serde(flatten)]
+    pub health: HealthCheck,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SonarrSeries {
+    pub title: String,
+    pub tvdb_id: Option<i64>,
+    pub tmdb_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SonarrEpisode {
+    pub season_number: i64,
+    pub episode_number: i64,
+}
+
+/// Radarr webhook, only what the bot reads from it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RadarrWebhook {
+    pub event_type: String,
+    pub movie: Option<RadarrMovie>,
+    pub release: Option<Release>,
+    #[serde(default)]
+    pub is_upgrade: bool,
+    #[serde(flatten)]
+    pub health: HealthCheck,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RadarrMovie {
+    pub title: String,
+    pub year: Option<i32>,
+    pub tmdb_id: Option<i64>,
+}
+
+/// Release grabbed by Sonarr or Radarr.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub release_title: Option<String>,
+    pub quality: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// Fields of `Health` and `HealthRestored` webhooks.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    /// `notice`, `warning` or `error`.
+    pub level: Option<String>,
+    pub message: Option<String>,
+    /// The check, e.g. `IndexerStatusCheck`.
+    #[serde(rename = "type")]
+    pub check_type: Option<String>,
+    pub wiki_url: Option<String>,
+}
+
+/// A health check Sonarr or Radarr reports failing, or passing again.
+#[derive(Debug, PartialEq)]
+pub struct HealthEvent {
+    /// `Sonarr` or `Radarr`.
+    pub source: &'static str,
+    pub check_type: String,
+    pub level: Option<String>,
+    pub message: String,
+    pub wiki_url: Option<String>,
+    pub restored: bool,
+}
+
+impl HealthCheck {
+    fn event(&self, source: &'static str, event_type: &str) -> Option<HealthEvent> {
+        let restored = match event_type {
+            "Health" => false,
+            "HealthRestored" => true,
+            _ => return None,
+        };
+        Some(HealthEvent {
+            source,
+            check_type: self.check_type.clone()?,
+            level: self.level.clone(),
+            message: self.message.clone().unwrap_or_default(),
+            wiki_url: self.wiki_url.clone(),
+            restored,
+        })
+    }
+}
+
+/// A release Sonarr or Radarr sent to the download client.
+#[derive(Debug, PartialEq)]
+pub struct Grab {
+    /// `movie` or `tv`, as in Seerr.
+    pub media_type: &'static str,
+    pub tmdb_id: Option<i64>,
+    pub title: String,
+    pub release: Release,
+}
+
+/// A file Sonarr or Radarr just imported.
+#[derive(Debug, PartialEq)]
+pub struct Import {
+    /// `movie` or `tv`, as in Seerr.
+    pub media_type: &'static str,
+    pub tmdb_id: Option<i64>,
+    pub tvdb_id: Option<i64>,
+    pub title: String,
+    pub upgrade: bool,
+}
+
+impl SonarrWebhook {
+    fn title(&self) -> Option<String> {
+        let mut title = self.series.as_ref()?.title.clone();
+        let episodes: Vec<String> = self
+            .episodes
+            .iter()
+            .map(|e| format!("S{:02}E{:02}", e.season_number, e.episode_number))
+            .collect();
+        if !episodes.is_empty() {
+            title.push_str(&format!(" {}", episodes.join(", ")));
+        }
+        Some(title)
+    }
+
+    /// The import this webhook reports, if it is one.
+    pub fn import(&self) -> Option<Import> {
+        if self.event_type != "Download" {
+            return None;
+        }
+        let series = self.series.as_ref()?;
+        Some(Import {
+            media_type: "tv",
+            tmdb_id: series.tmdb_id,
+            tvdb_id: series.tvdb_id,
+            title: self.title()?,
+            upgrade: self.is_upgrade,
+        })
+    }
+
+    /// The grab this webhook reports, if it is one.
+    pub fn grab(&self) -> Option<Grab> {
+        if self.event_type != "Grab" {
+            return None;
+        }
+        Some(Grab {
+            media_type: "tv",
+            tmdb_id: self.series.as_ref()?.tmdb_id,
+            title: self.title()?,
+            release: self.release.clone()?,
+        })
+    }
+
+    /// The health check this webhook reports, if it is one.
+    pub fn health(&self) -> Option<HealthEvent> {
+        self.health.event("Sonarr", &self.event_type)
+    }
+}
+
+impl RadarrWebhook {
+    fn title(&self) -> Option<String> {
+        let movie = self.movie.as_ref()?;
+        Some(match movie.year {
+            Some(year) => format!("{} ({year})", movie.title),
+            None => movie.title.clone(),
+        })
+    }
+
+    /// The import this webhook reports, if it is one.
+    pub fn import(&self) -> Option<Import> {
+        if self.event_type != "Download" {
+            return None;
+        }
+        Some(Import {
+            media_type: "movie",
+            tmdb_id: self.movie.as_ref()?.tmdb_id,
+            tvdb_id: None,
+            title: self.title()?,
+            upgrade: self.is_upgrade,
+        })
+    }
+
+    /// The grab this webhook reports, if it is one.
+    pub fn grab(&self) -> Option<Grab> {
+        if self.event_type != "Grab" {
+            return None;
+        }
+        Some(Grab {
+            media_type: "movie",
+            tmdb_id: self.movie.as_ref()?.tmdb_id,
+            title: self.title()?,
+            release: self.release.clone()?,
+        })
+    }
+
+    /// The health check this webhook reports, if it is one.
+    pub fn health(&self) -> Option<HealthEvent> {
+        self.health.event("Radarr", &self.event_type)
+    }
+}
+
+pub async fn handle_sonarr_webhook(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<SonarrWebhook>,
+) -> StatusCode {
+    let span = info_span!("webhook", source = "sonarr", event_type = %payload.event_type);
+    let stream = Stream::Sonarr;
+    receive(
+        &state,
+        stream,
+        payload.import(),
+        payload.grab(),
+        payload.health(),
+    )
+    .instrument(span)
+    .await
+}
+
+pub async fn handle_radarr_webhook(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<RadarrWebhook>,
+) -> StatusCode {
+    let span = info_span!("webhook", source = "radarr", event_type = %payload.event_type);
+    let stream = Stream::Radarr;
+    receive(
+        &state,
+        stream,
+        payload.import(),
+        payload.grab(),
+        payload.health(),
+    )
+    .instrument(span)
+    .await
+}
+
+async fn receive(
+    state: &AppState,
+    stream: Stream,
+    import: Option<Import>,
+    grab: Option<Grab>,
+    health: Option<HealthEvent>,
+) -> StatusCode {
+    match routing::route(&state.db, state.room.room_id().as_str(), stream).await {
+        Ok(Some(false)) => {
+            info!(%stream, "Stream turned off in the room, skipping webhook");
+            return StatusCode::OK;
+        }
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to read the room routing: {e:#}");
+            return StatusCode::INTERNAL_SERVER_ERROR;
+        }
+    }
+    let result = match (import, grab, health) {
+        (Some(import), _, _) => notify_issues(state, &import).await,
+        (_, Some(grab), _) => notify_requests(state, &grab).await,
+        (_, _, Some(health)) => health_tickets::file(state, &health).await,
+        // Renames, tests, ... are not relayed
+        _ => return StatusCode::OK,
+    };
+    match result {
+        Ok(()) => StatusCode::OK,
+        Err(e) => {
+            error!("Error handling Sonarr or Radarr webhook: {e:#}");
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
+
+fn render_grab(grab: &Grab) -> String {
+    let release = &grab.release;
+    let mut details = Vec::new();
+    if let Some(quality) = &release.quality {
+        details.push(markdown::escape(quality));
+    }
+    if release.size > 0 {
+        details.push(format_size(release.size));
+    }
+    let mut markdown = format!(
+        "**⬇️ Download started:** {}",
+        markdown::escape(release.release_title.as_deref().unwrap_or(&grab.title))
+    );
+    if !details.is_empty() {
+        markdown.push_str(&format!(" ({})", details.join(", ")));
+    }
+    markdown
+}
+
+/// Tells the threads of the tracked requests for the media that a download
+/// started.
+async fn notify_requests(state: &AppState, grab: &Grab) -> Result<()> {
+    if !state.settings.get().download_notices_enabled {
+        return Ok(());
+    }
+    let Some(tmdb_id) = grab.tmdb_id else {
+        return Ok(());
+    };
+    let markdown = render_grab(grab);
+    for request in db::list_waiting_requests_for_media(&state.db, grab.media_type, tmdb_id).await? {
+        let root: OwnedEventId = request.matrix_event_id.as_str().try_into()?;
+        matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+        info!(request_id = request.request_id, title = %grab.title, "Download notice sent");
+    }
+    Ok(())
+}
+
+fn render_notice(import: &Import, auto_resolve_after: Option<std::time::Duration>) -> String {
+    let verb = if import.upgrade {
+        "upgraded"
+    } else {
+        "imported"
+    };
+    let mut markdown = format!(
+        "**📦 A new version of {} was just {verb}, does this fix it?**",
+        markdown::escape(&import.title)
+    );
+    if let Some(after) = auto_resolve_after {
+        let after = chrono::Duration::from_std(after).unwrap_or(chrono::Duration::MAX);
+        markdown.push_str(&format!(
+            "  \nWithout any reply, the issue is resolved in {}.",
+            format_duration(after)
+        ));
+    }
+    markdown
+}
+
+/// Asks in the thread of every open issue about the imported media whether
+/// the new file fixes it.
+async fn notify_issues(state: &AppState, import: &Import) -> Result<()> {
+    let issues = db::list_issues_to_notify_of_import(
+        &state.db,
+        import.media_type,
+        import.tmdb_id,
+        import.tvdb_id,
+    )
+    .await?;
+    let markdown = render_notice(import, state.settings.get().import_auto_resolve_after);
+    for issue in issues {
+        let root: OwnedEventId = issue.matrix_event_id.as_str().try_into()?;
+        matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+        db::record_import_notice(&state.db, issue.issue_id).await?;
+        info!(issue_id = issue.issue_id, title = %import.title, "Import notice sent");
+    }
+    Ok(())
+}
+
+/// Resolves the issues nobody answered since they were told about an import,
+/// once the configured grace period is over.
+pub async fn auto_resolve(state: &AppState) -> Result<()> {
+    let Some(after) = state.settings.get().import_auto_resolve_after else {
+        return Ok(());
+    };
+    let before = Utc::now() - chrono::Duration::from_std(after)?;
+    for issue in db::list_issues_to_auto_resolve(&state.db, before).await? {
+        let result = state.seerr_client.resolve_issue(issue.issue_id).await;
+        audit::record(
+            &state.db,
+            AUTO_RESOLVE_ACTOR,
+            "seerr.resolve_issue",
+            Some(issue.issue_id),
+            None,
+            &result,
+        )
+        .await;
+        result?;
+        lifecycle::apply(&state.db, issue.issue_id, IssueEvent::Resolved, None).await?;
+
+        let root: OwnedEventId = issue.matrix_event_id.as_str().try_into()?;
+        let markdown =
+            "**Resolved automatically, nobody replied since the new version was imported**";
+        matrix::send_thread_markdown(&state.room, &root, markdown).await?;
+        info!(issue_id = issue.issue_id, "Issue resolved after an import");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn sonarr_download_is_an_import() {
+        let payload: SonarrWebhook = serde_json::from_str(
+            r#"{"eventType": "Download", "isUpgrade": true,
+                "series": {"title": "Some Show", "tvdbId": 81189, "tmdbId": 1396},
+                "episodes": [{"seasonNumber": 1, "episodeNumber": 2}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            payload.import(),
+            Some(Import {
+                media_type: "tv",
+                tmdb_id: Some(1396),
+                tvdb_id: Some(81189),
+                title: "Some Show S01E02".to_string(),
+                upgrade: true,
+            })
+        );
+
+        let grab: SonarrWebhook =
+            serde_json::from_str(r#"{"eventType": "Grab", "series": {"title": "Some Show"}}"#)
+                .unwrap();
+        assert_eq!(grab.import(), None);
+    }
+
+    #[test]
+    fn radarr_download_is_an_import() {
+        let payload: RadarrWebhook = serde_json::from_str(
+            r#"{"eventType": "Download", "movie": {"title": "Some Movie", "year": 2024, "tmdbId": 693134}}"#,
+        )
+        .unwrap();
+        let import = payload.import().unwrap();
+        assert_eq!(import.media_type, "movie");
+        assert_eq!(import.tmdb_id, Some(693134));
+        assert_eq!(import.title, "Some Movie (2024)");
+        assert!(!import.upgrade);
+
+        let test: RadarrWebhook = serde_json::from_str(r#"{"eventType": "Test"}"#).unwrap();
+        assert_eq!(test.import(), None);
+    }
+
+    #[test]
+    fn grab_notice_shows_the_release() {
+        let payload: RadarrWebhook = serde_json::from_str(
+            r#"{"eventType": "Grab", "movie": {"title": "Some Movie", "year": 2024, "tmdbId": 693134},
+                "release": {"releaseTitle": "Some.Movie.2024.1080p.WEB", "quality": "WEBDL-1080p", "size": 4200000000}}"#,
+        )
+        .unwrap();
+        assert_eq!(payload.import(), None);
+        let grab = payload.grab().unwrap();
+        assert_eq!(grab.tmdb_id, Some(693134));
+        assert_eq!(
+            render_grab(&grab),
+            r"**⬇️ Download started:** Some\.Movie\.2024\.1080p\.WEB (WEBDL\-1080p, 4.2 GB)"
+        );
+    }
+
+    #[test]
+    fn health_webhooks_are_health_events() {
+        let payload: SonarrWebhook = serde_json::from_str(
+            r#"{"eventType": "Health", "level": "error", "type": "IndexerStatusCheck",
+                "message": "All indexers are unavailable due to failures",
+                "wikiUrl": "https://wiki.servarr.com/sonarr/system#indexers-are-unavailable-due-to-failures"}"#,
+        )
+        .unwrap();
+        assert_eq!(payload.import(), None);
+        let health = payload.health().unwrap();
+        assert_eq!(health.source, "Sonarr");
+        assert_eq!(health.check_type, "IndexerStatusCheck");
+        assert_eq!(health.level.as_deref(), Some("error"));
+        assert!(!health.restored);
+
+        let restored: RadarrWebhook = serde_json::from_str(
+            r#"{"eventType": "HealthRestored", "level": "warning", "type": "DownloadClientCheck",
+                "message": "Unable to communicate with qBittorrent"}"#,
+        )
+        .unwrap();
+        assert!(restored.health().unwrap().restored);
+
+        let test: RadarrWebhook = serde_json::from_str(r#"{"eventType": "Test"}"#).unwrap();
+        assert_eq!(test.health(), None);
+    }
+
+    #[test]
+    fn notice_mentions_auto_resolve() {
+        let import = Import {
+            media_type: "movie",
+            tmdb_id: Some(1),
+            tvdb_id: None,
+            title: "Some Movie (2024)".to_string(),
+            upgrade: false,
+        };
+        let markdown = render_notice(&import, Some(Duration::from_secs(48 * 3600)));
+        assert!(markdown.contains(r"Some Movie \(2024\) was just imported"));
+        assert!(markdown.contains("resolved in 2 days"));
+        assert!(!render_notice(&import, None).contains("resolved in"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueState {
+    Open,
+    /// An admin said they are looking at it.
+    Acknowledged,
+    InProgress,
+    Resolved,
+    /// Open again after having been resolved.
+    Reopened,
+}
+
+impl IssueState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueState::Open => "open",
+            IssueState::Acknowledged => "acknowledged",
+            IssueState::InProgress => "in_progress",
+            IssueState::Resolved => "resolved",
+            IssueState::Reopened => "reopened",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open" => Some(IssueState::Open),
+            "acknowledged" => Some(IssueState::Acknowledged),
+            "in_progress" => Some(IssueState::InProgress),
+            "resolved" => Some(IssueState::Resolved),
+            "reopened" => Some(IssueState::Reopened),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IssueState::Open => "🔴 Open",
+            IssueState::Acknowledged => "👀 Acknowledged",
+            IssueState::InProgress => "🟡 In progress",
+            IssueState::Resolved => "✅ Resolved",
+            IssueState::Reopened => "🔄 Reopened",
+        }
+    }
+}
+
+impl fmt::Display for IssueState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What an issue is about, as picked by the reporter in Seerr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueCategory {
+    Video,
+    Audio,
+    Subtitles,
+    Other,
+}
+
+impl IssueCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IssueCategory::Video => "video",
+            IssueCategory::Audio => "audio",
+            IssueCategory::Subtitles => "subtitles",
+            IssueCategory::Other => "other",
+        }
+    }
+
+    /// Accepts the names used by the bot and the `{{issue_type}}` of Seerr
+    /// webhooks, e.g. `Subtitle`, in any case.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "video" => Some(IssueCategory::Video),
+            "audio" => Some(IssueCategory::Audio),
+            "subtitle" | "subtitles" => Some(IssueCategory::Subtitles),
+            "other" => Some(IssueCategory::Other),
+            _ => None,
+        }
+    }
+
+    /// From the `issueType` code of the Seerr API.
+    pub fn from_seerr(issue_type: i64) -> Option<Self> {
+        match issue_type {
+            1 => Some(IssueCategory::Video),
+            2 => Some(IssueCategory::Audio),
+            3 => Some(IssueCategory::Subtitles),
+            4 => Some(IssueCategory::Other),
+            _ => None,
+        }
+    }
+
+    /// The `issueType` code of the Seerr API.
+    pub fn seerr_type(&self) -> i64 {
+        match self {
+            IssueCategory::Video => 1,
+            IssueCategory::Audio => 2,
+            IssueCategory::Subtitles => 3,
+            IssueCategory::Other => 4,
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            IssueCategory::Video => "🎬",
+            IssueCategory::Audio => "🔊",
+            IssueCategory::Subtitles => "🔤",
+            IssueCategory::Other => "❓",
+        }
+    }
+}
+
+impl fmt::Display for IssueCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_category() {
+        assert_eq!(
+            IssueCategory::parse("Subtitle"),
+            Some(IssueCategory::Subtitles)
+        );
+        assert_eq!(
+            IssueCategory::parse("subtitles"),
+            Some(IssueCategory::Subtitles)
+        );
+        assert_eq!(IssueCategory::parse("VIDEO"), Some(IssueCategory::Video));
+        assert_eq!(IssueCategory::parse("lighting"), None);
+        assert_eq!(IssueCategory::from_seerr(2), Some(IssueCategory::Audio));
+        assert_eq!(IssueCategory::from_seerr(0), None);
+    }
+}
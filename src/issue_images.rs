@@ -0,0 +1,116 @@
+use matrix_sdk::ruma::OwnedEventId;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::matrix;
+
+/// Screenshots posted for a single issue description or comment, the rest
+/// staying links.
+const MAX_SCREENSHOTS: usize = 4;
+const IMAGE_EXTENSIONS: [&str; 5] = [".png", ".jpg", ".jpeg", ".gif", ".webp"];
+
+/// Links to images in the text of an issue or comment, whether written as
+/// Markdown images or bare URLs, in order and without duplicates.
+pub fn image_urls(text: &str) -> Vec<&str> {
+    let mut urls = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        let end = candidate
+            .find(|c: char| c.is_whitespace() || matches!(c, ')' | '>' | '"' | '\'' | ']'))
+            .unwrap_or(candidate.len());
+        // Punctuation ending the sentence the link is in
+        let url = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!']);
+        let is_url = url.starts_with("http://") || url.starts_with("https://");
+        if is_url && is_image(url) && !urls.contains(&url) {
+            urls.push(url);
+        }
+        rest = &candidate[end..];
+    }
+    urls
+}
+
+fn is_image(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_lowercase();
+    IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext))
+}
+
+/// File name shown for an image, the last part of its path.
+fn file_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/')
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("image")
+}
+
+/// Posts the poster of the media and the screenshots linked in `text` in the
+/// thread with `ISSUE_IMAGES_ENABLED`, uploaded to the homeserver for clients
+/// to show them. An image that can't be fetched is left out. Runs in the
+/// background, for slow image hosts not to hold up the webhook.
+pub fn post(
+    state: &AppState,
+    thread_root_event_id: &OwnedEventId,
+    poster: Option<&str>,
+    text: &str,
+) {
+    if !state.settings.get().issue_images_enabled {
+        return;
+    }
+    let screenshots = image_urls(text).into_iter().take(MAX_SCREENSHOTS);
+    let images: Vec<(String, String)> = poster
+        .map(|url| (url, "Poster"))
+        .into_iter()
+        .chain(screenshots.map(|url| (url, file_name(url))))
+        .map(|(url, body)| (url.to_string(), body.to_string()))
+        .collect();
+    if images.is_empty() {
+        return;
+    }
+
+    let room = state.room.clone();
+    let thread_root_event_id = thread_root_event_id.clone();
+    tokio::spawn(async move {
+        let client = room.client();
+        for (url, body) in images {
+            let sent = async {
+                let image = matrix::upload_image(&client, &url).await?;
+                matrix::send_thread_image(&room, &thread_root_event_id, &image, &body).await
+            }
+            .await;
+            match sent {
+                Ok(event_id) => info!(url, %event_id, "Issue image posted"),
+                Err(e) => warn!(url, "Failed to post issue image: {e:#}"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_linked_images() {
+        let text = "Audio is out of sync, see ![screenshot](https://i.imgur.com/abc.PNG) \
+                    and https://example.com/shot.jpg?raw=1, not https://example.com/page \
+                    (again: <https://i.imgur.com/abc.PNG>)";
+        assert_eq!(
+            image_urls(text),
+            vec![
+                "https://i.imgur.com/abc.PNG",
+                "https://example.com/shot.jpg?raw=1"
+            ]
+        );
+        assert!(image_urls("No picture, sorry").is_empty());
+    }
+
+    #[test]
+    fn names_images_after_their_file() {
+        assert_eq!(
+            file_name("https://example.com/shots/frame.png?raw=1"),
+            "frame.png"
+        );
+        assert_eq!(file_name("https://example.com/"), "image");
+    }
+}
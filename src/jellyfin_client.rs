@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::config::ServiceConfig;
+
+#[derive(Clone)]
+pub struct JellyfinClient {
+    base_url: String,
+    api_key: String,
+    client: Client,
+}
+
+/// Playback session, only what the bot reads from it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Session {
+    pub user_name: Option<String>,
+    pub client: Option<String>,
+    pub now_playing_item: Option<NowPlayingItem>,
+    pub play_state: Option<PlayState>,
+    pub transcoding_info: Option<TranscodingInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct NowPlayingItem {
+    pub name: String,
+    pub series_name: Option<String>,
+    /// Season number of an episode.
+    pub parent_index_number: Option<i64>,
+    /// Episode number of an episode.
+    pub index_number: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlayState {
+    #[serde(default)]
+    pub is_paused: bool,
+    /// `DirectPlay`, `DirectStream` or `Transcode`.
+    pub play_method: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TranscodingInfo {
+    /// Bits per second.
+    pub bitrate: Option<u64>,
+}
+
+impl JellyfinClient {
+    pub fn new(config: &ServiceConfig) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            client: Client::new(),
+        }
+    }
+
+    /// Sessions active in the last minutes, playing or not.
+    pub async fn sessions(&self) -> Result<Vec<Session>> {
+        self.client
+            .get(format!("{}/Sessions", self.base_url))
+            .query(&[("activeWithinSeconds", 960)])
+            .header("X-Emby-Token", &self.api_key)
+            .send()
+            .await
+            .context("Failed to reach Jellyfin")?
+            .error_for_status()
+            .context("Jellyfin returned error for sessions")?
+            .json()
+            .await
+            .context("Invalid sessions from Jellyfin")
+    }
+
+    /// Starts a scan of every library, which runs in the background.
+    pub async fn refresh_library(&self) -> Result<()> {
+        self.client
+            .post(format!("{}/Library/Refresh", self.base_url))
+            .header("X-Emby-Token", &self.api_key)
+            .send()
+            .await
+            .context("Failed to reach Jellyfin")?
+            .error_for_status()
+            .context("Jellyfin returned error for library refresh")?;
+        Ok(())
+    }
+}
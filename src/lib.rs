@@ -1,15 +1,140 @@
+pub mod admin;
+pub mod alerts;
+pub mod attachments;
+pub mod audit;
+pub mod availability;
+pub mod batching;
+pub mod bot;
+pub mod calendar;
+pub mod check;
 pub mod commands;
+pub mod comment_echo;
+pub mod concurrency;
 pub mod config;
+pub mod cross_refs;
+pub mod dashboard;
 pub mod db;
+pub mod digest;
+pub mod disk;
+pub mod export;
+pub mod follow_ups;
+pub mod health;
+pub mod health_tickets;
+pub mod heartbeat;
+pub mod home_assistant;
+pub mod http_metrics;
+pub mod imports;
+pub mod issue;
+pub mod issue_images;
+pub mod jellyfin_client;
+pub mod lifecycle;
+pub mod logging;
+pub mod maintenance;
+pub mod markdown;
 pub mod matrix;
+pub mod media_details;
+pub mod metrics;
+pub mod notifier;
+pub mod now_playing;
+pub mod outbox;
+pub mod outgoing;
+pub mod pending_actions;
+pub mod presence;
+pub mod priority;
+pub mod push;
+pub mod qbittorrent_client;
+pub mod queue;
+pub mod quiet_hours;
+pub mod quota;
+pub mod radarr_client;
+pub mod reactions;
+pub mod reconcile;
+pub mod redaction;
+pub mod remediation;
+pub mod remind;
+pub mod reminders;
+pub mod report;
+pub mod request;
+pub mod room_config;
+pub mod routing;
+pub mod rules;
+pub mod scheduler;
 pub mod seerr;
 pub mod seerr_client;
+pub mod settings;
+pub mod shutdown;
+pub mod sonarr_client;
+pub mod stats;
+pub mod supervisor;
+pub mod sync_loop;
+pub mod time_format;
+pub mod translation;
+pub mod verification;
+pub mod votes;
 pub mod webhook;
+pub mod webhook_drift;
+pub mod whats_new;
+
+use std::sync::Arc;
 
 use matrix_sdk::Room;
 use sqlx::PgPool;
+use tokio::sync::Notify;
+
+use crate::alerts::Alerts;
+use crate::batching::Batcher;
+use crate::comment_echo::ForwardedComments;
+use crate::concurrency::Limiter;
+use crate::config::HomeAssistantConfig;
+use crate::heartbeat::SyncHealth;
+use crate::http_metrics::HttpMetrics;
+use crate::jellyfin_client::JellyfinClient;
+use crate::media_details::MediaDetailsCache;
+use crate::outgoing::OutgoingWebhooks;
+use crate::push::Push;
+use crate::quiet_hours::Pause;
+use crate::reactions::CommentPrompts;
+use crate::seerr_client::SeerrClient;
+use crate::settings::LiveSettings;
+use crate::translation::Translator;
 
 pub struct AppState {
     pub room: Room,
     pub db: PgPool,
+    /// Bearer token for the `/admin` endpoints, which are disabled without one.
+    pub admin_api_token: Option<String>,
+    pub settings: Arc<LiveSettings>,
+    pub alerts: Arc<Alerts>,
+    pub seerr_client: SeerrClient,
+    /// Media server whose library is refreshed when media becomes available.
+    pub jellyfin_client: Option<JellyfinClient>,
+    pub outgoing: OutgoingWebhooks,
+    pub push: Option<Push>,
+    /// Token and template of `/webhook/home-assistant`, disabled without one.
+    pub home_assistant: Option<HomeAssistantConfig>,
+    /// Shared with the command handlers.
+    pub limiter: Arc<Limiter>,
+    /// Translates Seerr comments, disabled without `TRANSLATION_URL`.
+    pub translator: Option<Translator>,
+    /// Where Seerr users reach the bot, to open attachments forwarded from
+    /// issue threads.
+    pub public_url: Option<String>,
+    /// Coalesces request notifications arriving together, disabled without
+    /// `NOTIFICATION_BATCH_THRESHOLD`.
+    pub batcher: Option<Batcher>,
+    /// TMDB details shown on cards with `MEDIA_DETAILS_ENABLED`.
+    pub media_details: MediaDetailsCache,
+    /// Set by `!bot pause`, holding notifications until it ends.
+    pub pause: Pause,
+    /// Wakes the quiet hours worker to deliver the held notifications without
+    /// waiting for its next poll.
+    pub release_held: Notify,
+    /// Comments sent to Seerr from issue threads, not to post them back.
+    pub forwarded_comments: ForwardedComments,
+    /// Admins asked for a comment with the comment reaction.
+    pub comment_prompts: CommentPrompts,
+    /// Last sync and recovery from sync failures.
+    pub sync_health: Arc<SyncHealth>,
+    /// Calls to the Seerr, Sonarr and Radarr APIs.
+    pub http_metrics: Arc<HttpMetrics>,
 }
@@ -0,0 +1,127 @@
+use std::fmt;
+
+use anyhow::Result;
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::db;
+use crate::issue::IssueState;
+
+/// Something that happened to an issue, reported by a Seerr webhook or done
+/// with a command. Issues start [`IssueState::Open`] when their card is
+/// posted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueEvent {
+    Acknowledged,
+    Commented,
+    Resolved,
+    Reopened,
+}
+
+impl fmt::Display for IssueEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IssueEvent::Acknowledged => "acknowledged",
+            IssueEvent::Commented => "commented",
+            IssueEvent::Resolved => "resolved",
+            IssueEvent::Reopened => "reopened",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidTransition {
+    pub from: IssueState,
+    pub event: IssueEvent,
+}
+
+impl fmt::Display for InvalidTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a {} issue can't be {}", self.from, self.event)
+    }
+}
+
+/// State of an issue in `from` after `event`:
+///
+/// ```text
+/// Open ──ack──▶ Acknowledged ──comment──▶ InProgress ──resolve──▶ Resolved ──reopen──▶ Reopened
+/// ```
+///
+/// Comments move any unresolved issue to `InProgress` and leave resolved
+/// ones alone, resolving works from every state and acknowledging only before
+/// work started.
+pub fn next_state(from: IssueState, event: IssueEvent) -> Result<IssueState, InvalidTransition> {
+    use IssueState::*;
+    match (from, event) {
+        (Open | Reopened | Acknowledged, IssueEvent::Acknowledged) => Ok(Acknowledged),
+        (Resolved, IssueEvent::Commented) => Ok(Resolved),
+        (_, IssueEvent::Commented) => Ok(InProgress),
+        (_, IssueEvent::Resolved) => Ok(Resolved),
+        (Resolved, IssueEvent::Reopened) => Ok(Reopened),
+        (from, event) => Err(InvalidTransition { from, event }),
+    }
+}
+
+/// Records `event` on the issue and returns its new state, `actor` being
+/// kept as the acknowledger or resolver. An invalid transition is logged and
+/// leaves the issue untouched, in which case `None` is returned.
+pub async fn apply(
+    pool: &PgPool,
+    issue_id: i64,
+    event: IssueEvent,
+    actor: Option<&str>,
+) -> Result<Option<IssueState>> {
+    let from = db::get_issue_status(pool, issue_id)
+        .await?
+        .unwrap_or(IssueState::Open);
+    match next_state(from, event) {
+        Ok(state) => {
+            db::set_issue_status(pool, issue_id, state, actor).await?;
+            Ok(Some(state))
+        }
+        Err(e) => {
+            warn!(issue_id, "Ignoring issue event: {e}");
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_lifecycle() {
+        let mut state = IssueState::Open;
+        for (event, expected) in [
+            (IssueEvent::Acknowledged, IssueState::Acknowledged),
+            (IssueEvent::Commented, IssueState::InProgress),
+            (IssueEvent::Resolved, IssueState::Resolved),
+            (IssueEvent::Commented, IssueState::Resolved),
+            (IssueEvent::Reopened, IssueState::Reopened),
+            (IssueEvent::Resolved, IssueState::Resolved),
+        ] {
+            state = next_state(state, event).unwrap();
+            assert_eq!(state, expected, "after {event}");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_transitions() {
+        assert_eq!(
+            next_state(IssueState::Open, IssueEvent::Reopened),
+            Err(InvalidTransition {
+                from: IssueState::Open,
+                event: IssueEvent::Reopened,
+            })
+        );
+        assert!(next_state(IssueState::InProgress, IssueEvent::Acknowledged).is_err());
+        assert!(next_state(IssueState::Resolved, IssueEvent::Acknowledged).is_err());
+        assert_eq!(
+            next_state(IssueState::Reopened, IssueEvent::Reopened)
+                .unwrap_err()
+                .to_string(),
+            "a reopened issue can't be reopened"
+        );
+    }
+}
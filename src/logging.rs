@@ -0,0 +1,90 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::{LogFormat, LogRotation, LoggingConfig};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Flushes the log file and pending error reports when dropped, so keep it
+/// alive until the process exits.
+#[derive(Default)]
+pub struct LogGuard {
+    _file: Option<WorkerGuard>,
+    #[cfg(feature = "sentry")]
+    _sentry: Option<sentry::ClientInitGuard>,
+}
+
+/// Installs the global subscriber.
+pub fn init(config: &LoggingConfig) -> Result<LogGuard> {
+    let filter = EnvFilter::try_new(&config.filter)
+        .with_context(|| format!("Invalid log filter {:?}", config.filter))?;
+
+    let mut guard = LogGuard::default();
+    let mut layers: Vec<BoxedLayer> = vec![layer(config.format, std::io::stdout, true)];
+    if let Some(path) = &config.file {
+        let (writer, file_guard) = tracing_appender::non_blocking(appender(path, config.rotation)?);
+        layers.push(layer(config.format, writer, false));
+        guard._file = Some(file_guard);
+    }
+    #[cfg(feature = "sentry")]
+    if let Some(dsn) = &config.sentry_dsn {
+        guard._sentry = Some(sentry::init((
+            dsn.as_str(),
+            sentry::ClientOptions {
+                release: sentry::release_name!(),
+                attach_stacktrace: true,
+                ..Default::default()
+            },
+        )));
+        // Error events become Sentry events, with the fields of their spans
+        // (notification type, issue id, ...) as context
+        layers.push(sentry::integrations::tracing::layer().boxed());
+    }
+
+    tracing_subscriber::registry()
+        .with(layers.with_filter(filter))
+        .try_init()
+        .context("Failed to install the log subscriber")?;
+
+    #[cfg(not(feature = "sentry"))]
+    if config.sentry_dsn.is_some() {
+        tracing::warn!("SENTRY_DSN is set but michel-bot was built without the sentry feature");
+    }
+    Ok(guard)
+}
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'w> tracing_subscriber::fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Pretty => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+fn appender(path: &Path, rotation: LogRotation) -> Result<RollingFileAppender> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("LOG_FILE {} has no file name", path.display()))?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let rotation = match rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+    RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(file_name.to_string_lossy())
+        .build(directory)
+        .with_context(|| format!("Failed to open log file {}", path.display()))
+}
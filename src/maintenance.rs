@@ -0,0 +1,248 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use matrix_sdk::ruma::OwnedEventId;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db;
+use crate::markdown;
+use crate::matrix;
+use crate::stats;
+use crate::time_format::TimeFormat;
+
+/// The running maintenance, as JSON.
+const MAINTENANCE_SETTING: &str = "maintenance";
+
+/// What `!maintenance start` announced, persisted so the window and its
+/// pinned announcement outlive a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Window {
+    pub reason: String,
+    pub started_at: DateTime<Utc>,
+    pub announcement: OwnedEventId,
+}
+
+/// A service-down alert held back during a maintenance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suppressed {
+    pub at: DateTime<Utc>,
+    pub title: String,
+    pub details: String,
+}
+
+#[derive(Debug)]
+struct Running {
+    window: Window,
+    suppressed: Vec<Suppressed>,
+}
+
+/// `!maintenance`, during which the admins are not messaged about the outages
+/// it causes. The alerts held back are kept in memory for the summary posted
+/// when it ends.
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    running: Mutex<Option<Running>>,
+}
+
+impl Maintenance {
+    pub fn window(&self) -> Option<Window> {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running.as_ref().map(|running| running.window.clone())
+    }
+
+    /// Keeps the alert for the summary when a maintenance is running.
+    /// Returns whether it was held back.
+    pub fn suppress(&self, title: &str, details: &str) -> bool {
+        self.suppress_at(Utc::now(), title, details)
+    }
+
+    fn suppress_at(&self, at: DateTime<Utc>, title: &str, details: &str) -> bool {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(running) = running.as_mut() else {
+            return false;
+        };
+        info!(title, "Alert held back during maintenance");
+        running.suppressed.push(Suppressed {
+            at,
+            title: title.to_string(),
+            details: details.to_string(),
+        });
+        true
+    }
+
+    fn begin(&self, window: Window) {
+        *self.running.lock().unwrap_or_else(|e| e.into_inner()) = Some(Running {
+            window,
+            suppressed: Vec::new(),
+        });
+    }
+
+    fn finish(&self) -> Option<(Window, Vec<Suppressed>)> {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running
+            .take()
+            .map(|running| (running.window, running.suppressed))
+    }
+}
+
+/// Announces the maintenance in the room, pins the announcement and holds
+/// the service-down alerts back until [`end`].
+pub async fn start(state: &AppState, reason: &str, started_by: &str) -> Result<Window> {
+    let started_at = Utc::now();
+    let time_format = state.settings.get().time_format;
+    let markdown = format!(
+        "#### 🚧 Maintenance: {}\nStarted by {} on {}, some services may be unavailable \
+         until it is over.",
+        markdown::escape(reason),
+        markdown::escape(started_by),
+        time_format.datetime(started_at)
+    );
+    let announcement = matrix::send_markdown(&state.room, &markdown).await?;
+    if let Err(e) = matrix::pin_event(&state.room, &announcement).await {
+        warn!("Failed to pin the maintenance announcement: {e:#}");
+    }
+
+    let window = Window {
+        reason: reason.to_string(),
+        started_at,
+        announcement,
+    };
+    db::set_setting(
+        &state.db,
+        MAINTENANCE_SETTING,
+        &serde_json::to_string(&window)?,
+    )
+    .await?;
+    state.alerts.maintenance.begin(window.clone());
+    info!(reason, "Maintenance started");
+    Ok(window)
+}
+
+/// Ends the maintenance, unpins its announcement and posts the summary of the
+/// alerts held back. Returns whether one was running.
+pub async fn end(state: &AppState) -> Result<bool> {
+    let Some((window, suppressed)) = state.alerts.maintenance.finish() else {
+        return Ok(false);
+    };
+    db::delete_setting(&state.db, MAINTENANCE_SETTING).await?;
+    if let Err(e) = matrix::unpin_event(&state.room, &window.announcement).await {
+        warn!("Failed to unpin the maintenance announcement: {e:#}");
+    }
+
+    let time_format = state.settings.get().time_format;
+    let markdown = summary(&window, &suppressed, Utc::now(), &time_format);
+    matrix::send_long_markdown(&state.room, None, &markdown).await?;
+    info!(
+        reason = window.reason,
+        suppressed = suppressed.len(),
+        "Maintenance over"
+    );
+    Ok(true)
+}
+
+/// Picks up a maintenance that was running when the bot stopped. The alerts
+/// held back before the restart are not in its summary.
+pub async fn restore(state: &AppState) -> Result<()> {
+    let Some(value) = db::get_setting(&state.db, MAINTENANCE_SETTING).await? else {
+        return Ok(());
+    };
+    let window: Window = serde_json::from_str(&value)?;
+    info!(reason = window.reason, "Maintenance still running");
+    state.alerts.maintenance.begin(window);
+    Ok(())
+}
+
+fn summary(
+    window: &Window,
+    suppressed: &[Suppressed],
+    now: DateTime<Utc>,
+    time_format: &TimeFormat,
+) -> String {
+    let mut markdown = format!(
+        "#### ✅ Maintenance over: {}\nLasted {}. ",
+        markdown::escape(&window.reason),
+        stats::format_duration(now - window.started_at)
+    );
+    match suppressed.len() {
+        0 => markdown.push_str("No service-down alert was held back."),
+        1 => markdown.push_str("1 service-down alert was held back:\n"),
+        count => markdown.push_str(&format!("{count} service-down alerts were held back:\n")),
+    }
+    for alert in suppressed {
+        markdown.push_str(&format!(
+            "- {} **{}**",
+            time_format.datetime(alert.at),
+            markdown::escape(&alert.title)
+        ));
+        if !alert.details.is_empty() {
+            markdown.push_str(&format!(": {}", markdown::escape(&alert.details)));
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn window(started_at: DateTime<Utc>) -> Window {
+        Window {
+            reason: "Upgrading Jellyfin".to_string(),
+            started_at,
+            announcement: OwnedEventId::try_from("$announcement:example.com").unwrap(),
+        }
+    }
+
+    #[test]
+    fn alerts_are_held_back_only_during_maintenance() {
+        let maintenance = Maintenance::default();
+        let start = Utc.with_ymd_and_hms(2026, 3, 9, 21, 0, 0).unwrap();
+        assert!(!maintenance.suppress_at(start, "Seerr is failing", "timeout"));
+
+        maintenance.begin(window(start));
+        assert!(maintenance.suppress_at(start, "Seerr is failing", "timeout"));
+        let (window, suppressed) = maintenance.finish().unwrap();
+        assert_eq!(window.reason, "Upgrading Jellyfin");
+        assert_eq!(suppressed.len(), 1);
+
+        assert!(maintenance.window().is_none());
+        assert!(!maintenance.suppress_at(start, "Seerr works again", ""));
+    }
+
+    #[test]
+    fn summary_lists_the_alerts_held_back() {
+        let start = Utc.with_ymd_and_hms(2026, 3, 9, 21, 0, 0).unwrap();
+        let suppressed = [
+            Suppressed {
+                at: start + chrono::Duration::minutes(5),
+                title: "Seerr is failing".to_string(),
+                details: "3 errors in a row".to_string(),
+            },
+            Suppressed {
+                at: start + chrono::Duration::minutes(20),
+                title: "Seerr works again".to_string(),
+                details: String::new(),
+            },
+        ];
+        let end = start + chrono::Duration::minutes(45);
+        let time_format = TimeFormat::default();
+        assert_eq!(
+            summary(&window(start), &suppressed, end, &time_format),
+            "#### ✅ Maintenance over: Upgrading Jellyfin\nLasted 45 min. \
+             2 service-down alerts were held back:\n\
+             - 2026-03-09 21:05 **Seerr is failing**: 3 errors in a row\n\
+             - 2026-03-09 21:20 **Seerr works again**\n"
+        );
+        assert_eq!(
+            summary(&window(start), &[], end, &time_format),
+            "#### ✅ Maintenance over: Upgrading Jellyfin\nLasted 45 min. \
+             No service-down alert was held back."
+        );
+    }
+}
@@ -0,0 +1,354 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd, html};
+
+/// Size budget for the rendered `body` + `formatted_body` of one message. The
+/// homeserver caps whole events at 64 KiB, so keep a margin for JSON escaping
+/// and the rest of the event envelope.
+pub const MAX_MESSAGE_BYTES: usize = 32 * 1024;
+
+fn options() -> Options {
+    Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES
+}
+
+/// Renders Markdown into a `(plain_body, html_body)` pair suitable for a
+/// Matrix `m.text` message.
+pub fn render(markdown: &str) -> (String, String) {
+    (to_plain(markdown), to_html(markdown, None))
+}
+
+/// Like [`render`], with the headings in `color`, e.g. `#d32f2f`, for
+/// clients that support `data-mx-color`.
+pub fn render_colored(markdown: &str, color: &str) -> (String, String) {
+    (to_plain(markdown), to_html(markdown, Some(color)))
+}
+
+/// Escapes user-provided text (issue subjects, comments, user names, ...) so
+/// it renders as typed: Markdown syntax and HTML tags in it can't turn into
+/// links, images or markup in the room.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        // CommonMark lets any ASCII punctuation be backslash-escaped
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Renders `markdown` followed by `hidden` collapsed under `summary`, for
+/// clients that support `<details>`. `summary` is plain text.
+pub fn render_with_details(markdown: &str, summary: &str, hidden: &str) -> (String, String) {
+    let (plain, html) = render(markdown);
+    let (hidden_plain, hidden_html) = render(hidden);
+    (
+        format!("{plain}\n\n{summary}: {hidden_plain}"),
+        format!(
+            "{html}<details><summary>{}</summary>{hidden_html}</details>",
+            escape_html(summary)
+        ),
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Splits Markdown into chunks that each render within `max_bytes`, cutting
+/// on line boundaries. Fenced code blocks cut in the middle are closed and
+/// reopened on the next page.
+pub fn paginate(markdown: &str, max_bytes: usize) -> Vec<String> {
+    let fits = |chunk: &str| {
+        let (plain, html) = render(chunk);
+        plain.len() + html.len() <= max_bytes
+    };
+
+    if fits(markdown) {
+        return vec![markdown.to_string()];
+    }
+
+    let mut pages = Vec::new();
+    let mut current = String::new();
+    let mut open_fence: Option<String> = None;
+
+    for line in markdown
+        .lines()
+        .flat_map(|l| split_long_line(l, max_bytes / 4))
+    {
+        let mut candidate = current.clone();
+        candidate.push_str(&line);
+        candidate.push('\n');
+
+        let closed = match &open_fence {
+            Some(fence) if !line.trim_start().starts_with(fence.as_str()) => {
+                format!("{candidate}{fence}\n")
+            }
+            _ => candidate.clone(),
+        };
+
+        if !current.is_empty() && !fits(&closed) {
+            let mut page = std::mem::take(&mut current);
+            if let Some(fence) = &open_fence {
+                page.push_str(fence);
+                page.push('\n');
+                current.push_str(fence);
+                current.push('\n');
+            }
+            pages.push(page);
+            current.push_str(&line);
+            current.push('\n');
+        } else {
+            current = candidate;
+        }
+
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            open_fence = match open_fence {
+                Some(_) => None,
+                None => Some("```".to_string()),
+            };
+        }
+    }
+
+    if !current.trim().is_empty() {
+        pages.push(current);
+    }
+    pages
+}
+
+fn split_long_line(line: &str, max_bytes: usize) -> Vec<String> {
+    if line.len() <= max_bytes {
+        return vec![line.to_string()];
+    }
+
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    for c in line.chars() {
+        if part.len() + c.len_utf8() > max_bytes {
+            parts.push(std::mem::take(&mut part));
+        }
+        part.push(c);
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+fn to_html(markdown: &str, heading_color: Option<&str>) -> String {
+    let font = heading_color.map(|color| {
+        let color = escape_html(color);
+        format!("<font data-mx-color=\"{color}\" color=\"{color}\">")
+    });
+    // Raw HTML is never meant to reach the room, show it as text
+    let events = Parser::new_ext(markdown, options()).flat_map(|event| match (event, &font) {
+        (Event::Html(html) | Event::InlineHtml(html), _) => vec![Event::Text(html)],
+        (event @ Event::Start(Tag::Heading { .. }), Some(font)) => {
+            vec![event, Event::InlineHtml(font.clone().into())]
+        }
+        (event @ Event::End(TagEnd::Heading(_)), Some(_)) => {
+            vec![Event::InlineHtml("</font>".into()), event]
+        }
+        (event, _) => vec![event],
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, events);
+    out.trim_end().to_string()
+}
+
+fn to_plain(markdown: &str) -> String {
+    let mut out = String::new();
+    let mut list_counters: Vec<Option<u64>> = Vec::new();
+    let mut link_targets: Vec<String> = Vec::new();
+    let mut link_text_start = 0;
+
+    for event in Parser::new_ext(markdown, options()) {
+        match event {
+            Event::Text(text) | Event::Code(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                out.push_str(&text)
+            }
+            Event::SoftBreak | Event::HardBreak => out.push('\n'),
+            Event::Rule => push_block_break(&mut out),
+            Event::Start(Tag::List(start)) => list_counters.push(start),
+            Event::End(TagEnd::List(_)) => {
+                list_counters.pop();
+            }
+            Event::Start(Tag::Item) => {
+                push_block_break(&mut out);
+                match list_counters.last_mut() {
+                    Some(Some(n)) => {
+                        out.push_str(&format!("{n}. "));
+                        *n += 1;
+                    }
+                    _ => out.push_str("- "),
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) => {
+                link_targets.push(dest_url.to_string());
+                link_text_start = out.len();
+            }
+            Event::End(TagEnd::Link) => {
+                if let Some(url) = link_targets.pop()
+                    && out[link_text_start..] != url
+                {
+                    out.push_str(&format!(" ({url})"));
+                }
+            }
+            Event::Start(Tag::Paragraph | Tag::Heading { .. } | Tag::CodeBlock(_)) => {
+                push_block_break(&mut out)
+            }
+            Event::End(TagEnd::TableCell) => out.push_str(" | "),
+            Event::End(TagEnd::TableRow | TagEnd::TableHead) => {
+                let trimmed = out.trim_end_matches(" | ").len();
+                out.truncate(trimmed);
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+
+    out.trim().to_string()
+}
+
+fn push_block_break(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_heading_and_bold_fields() {
+        let (plain, html) = render("#### 🔴 New issue\n**Subject:** Broken  \n**By:** alice");
+        assert_eq!(plain, "🔴 New issue\nSubject: Broken\nBy: alice");
+        assert_eq!(
+            html,
+            "<h4>🔴 New issue</h4>\n<p><strong>Subject:</strong> Broken<br />\n<strong>By:</strong> alice</p>"
+        );
+    }
+
+    #[test]
+    fn render_colored_colors_headings() {
+        let (plain, html) = render_colored(
+            "#### 🚨 Disk full
+**Free:** 2 GB",
+            "#d32f2f",
+        );
+        assert_eq!(plain, "🚨 Disk full\nFree: 2 GB");
+        assert!(html.starts_with(
+            "<h4><font data-mx-color=\"#d32f2f\" color=\"#d32f2f\">🚨 Disk full</font></h4>"
+        ));
+        assert!(html.contains("<strong>Free:</strong> 2 GB"));
+    }
+
+    #[test]
+    fn render_with_details_collapses_hidden_part() {
+        let (plain, html) = render_with_details("**Fixed**", "Original <fr>", "Corrigé");
+        assert!(plain.starts_with("Fixed"));
+        assert!(plain.ends_with("\n\nOriginal <fr>: Corrigé"));
+        assert!(html.ends_with(
+            "<details><summary>Original &lt;fr&gt;</summary><p>Corrigé</p>\n</details>"
+        ));
+    }
+
+    #[test]
+    fn render_shows_raw_html_as_text() {
+        let (_, html) =
+            render("**Comment:** <img src=x onerror=alert(1)>\n\n<script>alert(1)</script>");
+        assert!(
+            !html.contains("<img") && !html.contains("<script"),
+            "{html}"
+        );
+        assert!(
+            html.contains("&lt;img src=x onerror=alert(1)&gt;"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn escape_neutralizes_hostile_payloads() {
+        let hostile = [
+            "<a href=\"https://evil.example\">click</a>",
+            "![pixel](https://evil.example/track.png)",
+            "[free movies](javascript:alert(1))",
+            "**bold** _it_ `code` # heading",
+            "&lt;b&gt; & <b>",
+        ];
+        for text in hostile {
+            let (plain, html) = render(&format!("**Subject:** {}", escape(text)));
+            assert_eq!(plain, format!("Subject: {text}"));
+            for tag in ["<a", "<img", "<em", "<code", "<h1", "<b>"] {
+                assert!(!html.contains(tag), "{tag} in {html}");
+            }
+            assert!(html.starts_with("<p><strong>Subject:</strong> "), "{html}");
+        }
+    }
+
+    #[test]
+    fn escape_keeps_plain_text() {
+        let (plain, _) = render(&escape("Season 2 - episode 3 isn't playing (404)!"));
+        assert_eq!(plain, "Season 2 - episode 3 isn't playing (404)!");
+    }
+
+    #[test]
+    fn paginate_keeps_short_messages_whole() {
+        assert_eq!(paginate("short", 1024), vec!["short".to_string()]);
+    }
+
+    #[test]
+    fn paginate_splits_on_lines_within_budget() {
+        let markdown: String = (0..200).map(|i| format!("- item number {i}\n")).collect();
+        let pages = paginate(&markdown, 2048);
+
+        assert!(pages.len() > 1);
+        for page in &pages {
+            let (plain, html) = render(page);
+            assert!(plain.len() + html.len() <= 2048);
+        }
+        assert_eq!(pages.concat(), markdown);
+    }
+
+    #[test]
+    fn paginate_reopens_code_fences() {
+        let lines: String = (0..100).map(|i| format!("line {i}\n")).collect();
+        let markdown = format!("```\n{lines}```\n");
+        let pages = paginate(&markdown, 1024);
+
+        assert!(pages.len() > 1);
+        for page in &pages {
+            assert!(page.starts_with("```"));
+            assert!(page.trim_end().ends_with("```"));
+        }
+    }
+
+    #[test]
+    fn paginate_hard_splits_huge_lines() {
+        let markdown = "x".repeat(10_000);
+        let pages = paginate(&markdown, 4096);
+
+        assert!(pages.len() > 1);
+        assert_eq!(pages.concat().replace('\n', ""), markdown);
+    }
+
+    #[test]
+    fn render_lists() {
+        let (plain, _) = render("Items:\n\n- one\n- two\n\n1. first\n2. second");
+        assert_eq!(plain, "Items:\n- one\n- two\n1. first\n2. second");
+    }
+
+    #[test]
+    fn render_links_keep_target_in_plain_body() {
+        let (plain, html) = render("[thread](https://matrix.to/#/!r/$e) and <https://example.com>");
+        assert_eq!(
+            plain,
+            "thread (https://matrix.to/#/!r/$e) and https://example.com"
+        );
+        assert!(html.contains("<a href=\"https://matrix.to/#/!r/$e\">thread</a>"));
+    }
+}
@@ -1,52 +1,418 @@
-use anyhow::{Context, Result};
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result, bail};
+use matrix_sdk::authentication::matrix::MatrixSession;
+use matrix_sdk::ruma::api::client::error::{ErrorKind, RetryAfter};
+use matrix_sdk::ruma::api::client::receipt::create_receipt::v3::ReceiptType;
 use matrix_sdk::ruma::events::reaction::ReactionEventContent;
+use matrix_sdk::ruma::events::receipt::ReceiptThread;
 use matrix_sdk::ruma::events::relation::Annotation;
-use matrix_sdk::ruma::events::room::message::RoomMessageEventContent;
-use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId, OwnedRoomOrAliasId};
-use matrix_sdk::{Client, Room};
-use tracing::info;
+use matrix_sdk::ruma::events::room::ImageInfo;
+use matrix_sdk::ruma::events::room::message::{
+    FileInfo, FileMessageEventContent, ImageMessageEventContent, MessageType, ReplacementMetadata,
+    RoomMessageEventContent,
+};
+use matrix_sdk::ruma::events::room::pinned_events::RoomPinnedEventsEventContent;
+use matrix_sdk::ruma::events::{Mentions, MessageLikeEventContent, StateEventType};
+use matrix_sdk::ruma::{
+    OwnedEventId, OwnedMxcUri, OwnedRoomId, OwnedRoomOrAliasId, OwnedUserId, TransactionId, UInt,
+    UserId,
+};
+use matrix_sdk::{Client, Room, RoomState};
+use matrix_sdk::{HttpError, SessionMeta, SessionTokens};
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::MatrixAuth;
+use crate::notifier::MatrixNotifier;
+
+const MAX_SEND_ATTEMPTS: u32 = 5;
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_JOIN_ATTEMPTS: u32 = 10;
+const MEMBERSHIP_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+/// Saved next to the sqlite store, to restore the session on start instead of
+/// logging in on a new device.
+const SESSION_FILE: &str = "session.json";
 
+/// Without `store_path` the session and encryption keys only live in memory,
+/// every start logs in on a new device.
 pub async fn create_and_login(
     homeserver_url: &str,
     user_id: &str,
-    password: &str,
+    auth: &MatrixAuth,
+    store_path: Option<&Path>,
 ) -> Result<Client> {
-    let url = homeserver_url.parse().context("Invalid homeserver URL")?;
-    let client = Client::new(url)
+    let url: reqwest::Url = homeserver_url.parse().context("Invalid homeserver URL")?;
+    let mut builder = Client::builder().homeserver_url(url);
+    if let Some(path) = store_path {
+        builder = builder.sqlite_store(path, None);
+    }
+    let client = builder
+        .build()
         .await
         .context("Failed to create Matrix client")?;
 
-    client
+    // An access token given in the config always wins over the saved one
+    if let Some(path) = store_path
+        && !matches!(auth, MatrixAuth::AccessToken { .. })
+        && let Some(session) = load_session(path)?
+    {
+        // MATRIX_USER_ID may be just the localpart
+        let saved_user = &session.meta.user_id;
+        if saved_user.as_str() != user_id && saved_user.localpart() != user_id {
+            bail!(
+                "The Matrix store at {} belongs to {}, not {user_id}",
+                path.display(),
+                saved_user
+            );
+        }
+        client
+            .restore_session(session)
+            .await
+            .context("Failed to restore the saved Matrix session")?;
+        info!("Restored the Matrix session of {user_id}");
+        return Ok(client);
+    }
+
+    match auth {
+        MatrixAuth::Password(password) => {
+            client
+                .matrix_auth()
+                .login_username(user_id, password)
+                .initial_device_display_name("michel-bot")
+                .send()
+                .await
+                .context("Failed to login to Matrix")?;
+        }
+        MatrixAuth::LoginToken(token) => {
+            client
+                .matrix_auth()
+                .login_token(token)
+                .initial_device_display_name("michel-bot")
+                .send()
+                .await
+                .context("Failed to login to Matrix with login token")?;
+        }
+        MatrixAuth::AccessToken { token, device_id } => {
+            let whoami = whoami(homeserver_url, token).await?;
+            let device_id = device_id
+                .clone()
+                .or(whoami.device_id)
+                .context("Access token has no device, set MATRIX_DEVICE_ID")?;
+            let session = MatrixSession {
+                meta: SessionMeta {
+                    user_id: whoami.user_id.as_str().try_into()?,
+                    device_id: device_id.into(),
+                },
+                tokens: SessionTokens {
+                    access_token: token.clone(),
+                    refresh_token: None,
+                },
+            };
+            client
+                .restore_session(session)
+                .await
+                .context("Failed to restore Matrix session from access token")?;
+        }
+    }
+
+    if let Some(path) = store_path {
+        save_session(&client, path)?;
+    }
+    info!("Logged in to Matrix as {user_id}");
+    Ok(client)
+}
+
+fn load_session(store_path: &Path) -> Result<Option<MatrixSession>> {
+    let path = store_path.join(SESSION_FILE);
+    let content = match std::fs::read(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    serde_json::from_slice(&content)
+        .map(Some)
+        .with_context(|| format!("Invalid Matrix session in {}", path.display()))
+}
+
+fn save_session(client: &Client, store_path: &Path) -> Result<()> {
+    let Some(session) = client.matrix_auth().session() else {
+        return Ok(());
+    };
+    let path = store_path.join(SESSION_FILE);
+    std::fs::create_dir_all(store_path)
+        .with_context(|| format!("Failed to create {}", store_path.display()))?;
+    std::fs::write(&path, serde_json::to_vec(&session)?)
+        .with_context(|| format!("Failed to save the Matrix session to {}", path.display()))
+}
+
+/// Logs in again once the homeserver no longer accepts the access token
+/// (expired, or sessions lost in a restart), on the same device to keep its
+/// encryption keys. Access and login tokens can't be used again, they must be
+/// replaced by hand.
+pub async fn relogin(
+    client: &Client,
+    user_id: &str,
+    auth: &MatrixAuth,
+    store_path: Option<&Path>,
+) -> Result<()> {
+    if client
+        .session_tokens()
+        .is_some_and(|tokens| tokens.refresh_token.is_some())
+    {
+        client
+            .matrix_auth()
+            .refresh_access_token()
+            .await
+            .context("Failed to refresh the Matrix access token")?;
+        if let Some(path) = store_path {
+            save_session(client, path)?;
+        }
+        return Ok(());
+    }
+
+    let MatrixAuth::Password(password) = auth else {
+        bail!("The Matrix access token is no longer valid, replace it or set MATRIX_PASSWORD");
+    };
+    let mut login = client
         .matrix_auth()
         .login_username(user_id, password)
-        .initial_device_display_name("michel-bot")
+        .initial_device_display_name("michel-bot");
+    if let Some(device_id) = client.device_id() {
+        login = login.device_id(device_id.as_str());
+    }
+    login
         .send()
         .await
-        .context("Failed to login to Matrix")?;
+        .context("Failed to login to Matrix again")?;
+    if let Some(path) = store_path {
+        save_session(client, path)?;
+    }
+    info!("Logged in to Matrix again as {user_id}");
+    Ok(())
+}
 
-    info!("Logged in to Matrix as {user_id}");
-    Ok(client)
+#[derive(Deserialize)]
+struct WhoAmI {
+    user_id: String,
+    device_id: Option<String>,
 }
 
-pub async fn join_room(client: &Client, room_alias: &str) -> Result<(Room, OwnedRoomId)> {
-    let alias: OwnedRoomOrAliasId = room_alias.try_into().context("Invalid room alias")?;
-    let room = client
-        .join_room_by_id_or_alias(&alias, &[])
+async fn whoami(homeserver_url: &str, access_token: &str) -> Result<WhoAmI> {
+    reqwest::Client::new()
+        .get(format!(
+            "{}/_matrix/client/v3/account/whoami",
+            homeserver_url.trim_end_matches('/')
+        ))
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .context("Failed to reach homeserver")?
+        .error_for_status()
+        .context("Homeserver rejected the access token")?
+        .json()
         .await
-        .context("Failed to join room")?;
+        .context("Invalid whoami response")
+}
+
+/// Joins the room given as an alias or a room id, retrying with backoff since
+/// the alias may not be resolvable yet while the homeserver is starting.
+pub async fn join_room(client: &Client, room_alias: &str) -> Result<(Room, OwnedRoomId)> {
+    let alias: OwnedRoomOrAliasId = room_alias
+        .try_into()
+        .context("Invalid room alias or room id")?;
+
+    let mut attempt = 1;
+    let room = loop {
+        match client.join_room_by_id_or_alias(&alias, &[]).await {
+            Ok(room) => break room,
+            Err(e) if attempt < MAX_JOIN_ATTEMPTS => {
+                let delay = backoff_delay(attempt);
+                warn!(
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "Failed to join room {room_alias}, retrying: {e}"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e).context("Failed to join room"),
+        }
+    };
+
     let room_id = room.room_id().to_owned();
     info!("Joined room {room_alias} ({room_id})");
     Ok((room, room_id))
 }
 
+/// Periodically checks that the bot is still joined to `room_id` and joins it
+/// again if it was kicked or left, until `shutdown` is cancelled.
+pub async fn watch_membership(client: Client, room_id: OwnedRoomId, shutdown: CancellationToken) {
+    let mut interval = tokio::time::interval(MEMBERSHIP_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        let joined = client
+            .get_room(&room_id)
+            .is_some_and(|room| room.state() == RoomState::Joined);
+        if joined {
+            continue;
+        }
+
+        warn!(%room_id, "No longer joined to the room, joining again");
+        match client.join_room_by_id(&room_id).await {
+            Ok(_) => info!(%room_id, "Joined room again"),
+            Err(e) => warn!(%room_id, "Failed to join room again: {e}"),
+        }
+    }
+}
+
+/// Posts a message and a reaction in `room` and redacts both, so missing
+/// rights show up at startup rather than on the first webhook.
+pub async fn self_test(room: &Room) -> Result<()> {
+    let message = send_markdown(room, "🔧 Startup self-test, this message will be removed")
+        .await
+        .context("Cannot post messages in the room")?;
+    let reaction = send_reaction(room, &message, "✅")
+        .await
+        .context("Cannot react in the room")?;
+    redact_event(room, &reaction, Some("Startup self-test"))
+        .await
+        .context("Cannot redact its own reactions in the room")?;
+    redact_event(room, &message, Some("Startup self-test"))
+        .await
+        .context("Cannot redact its own messages in the room")?;
+
+    // Pinning the dashboard needs a state event, which can't be tried without
+    // side effects, so only the power levels are checked
+    if let Ok(power_levels) = room.power_levels().await
+        && !power_levels.user_can_send_state(room.own_user_id(), StateEventType::RoomPinnedEvents)
+    {
+        warn!("The bot's power level is too low to pin messages in the room");
+    }
+    Ok(())
+}
+
 pub async fn send_html_message(
     room: &Room,
     plain_body: &str,
     html_body: &str,
 ) -> Result<OwnedEventId> {
     let content = RoomMessageEventContent::text_html(plain_body, html_body);
-    let response = room.send(content).await.context("Failed to send message")?;
-    Ok(response.event_id)
+    send_with_retry(room, content)
+        .await
+        .context("Failed to send message")
+}
+
+pub async fn send_markdown(room: &impl MatrixNotifier, markdown: &str) -> Result<OwnedEventId> {
+    let (plain_body, html_body) = crate::markdown::render(markdown);
+    room.send_message(&plain_body, &html_body).await
+}
+
+/// Like [`send_html_message`], notifying everyone in the room.
+pub async fn send_html_message_mentioning_room(
+    room: &Room,
+    plain_body: &str,
+    html_body: &str,
+) -> Result<OwnedEventId> {
+    let content = RoomMessageEventContent::text_html(
+        format!("@room\n{plain_body}"),
+        format!("<p>@room</p>{html_body}"),
+    )
+    .add_mentions(Mentions::with_room_mention());
+    send_with_retry(room, content)
+        .await
+        .context("Failed to send message")
+}
+
+/// Sends Markdown to `user_id` in the DM room shared with them, creating it
+/// if needed.
+pub async fn send_direct_markdown(
+    client: &Client,
+    user_id: &UserId,
+    markdown: &str,
+) -> Result<OwnedEventId> {
+    let (plain_body, html_body) = crate::markdown::render(markdown);
+    send_direct_html(client, user_id, &plain_body, &html_body).await
+}
+
+pub async fn send_direct_html(
+    client: &Client,
+    user_id: &UserId,
+    plain_body: &str,
+    html_body: &str,
+) -> Result<OwnedEventId> {
+    let room = match client.get_dm_room(user_id) {
+        Some(room) => room,
+        None => client
+            .create_dm(user_id)
+            .await
+            .with_context(|| format!("Failed to create DM room with {user_id}"))?,
+    };
+    send_html_message(&room, plain_body, html_body).await
+}
+
+/// DMs every user in `user_ids`, logging the ones that can't be reached.
+pub async fn notify_users(client: &Client, user_ids: &[OwnedUserId], markdown: &str) {
+    let (plain_body, html_body) = crate::markdown::render(markdown);
+    notify_users_html(client, user_ids, &plain_body, &html_body).await
+}
+
+pub async fn notify_users_html(
+    client: &Client,
+    user_ids: &[OwnedUserId],
+    plain_body: &str,
+    html_body: &str,
+) {
+    for user_id in user_ids {
+        if let Err(e) = send_direct_html(client, user_id, plain_body, html_body).await {
+            warn!(%user_id, "Failed to notify user: {e:#}");
+        }
+    }
+}
+
+pub async fn send_thread_markdown(
+    room: &impl MatrixNotifier,
+    thread_root_event_id: &OwnedEventId,
+    markdown: &str,
+) -> Result<OwnedEventId> {
+    let (plain_body, html_body) = crate::markdown::render(markdown);
+    room.send_thread_reply(thread_root_event_id, &plain_body, &html_body)
+        .await
+}
+
+/// Sends Markdown that may exceed the event size limit: the first page is
+/// posted normally (or in `thread_root_event_id`'s thread) and the remaining
+/// pages are threaded under it. Returns the event id of the first page.
+pub async fn send_long_markdown(
+    room: &impl MatrixNotifier,
+    thread_root_event_id: Option<&OwnedEventId>,
+    markdown: &str,
+) -> Result<OwnedEventId> {
+    let mut pages =
+        crate::markdown::paginate(markdown, crate::markdown::MAX_MESSAGE_BYTES).into_iter();
+    let first_page = pages.next().unwrap_or_default();
+
+    let first_event_id = match thread_root_event_id {
+        Some(root) => send_thread_markdown(room, root, &first_page).await?,
+        None => send_markdown(room, &first_page).await?,
+    };
+    let thread_root = thread_root_event_id.unwrap_or(&first_event_id);
+
+    for page in pages {
+        send_thread_markdown(room, thread_root, &page).await?;
+    }
+
+    Ok(first_event_id)
 }
 
 pub async fn send_thread_reply(
@@ -62,11 +428,176 @@ pub async fn send_thread_reply(
             thread_root_event_id.clone(),
         ),
     ));
+    send_with_retry(room, content)
+        .await
+        .context("Failed to send thread reply")
+}
+
+/// An image uploaded to the homeserver, since clients only show `mxc://`
+/// images.
+#[derive(Debug, Clone)]
+pub struct UploadedImage {
+    pub uri: OwnedMxcUri,
+    pub mimetype: mime::Mime,
+    pub size: usize,
+}
+
+/// Images larger than this are not downloaded.
+const MAX_IMAGE_SIZE: usize = 10 * 1024 * 1024;
+const IMAGE_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Downloads the image at `url` and uploads it to the homeserver. The URL
+/// often comes from a Seerr user, so only public hosts are fetched, and only
+/// images of at most `MAX_IMAGE_SIZE`.
+pub async fn upload_image(client: &Client, url: &str) -> Result<UploadedImage> {
+    let parsed: reqwest::Url = url.parse().context("Invalid image URL")?;
+    if !is_public_url(&parsed) {
+        bail!("Image URL is not on a public host");
+    }
+    let http = reqwest::Client::builder()
+        .timeout(IMAGE_DOWNLOAD_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= 5 || !is_public_url(attempt.url()) {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()?;
+    let mut response = http
+        .get(parsed)
+        .send()
+        .await
+        .context("Failed to download image")?
+        .error_for_status()
+        .context("Image URL returned an error")?;
+    let mimetype: mime::Mime = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .context("Image URL returned no content type")?;
+    if mimetype.type_() != mime::IMAGE {
+        bail!("Image URL returned {mimetype}, not an image");
+    }
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_IMAGE_SIZE as u64)
+    {
+        bail!("Image is larger than {MAX_IMAGE_SIZE} bytes");
+    }
+    // The length may be missing or wrong, count what actually comes
+    let mut data = Vec::new();
+    while let Some(chunk) = response.chunk().await.context("Failed to download image")? {
+        if data.len() + chunk.len() > MAX_IMAGE_SIZE {
+            bail!("Image is larger than {MAX_IMAGE_SIZE} bytes");
+        }
+        data.extend_from_slice(&chunk);
+    }
+    let size = data.len();
+    let response = client
+        .media()
+        .upload(&mimetype, data, None)
+        .await
+        .context("Failed to upload image")?;
+    Ok(UploadedImage {
+        uri: response.content_uri,
+        mimetype,
+        size,
+    })
+}
+
+/// Whether `url` is an HTTP URL outside of the bot's network, as far as its
+/// host tells: loopback, private and link-local addresses are not.
+fn is_public_url(url: &reqwest::Url) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => is_public_ipv4(ip),
+        Ok(IpAddr::V6(ip)) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4(ip),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+        Err(_) => {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            host != "localhost" && !host.ends_with(".localhost") && host.contains('.')
+        }
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast())
+}
+
+/// Posts an uploaded image in the thread, `body` being its file name or
+/// description.
+pub async fn send_thread_image(
+    room: &Room,
+    thread_root_event_id: &OwnedEventId,
+    image: &UploadedImage,
+    body: &str,
+) -> Result<OwnedEventId> {
+    let mut info = ImageInfo::new();
+    info.mimetype = Some(image.mimetype.to_string());
+    info.size = UInt::new(image.size as u64);
+    let image = ImageMessageEventContent::plain(body.to_string(), image.uri.clone())
+        .info(Some(Box::new(info)));
+    let mut content = RoomMessageEventContent::new(MessageType::Image(image));
+    content.relates_to = Some(matrix_sdk::ruma::events::room::message::Relation::Thread(
+        matrix_sdk::ruma::events::relation::Thread::plain(
+            thread_root_event_id.clone(),
+            thread_root_event_id.clone(),
+        ),
+    ));
+    send_with_retry(room, content)
+        .await
+        .context("Failed to send image")
+}
+
+/// Uploads `data` to the homeserver and posts it as a file, in the thread of
+/// `thread_root_event_id` when there is one.
+pub async fn send_file(
+    room: &Room,
+    thread_root_event_id: Option<&OwnedEventId>,
+    filename: &str,
+    mimetype: &mime::Mime,
+    data: Vec<u8>,
+) -> Result<OwnedEventId> {
+    let size = data.len();
     let response = room
-        .send(content)
+        .client()
+        .media()
+        .upload(mimetype, data, None)
         .await
-        .context("Failed to send thread reply")?;
-    Ok(response.event_id)
+        .context("Failed to upload file")?;
+    let mut info = FileInfo::new();
+    info.mimetype = Some(mimetype.to_string());
+    info.size = UInt::new(size as u64);
+    let file = FileMessageEventContent::plain(filename.to_string(), response.content_uri)
+        .info(Some(Box::new(info)));
+    let mut content = RoomMessageEventContent::new(MessageType::File(file));
+    if let Some(root) = thread_root_event_id {
+        content.relates_to = Some(matrix_sdk::ruma::events::room::message::Relation::Thread(
+            matrix_sdk::ruma::events::relation::Thread::plain(root.clone(), root.clone()),
+        ));
+    }
+    send_with_retry(room, content)
+        .await
+        .context("Failed to send file")
 }
 
 pub async fn send_reaction(
@@ -76,11 +607,9 @@ pub async fn send_reaction(
 ) -> Result<OwnedEventId> {
     let annotation = Annotation::new(event_id.clone(), emoji.to_string());
     let content = ReactionEventContent::new(annotation);
-    let response = room
-        .send(content)
+    send_with_retry(room, content)
         .await
-        .context("Failed to send reaction")?;
-    Ok(response.event_id)
+        .context("Failed to send reaction")
 }
 
 pub async fn redact_event(
@@ -88,8 +617,249 @@ pub async fn redact_event(
     event_id: &OwnedEventId,
     reason: Option<&str>,
 ) -> Result<()> {
-    room.redact(event_id, reason, None)
+    let txn_id = TransactionId::new();
+    with_retry(|| async {
+        room.redact(event_id, reason, Some(txn_id.clone()))
+            .await
+            .map_err(matrix_sdk::Error::from)
+    })
+    .await
+    .context("Failed to redact event")?;
+    Ok(())
+}
+
+pub async fn edit_html_message(
+    room: &Room,
+    event_id: &OwnedEventId,
+    plain_body: &str,
+    html_body: &str,
+) -> Result<OwnedEventId> {
+    let content = RoomMessageEventContent::text_html(plain_body, html_body)
+        .make_replacement(ReplacementMetadata::new(event_id.clone(), None));
+    send_with_retry(room, content)
+        .await
+        .context("Failed to edit message")
+}
+
+pub async fn pin_event(room: &Room, event_id: &OwnedEventId) -> Result<()> {
+    let mut pinned = room
+        .load_pinned_events()
+        .await
+        .context("Failed to load pinned events")?
+        .unwrap_or_default();
+    if pinned.contains(event_id) {
+        return Ok(());
+    }
+    pinned.push(event_id.clone());
+    room.send_state_event(RoomPinnedEventsEventContent::new(pinned))
+        .await
+        .context("Failed to pin event")?;
+    Ok(())
+}
+
+pub async fn unpin_event(room: &Room, event_id: &OwnedEventId) -> Result<()> {
+    let mut pinned = room
+        .load_pinned_events()
+        .await
+        .context("Failed to load pinned events")?
+        .unwrap_or_default();
+    let len = pinned.len();
+    pinned.retain(|id| id != event_id);
+    if pinned.len() == len {
+        return Ok(());
+    }
+    room.send_state_event(RoomPinnedEventsEventContent::new(pinned))
+        .await
+        .context("Failed to unpin event")?;
+    Ok(())
+}
+
+pub fn event_permalink(room_id: &str, event_id: &str) -> String {
+    format!("https://matrix.to/#/{room_id}/{event_id}")
+}
+
+pub async fn set_typing(room: &Room, typing: bool) -> Result<()> {
+    room.typing_notice(typing)
+        .await
+        .context("Failed to send typing notice")?;
+    Ok(())
+}
+
+pub async fn send_read_receipt(
+    room: &Room,
+    event_id: &OwnedEventId,
+    thread_root_event_id: Option<&OwnedEventId>,
+) -> Result<()> {
+    let thread = match thread_root_event_id {
+        Some(root) => ReceiptThread::Thread(root.clone()),
+        None => ReceiptThread::Main,
+    };
+    room.send_single_receipt(ReceiptType::Read, thread, event_id.clone())
         .await
-        .context("Failed to redact event")?;
+        .context("Failed to send read receipt")?;
     Ok(())
 }
+
+/// Sends a message-like event, retrying rate-limited and transient failures.
+/// The same transaction id is reused across attempts so the homeserver
+/// deduplicates a send whose response got lost.
+async fn send_with_retry<C>(room: &Room, content: C) -> matrix_sdk::Result<OwnedEventId>
+where
+    C: MessageLikeEventContent + Clone,
+{
+    let txn_id = TransactionId::new();
+    with_retry(|| async {
+        room.send(content.clone())
+            .with_transaction_id(txn_id.clone())
+            .await
+            .map(|response| response.event_id)
+    })
+    .await
+}
+
+async fn with_retry<T, F, Fut>(mut op: F) -> matrix_sdk::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = matrix_sdk::Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < MAX_SEND_ATTEMPTS => {
+                let Some(delay) = retry_delay(&e, attempt) else {
+                    return Err(e);
+                };
+                warn!(
+                    attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    "Matrix request failed, retrying: {e}"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Returns how long to wait before retrying, or `None` if the error is not
+/// worth retrying.
+fn retry_delay(error: &matrix_sdk::Error, attempt: u32) -> Option<Duration> {
+    if let Some(ErrorKind::LimitExceeded { retry_after }) = error.client_api_error_kind() {
+        return Some(match retry_after {
+            Some(retry_after) => rate_limit_delay(retry_after),
+            None => backoff_delay(attempt),
+        });
+    }
+
+    let matrix_sdk::Error::Http(http_error) = error else {
+        return None;
+    };
+    match http_error.as_ref() {
+        HttpError::Reqwest(_) => Some(backoff_delay(attempt)),
+        e if e
+            .as_client_api_error()
+            .is_some_and(|e| e.status_code.is_server_error()) =>
+        {
+            Some(backoff_delay(attempt))
+        }
+        _ => None,
+    }
+}
+
+fn rate_limit_delay(retry_after: &RetryAfter) -> Duration {
+    let delay = match retry_after {
+        RetryAfter::Delay(delay) => *delay,
+        RetryAfter::DateTime(at) => at
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    };
+    delay.min(MAX_RETRY_DELAY)
+}
+
+fn backoff_delay(attempt: u32) -> Duration {
+    BASE_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::{RecordingNotifier, Sent};
+
+    #[tokio::test]
+    async fn long_markdown_continues_in_the_thread_of_its_first_page() {
+        let room = RecordingNotifier::default();
+        let line = format!("{}\n\n", "a".repeat(1000));
+        let markdown = line.repeat(40);
+
+        let first = send_long_markdown(&room, None, &markdown).await.unwrap();
+        let sent = room.sent();
+        assert!(sent.len() > 1);
+        assert!(matches!(&sent[0], Sent::Message { .. }));
+        assert!(
+            sent[1..]
+                .iter()
+                .all(|s| matches!(s, Sent::ThreadReply { root, .. } if *root == first))
+        );
+
+        let root = OwnedEventId::try_from("$card:test").unwrap();
+        let room = RecordingNotifier::default();
+        send_long_markdown(&room, Some(&root), "**Done**")
+            .await
+            .unwrap();
+        assert_eq!(
+            room.sent(),
+            vec![Sent::ThreadReply {
+                root,
+                plain: "Done".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn only_fetches_images_from_public_hosts() {
+        let public = |url: &str| is_public_url(&url.parse().unwrap());
+        assert!(public("https://image.tmdb.org/t/p/w500/poster.jpg"));
+        assert!(public("https://93.184.216.34/shot.png"));
+        assert!(!public("http://localhost:5055/shot.png"));
+        assert!(!public("http://seerr:5055/shot.png"));
+        assert!(!public("http://127.0.0.1/shot.png"));
+        assert!(!public("http://192.168.1.10/shot.png"));
+        assert!(!public("http://169.254.169.254/latest/meta-data"));
+        assert!(!public("http://[::1]/shot.png"));
+        assert!(!public("http://[::ffff:10.0.0.1]/shot.png"));
+        assert!(!public("file:///etc/passwd"));
+    }
+
+    #[test]
+    fn backoff_doubles_until_capped() {
+        assert_eq!(backoff_delay(1), Duration::from_millis(500));
+        assert_eq!(backoff_delay(2), Duration::from_secs(1));
+        assert_eq!(backoff_delay(3), Duration::from_secs(2));
+        assert_eq!(backoff_delay(20), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn rate_limit_honors_retry_after() {
+        let delay = rate_limit_delay(&RetryAfter::Delay(Duration::from_millis(1500)));
+        assert_eq!(delay, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn rate_limit_in_the_past_retries_immediately() {
+        let past = SystemTime::now() - Duration::from_secs(10);
+        assert_eq!(
+            rate_limit_delay(&RetryAfter::DateTime(past)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn rate_limit_is_capped() {
+        let delay = rate_limit_delay(&RetryAfter::Delay(Duration::from_secs(3600)));
+        assert_eq!(delay, MAX_RETRY_DELAY);
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::AppState;
+use crate::markdown;
+use crate::seerr::SeerrMediaDetails;
+
+/// TMDB details hardly ever change, and the notifications of a request or an
+/// issue keep asking for the same media.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Longer overviews are cut, for cards to stay readable in the room.
+const OVERVIEW_MAX_CHARS: usize = 300;
+
+type Key = (String, i64);
+
+/// Media details fetched through Seerr's TMDB proxy, kept for a day.
+#[derive(Default)]
+pub struct MediaDetailsCache {
+    entries: Mutex<HashMap<Key, (Instant, SeerrMediaDetails)>>,
+}
+
+impl MediaDetailsCache {
+    fn get_at(&self, key: &Key, now: Instant) -> Option<SeerrMediaDetails> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .filter(|(fetched_at, _)| now.duration_since(*fetched_at) < CACHE_TTL)
+            .map(|(_, details)| details.clone())
+    }
+
+    fn insert_at(&self, key: Key, details: SeerrMediaDetails, now: Instant) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (fetched_at, _)| now.duration_since(*fetched_at) < CACHE_TTL);
+        entries.insert(key, (now, details));
+    }
+}
+
+/// Details shown on the card of a movie or show with `MEDIA_DETAILS_ENABLED`.
+/// The card goes without them when Seerr can't say.
+pub async fn lookup(
+    state: &AppState,
+    media_type: Option<&str>,
+    tmdb_id: Option<i64>,
+) -> Option<SeerrMediaDetails> {
+    if !state.settings.get().media_details_enabled {
+        return None;
+    }
+    let (Some(media_type @ ("movie" | "tv")), Some(tmdb_id)) = (media_type, tmdb_id) else {
+        return None;
+    };
+    let key = (media_type.to_string(), tmdb_id);
+    if let Some(details) = state.media_details.get_at(&key, Instant::now()) {
+        return Some(details);
+    }
+
+    match state.seerr_client.media_details(media_type, tmdb_id).await {
+        Ok(details) => {
+            state
+                .media_details
+                .insert_at(key, details.clone(), Instant::now());
+            Some(details)
+        }
+        Err(e) => {
+            warn!(media_type, tmdb_id, "Failed to fetch media details: {e:#}");
+            None
+        }
+    }
+}
+
+/// Card lines for what TMDB knows of the media, each starting a new line.
+pub fn fields(details: &SeerrMediaDetails) -> String {
+    let mut fields = String::new();
+    let runtime = match (details.runtime, details.episode_run_time.first()) {
+        (Some(minutes), _) if minutes > 0 => Some(format_runtime(minutes)),
+        (_, Some(&minutes)) if minutes > 0 => {
+            Some(format!("{} per episode", format_runtime(minutes)))
+        }
+        _ => None,
+    };
+    if let Some(runtime) = runtime {
+        fields.push_str(&format!("  \n**Runtime:** {runtime}"));
+    }
+    if let Some(rating) = details.vote_average.filter(|rating| *rating > 0.0) {
+        fields.push_str(&format!("  \n**Rating:** ⭐ {rating:.1}/10"));
+    }
+    if !details.genres.is_empty() {
+        let genres: Vec<&str> = details.genres.iter().map(|g| g.name.as_str()).collect();
+        fields.push_str(&format!(
+            "  \n**Genres:** {}",
+            markdown::escape(&genres.join(", "))
+        ));
+    }
+    if let Some(overview) = details.overview.as_deref().map(str::trim)
+        && !overview.is_empty()
+    {
+        fields.push_str(&format!(
+            "  \n**Overview:** {}",
+            markdown::escape(&shorten(overview))
+        ));
+    }
+    fields
+}
+
+fn format_runtime(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{minutes} min"),
+        (hours, 0) => format!("{hours} h"),
+        (hours, minutes) => format!("{hours} h {minutes} min"),
+    }
+}
+
+fn shorten(overview: &str) -> String {
+    if overview.chars().count() <= OVERVIEW_MAX_CHARS {
+        return overview.to_string();
+    }
+    let cut: String = overview.chars().take(OVERVIEW_MAX_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seerr::SeerrGenre;
+
+    #[test]
+    fn fields_show_what_tmdb_knows() {
+        let dune = SeerrMediaDetails {
+            runtime: Some(155),
+            vote_average: Some(7.84),
+            genres: vec![
+                SeerrGenre {
+                    name: "Science Fiction".to_string(),
+                },
+                SeerrGenre {
+                    name: "Adventure".to_string(),
+                },
+            ],
+            overview: Some("Paul Atreides travels to Arrakis.".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            fields(&dune),
+            "  \n**Runtime:** 2 h 35 min  \n**Rating:** ⭐ 7.8/10  \n\
+             **Genres:** Science Fiction, Adventure  \n\
+             **Overview:** Paul Atreides travels to Arrakis\\."
+        );
+
+        let show = SeerrMediaDetails {
+            episode_run_time: vec![45],
+            vote_average: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(fields(&show), "  \n**Runtime:** 45 min per episode");
+        assert_eq!(fields(&SeerrMediaDetails::default()), "");
+    }
+
+    #[test]
+    fn long_overviews_are_cut() {
+        let overview = "word ".repeat(100);
+        let short = shorten(&overview);
+        assert!(short.ends_with("word…"));
+        assert_eq!(short.chars().count(), OVERVIEW_MAX_CHARS);
+    }
+
+    #[test]
+    fn cached_details_expire() {
+        let cache = MediaDetailsCache::default();
+        let key = ("movie".to_string(), 438631);
+        let now = Instant::now();
+        cache.insert_at(key.clone(), SeerrMediaDetails::default(), now);
+        assert!(cache.get_at(&key, now + Duration::from_secs(60)).is_some());
+        assert!(cache.get_at(&key, now + CACHE_TTL).is_none());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use sqlx::PgPool;
+
+use crate::db;
+use crate::heartbeat::SyncHealth;
+use crate::http_metrics::HttpMetrics;
+
+/// Content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Upper bounds of the issue response time buckets, in seconds: from five
+/// minutes to a month.
+const RESPONSE_TIME_BUCKETS: &[f64] = &[
+    300.0,
+    900.0,
+    3_600.0,
+    14_400.0,
+    43_200.0,
+    86_400.0,
+    259_200.0,
+    604_800.0,
+    1_209_600.0,
+    2_592_000.0,
+];
+
+/// Prometheus histogram, each bucket counting the values up to its bound.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    buckets: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    pub fn observe(&mut self, value: f64) {
+        for (bound, count) in self.buckets.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Appends the histogram to `out` in the text exposition format.
+    pub fn render(&self, name: &str, help: &str, out: &mut String) {
+        let _ = writeln!(out, "# HELP {name} {help}");
+        let _ = writeln!(out, "# TYPE {name} histogram");
+        self.render_series(name, "", out);
+    }
+
+    /// Appends the samples of the histogram with `labels` (`key="value"`
+    /// pairs, comma separated), for a histogram whose `HELP` and `TYPE` are
+    /// already written.
+    pub fn render_series(&self, name: &str, labels: &str, out: &mut String) {
+        let prefix = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{labels},")
+        };
+        for (bound, count) in self.buckets.iter().zip(&self.counts) {
+            let _ = writeln!(out, "{name}_bucket{{{prefix}le=\"{bound}\"}} {count}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{prefix}le=\"+Inf\"}} {}", self.count);
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+    }
+}
+
+/// The metrics of `GET /admin/metrics`. Issue response times are computed
+/// from the database on each scrape, so they survive restarts, the Matrix
+/// sync and HTTP client ones count since the bot started.
+pub async fn render(pool: &PgPool, sync: &SyncHealth, http: &HttpMetrics) -> Result<String> {
+    let mut first_response = Histogram::new(RESPONSE_TIME_BUCKETS);
+    let mut resolution = Histogram::new(RESPONSE_TIME_BUCKETS);
+    for (to_first_response, to_resolution) in db::issue_response_times(pool).await? {
+        if let Some(seconds) = to_first_response {
+            first_response.observe(seconds);
+        }
+        if let Some(seconds) = to_resolution {
+            resolution.observe(seconds);
+        }
+    }
+
+    let mut out = String::new();
+    first_response.render(
+        "michel_issue_first_response_seconds",
+        "Time between an issue being reported and first answered.",
+        &mut out,
+    );
+    resolution.render(
+        "michel_issue_resolution_seconds",
+        "Time between an issue being reported and resolved.",
+        &mut out,
+    );
+    render_sync(sync, &mut out);
+    http.render(&mut out);
+    Ok(out)
+}
+
+fn render_sync(sync: &SyncHealth, out: &mut String) {
+    let _ = writeln!(
+        out,
+        "# HELP michel_matrix_sync_failures_total Matrix sync runs that failed.\n\
+         # TYPE michel_matrix_sync_failures_total counter\n\
+         michel_matrix_sync_failures_total {}",
+        sync.failures()
+    );
+    let _ = writeln!(
+        out,
+        "# HELP michel_matrix_sync_consecutive_failures Matrix sync failures since the last \
+         successful sync.\n\
+         # TYPE michel_matrix_sync_consecutive_failures gauge\n\
+         michel_matrix_sync_consecutive_failures {}",
+        sync.consecutive_failures()
+    );
+    let (relogins, failed_relogins) = sync.relogins();
+    let _ = writeln!(
+        out,
+        "# HELP michel_matrix_relogins_total Logins again after the access token was rejected.\n\
+         # TYPE michel_matrix_relogins_total counter\n\
+         michel_matrix_relogins_total{{outcome=\"success\"}} {relogins}\n\
+         michel_matrix_relogins_total{{outcome=\"failure\"}} {failed_relogins}"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histograms_count_values_up_to_each_bound() {
+        let mut histogram = Histogram::new(&[60.0, 3_600.0]);
+        histogram.observe(30.0);
+        histogram.observe(600.0);
+        histogram.observe(7_200.0);
+
+        let mut out = String::new();
+        histogram.render("response_seconds", "Response time.", &mut out);
+        assert_eq!(
+            out,
+            "# HELP response_seconds Response time.\n\
+             # TYPE response_seconds histogram\n\
+             response_seconds_bucket{le=\"60\"} 1\n\
+             response_seconds_bucket{le=\"3600\"} 2\n\
+             response_seconds_bucket{le=\"+Inf\"} 3\n\
+             response_seconds_sum 7830\n\
+             response_seconds_count 3\n"
+        );
+    }
+
+    #[test]
+    fn sync_metrics_count_failures_and_relogins() {
+        let sync = SyncHealth::default();
+        sync.record_failure();
+        sync.record_relogin(true);
+
+        let mut out = String::new();
+        render_sync(&sync, &mut out);
+        assert!(out.contains("\nmichel_matrix_sync_failures_total 1\n"));
+        assert!(out.contains("\nmichel_matrix_sync_consecutive_failures 1\n"));
+        assert!(out.contains("michel_matrix_relogins_total{outcome=\"success\"} 1\n"));
+        assert!(out.contains("michel_matrix_relogins_total{outcome=\"failure\"} 0\n"));
+    }
+}
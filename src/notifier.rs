@@ -0,0 +1,169 @@
+use std::future::Future;
+
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::ruma::OwnedEventId;
+
+use crate::matrix;
+
+/// What the bot does in its room, so the logic posting there can be tested
+/// against [`RecordingNotifier`] instead of a homeserver.
+pub trait MatrixNotifier: Sync {
+    fn send_message(
+        &self,
+        plain_body: &str,
+        html_body: &str,
+    ) -> impl Future<Output = Result<OwnedEventId>> + Send;
+
+    fn send_thread_reply(
+        &self,
+        thread_root_event_id: &OwnedEventId,
+        plain_body: &str,
+        html_body: &str,
+    ) -> impl Future<Output = Result<OwnedEventId>> + Send;
+
+    fn send_reaction(
+        &self,
+        event_id: &OwnedEventId,
+        emoji: &str,
+    ) -> impl Future<Output = Result<OwnedEventId>> + Send;
+
+    fn redact(
+        &self,
+        event_id: &OwnedEventId,
+        reason: Option<&str>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn edit(
+        &self,
+        event_id: &OwnedEventId,
+        plain_body: &str,
+        html_body: &str,
+    ) -> impl Future<Output = Result<OwnedEventId>> + Send;
+}
+
+impl MatrixNotifier for Room {
+    async fn send_message(&self, plain_body: &str, html_body: &str) -> Result<OwnedEventId> {
+        matrix::send_html_message(self, plain_body, html_body).await
+    }
+
+    async fn send_thread_reply(
+        &self,
+        thread_root_event_id: &OwnedEventId,
+        plain_body: &str,
+        html_body: &str,
+    ) -> Result<OwnedEventId> {
+        matrix::send_thread_reply(self, thread_root_event_id, plain_body, html_body).await
+    }
+
+    async fn send_reaction(&self, event_id: &OwnedEventId, emoji: &str) -> Result<OwnedEventId> {
+        matrix::send_reaction(self, event_id, emoji).await
+    }
+
+    async fn redact(&self, event_id: &OwnedEventId, reason: Option<&str>) -> Result<()> {
+        matrix::redact_event(self, event_id, reason).await
+    }
+
+    async fn edit(
+        &self,
+        event_id: &OwnedEventId,
+        plain_body: &str,
+        html_body: &str,
+    ) -> Result<OwnedEventId> {
+        matrix::edit_html_message(self, event_id, plain_body, html_body).await
+    }
+}
+
+/// Something the bot did through a [`RecordingNotifier`].
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sent {
+    Message {
+        plain: String,
+    },
+    ThreadReply {
+        root: OwnedEventId,
+        plain: String,
+    },
+    Reaction {
+        event_id: OwnedEventId,
+        emoji: String,
+    },
+    Redaction {
+        event_id: OwnedEventId,
+        reason: Option<String>,
+    },
+    Edit {
+        event_id: OwnedEventId,
+        plain: String,
+    },
+}
+
+/// Test double keeping what was sent, the events it creates being numbered
+/// `$1:test`, `$2:test`, ...
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct RecordingNotifier {
+    sent: std::sync::Mutex<Vec<Sent>>,
+}
+
+#[cfg(test)]
+impl RecordingNotifier {
+    pub fn sent(&self) -> Vec<Sent> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    fn record(&self, sent: Sent) -> OwnedEventId {
+        let mut all = self.sent.lock().unwrap();
+        all.push(sent);
+        OwnedEventId::try_from(format!("${}:test", all.len())).unwrap()
+    }
+}
+
+#[cfg(test)]
+impl MatrixNotifier for RecordingNotifier {
+    async fn send_message(&self, plain_body: &str, _html_body: &str) -> Result<OwnedEventId> {
+        Ok(self.record(Sent::Message {
+            plain: plain_body.to_string(),
+        }))
+    }
+
+    async fn send_thread_reply(
+        &self,
+        thread_root_event_id: &OwnedEventId,
+        plain_body: &str,
+        _html_body: &str,
+    ) -> Result<OwnedEventId> {
+        Ok(self.record(Sent::ThreadReply {
+            root: thread_root_event_id.clone(),
+            plain: plain_body.to_string(),
+        }))
+    }
+
+    async fn send_reaction(&self, event_id: &OwnedEventId, emoji: &str) -> Result<OwnedEventId> {
+        Ok(self.record(Sent::Reaction {
+            event_id: event_id.clone(),
+            emoji: emoji.to_string(),
+        }))
+    }
+
+    async fn redact(&self, event_id: &OwnedEventId, reason: Option<&str>) -> Result<()> {
+        self.record(Sent::Redaction {
+            event_id: event_id.clone(),
+            reason: reason.map(str::to_string),
+        });
+        Ok(())
+    }
+
+    async fn edit(
+        &self,
+        event_id: &OwnedEventId,
+        plain_body: &str,
+        _html_body: &str,
+    ) -> Result<OwnedEventId> {
+        Ok(self.record(Sent::Edit {
+            event_id: event_id.clone(),
+            plain: plain_body.to_string(),
+        }))
+    }
+}
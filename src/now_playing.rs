@@ -0,0 +1,113 @@
+use crate::jellyfin_client::{NowPlayingItem, Session};
+use crate::markdown;
+
+fn item_title(item: &NowPlayingItem) -> String {
+    let name = markdown::escape(&item.name);
+    match (
+        item.series_name.as_deref().map(markdown::escape),
+        item.parent_index_number,
+        item.index_number,
+    ) {
+        (Some(series), Some(season), Some(episode)) => {
+            format!("{series} S{season:02}E{episode:02} \"{name}\"")
+        }
+        (Some(series), _, _) => format!("{series} \"{name}\""),
+        _ => name,
+    }
+}
+
+fn format_bitrate(bits_per_second: u64) -> String {
+    format!("{:.1} Mbps", bits_per_second as f64 / 1_000_000.0)
+}
+
+/// Markdown listing what is being streamed, how, and the bandwidth used by
+/// transcodes.
+pub fn render(sessions: &[Session]) -> String {
+    let playing: Vec<(&Session, &NowPlayingItem)> = sessions
+        .iter()
+        .filter_map(|s| Some((s, s.now_playing_item.as_ref()?)))
+        .collect();
+    if playing.is_empty() {
+        return "**▶️ Now playing**  \nNothing is playing".to_string();
+    }
+
+    let mut markdown = format!("**▶️ Now playing ({})**\n", playing.len());
+    let mut bandwidth = 0;
+    for (session, item) in playing {
+        let user = markdown::escape(session.user_name.as_deref().unwrap_or("unknown"));
+        markdown.push_str(&format!("- {user}: {}", item_title(item)));
+        if let Some(client) = &session.client {
+            markdown.push_str(&format!(" on {}", markdown::escape(client)));
+        }
+        let play_state = session.play_state.as_ref();
+        let method = match play_state.and_then(|p| p.play_method.as_deref()) {
+            Some("Transcode") => "🔁 transcoding",
+            Some("DirectStream") => "direct stream",
+            _ => "direct play",
+        };
+        markdown.push_str(&format!(", {method}"));
+        if let Some(bitrate) = session.transcoding_info.as_ref().and_then(|t| t.bitrate) {
+            bandwidth += bitrate;
+            markdown.push_str(&format!(" ({})", format_bitrate(bitrate)));
+        }
+        if play_state.is_some_and(|p| p.is_paused) {
+            markdown.push_str(", ⏸ paused");
+        }
+        markdown.push('\n');
+    }
+    if bandwidth > 0 {
+        markdown.push_str(&format!(
+            "\n**Transcoding bandwidth:** {}\n",
+            format_bitrate(bandwidth)
+        ));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_lists_streams() {
+        let sessions: Vec<Session> = serde_json::from_str(
+            r#"[
+                {"UserName": "alice", "Client": "Jellyfin Web",
+                 "NowPlayingItem": {"Name": "Pilot", "SeriesName": "Some Show", "ParentIndexNumber": 1, "IndexNumber": 1},
+                 "PlayState": {"IsPaused": false, "PlayMethod": "Transcode"},
+                 "TranscodingInfo": {"Bitrate": 8000000}},
+                {"UserName": "bob", "NowPlayingItem": {"Name": "Some Movie"},
+                 "PlayState": {"IsPaused": true, "PlayMethod": "DirectPlay"}},
+                {"UserName": "carol"}
+            ]"#,
+        )
+        .unwrap();
+
+        let markdown = render(&sessions);
+
+        assert!(markdown.contains("Now playing (2)"));
+        assert!(markdown.contains(
+            "- alice: Some Show S01E01 \"Pilot\" on Jellyfin Web, 🔁 transcoding (8.0 Mbps)"
+        ));
+        assert!(markdown.contains("- bob: Some Movie, direct play, ⏸ paused"));
+        assert!(markdown.contains("**Transcoding bandwidth:** 8.0 Mbps"));
+    }
+
+    #[test]
+    fn render_escapes_names() {
+        let sessions: Vec<Session> = serde_json::from_str(
+            r#"[{"UserName": "_dave_",
+                 "NowPlayingItem": {"Name": "[Pilot](x)", "SeriesName": "*Show*"}}]"#,
+        )
+        .unwrap();
+
+        let markdown = render(&sessions);
+
+        assert!(markdown.contains(r#"- \_dave\_: \*Show\* "\[Pilot\]\(x\)""#));
+    }
+
+    #[test]
+    fn render_idle_server() {
+        assert!(render(&[]).contains("Nothing is playing"));
+    }
+}
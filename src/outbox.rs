@@ -0,0 +1,238 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::db::{self, StuckOutboxItem};
+use crate::markdown;
+use crate::matrix;
+use crate::quiet_hours;
+use crate::seerr::SeerrWebhookPayload;
+use crate::stats::format_duration;
+use crate::webhook;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+const BATCH_SIZE: i64 = 50;
+const BASE_RETRY_SECS: i64 = 30;
+const MAX_RETRY_SECS: i64 = 3600;
+
+/// Items that failed this many times are kept for inspection but no longer
+/// retried.
+pub const MAX_ATTEMPTS: i32 = 10;
+
+/// Stores a webhook whose processing failed so it can be retried later.
+pub async fn enqueue(
+    state: &AppState,
+    payload: &SeerrWebhookPayload,
+    error: &anyhow::Error,
+) -> Result<i64> {
+    let payload = serde_json::to_string(payload)?;
+    db::enqueue_outbox(&state.db, &payload, &format!("{error:#}")).await
+}
+
+/// Retries due outbox items until `shutdown` is cancelled.
+pub async fn run(state: Arc<AppState>, shutdown: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+        // Retries would break the quiet hours, they wait for the morning too
+        if quiet_hours::is_quiet(&state) {
+            continue;
+        }
+
+        if let Err(e) = process(&state, false).await {
+            error!("Failed to process outbox: {e:#}");
+        }
+    }
+}
+
+/// Makes a last delivery attempt for every pending item, ignoring their
+/// backoff. Returns the number of items left in the outbox.
+pub async fn flush(state: &AppState) -> Result<usize> {
+    process(state, true).await
+}
+
+async fn process(state: &AppState, include_pending: bool) -> Result<usize> {
+    let items = db::list_due_outbox(&state.db, include_pending, MAX_ATTEMPTS, BATCH_SIZE).await?;
+    let mut remaining = 0;
+
+    for item in items {
+        let payload: SeerrWebhookPayload = match serde_json::from_str(&item.payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!(outbox_id = item.id, "Dropping unreadable outbox item: {e}");
+                db::delete_outbox(&state.db, item.id).await?;
+                continue;
+            }
+        };
+
+        match webhook::process_payload(state, &payload).await {
+            Ok(()) => {
+                db::delete_outbox(&state.db, item.id).await?;
+                info!(outbox_id = item.id, "Outbox item delivered");
+            }
+            Err(e) => {
+                remaining += 1;
+                let delay = retry_delay_secs(item.attempts + 1);
+                warn!(
+                    outbox_id = item.id,
+                    attempts = item.attempts + 1,
+                    "Outbox item failed again: {e:#}"
+                );
+                db::record_outbox_failure(&state.db, item.id, &format!("{e:#}"), delay).await?;
+            }
+        }
+    }
+
+    Ok(remaining)
+}
+
+/// Characters of the payload shown to the admins.
+const PAYLOAD_EXCERPT_CHARS: usize = 300;
+
+/// An outbox item retried `OUTBOX_STUCK_ATTEMPTS` times or waiting for longer
+/// than `OUTBOX_STUCK_AFTER_MINUTES`.
+#[derive(Debug, Serialize)]
+pub struct StuckItem {
+    pub id: i64,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the admins were told, `None` until the watchdog runs.
+    pub escalated_at: Option<DateTime<Utc>>,
+    pub payload_excerpt: String,
+}
+
+impl From<StuckOutboxItem> for StuckItem {
+    fn from(item: StuckOutboxItem) -> Self {
+        Self {
+            id: item.id,
+            attempts: item.attempts,
+            last_error: item.last_error,
+            created_at: item.created_at,
+            escalated_at: item.escalated_at,
+            payload_excerpt: excerpt(&item.payload),
+        }
+    }
+}
+
+/// Items stuck in the outbox, oldest first.
+pub async fn stuck(state: &AppState) -> Result<Vec<StuckItem>> {
+    let settings = state.settings.get();
+    let created_before = Utc::now() - chrono::Duration::from_std(settings.outbox_stuck_after)?;
+    let items = db::list_stuck_outbox(&state.db, settings.outbox_stuck_attempts, created_before)
+        .await?
+        .into_iter()
+        .map(StuckItem::from)
+        .collect();
+    Ok(items)
+}
+
+/// DMs the admins about every item that got stuck since the last check, once
+/// per item, with what it is and why it keeps failing.
+pub async fn watch(state: &AppState) -> Result<()> {
+    let now = Utc::now();
+    for item in stuck(state).await? {
+        if item.escalated_at.is_some() {
+            continue;
+        }
+        warn!(
+            outbox_id = item.id,
+            attempts = item.attempts,
+            "Outbox item stuck, notifying admins"
+        );
+        let markdown = render_escalation(&item, now);
+        let client = state.room.client();
+        matrix::notify_users(&client, &state.settings.get().admin_users, &markdown).await;
+        db::mark_outbox_escalated(&state.db, item.id).await?;
+    }
+    Ok(())
+}
+
+fn render_escalation(item: &StuckItem, now: DateTime<Utc>) -> String {
+    let retried = if item.attempts >= MAX_ATTEMPTS {
+        "no longer retried"
+    } else {
+        "still retried"
+    };
+    let mut markdown = format!(
+        "**🚨 Webhook stuck in the outbox**  \n\
+         Item {} was received {} ago and failed {} times, {retried}.",
+        item.id,
+        format_duration(now - item.created_at),
+        item.attempts
+    );
+    if let Some(error) = &item.last_error {
+        markdown.push_str(&format!("  \nLast error: {}", markdown::escape(error)));
+    }
+    markdown.push_str(&format!("\n\n```json\n{}\n```", item.payload_excerpt));
+    markdown
+}
+
+fn excerpt(payload: &str) -> String {
+    let mut chars = payload.chars();
+    let excerpt: String = chars.by_ref().take(PAYLOAD_EXCERPT_CHARS).collect();
+    if chars.next().is_some() {
+        format!("{excerpt}…")
+    } else {
+        excerpt
+    }
+}
+
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    BASE_RETRY_SECS
+        .saturating_mul(2i64.pow(exponent))
+        .min(MAX_RETRY_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_backs_off_exponentially() {
+        assert_eq!(retry_delay_secs(1), 30);
+        assert_eq!(retry_delay_secs(2), 60);
+        assert_eq!(retry_delay_secs(3), 120);
+        assert_eq!(retry_delay_secs(9), MAX_RETRY_SECS);
+    }
+
+    #[test]
+    fn escalation_shows_the_error_and_payload() {
+        use chrono::TimeZone;
+
+        let created_at = Utc.with_ymd_and_hms(2026, 3, 9, 12, 0, 0).unwrap();
+        let item = StuckItem::from(StuckOutboxItem {
+            id: 12,
+            payload: r#"{"notification_type":"ISSUE_CREATED","issue_id":"42"}"#.to_string(),
+            attempts: 6,
+            last_error: Some("Matrix send failed: 502".to_string()),
+            created_at,
+            escalated_at: None,
+        });
+        assert_eq!(
+            render_escalation(&item, created_at + chrono::Duration::hours(3)),
+            "**🚨 Webhook stuck in the outbox**  \n\
+             Item 12 was received 3 h ago and failed 6 times, still retried.  \n\
+             Last error: Matrix send failed: 502\n\n\
+             ```json\n{\"notification_type\":\"ISSUE_CREATED\",\"issue_id\":\"42\"}\n```"
+        );
+    }
+
+    #[test]
+    fn long_payloads_are_cut() {
+        let payload = "x".repeat(PAYLOAD_EXCERPT_CHARS + 1);
+        let excerpt = excerpt(&payload);
+        assert_eq!(excerpt.chars().count(), PAYLOAD_EXCERPT_CHARS + 1);
+        assert!(excerpt.ends_with('…'));
+        assert_eq!(super::excerpt("{}"), "{}");
+    }
+}
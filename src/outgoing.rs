@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+/// Something the bot did that other automation may want to chain off.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BotEvent {
+    /// An admin resolved an issue from its Matrix thread.
+    IssueResolved { issue_id: i64, resolved_by: String },
+    CommandExecuted {
+        command: String,
+        sender: String,
+        issue_id: Option<i64>,
+        success: bool,
+        /// `success`, `seerr_error` or `error`.
+        outcome: String,
+    },
+    /// The scheduled reconciliation found the room out of sync with Seerr.
+    ReconcileMismatch {
+        created: usize,
+        resolved: usize,
+        reopened: usize,
+        requests: usize,
+        orphaned: Vec<i64>,
+    },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    #[serde(flatten)]
+    event: &'a BotEvent,
+    at: DateTime<Utc>,
+}
+
+/// POSTs bot events as JSON to the `OUTGOING_WEBHOOK_URLS`.
+#[derive(Clone, Default)]
+pub struct OutgoingWebhooks {
+    urls: Arc<Vec<String>>,
+    http: reqwest::Client,
+}
+
+impl OutgoingWebhooks {
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            urls: Arc::new(urls),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Sends `event` in the background, so a slow or failing receiver never
+    /// holds up the bot. Failures are only logged.
+    pub fn emit(&self, event: BotEvent) {
+        if self.urls.is_empty() {
+            return;
+        }
+        let body = match serde_json::to_value(Envelope {
+            event: &event,
+            at: Utc::now(),
+        }) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize outgoing webhook: {e}");
+                return;
+            }
+        };
+        for url in self.urls.iter() {
+            let request = self
+                .http
+                .post(url)
+                .timeout(Duration::from_secs(10))
+                .json(&body);
+            let url = url.clone();
+            tokio::spawn(async move {
+                match request
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                {
+                    Ok(_) => debug!(%url, "Outgoing webhook sent"),
+                    Err(e) => warn!(%url, "Failed to send outgoing webhook: {e}"),
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn events_are_tagged() {
+        let event = BotEvent::IssueResolved {
+            issue_id: 42,
+            resolved_by: "@alice:localhost".to_string(),
+        };
+        let body = serde_json::to_value(Envelope {
+            event: &event,
+            at: Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap(),
+        })
+        .unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "event": "issue_resolved",
+                "issue_id": 42,
+                "resolved_by": "@alice:localhost",
+                "at": "2026-03-01T12:00:00Z",
+            })
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use matrix_sdk::ruma::OwnedEventId;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::alerts::Subsystem;
+use crate::audit;
+use crate::db::{self, CommentOrigin, NewPendingSeerrAction, PendingSeerrAction};
+use crate::lifecycle::{self, IssueEvent};
+use crate::markdown;
+use crate::matrix;
+use crate::outgoing::BotEvent;
+use crate::webhook;
+
+const BATCH_SIZE: i64 = 50;
+
+const COMMENT: &str = "comment";
+const RESOLVE: &str = "resolve";
+
+/// Something an admin did in an issue thread that Seerr must be told about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeerrAction<'a> {
+    /// `message` is sent to Seerr, `comment` is how it was written in the
+    /// thread.
+    Comment {
+        message: &'a str,
+        comment: &'a str,
+    },
+    Resolve,
+}
+
+/// Whether `error` means Seerr could not be reached, rather than that it
+/// refused the call. Only then is the action queued.
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    Subsystem::of(error) == Some(Subsystem::Seerr)
+}
+
+/// Keeps the action for when Seerr is back. `matrix_event_id` is the message
+/// that asked for it.
+pub async fn queue(
+    pool: &PgPool,
+    issue_id: i64,
+    action: SeerrAction<'_>,
+    matrix_event_id: &str,
+    thread_root_event_id: &OwnedEventId,
+    requested_by: &str,
+) -> Result<()> {
+    let (kind, message, comment) = match action {
+        SeerrAction::Comment { message, comment } => (COMMENT, Some(message), Some(comment)),
+        SeerrAction::Resolve => (RESOLVE, None, None),
+    };
+    let id = db::insert_pending_seerr_action(
+        pool,
+        &NewPendingSeerrAction {
+            issue_id,
+            action: kind,
+            message,
+            comment,
+            matrix_event_id,
+            thread_root_event_id: thread_root_event_id.as_str(),
+            requested_by,
+        },
+    )
+    .await?;
+    info!(
+        id,
+        issue_id,
+        action = kind,
+        "Seerr action queued until it is reachable"
+    );
+    Ok(())
+}
+
+/// Applies the queued actions every `poll` until `shutdown` is cancelled.
+pub async fn run(state: Arc<AppState>, poll: Duration, shutdown: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(poll) => {}
+        }
+        if let Err(e) = process(&state).await {
+            error!("Failed to apply queued Seerr actions: {e:#}");
+        }
+    }
+}
+
+async fn process(state: &AppState) -> Result<()> {
+    for action in db::list_pending_seerr_actions(&state.db, BATCH_SIZE).await? {
+        // Not to interleave with a command or webhook about the same issue
+        let _permit = state.limiter.acquire(Some(action.issue_id)).await;
+        match apply(state, &action).await {
+            Ok(()) => {
+                db::delete_pending_seerr_action(&state.db, action.id).await?;
+                info!(
+                    id = action.id,
+                    issue_id = action.issue_id,
+                    "Queued Seerr action applied"
+                );
+            }
+            // Later actions wait too, a resolution must not overtake its comment
+            Err(e) if is_unreachable(&e) => {
+                let error = format!("{e:#}");
+                db::record_pending_seerr_action_failure(&state.db, action.id, &error).await?;
+                return Ok(());
+            }
+            Err(e) => {
+                warn!(id = action.id, "Seerr refused a queued action: {e:#}");
+                db::delete_pending_seerr_action(&state.db, action.id).await?;
+                let root: OwnedEventId = action.thread_root_event_id.as_str().try_into()?;
+                let markdown = format!(
+                    "**⚠️ Seerr refused the queued {}**  \n{}",
+                    action.action,
+                    markdown::escape(&e.root_cause().to_string())
+                );
+                matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn apply(state: &AppState, action: &PendingSeerrAction) -> Result<()> {
+    let issue_id = action.issue_id;
+    match action.action.as_str() {
+        COMMENT => {
+            let message = action.message.as_deref().unwrap_or_default();
+            state.forwarded_comments.remember(issue_id, message);
+            let result = state.seerr_client.add_comment(issue_id, message).await;
+            audit::record(
+                &state.db,
+                &action.requested_by,
+                "seerr.add_comment",
+                Some(issue_id),
+                Some("queued"),
+                &result,
+            )
+            .await;
+            let seerr_comment_id = result?;
+            db::insert_comment_event(
+                &state.db,
+                issue_id,
+                seerr_comment_id,
+                &action.matrix_event_id,
+                CommentOrigin::Matrix,
+                action.comment.as_deref().unwrap_or(message),
+            )
+            .await?;
+        }
+        RESOLVE => {
+            let result = state.seerr_client.resolve_issue(issue_id).await;
+            audit::record(
+                &state.db,
+                &action.requested_by,
+                "seerr.resolve_issue",
+                Some(issue_id),
+                Some("queued"),
+                &result,
+            )
+            .await;
+            result?;
+            let resolved_by = Some(action.requested_by.as_str());
+            lifecycle::apply(&state.db, issue_id, IssueEvent::Resolved, resolved_by).await?;
+            state.outgoing.emit(BotEvent::IssueResolved {
+                issue_id,
+                resolved_by: action.requested_by.clone(),
+            });
+
+            let root: OwnedEventId = action.thread_root_event_id.as_str().try_into()?;
+            let markdown = format!(
+                "**Issue {issue_id} resolved**, now that Seerr is back{}",
+                webhook::seerr_link(state.seerr_client.issue_url(issue_id).as_deref())
+            );
+            matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+        }
+        other => bail!("Unknown queued Seerr action {other}"),
+    }
+    Ok(())
+}
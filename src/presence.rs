@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use matrix_sdk::Client;
+use matrix_sdk::ruma::OwnedMxcUri;
+use matrix_sdk::ruma::api::client::presence::set_presence;
+use matrix_sdk::ruma::presence::PresenceState;
+use sqlx::PgPool;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::alerts::{Alerts, Subsystem};
+use crate::db;
+use crate::seerr_client::SeerrClient;
+
+const STATUS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const STATUS_HEALTHY: &str = "Watching Seerr ✅";
+const STATUS_DEGRADED: &str = "Seerr unreachable, notifications may be delayed ⚠️";
+/// Remembers which URL the current avatar was uploaded from so it isn't
+/// uploaded again on every start.
+const AVATAR_SOURCE_SETTING: &str = "avatar_source_url";
+
+/// Sets the bot display name and avatar when they differ from the configured
+/// ones. The avatar is either an `mxc://` URI or an HTTP URL to upload from.
+pub async fn set_profile(
+    client: &Client,
+    pool: &PgPool,
+    display_name: Option<&str>,
+    avatar_url: Option<&str>,
+) -> Result<()> {
+    let account = client.account();
+
+    if let Some(name) = display_name
+        && account.get_display_name().await?.as_deref() != Some(name)
+    {
+        account.set_display_name(Some(name)).await?;
+        info!("Display name set to {name}");
+    }
+
+    let Some(avatar_url) = avatar_url else {
+        return Ok(());
+    };
+    if avatar_url.starts_with("mxc://") {
+        let avatar_url = OwnedMxcUri::from(avatar_url);
+        if account.get_avatar_url().await?.as_ref() != Some(&avatar_url) {
+            account.set_avatar_url(Some(&avatar_url)).await?;
+            info!("Avatar set to {avatar_url}");
+        }
+        return Ok(());
+    }
+    if db::get_setting(pool, AVATAR_SOURCE_SETTING)
+        .await?
+        .as_deref()
+        == Some(avatar_url)
+    {
+        return Ok(());
+    }
+
+    let response = reqwest::get(avatar_url)
+        .await
+        .context("Failed to download avatar")?
+        .error_for_status()
+        .context("Avatar URL returned an error")?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(mime::IMAGE_PNG);
+    let data = response
+        .bytes()
+        .await
+        .context("Failed to download avatar")?;
+
+    let mxc_uri = account.upload_avatar(&content_type, data.to_vec()).await?;
+    db::set_setting(pool, AVATAR_SOURCE_SETTING, avatar_url).await?;
+    info!("Avatar uploaded from {avatar_url} ({mxc_uri})");
+
+    Ok(())
+}
+
+async fn set_status(client: &Client, status: &str) -> Result<()> {
+    let user_id = client.user_id().context("Client is not logged in")?;
+    let mut request = set_presence::v3::Request::new(user_id.to_owned(), PresenceState::Online);
+    request.status_msg = Some(status.to_string());
+    client.send(request).await?;
+    Ok(())
+}
+
+/// Publishes a presence status reflecting whether Seerr is reachable, so room
+/// members can tell when notifications might be delayed.
+pub async fn watch(
+    client: Client,
+    seerr_client: SeerrClient,
+    alerts: Arc<Alerts>,
+    shutdown: CancellationToken,
+) {
+    let mut interval = tokio::time::interval(STATUS_CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut healthy = None;
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = interval.tick() => {}
+        }
+
+        let now_healthy = match seerr_client.status().await {
+            Ok(()) => {
+                alerts.success(Subsystem::Seerr);
+                true
+            }
+            Err(e) => {
+                warn!("Seerr status check failed: {e:#}");
+                alerts.failure(Subsystem::Seerr, &e);
+                false
+            }
+        };
+        if healthy == Some(now_healthy) {
+            continue;
+        }
+
+        let status = if now_healthy {
+            STATUS_HEALTHY
+        } else {
+            STATUS_DEGRADED
+        };
+        match set_status(&client, status).await {
+            Ok(()) => healthy = Some(now_healthy),
+            Err(e) => warn!("Failed to update presence: {e:#}"),
+        }
+    }
+}
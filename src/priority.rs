@@ -0,0 +1,128 @@
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::ruma::OwnedEventId;
+
+use crate::config::PriorityConfig;
+use crate::markdown;
+use crate::matrix;
+
+/// How urgent a notification is, shown on its card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+    Critical,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "low" => Some(Priority::Low),
+            "normal" => Some(Priority::Normal),
+            "high" => Some(Priority::High),
+            "critical" => Some(Priority::Critical),
+            _ => None,
+        }
+    }
+
+    /// From an Alertmanager style `severity` label, e.g. `critical`,
+    /// `warning` or `info`. Priority names are accepted too.
+    pub fn from_severity(severity: &str) -> Self {
+        match severity.trim().to_lowercase().as_str() {
+            "critical" | "error" | "page" | "emergency" => Priority::Critical,
+            "warning" | "warn" => Priority::High,
+            "info" | "notice" => Priority::Normal,
+            "none" | "debug" => Priority::Low,
+            other => Self::parse(other).unwrap_or(Priority::Normal),
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Priority::Low => "🔵",
+            Priority::Normal => "⚪",
+            Priority::High => "🟠",
+            Priority::Critical => "🚨",
+        }
+    }
+
+    /// Color of the card headings, normal cards keep the client's.
+    fn color(&self) -> Option<&'static str> {
+        match self {
+            Priority::Low => Some("#1e88e5"),
+            Priority::Normal => None,
+            Priority::High => Some("#f57c00"),
+            Priority::Critical => Some("#d32f2f"),
+        }
+    }
+
+    /// `**Priority:**` line of a card, nothing for normal ones.
+    pub fn field(&self) -> String {
+        match self {
+            Priority::Normal => String::new(),
+            priority => format!(
+                "**Priority:** {} {}  \n",
+                priority.icon(),
+                priority.as_str()
+            ),
+        }
+    }
+
+    /// Renders a card with its headings in the color of the priority.
+    pub fn render(&self, markdown: &str) -> (String, String) {
+        match self.color() {
+            Some(color) => markdown::render_colored(markdown, color),
+            None => markdown::render(markdown),
+        }
+    }
+}
+
+/// Posts a card in the color of its priority, mentioning `@room` in critical
+/// ones with `PRIORITY_MENTION_ROOM`.
+pub async fn send_card(
+    room: &Room,
+    markdown: &str,
+    priority: Priority,
+    config: &PriorityConfig,
+) -> Result<OwnedEventId> {
+    let (plain, html) = priority.render(markdown);
+    if priority == Priority::Critical && config.mention_room {
+        return matrix::send_html_message_mentioning_room(room, &plain, &html).await;
+    }
+    matrix::send_html_message(room, &plain, &html).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn severities_map_to_priorities() {
+        assert_eq!(Priority::from_severity("critical"), Priority::Critical);
+        assert_eq!(Priority::from_severity("Warning"), Priority::High);
+        assert_eq!(Priority::from_severity("info"), Priority::Normal);
+        assert_eq!(Priority::from_severity("low"), Priority::Low);
+        assert_eq!(Priority::from_severity("whatever"), Priority::Normal);
+    }
+
+    #[test]
+    fn only_unusual_priorities_show_on_cards() {
+        assert_eq!(Priority::Normal.field(), "");
+        assert_eq!(Priority::High.field(), "**Priority:** 🟠 high  \n");
+
+        let (_, html) = Priority::Critical.render("#### Seerr is down");
+        assert!(html.contains("data-mx-color=\"#d32f2f\""));
+        let (_, html) = Priority::Normal.render("#### New issue");
+        assert_eq!(html, "<h4>New issue</h4>");
+    }
+}
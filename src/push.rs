@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::config::{PushConfig, PushEvent, PushProvider};
+
+/// Mirrors selected notifications to ntfy or Gotify, so admins get a phone
+/// ping even when they are not watching Matrix.
+#[derive(Clone)]
+pub struct Push {
+    config: PushConfig,
+    http: reqwest::Client,
+}
+
+impl Push {
+    pub fn new(config: &PushConfig) -> Self {
+        Self {
+            config: config.clone(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    fn request(&self, title: &str, message: &str) -> reqwest::RequestBuilder {
+        match self.config.provider {
+            PushProvider::Ntfy => {
+                let mut request = self
+                    .http
+                    .post(&self.config.url)
+                    .header("Title", title)
+                    .header("Priority", "high")
+                    .body(message.to_string());
+                if let Some(token) = &self.config.token {
+                    request = request.bearer_auth(token);
+                }
+                request
+            }
+            PushProvider::Gotify => self
+                .http
+                .post(format!("{}/message", self.config.url.trim_end_matches('/')))
+                .header(
+                    "X-Gotify-Key",
+                    self.config.token.as_deref().unwrap_or_default(),
+                )
+                .json(&json!({ "title": title, "message": message, "priority": 8 })),
+        }
+    }
+
+    /// Pushes the notification in the background if `event` is one of the
+    /// `PUSH_EVENTS`. Failures are only logged.
+    pub fn send(&self, event: PushEvent, title: &str, message: &str) {
+        if !self.config.events.contains(&event) {
+            return;
+        }
+        let request = self
+            .request(title, message)
+            .timeout(Duration::from_secs(10));
+        tokio::spawn(async move {
+            match request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+            {
+                Ok(_) => debug!(?event, "Push notification sent"),
+                Err(e) => warn!(?event, "Failed to send push notification: {e}"),
+            }
+        });
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use reqwest::Client;
+use reqwest::header::{COOKIE, SET_COOKIE};
+use serde::Deserialize;
+
+use crate::config::DownloadClientConfig;
+
+#[derive(Clone)]
+pub struct QbittorrentClient {
+    base_url: String,
+    credentials: Option<(String, String)>,
+    client: Client,
+}
+
+/// Torrent as listed by qBittorrent, only what the bot reads from it.
+#[derive(Debug, Deserialize)]
+pub struct Torrent {
+    pub name: String,
+    /// From 0 to 1.
+    pub progress: f64,
+    /// Seconds left, 8640000 when unknown.
+    pub eta: i64,
+    /// Bytes per second.
+    pub dlspeed: u64,
+    pub size: u64,
+    pub state: String,
+}
+
+impl QbittorrentClient {
+    pub fn new(config: &DownloadClientConfig) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            credentials: config.username.clone().zip(config.password.clone()),
+            client: Client::new(),
+        }
+    }
+
+    /// Session cookie, `None` when qBittorrent lets the bot in without
+    /// credentials (e.g. a whitelisted subnet).
+    async fn login(&self) -> Result<Option<String>> {
+        let Some((username, password)) = &self.credentials else {
+            return Ok(None);
+        };
+        let response = self
+            .client
+            .post(format!("{}/api/v2/auth/login", self.base_url))
+            // qBittorrent rejects logins without a matching Referer
+            .header("Referer", &self.base_url)
+            .form(&[("username", username), ("password", password)])
+            .send()
+            .await
+            .context("Failed to reach qBittorrent")?
+            .error_for_status()
+            .context("qBittorrent returned error for login")?;
+        let cookie = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .find_map(|value| value.split(';').next().filter(|c| c.starts_with("SID=")))
+            .map(str::to_string);
+        cookie
+            .context(
+                "qBittorrent login failed, check QBITTORRENT_USERNAME and QBITTORRENT_PASSWORD",
+            )
+            .map(Some)
+    }
+
+    /// Torrents that are downloading, stalled or queued for download.
+    pub async fn downloading(&self) -> Result<Vec<Torrent>> {
+        let mut request = self
+            .client
+            .get(format!("{}/api/v2/torrents/info", self.base_url))
+            .query(&[("filter", "downloading")]);
+        if let Some(cookie) = self.login().await? {
+            request = request.header(COOKIE, cookie);
+        }
+        request
+            .send()
+            .await
+            .context("Failed to reach qBittorrent")?
+            .error_for_status()
+            .context("qBittorrent returned error for torrents")?
+            .json()
+            .await
+            .context("Invalid torrents from qBittorrent")
+    }
+}
@@ -0,0 +1,87 @@
+use chrono::Duration;
+
+use crate::markdown;
+use crate::qbittorrent_client::Torrent;
+use crate::stats::format_duration;
+
+/// qBittorrent's ETA for torrents that are not progressing.
+const UNKNOWN_ETA: i64 = 8_640_000;
+
+pub(crate) fn format_size(bytes: u64) -> String {
+    let gb = bytes as f64 / 1e9;
+    if gb >= 1.0 {
+        format!("{gb:.1} GB")
+    } else {
+        format!("{:.0} MB", bytes as f64 / 1e6)
+    }
+}
+
+pub fn render(torrents: &[Torrent]) -> String {
+    if torrents.is_empty() {
+        return "**⬇️ Download queue**  \nNothing is downloading".to_string();
+    }
+
+    let mut markdown = format!("**⬇️ Download queue ({})**\n", torrents.len());
+    for torrent in torrents {
+        markdown.push_str(&format!(
+            "- {}: {:.0}% of {}",
+            markdown::escape(&torrent.name),
+            torrent.progress * 100.0,
+            format_size(torrent.size)
+        ));
+        if torrent.dlspeed > 0 {
+            markdown.push_str(&format!(", {}/s", format_size(torrent.dlspeed)));
+        }
+        if torrent.eta < UNKNOWN_ETA {
+            markdown.push_str(&format!(
+                ", ETA {}",
+                format_duration(Duration::seconds(torrent.eta))
+            ));
+        } else {
+            markdown.push_str(&format!(", {}", markdown::escape(&torrent.state)));
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_lists_progress_and_eta() {
+        let torrents: Vec<Torrent> = serde_json::from_str(
+            r#"[
+                {"name": "Some.Movie.2024.1080p", "progress": 0.456, "eta": 300, "dlspeed": 12300000,
+                 "size": 4200000000, "state": "downloading"},
+                {"name": "Some.Show.S01", "progress": 0.1, "eta": 8640000, "dlspeed": 0,
+                 "size": 800000000, "state": "stalledDL"}
+            ]"#,
+        )
+        .unwrap();
+
+        let markdown = render(&torrents);
+
+        assert!(markdown.contains("Download queue (2)"));
+        assert!(
+            markdown.contains(r"- Some\.Movie\.2024\.1080p: 46% of 4.2 GB, 12 MB/s, ETA 5 min")
+        );
+        assert!(markdown.contains(r"- Some\.Show\.S01: 10% of 800 MB, stalledDL"));
+    }
+
+    #[test]
+    fn render_escapes_names() {
+        let torrents: Vec<Torrent> = serde_json::from_str(
+            r#"[{"name": "[Free](https://evil.example) *hot*", "progress": 0.5, "eta": 8640000,
+                 "dlspeed": 0, "size": 800000000, "state": "<b>stalled</b>"}]"#,
+        )
+        .unwrap();
+
+        let markdown = render(&torrents);
+
+        assert!(markdown.contains(
+            r"- \[Free\]\(https\:\/\/evil\.example\) \*hot\*: 50% of 800 MB, \<b\>stalled\<\/b\>"
+        ));
+    }
+}
@@ -0,0 +1,320 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::db;
+use crate::home_assistant::{self, HomeAssistantNotification};
+use crate::matrix;
+use crate::outbox;
+use crate::seerr::SeerrWebhookPayload;
+use crate::webhook;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// When the running pause ends, as a Unix timestamp.
+const PAUSE_SETTING: &str = "paused_until";
+const SEERR: &str = "seerr";
+const HOME_ASSISTANT: &str = "home_assistant";
+
+/// `QUIET_HOURS`, e.g. `23:00-08:00`, during which Seerr notifications are
+/// held until the morning. Alerts to the admins still go through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        (start != end).then_some(Self { start, end })
+    }
+
+    /// Whether `time` of day is quiet, the hours usually spanning midnight.
+    fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+
+    pub fn is_quiet(&self, now: DateTime<Utc>, timezone: Tz) -> bool {
+        self.contains(now.with_timezone(&timezone).time())
+    }
+}
+
+/// `!bot pause`, holding every notification like quiet hours do Seerr's,
+/// during maintenance for instance.
+#[derive(Debug, Default)]
+pub struct Pause {
+    until: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl Pause {
+    fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some_and(|until| now < until)
+    }
+
+    fn set(&self, until: Option<DateTime<Utc>>) {
+        *self.until.lock().unwrap_or_else(|e| e.into_inner()) = until;
+    }
+
+    /// Forgets a pause whose time is up, returns whether there was one.
+    fn expire(&self, now: DateTime<Utc>) -> bool {
+        let mut until = self.until.lock().unwrap_or_else(|e| e.into_inner());
+        if until.is_some_and(|until| now >= until) {
+            *until = None;
+            return true;
+        }
+        false
+    }
+}
+
+/// Whether Seerr notifications are held right now.
+pub fn is_quiet(state: &AppState) -> bool {
+    let settings = state.settings.get();
+    let now = Utc::now();
+    state.pause.is_active(now)
+        || settings
+            .quiet_hours
+            .is_some_and(|quiet| quiet.is_quiet(now, settings.time_format.timezone))
+}
+
+/// Whether a Seerr webhook is held: during quiet hours, and after them until
+/// the worker delivered the held ones, for it not to overtake them.
+pub async fn should_hold(state: &AppState) -> bool {
+    if is_quiet(state) {
+        return true;
+    }
+    match db::has_quiet_notifications(&state.db, SEERR).await {
+        Ok(false) => false,
+        Ok(true) => {
+            state.release_held.notify_one();
+            true
+        }
+        Err(e) => {
+            warn!("Failed to check for held notifications: {e:#}");
+            false
+        }
+    }
+}
+
+/// Whether the bot is paused, holding the Home Assistant notifications too.
+pub fn is_paused(state: &AppState) -> bool {
+    state.pause.is_active(Utc::now())
+}
+
+/// Keeps a webhook received during quiet hours for the morning batch.
+pub async fn hold(state: &AppState, payload: &SeerrWebhookPayload) -> Result<i64> {
+    let payload = serde_json::to_string(payload)?;
+    db::enqueue_quiet_notification(&state.db, SEERR, &payload).await
+}
+
+/// Keeps a Home Assistant notification received while paused.
+pub async fn hold_home_assistant(
+    state: &AppState,
+    notification: &HomeAssistantNotification,
+) -> Result<i64> {
+    let payload = serde_json::to_string(notification)?;
+    db::enqueue_quiet_notification(&state.db, HOME_ASSISTANT, &payload).await
+}
+
+/// Holds notifications for `duration`, even across restarts. Returns when
+/// the pause ends.
+pub async fn pause(state: &AppState, duration: chrono::Duration) -> Result<DateTime<Utc>> {
+    let until = Utc::now() + duration;
+    db::set_setting(&state.db, PAUSE_SETTING, &until.timestamp().to_string()).await?;
+    state.pause.set(Some(until));
+    info!(%until, "Notifications paused");
+    Ok(until)
+}
+
+/// Ends the pause early and posts what was held, but what quiet hours still
+/// hold. Returns whether the bot was paused.
+pub async fn resume(state: &AppState) -> Result<bool> {
+    let was_paused = is_paused(state);
+    db::delete_setting(&state.db, PAUSE_SETTING).await?;
+    state.pause.set(None);
+    if was_paused {
+        info!("Notifications resumed");
+        release(state, Held::Paused).await?;
+    }
+    Ok(was_paused)
+}
+
+/// Picks up a pause that was running when the bot stopped.
+pub async fn restore_pause(state: &AppState) -> Result<()> {
+    let until = db::get_setting(&state.db, PAUSE_SETTING)
+        .await?
+        .and_then(|secs| secs.parse().ok())
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+    if let Some(until) = until.filter(|until| *until > Utc::now()) {
+        info!(%until, "Notifications still paused");
+        state.pause.set(Some(until));
+    }
+    Ok(())
+}
+
+/// Why notifications were held, for the message posted before them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Held {
+    QuietHours,
+    Paused,
+}
+
+impl Held {
+    fn heading(&self, count: usize) -> String {
+        match self {
+            Held::QuietHours => {
+                format!("**🌅 Good morning! {count} notifications came in during quiet hours**")
+            }
+            Held::Paused => format!("**▶️ {count} notifications came in while paused**"),
+        }
+    }
+}
+
+/// Delivers the held notifications once the quiet hours or the pause are
+/// over, until `shutdown` is cancelled.
+pub async fn run(state: Arc<AppState>, shutdown: CancellationToken) {
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            _ = state.release_held.notified() => {}
+        }
+        let held = if state.pause.expire(Utc::now()) {
+            Held::Paused
+        } else {
+            Held::QuietHours
+        };
+        if is_paused(&state) {
+            continue;
+        }
+
+        if let Err(e) = release(&state, held).await {
+            error!("Failed to deliver notifications held during quiet hours: {e:#}");
+        }
+    }
+}
+
+/// Posts the notifications held during the night or the pause in one go,
+/// under a message saying how many there are. Seerr ones wait while quiet
+/// hours last. Those held meanwhile, webhooks arriving while the backlog is
+/// delivered, follow. Returns how many were delivered.
+async fn release(state: &AppState, reason: Held) -> Result<usize> {
+    let mut delivered = 0;
+    loop {
+        let quiet = is_quiet(state);
+        let held: Vec<_> = db::list_quiet_notifications(&state.db)
+            .await?
+            .into_iter()
+            .filter(|item| item.source == HOME_ASSISTANT || !quiet)
+            .collect();
+        if held.is_empty() {
+            break;
+        }
+        if delivered == 0 {
+            matrix::send_markdown(&state.room, &reason.heading(held.len())).await?;
+        }
+        for item in &held {
+            deliver(state, item).await?;
+        }
+        delivered += held.len();
+    }
+    if delivered > 0 {
+        info!(count = delivered, "Held notifications delivered");
+    }
+    Ok(delivered)
+}
+
+async fn deliver(state: &AppState, item: &db::QuietNotification) -> Result<()> {
+    if item.source == HOME_ASSISTANT {
+        match serde_json::from_str::<HomeAssistantNotification>(&item.payload) {
+            Ok(notification) => {
+                // Not retried, like the ones Home Assistant sends at once
+                if let Err(e) = home_assistant::deliver(state, &notification).await {
+                    warn!(quiet_id = item.id, "Held notification failed: {e:#}");
+                }
+            }
+            Err(e) => warn!(
+                quiet_id = item.id,
+                "Dropping unreadable held notification: {e}"
+            ),
+        }
+        db::delete_quiet_notification(&state.db, item.id).await?;
+        return Ok(());
+    }
+    match serde_json::from_str::<SeerrWebhookPayload>(&item.payload) {
+        Ok(payload) => {
+            if let Err(e) = webhook::process_payload(state, &payload).await {
+                // Retried like any other webhook that failed
+                warn!(quiet_id = item.id, "Held notification failed: {e:#}");
+                outbox::enqueue(state, &payload, &e).await?;
+            }
+        }
+        Err(e) => warn!(
+            quiet_id = item.id,
+            "Dropping unreadable held notification: {e}"
+        ),
+    }
+    db::delete_quiet_notification(&state.db, item.id).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn parse_quiet_hours() {
+        assert_eq!(
+            QuietHours::parse("23:00 - 08:30"),
+            Some(QuietHours {
+                start: time(23, 0),
+                end: time(8, 30),
+            })
+        );
+        assert_eq!(QuietHours::parse("23:00"), None);
+        assert_eq!(QuietHours::parse("25:00-08:00"), None);
+        assert_eq!(QuietHours::parse("08:00-08:00"), None);
+    }
+
+    #[test]
+    fn quiet_hours_span_midnight() {
+        let night = QuietHours::parse("23:00-08:00").unwrap();
+        assert!(night.contains(time(23, 0)));
+        assert!(night.contains(time(3, 0)));
+        assert!(!night.contains(time(8, 0)));
+        assert!(!night.contains(time(12, 0)));
+
+        let afternoon = QuietHours::parse("13:00-15:00").unwrap();
+        assert!(afternoon.contains(time(14, 0)));
+        assert!(!afternoon.contains(time(22, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_are_in_the_bot_timezone() {
+        let night = QuietHours::parse("23:00-08:00").unwrap();
+        // 07:30 UTC is 09:30 in Paris in summer
+        let now = Utc.with_ymd_and_hms(2025, 7, 1, 7, 30, 0).unwrap();
+        assert!(night.is_quiet(now, Tz::UTC));
+        assert!(!night.is_quiet(now, chrono_tz::Europe::Paris));
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::seerr::{SeerrQuotaStatus, SeerrRequest};
+use crate::time_format::TimeFormat;
+
+/// Declined requests don't count towards quotas.
+const REQUEST_STATUS_DECLINED: i64 = 3;
+
+/// Seerr refused to approve a request because its requester used up their
+/// quota of movie or series requests.
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    pub request_id: i64,
+    /// `movie` or `tv`.
+    pub media_type: String,
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+    pub days: Option<i64>,
+    /// When the oldest request of the quota leaves it, `None` when unknown.
+    pub resets_at: Option<DateTime<Utc>>,
+}
+
+impl QuotaExceeded {
+    /// `requests` are the latest requests of the requester, the quota
+    /// counting those of the last `days` days.
+    pub fn new(
+        request_id: i64,
+        media_type: &str,
+        quota: &SeerrQuotaStatus,
+        requests: &[SeerrRequest],
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            request_id,
+            media_type: media_type.to_string(),
+            limit: quota.limit,
+            remaining: quota.remaining,
+            days: quota.days,
+            resets_at: quota
+                .days
+                .and_then(|days| reset_at(requests, media_type, days, now)),
+        }
+    }
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The requester of request {} used up their {} quota",
+            self.request_id, self.media_type
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// Whether the message of a Seerr error is its quota rejection, e.g.
+/// `Movie Quota exceeded.`
+pub fn is_quota_error(message: &str) -> bool {
+    message.to_ascii_lowercase().contains("quota")
+}
+
+/// When the oldest request counted in a quota of `days` days leaves it.
+fn reset_at(
+    requests: &[SeerrRequest],
+    media_type: &str,
+    days: i64,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let window = chrono::Duration::days(days);
+    requests
+        .iter()
+        .filter(|request| request.status != REQUEST_STATUS_DECLINED)
+        .filter(|request| {
+            request.media.as_ref().and_then(|m| m.media_type.as_deref()) == Some(media_type)
+        })
+        .filter_map(|request| request.created_at)
+        .filter(|created_at| *created_at > now - window)
+        .min()
+        .map(|created_at| created_at + window)
+}
+
+/// The reply to an approval Seerr refused over the requester's quota, `None`
+/// for other errors.
+pub fn reply(error: &anyhow::Error, time_format: &TimeFormat) -> Option<String> {
+    error
+        .downcast_ref::<QuotaExceeded>()
+        .map(|quota| render(quota, time_format))
+}
+
+fn render(quota: &QuotaExceeded, time_format: &TimeFormat) -> String {
+    let kind = if quota.media_type == "tv" {
+        "series"
+    } else {
+        "movie"
+    };
+    let mut markdown = format!(
+        "**⚠️ Request {} not approved, its requester used up their {kind} quota**",
+        quota.request_id
+    );
+    if let (Some(limit), Some(days)) = (quota.limit, quota.days) {
+        let remaining = quota.remaining.unwrap_or_default();
+        markdown.push_str(&format!(
+            "  \nRemaining: {remaining} of {limit} requests per {days} days"
+        ));
+    }
+    if let Some(resets_at) = quota.resets_at {
+        markdown.push_str(&format!(
+            "  \nA request is freed on {}",
+            time_format.datetime(resets_at)
+        ));
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn request(media_type: &str, status: i64, created_at: DateTime<Utc>) -> SeerrRequest {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "status": status,
+            "media": { "mediaType": media_type },
+            "createdAt": created_at,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn quota_resets_when_the_oldest_request_leaves_it() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 10, 12, 0, 0).unwrap();
+        let days_ago = |days| now - chrono::Duration::days(days);
+        let requests = [
+            request("movie", 2, days_ago(2)),
+            request("movie", 2, days_ago(5)),
+            request("movie", REQUEST_STATUS_DECLINED, days_ago(6)),
+            request("tv", 2, days_ago(6)),
+            request("movie", 2, days_ago(9)),
+        ];
+        let quota = SeerrQuotaStatus {
+            days: Some(7),
+            limit: Some(2),
+            used: 2,
+            remaining: Some(0),
+        };
+
+        let exceeded = QuotaExceeded::new(12, "movie", &quota, &requests, now);
+        assert_eq!(
+            exceeded.resets_at,
+            Some(days_ago(5) + chrono::Duration::days(7))
+        );
+        assert_eq!(
+            render(&exceeded, &TimeFormat::default()),
+            "**⚠️ Request 12 not approved, its requester used up their movie quota**  \n\
+             Remaining: 0 of 2 requests per 7 days  \n\
+             A request is freed on 2026-10-12 12:00"
+        );
+
+        let error = anyhow::Error::new(exceeded).context("Failed to approve");
+        assert!(reply(&error, &TimeFormat::default()).is_some());
+        assert!(reply(&anyhow::anyhow!("502"), &TimeFormat::default()).is_none());
+        assert!(is_quota_error("Movie Quota exceeded."));
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::ServiceConfig;
+use crate::disk::RootFolder;
+use crate::http_metrics::{HttpMetrics, SendRecorded, ServiceMetrics};
+
+/// Service label of the Radarr API metrics.
+const SERVICE: &str = "radarr";
+
+#[derive(Clone)]
+pub struct RadarrClient {
+    base_url: String,
+    api_key: String,
+    client: Client,
+    metrics: ServiceMetrics,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Movie {
+    #[serde(default)]
+    pub id: i64,
+    pub title: String,
+    pub year: Option<i64>,
+    pub in_cinemas: Option<DateTime<Utc>>,
+    pub digital_release: Option<DateTime<Utc>>,
+    pub physical_release: Option<DateTime<Utc>>,
+    pub movie_file: Option<MovieFile>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MovieFile {
+    pub id: i64,
+    pub quality: Option<QualityModel>,
+}
+
+impl MovieFile {
+    pub fn quality_name(&self) -> &str {
+        self.quality
+            .as_ref()
+            .map(|q| q.quality.name.as_str())
+            .unwrap_or("Unknown")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QualityModel {
+    pub quality: Quality,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Quality {
+    pub name: String,
+}
+
+impl RadarrClient {
+    pub fn new(config: &ServiceConfig) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            client: Client::new(),
+            metrics: Arc::new(HttpMetrics::default()).service(SERVICE),
+        }
+    }
+
+    /// Records the API calls in `metrics`, for `/admin/metrics`.
+    pub fn with_metrics(mut self, metrics: &Arc<HttpMetrics>) -> Self {
+        self.metrics = metrics.service(SERVICE);
+        self
+    }
+
+    /// Movies with a cinema, digital or physical release between `start` and
+    /// `end`.
+    pub async fn calendar(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Movie>> {
+        self.client
+            .get(format!("{}/api/v3/calendar", self.base_url))
+            .query(&[("start", start.to_rfc3339()), ("end", end.to_rfc3339())])
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/calendar")
+            .await
+            .context("Failed to reach Radarr")?
+            .error_for_status()
+            .context("Radarr returned error for calendar")?
+            .json()
+            .await
+            .context("Invalid calendar from Radarr")
+    }
+
+    /// The movie of the library with this TMDB id.
+    pub async fn movie_by_tmdb_id(&self, tmdb_id: i64) -> Result<Option<Movie>> {
+        let movies: Vec<Movie> = self
+            .client
+            .get(format!("{}/api/v3/movie", self.base_url))
+            .query(&[("tmdbId", tmdb_id)])
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/movie")
+            .await
+            .context("Failed to reach Radarr")?
+            .error_for_status()
+            .context("Radarr returned error for movie lookup")?
+            .json()
+            .await
+            .context("Invalid movie from Radarr")?;
+        Ok(movies.into_iter().next())
+    }
+
+    pub async fn delete_movie_file(&self, movie_file_id: i64) -> Result<()> {
+        self.client
+            .delete(format!(
+                "{}/api/v3/moviefile/{movie_file_id}",
+                self.base_url
+            ))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/moviefile/{id}")
+            .await
+            .context("Failed to reach Radarr")?
+            .error_for_status()
+            .context("Radarr returned error for movie file deletion")?;
+        Ok(())
+    }
+
+    pub async fn search_movie(&self, movie_id: i64) -> Result<()> {
+        self.client
+            .post(format!("{}/api/v3/command", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .json(&json!({ "name": "MoviesSearch", "movieIds": [movie_id] }))
+            .send_recorded(&self.metrics, "/api/v3/command")
+            .await
+            .context("Failed to reach Radarr")?
+            .error_for_status()
+            .context("Radarr returned error for movie search")?;
+        Ok(())
+    }
+
+    pub async fn root_folders(&self) -> Result<Vec<RootFolder>> {
+        self.client
+            .get(format!("{}/api/v3/rootfolder", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/rootfolder")
+            .await
+            .context("Failed to reach Radarr")?
+            .error_for_status()
+            .context("Radarr returned error for root folders")?
+            .json()
+            .await
+            .context("Invalid root folders from Radarr")
+    }
+}
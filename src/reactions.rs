@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedEventId;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::config::ReactionEmojis;
+use crate::db;
+use crate::issue::IssueState;
+use crate::notifier::MatrixNotifier;
+
+/// How long an admin who reacted for a comment has to write it.
+const COMMENT_PROMPT_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// What an admin's reaction to an issue card does, per emoji in the
+/// `[reactions]` section of the config file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReactionAction {
+    /// Like `!issues ack`.
+    Ack,
+    /// Like `!issues resolve`.
+    Resolve,
+    /// Asks the admin for a comment, their next message in the thread being
+    /// sent to Seerr.
+    Comment,
+    /// Like `!issues mute`.
+    Mute,
+}
+
+impl ReactionAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            ReactionAction::Ack => "ack",
+            ReactionAction::Resolve => "resolve",
+            ReactionAction::Comment => "comment",
+            ReactionAction::Mute => "mute",
+        }
+    }
+}
+
+/// The action of a reaction with `key`. The `[reactions]` section comes first,
+/// the acknowledged emoji acknowledging when it doesn't map it.
+pub fn action(
+    shortcuts: &HashMap<String, ReactionAction>,
+    emojis: &ReactionEmojis,
+    key: &str,
+) -> Option<ReactionAction> {
+    if let Some(action) = shortcuts.get(key) {
+        return Some(*action);
+    }
+    (!emojis.acknowledged.is_empty() && key == emojis.acknowledged).then_some(ReactionAction::Ack)
+}
+
+/// Admins asked for a comment after reacting to an issue card, keyed by the
+/// card and the admin.
+#[derive(Debug, Default)]
+pub struct CommentPrompts {
+    pending: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl CommentPrompts {
+    pub fn prompt(&self, root_event_id: &str, sender: &str) {
+        self.prompt_at(root_event_id, sender, Instant::now());
+    }
+
+    /// Whether `sender` was asked for a comment in the thread of
+    /// `root_event_id`, the message at hand answering it.
+    pub fn take(&self, root_event_id: &str, sender: &str) -> bool {
+        self.take_at(root_event_id, sender, Instant::now())
+    }
+
+    fn prompt_at(&self, root_event_id: &str, sender: &str, now: Instant) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        pending.retain(|_, at| now.duration_since(*at) < COMMENT_PROMPT_TTL);
+        pending.insert((root_event_id.to_string(), sender.to_string()), now);
+    }
+
+    fn take_at(&self, root_event_id: &str, sender: &str, now: Instant) -> bool {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (root_event_id.to_string(), sender.to_string());
+        pending
+            .remove(&key)
+            .is_some_and(|at| now.duration_since(at) < COMMENT_PROMPT_TTL)
+    }
+}
+
+/// Moves the reaction on an issue card to the one configured for `state`,
+/// redacting the reactions left for previous states.
+pub async fn transition(
+    room: &impl MatrixNotifier,
+    pool: &PgPool,
+    emojis: &ReactionEmojis,
+    issue_id: i64,
+    root_event_id: &OwnedEventId,
+    state: IssueState,
+) -> Result<()> {
+    // The card is the one of the issue duplicated, whose state it shows
+    if db::get_duplicate_of(pool, issue_id).await?.is_some() {
+        return Ok(());
+    }
+    let existing = db::list_issue_reactions(pool, issue_id).await?;
+
+    for reaction in existing.iter().filter(|r| r.state != state.as_str()) {
+        let reaction_event_id: OwnedEventId = reaction.reaction_event_id.as_str().try_into()?;
+        let reason = format!("Issue is now {state}");
+        room.redact(&reaction_event_id, Some(&reason)).await?;
+        db::delete_issue_reaction(pool, issue_id, &reaction.state).await?;
+    }
+
+    if existing.iter().any(|r| r.state == state.as_str()) {
+        return Ok(());
+    }
+
+    let emoji = emojis.for_state(state);
+    if emoji.is_empty() {
+        return Ok(());
+    }
+
+    let reaction_event_id = room.send_reaction(root_event_id, emoji).await?;
+    db::insert_issue_reaction(pool, issue_id, state.as_str(), reaction_event_id.as_str()).await?;
+    info!(issue_id, %state, "Issue reaction updated");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcuts_come_before_the_acknowledged_emoji() {
+        let emojis = ReactionEmojis::default();
+        let shortcuts = HashMap::from([
+            ("✅".to_string(), ReactionAction::Resolve),
+            ("🔇".to_string(), ReactionAction::Mute),
+        ]);
+        assert_eq!(
+            action(&shortcuts, &emojis, "✅"),
+            Some(ReactionAction::Resolve)
+        );
+        assert_eq!(action(&shortcuts, &emojis, "👀"), Some(ReactionAction::Ack));
+        assert_eq!(action(&shortcuts, &emojis, "🎉"), None);
+
+        let shortcuts = HashMap::from([("👀".to_string(), ReactionAction::Comment)]);
+        assert_eq!(
+            action(&shortcuts, &emojis, "👀"),
+            Some(ReactionAction::Comment)
+        );
+    }
+
+    #[test]
+    fn comment_prompts_are_answered_once() {
+        let prompts = CommentPrompts::default();
+        let now = Instant::now();
+        prompts.prompt_at("$card", "@alice:localhost", now);
+
+        assert!(!prompts.take_at("$card", "@bob:localhost", now));
+        assert!(prompts.take_at("$card", "@alice:localhost", now));
+        assert!(!prompts.take_at("$card", "@alice:localhost", now));
+
+        prompts.prompt_at("$card", "@alice:localhost", now);
+        assert!(!prompts.take_at("$card", "@alice:localhost", now + COMMENT_PROMPT_TTL));
+    }
+}
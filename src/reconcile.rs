@@ -0,0 +1,428 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db::{self, TrackedIssue};
+use crate::issue::{IssueCategory, IssueState};
+use crate::outgoing::BotEvent;
+use crate::seerr::{
+    ISSUE_STATUS_RESOLVED, SeerrIssue, SeerrMedia, SeerrRequest, SeerrUser, SeerrWebhookPayload,
+};
+use crate::webhook;
+
+/// What a reconciliation or catch-up pass changed.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub created: usize,
+    pub resolved: usize,
+    pub reopened: usize,
+    pub comments: usize,
+    pub requests: usize,
+    pub failed: usize,
+    /// Tracked issues that no longer exist in Seerr.
+    pub orphaned: Vec<i64>,
+}
+
+impl Report {
+    /// Whether the room had drifted from Seerr.
+    fn is_mismatch(&self) -> bool {
+        self.created + self.resolved + self.reopened + self.requests > 0
+            || !self.orphaned.is_empty()
+    }
+
+    fn log(&self, message: &str) {
+        info!(
+            created = self.created,
+            resolved = self.resolved,
+            reopened = self.reopened,
+            comments = self.comments,
+            requests = self.requests,
+            failed = self.failed,
+            orphaned = self.orphaned.len(),
+            "{message}"
+        );
+    }
+}
+
+/// Change needed to bring the room in line with Seerr.
+#[derive(Debug, PartialEq)]
+enum Drift {
+    Missing,
+    Resolved,
+    Reopened,
+}
+
+/// What happened to an issue in Seerr that the room hasn't heard of, given
+/// whether Seerr has it resolved and how the bot tracks it.
+fn issue_drift(resolved_in_seerr: bool, tracked: Option<&TrackedIssue>) -> Vec<Drift> {
+    let Some(tracked) = tracked else {
+        return if resolved_in_seerr {
+            vec![Drift::Missing, Drift::Resolved]
+        } else {
+            vec![Drift::Missing]
+        };
+    };
+    // Rows without a card are posted by the repair pass
+    if tracked.matrix_event_id.is_none() {
+        return Vec::new();
+    }
+    let resolved_in_room = tracked.status == IssueState::Resolved.as_str();
+    match (resolved_in_seerr, resolved_in_room) {
+        (true, false) => vec![Drift::Resolved],
+        (false, true) => vec![Drift::Reopened],
+        _ => Vec::new(),
+    }
+}
+
+async fn tracked_issues(state: &AppState) -> Result<HashMap<i64, TrackedIssue>> {
+    Ok(db::list_tracked_issues(&state.db, None, i64::MAX)
+        .await?
+        .into_iter()
+        .map(|issue| (issue.issue_id, issue))
+        .collect())
+}
+
+/// Compares the issues open in Seerr with `issue_events` and replays the
+/// notifications that were lost: cards for issues the bot never heard of,
+/// resolutions and reopenings done while it was down or its webhook failed.
+pub async fn reconcile(state: &AppState) -> Result<Report> {
+    let tracked = tracked_issues(state).await?;
+    let open = state.seerr_client.list_issues("open").await?;
+    let open_ids: HashSet<i64> = open.iter().map(|issue| issue.id).collect();
+
+    let mut report = Report::default();
+    for issue in &open {
+        sync_issue(state, issue, tracked.get(&issue.id), &mut report).await;
+    }
+
+    let unresolved = tracked.values().filter(|issue| {
+        issue.status != IssueState::Resolved.as_str()
+            && issue.matrix_event_id.is_some()
+            && !open_ids.contains(&issue.issue_id)
+    });
+    for issue in unresolved {
+        match state.seerr_client.get_issue(issue.issue_id).await? {
+            Some(seerr_issue) => sync_issue(state, &seerr_issue, Some(issue), &mut report).await,
+            None => {
+                warn!(
+                    issue_id = issue.issue_id,
+                    "Tracked issue no longer exists in Seerr"
+                );
+                report.orphaned.push(issue.issue_id);
+            }
+        }
+    }
+
+    report.log("Reconciled issues with Seerr");
+    if report.is_mismatch() {
+        state.outgoing.emit(BotEvent::ReconcileMismatch {
+            created: report.created,
+            resolved: report.resolved,
+            reopened: report.reopened,
+            requests: report.requests,
+            orphaned: report.orphaned.clone(),
+        });
+    }
+    Ok(report)
+}
+
+/// Outcome of [`adopt`].
+#[derive(Debug, PartialEq)]
+pub enum Adoption {
+    Posted,
+    AlreadyTracked,
+    Resolved,
+    NotFound,
+}
+
+/// Posts the card of an open issue the bot never heard of, e.g. one created
+/// while it was down, so it can be handled from its thread like any other.
+pub async fn adopt(state: &AppState, issue_id: i64) -> Result<Adoption> {
+    if db::get_issue_event(&state.db, issue_id).await?.is_some() {
+        return Ok(Adoption::AlreadyTracked);
+    }
+    let Some(issue) = state.seerr_client.get_issue(issue_id).await? else {
+        return Ok(Adoption::NotFound);
+    };
+    if issue.status == ISSUE_STATUS_RESOLVED {
+        return Ok(Adoption::Resolved);
+    }
+    let payload = created_payload(state, &issue).await;
+    // Asked for explicitly, so not subject to the room's notification filter
+    webhook::handle_issue_created(state, &payload).await?;
+    info!(issue_id, "Adopted untracked issue");
+    Ok(Adoption::Posted)
+}
+
+/// Time of the last webhook processed, or catch-up completed.
+const LAST_EVENT_SETTING: &str = "last_event_at";
+
+/// Records that every notification up to `at` has been handled.
+pub async fn mark_processed(pool: &PgPool, at: DateTime<Utc>) -> Result<()> {
+    db::set_setting(pool, LAST_EVENT_SETTING, &at.timestamp().to_string()).await
+}
+
+/// Replays what changed in Seerr since the last processed webhook: issues
+/// opened, resolved or reopened, new comments and request status changes.
+/// The first start only records the current time.
+pub async fn catch_up(state: &AppState) -> Result<Report> {
+    let started_at = Utc::now();
+    let since = db::get_setting(&state.db, LAST_EVENT_SETTING)
+        .await?
+        .and_then(|secs| secs.parse().ok())
+        .and_then(|secs| DateTime::from_timestamp(secs, 0));
+    let mut report = Report::default();
+    let Some(since) = since else {
+        mark_processed(&state.db, started_at).await?;
+        return Ok(report);
+    };
+    info!(%since, "Catching up on notifications missed while offline");
+
+    let tracked = tracked_issues(state).await?;
+    for issue in state.seerr_client.list_issues_updated_since(since).await? {
+        sync_issue(state, &issue, tracked.get(&issue.id), &mut report).await;
+        // The first comment is the issue description
+        for comment in issue.comments.iter().skip(1) {
+            if comment.created_at.is_none_or(|at| at < since)
+                || db::get_comment_event(&state.db, comment.id)
+                    .await?
+                    .is_some()
+            {
+                continue;
+            }
+            report.comments += 1;
+            let payload = SeerrWebhookPayload {
+                notification_type: "ISSUE_COMMENT".to_string(),
+                issue_id: Some(issue.id.to_string()),
+                comment: Some(comment.message.clone()),
+                commented_by: Some(SeerrUser::name(comment.user.as_ref())),
+                comment_id: Some(comment.id.to_string()),
+                ..Default::default()
+            };
+            replay(state, &payload, &mut report).await;
+        }
+    }
+
+    for request in state
+        .seerr_client
+        .list_requests_updated_since(since)
+        .await?
+    {
+        sync_request(state, &request, &mut report).await?;
+    }
+
+    if report.failed == 0 {
+        mark_processed(&state.db, started_at).await?;
+    }
+    report.log("Caught up with Seerr");
+    Ok(report)
+}
+
+async fn sync_issue(
+    state: &AppState,
+    issue: &SeerrIssue,
+    tracked: Option<&TrackedIssue>,
+    report: &mut Report,
+) {
+    for drift in issue_drift(issue.status == ISSUE_STATUS_RESOLVED, tracked) {
+        let payload = match drift {
+            Drift::Missing => {
+                report.created += 1;
+                created_payload(state, issue).await
+            }
+            Drift::Resolved => {
+                report.resolved += 1;
+                SeerrWebhookPayload {
+                    notification_type: "ISSUE_RESOLVED".to_string(),
+                    issue_id: Some(issue.id.to_string()),
+                    comment: issue.comments.last().map(|c| c.message.clone()),
+                    commented_by: Some(SeerrUser::name(issue.modified_by.as_ref())),
+                    ..Default::default()
+                }
+            }
+            Drift::Reopened => {
+                report.reopened += 1;
+                SeerrWebhookPayload {
+                    notification_type: "ISSUE_REOPENED".to_string(),
+                    issue_id: Some(issue.id.to_string()),
+                    reported_by: Some(SeerrUser::name(issue.modified_by.as_ref())),
+                    ..Default::default()
+                }
+            }
+        };
+        replay(state, &payload, report).await;
+    }
+}
+
+async fn sync_request(state: &AppState, request: &SeerrRequest, report: &mut Report) -> Result<()> {
+    let Some(status) = request.request_status() else {
+        return Ok(());
+    };
+    let tracked = db::get_request_event(&state.db, request.id).await?;
+    if tracked.as_ref().is_some_and(|t| t.status == Some(status)) {
+        return Ok(());
+    }
+    // Only new cards show the title
+    let subject = match (&tracked, &request.media) {
+        (None, Some(media)) => media_title(state, media, request.id).await,
+        _ => format!("Request #{}", request.id),
+    };
+    report.requests += 1;
+    let payload = SeerrWebhookPayload {
+        notification_type: status.notification_type().to_string(),
+        subject,
+        request_id: Some(request.id.to_string()),
+        requested_by: Some(SeerrUser::name(request.requested_by.as_ref())),
+        media_type: request.media.as_ref().and_then(|m| m.media_type.clone()),
+        media_tmdbid: request
+            .media
+            .as_ref()
+            .and_then(|m| m.tmdb_id)
+            .map(|id| id.to_string()),
+        ..Default::default()
+    };
+    replay(state, &payload, report).await;
+    Ok(())
+}
+
+async fn media_title(state: &AppState, media: &SeerrMedia, id: i64) -> String {
+    match state.seerr_client.media_title(media).await {
+        Ok(title) => title,
+        Err(e) => {
+            warn!(id, "Failed to fetch media title: {e:#}");
+            format!("#{id}")
+        }
+    }
+}
+
+/// The `ISSUE_CREATED` notification Seerr would have sent for `issue`.
+async fn created_payload(state: &AppState, issue: &SeerrIssue) -> SeerrWebhookPayload {
+    let subject = match &issue.media {
+        Some(media) => media_title(state, media, issue.id).await,
+        None => format!("Issue #{}", issue.id),
+    };
+    SeerrWebhookPayload {
+        notification_type: "ISSUE_CREATED".to_string(),
+        subject,
+        // Seerr stores the description as the first comment
+        message: issue.comments.first().map(|c| c.message.clone()),
+        issue_id: Some(issue.id.to_string()),
+        issue_type: IssueCategory::from_seerr(issue.issue_type).map(|c| c.to_string()),
+        reported_by: Some(SeerrUser::name(issue.created_by.as_ref())),
+        media_type: issue.media.as_ref().and_then(|m| m.media_type.clone()),
+        media_tmdbid: issue
+            .media
+            .as_ref()
+            .and_then(|m| m.tmdb_id)
+            .map(|id| id.to_string()),
+        media_tvdbid: issue
+            .media
+            .as_ref()
+            .and_then(|m| m.tvdb_id)
+            .map(|id| id.to_string()),
+        ..Default::default()
+    }
+}
+
+async fn replay(state: &AppState, payload: &SeerrWebhookPayload, report: &mut Report) {
+    info!(
+        notification_type = %payload.notification_type,
+        issue_id = payload.issue_id.as_deref(),
+        "Replaying missed notification"
+    );
+    if let Err(e) = webhook::process_payload(state, payload).await {
+        warn!(
+            issue_id = payload.issue_id.as_deref(),
+            "Failed to replay missed notification: {e:#}"
+        );
+        report.failed += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestStatus;
+
+    fn tracked(status: IssueState) -> TrackedIssue {
+        TrackedIssue {
+            issue_id: 1,
+            matrix_event_id: Some("$card".to_string()),
+            subject: None,
+            reported_by: None,
+            category: None,
+            problem_season: None,
+            problem_episode: None,
+            status: status.as_str().to_string(),
+            comment_count: 0,
+            created_at: Utc::now(),
+            first_response_at: None,
+            resolved_at: None,
+            resolved_by: None,
+            acknowledged_by: None,
+        }
+    }
+
+    #[test]
+    fn detects_drift() {
+        assert_eq!(issue_drift(false, None), vec![Drift::Missing]);
+        assert_eq!(
+            issue_drift(true, None),
+            vec![Drift::Missing, Drift::Resolved]
+        );
+        assert_eq!(
+            issue_drift(false, Some(&tracked(IssueState::Resolved))),
+            vec![Drift::Reopened]
+        );
+        assert_eq!(
+            issue_drift(true, Some(&tracked(IssueState::InProgress))),
+            vec![Drift::Resolved]
+        );
+        assert!(issue_drift(false, Some(&tracked(IssueState::Open))).is_empty());
+        assert!(issue_drift(true, Some(&tracked(IssueState::Resolved))).is_empty());
+
+        let mut pending = tracked(IssueState::Open);
+        pending.matrix_event_id = None;
+        assert!(issue_drift(true, Some(&pending)).is_empty());
+    }
+
+    #[test]
+    fn parses_seerr_issue_list() {
+        let page: crate::seerr::SeerrPage<SeerrIssue> = serde_json::from_str(
+            r#"{
+                "pageInfo": {"pages": 1, "pageSize": 100, "results": 1, "page": 1},
+                "results": [{
+                    "id": 12,
+                    "issueType": 1,
+                    "status": 1,
+                    "media": {"tmdbId": 603, "mediaType": "movie"},
+                    "createdBy": {"displayName": "alice"},
+                    "comments": [{"id": 3, "message": "No sound"}]
+                }]
+            }"#,
+        )
+        .unwrap();
+        let issue = &page.results[0];
+        assert_eq!(issue.id, 12);
+        assert_eq!(issue.media.as_ref().unwrap().tmdb_id, Some(603));
+        assert_eq!(SeerrUser::name(issue.created_by.as_ref()), "alice");
+        assert_eq!(SeerrUser::name(issue.modified_by.as_ref()), "unknown");
+    }
+
+    #[test]
+    fn request_status_follows_media_availability() {
+        let request: SeerrRequest = serde_json::from_str(
+            r#"{"id": 4, "status": 2, "media": {"tmdbId": 603, "mediaType": "movie", "status": 5}}"#,
+        )
+        .unwrap();
+        assert_eq!(request.request_status(), Some(RequestStatus::Available));
+
+        let request: SeerrRequest =
+            serde_json::from_str(r#"{"id": 5, "status": 3, "media": {"status": 1}}"#).unwrap();
+        assert_eq!(request.request_status(), Some(RequestStatus::Declined));
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::event_handler::Ctx;
+use matrix_sdk::ruma::events::room::redaction::OriginalSyncRoomRedactionEvent;
+use tracing::{error, info, warn};
+
+use crate::AppState;
+use crate::db;
+use crate::matrix;
+use crate::reactions;
+use crate::votes;
+use crate::webhook;
+
+/// Re-posts an issue card when its root message gets redacted so that
+/// following thread replies and commands have something to hang off.
+pub async fn on_room_redaction(
+    event: OriginalSyncRoomRedactionEvent,
+    room: Room,
+    state: Ctx<Arc<AppState>>,
+) {
+    if room.room_id() != state.room.room_id() {
+        return;
+    }
+    let Some(redacted) = event.content.redacts.as_ref().or(event.redacts.as_ref()) else {
+        return;
+    };
+
+    if let Err(e) = handle_redaction(&state, redacted.as_str()).await {
+        error!(%redacted, "Error handling redaction: {e:#}");
+    }
+}
+
+async fn handle_redaction(state: &AppState, redacted: &str) -> Result<()> {
+    if votes::retract(state, redacted).await? {
+        return Ok(());
+    }
+    let Some(issue_event) = db::get_issue_event_by_matrix_event_id(&state.db, redacted).await?
+    else {
+        return Ok(());
+    };
+    let issue_id = issue_event.issue_id;
+    let Some(details) = db::get_issue_details(&state.db, issue_id).await? else {
+        return Ok(());
+    };
+
+    warn!(issue_id, %redacted, "Issue card was redacted, re-posting it");
+
+    let reporter = webhook::user_mention(state, &details.reported_by).await?;
+    let priority = state.settings.get().priorities.of_issue(details.category);
+    let media = webhook::issue_media_details(state, issue_id).await;
+    let markdown = format!(
+        "{}\n\n_Re-posted, the original message was removed._",
+        webhook::issue_card(
+            &details,
+            &reporter,
+            priority,
+            media.as_ref(),
+            state.seerr_client.issue_url(issue_id).as_deref(),
+        )
+    );
+    // No new @room for a card everyone was already notified of
+    let (plain, html) = priority.render(&markdown);
+    let event_id = matrix::send_html_message(&state.room, &plain, &html).await?;
+    db::update_issue_event_id(&state.db, issue_id, event_id.as_str()).await?;
+    info!(issue_id, %event_id, "Issue card re-posted");
+
+    // Reactions were on the redacted card, put the current one on the new card
+    let current = db::get_issue_status(&state.db, issue_id).await?;
+    db::clear_issue_reactions(&state.db, issue_id).await?;
+    if let Some(current) = current {
+        reactions::transition(
+            &state.room,
+            &state.db,
+            &state.settings.get().reaction_emojis,
+            issue_id,
+            &event_id,
+            current,
+        )
+        .await?;
+    }
+
+    webhook::refresh_dashboard(state).await;
+    Ok(())
+}
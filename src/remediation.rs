@@ -0,0 +1,342 @@
+use anyhow::Result;
+use sqlx::PgPool;
+
+use crate::audit;
+use crate::markdown;
+use crate::radarr_client::RadarrClient;
+use crate::seerr::SeerrIssue;
+use crate::sonarr_client::{Episode, SonarrClient};
+
+/// Episodes a TV issue is about, as reported in Seerr.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EpisodeScope {
+    Series,
+    Season(i64),
+    Episode { season: i64, episode: i64 },
+}
+
+impl EpisodeScope {
+    pub fn of(issue: &SeerrIssue) -> Self {
+        Self::from_numbers(issue.problem_season, issue.problem_episode)
+    }
+
+    /// From the season and episode stored with an issue, `None` for movies.
+    pub fn from_columns(season: Option<i64>, episode: Option<i64>) -> Option<Self> {
+        season.map(|season| Self::from_numbers(season, episode.unwrap_or(0)))
+    }
+
+    /// Season and episode as Seerr numbers them, 0 meaning all of them.
+    pub fn numbers(&self) -> (i64, i64) {
+        match *self {
+            EpisodeScope::Series => (0, 0),
+            EpisodeScope::Season(season) => (season, 0),
+            EpisodeScope::Episode { season, episode } => (season, episode),
+        }
+    }
+
+    fn from_numbers(season: i64, episode: i64) -> Self {
+        match (season, episode) {
+            (0, _) => EpisodeScope::Series,
+            (season, 0) => EpisodeScope::Season(season),
+            (season, episode) => EpisodeScope::Episode { season, episode },
+        }
+    }
+
+    /// Appended to the title of the media in messages, e.g. `The Expanse
+    /// S02E05`. Nothing for the whole series.
+    pub fn subject_suffix(&self) -> String {
+        match self {
+            EpisodeScope::Series => String::new(),
+            scope => format!(" {}", scope.label()),
+        }
+    }
+
+    fn season(&self) -> Option<i64> {
+        match self {
+            EpisodeScope::Series => None,
+            EpisodeScope::Season(season) | EpisodeScope::Episode { season, .. } => Some(*season),
+        }
+    }
+
+    fn contains(&self, episode: &Episode) -> bool {
+        match self {
+            EpisodeScope::Series => true,
+            EpisodeScope::Season(season) => episode.season_number == *season,
+            EpisodeScope::Episode {
+                season,
+                episode: number,
+            } => episode.season_number == *season && episode.episode_number == *number,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            EpisodeScope::Series => "(all seasons)".to_string(),
+            EpisodeScope::Season(season) => format!("season {season}"),
+            EpisodeScope::Episode { season, episode } => format!("S{season:02}E{episode:02}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SonarrAction {
+    Search,
+    /// Deletes the files of the episodes and searches for new ones.
+    Redownload,
+}
+
+/// Runs `action` in Sonarr for the episodes the issue is about, returns the
+/// reply for the issue thread.
+pub async fn sonarr(
+    db: &PgPool,
+    sonarr: &SonarrClient,
+    sender: &str,
+    issue_id: i64,
+    issue: &SeerrIssue,
+    action: SonarrAction,
+) -> Result<String> {
+    let Some(media) = issue.media.as_ref() else {
+        return Ok("**⚠️ The issue has no media in Seerr**".to_string());
+    };
+    if media.media_type.as_deref() != Some("tv") {
+        return Ok("**⚠️ The issue is not about a series, try `!radarr`**".to_string());
+    }
+    let Some(tvdb_id) = media.tvdb_id else {
+        return Ok("**⚠️ Seerr has no TVDB id for this series**".to_string());
+    };
+    let Some(series) = sonarr.series_by_tvdb_id(tvdb_id).await? else {
+        return Ok(format!("**⚠️ No series with TVDB id {tvdb_id} in Sonarr**"));
+    };
+
+    let scope = EpisodeScope::of(issue);
+    let target = format!("{} {}", series.title, scope.label());
+    // The audit log keeps the title as is
+    let shown = markdown::escape(&target);
+    let episodes: Vec<Episode> = match (&scope, action) {
+        (EpisodeScope::Series, SonarrAction::Search) => Vec::new(),
+        (EpisodeScope::Series, SonarrAction::Redownload) => {
+            return Ok(
+                "**⚠️ The issue is about the whole series, only `!sonarr search` is available**"
+                    .to_string(),
+            );
+        }
+        _ => sonarr
+            .episodes(series.id, scope.season())
+            .await?
+            .into_iter()
+            .filter(|episode| scope.contains(episode))
+            .collect(),
+    };
+
+    if scope != EpisodeScope::Series && episodes.is_empty() {
+        return Ok(format!("**⚠️ No episodes of {shown} in Sonarr**"));
+    }
+
+    let mut deleted = 0;
+    if action == SonarrAction::Redownload {
+        let mut file_ids: Vec<i64> = episodes
+            .iter()
+            .map(|e| e.episode_file_id)
+            .filter(|id| *id != 0)
+            .collect();
+        file_ids.sort_unstable();
+        file_ids.dedup();
+        for file_id in file_ids {
+            let result = sonarr.delete_episode_file(file_id).await;
+            let details = format!("{target}, file {file_id}");
+            audit::record(
+                db,
+                sender,
+                "sonarr.delete_episode_file",
+                Some(issue_id),
+                Some(&details),
+                &result,
+            )
+            .await;
+            result?;
+            deleted += 1;
+        }
+    }
+
+    let result = match scope {
+        EpisodeScope::Series => sonarr.search_series(series.id).await,
+        EpisodeScope::Season(season) if action == SonarrAction::Search => {
+            sonarr.search_season(series.id, season).await
+        }
+        _ => {
+            let ids: Vec<i64> = episodes.iter().map(|e| e.id).collect();
+            sonarr.search_episodes(&ids).await
+        }
+    };
+    audit::record(
+        db,
+        sender,
+        "sonarr.search",
+        Some(issue_id),
+        Some(&target),
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(match action {
+        SonarrAction::Search => format!("**🔍 Sonarr search started for {shown}**"),
+        SonarrAction::Redownload => {
+            format!("**♻️ Deleted {deleted} file(s) of {shown}, Sonarr search started**")
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RadarrAction {
+    Search,
+    /// Deletes the movie file, only if its quality matches when given, and
+    /// searches for a new one.
+    Delete {
+        quality: Option<String>,
+    },
+}
+
+/// Whether `quality` is a part of the file's quality name, so `720p` matches
+/// `WEBDL-720p`.
+fn quality_matches(file_quality: &str, quality: &str) -> bool {
+    file_quality
+        .to_lowercase()
+        .contains(&quality.to_lowercase())
+}
+
+/// Runs `action` in Radarr for the movie of the issue, returns the reply for
+/// the issue thread.
+pub async fn radarr(
+    db: &PgPool,
+    radarr: &RadarrClient,
+    sender: &str,
+    issue_id: i64,
+    issue: &SeerrIssue,
+    action: &RadarrAction,
+) -> Result<String> {
+    let Some(media) = issue.media.as_ref() else {
+        return Ok("**⚠️ The issue has no media in Seerr**".to_string());
+    };
+    if media.media_type.as_deref() != Some("movie") {
+        return Ok("**⚠️ The issue is not about a movie, try `!sonarr`**".to_string());
+    }
+    let Some(tmdb_id) = media.tmdb_id else {
+        return Ok("**⚠️ Seerr has no TMDB id for this movie**".to_string());
+    };
+    let Some(movie) = radarr.movie_by_tmdb_id(tmdb_id).await? else {
+        return Ok(format!("**⚠️ No movie with TMDB id {tmdb_id} in Radarr**"));
+    };
+
+    let title = markdown::escape(&movie.title);
+    let mut deleted = None;
+    if let RadarrAction::Delete { quality } = action {
+        let Some(file) = &movie.movie_file else {
+            return Ok(format!("**⚠️ {title} has no file in Radarr**"));
+        };
+        if let Some(quality) = quality
+            && !quality_matches(file.quality_name(), quality)
+        {
+            return Ok(format!(
+                "**⚠️ The file of {title} is {}, not {}, nothing deleted**",
+                markdown::escape(file.quality_name()),
+                markdown::escape(quality)
+            ));
+        }
+        let result = radarr.delete_movie_file(file.id).await;
+        let details = format!("{}, {} file {}", movie.title, file.quality_name(), file.id);
+        audit::record(
+            db,
+            sender,
+            "radarr.delete_movie_file",
+            Some(issue_id),
+            Some(&details),
+            &result,
+        )
+        .await;
+        result?;
+        deleted = Some(file.quality_name());
+    }
+
+    let result = radarr.search_movie(movie.id).await;
+    audit::record(
+        db,
+        sender,
+        "radarr.search",
+        Some(issue_id),
+        Some(&movie.title),
+        &result,
+    )
+    .await;
+    result?;
+
+    Ok(match deleted {
+        None => format!("**🔍 Radarr search started for {title}**"),
+        Some(quality) => format!(
+            "**♻️ Deleted the {} file of {title}, Radarr search started**",
+            markdown::escape(quality)
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(problem_season: i64, problem_episode: i64) -> SeerrIssue {
+        serde_json::from_value(serde_json::json!({
+            "problemSeason": problem_season,
+            "problemEpisode": problem_episode,
+        }))
+        .unwrap()
+    }
+
+    fn episode(season_number: i64, episode_number: i64) -> Episode {
+        serde_json::from_value(serde_json::json!({
+            "seasonNumber": season_number,
+            "episodeNumber": episode_number,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn quality_matches_part_of_the_name() {
+        assert!(quality_matches("WEBDL-720p", "720p"));
+        assert!(quality_matches("Bluray-1080p", "bluray"));
+        assert!(!quality_matches("Bluray-1080p", "2160p"));
+    }
+
+    #[test]
+    fn scope_from_stored_columns() {
+        assert_eq!(EpisodeScope::from_columns(None, None), None);
+        assert_eq!(
+            EpisodeScope::from_columns(Some(2), Some(0)),
+            Some(EpisodeScope::Season(2))
+        );
+        let scope = EpisodeScope::Episode {
+            season: 2,
+            episode: 5,
+        };
+        assert_eq!(scope.numbers(), (2, 5));
+        assert_eq!(scope.subject_suffix(), " S02E05");
+        assert_eq!(EpisodeScope::Series.subject_suffix(), "");
+    }
+
+    #[test]
+    fn scope_follows_the_seerr_issue() {
+        assert_eq!(EpisodeScope::of(&issue(0, 0)), EpisodeScope::Series);
+        assert_eq!(EpisodeScope::of(&issue(2, 0)), EpisodeScope::Season(2));
+        let scope = EpisodeScope::of(&issue(2, 5));
+        assert_eq!(
+            scope,
+            EpisodeScope::Episode {
+                season: 2,
+                episode: 5
+            }
+        );
+        assert_eq!(scope.label(), "S02E05");
+        assert!(scope.contains(&episode(2, 5)));
+        assert!(!scope.contains(&episode(2, 6)));
+        assert!(EpisodeScope::Season(2).contains(&episode(2, 6)));
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use matrix_sdk::ruma::OwnedEventId;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db::{self, UserReminder};
+use crate::matrix;
+use crate::time_format::TimeFormat;
+
+/// Parses a delay like `30m`, `4h`, `3d` or `2w`.
+pub fn parse_delay(delay: &str) -> Option<Duration> {
+    let unit = delay.chars().last()?;
+    let amount: i64 = delay[..delay.len() - unit.len_utf8()].parse().ok()?;
+    if amount <= 0 {
+        return None;
+    }
+    match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+fn render_reminder(reminder: &UserReminder) -> String {
+    format!(
+        "**⏰ Reminder for [{author}](https://matrix.to/#/{author}):** {}",
+        reminder.message,
+        author = reminder.author,
+    )
+}
+
+pub fn render_list(reminders: &[UserReminder], time_format: &TimeFormat) -> String {
+    if reminders.is_empty() {
+        return "**⏰ Reminders**  \nNo reminders pending, use `!remind 3d check subtitles`"
+            .to_string();
+    }
+
+    let mut markdown = format!("**⏰ Reminders ({})**\n", reminders.len());
+    for reminder in reminders {
+        markdown.push_str(&format!(
+            "- #{} {} by {}: {}\n",
+            reminder.id,
+            time_format.datetime(reminder.due_at),
+            reminder.author,
+            reminder.message
+        ));
+    }
+    markdown
+}
+
+/// Posts the reminders that are due in the thread they were set in.
+pub async fn send_due_reminders(state: &AppState) -> Result<()> {
+    let room_id = state.room.room_id().to_string();
+    for reminder in db::list_pending_user_reminders(&state.db, &room_id, Some(Utc::now())).await? {
+        let root: OwnedEventId = match reminder.thread_root_event_id.as_str().try_into() {
+            Ok(root) => root,
+            Err(e) => {
+                warn!(reminder_id = reminder.id, "Invalid reminder thread: {e}");
+                db::mark_user_reminder_sent(&state.db, reminder.id).await?;
+                continue;
+            }
+        };
+        matrix::send_thread_markdown(&state.room, &root, &render_reminder(&reminder)).await?;
+        db::mark_user_reminder_sent(&state.db, reminder.id).await?;
+        info!(reminder_id = reminder.id, author = %reminder.author, "Reminder sent");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_delays() {
+        assert_eq!(parse_delay("30m"), Some(Duration::minutes(30)));
+        assert_eq!(parse_delay("4h"), Some(Duration::hours(4)));
+        assert_eq!(parse_delay("3d"), Some(Duration::days(3)));
+        assert_eq!(parse_delay("2w"), Some(Duration::weeks(2)));
+        assert_eq!(parse_delay("0d"), None);
+        assert_eq!(parse_delay("3"), None);
+        assert_eq!(parse_delay("d"), None);
+        assert_eq!(parse_delay("3y"), None);
+        assert_eq!(parse_delay("tomorrow"), None);
+    }
+
+    #[test]
+    fn reminder_mentions_author() {
+        let reminder = UserReminder {
+            id: 3,
+            thread_root_event_id: "$root".to_string(),
+            author: "@alice:localhost".to_string(),
+            message: "check subtitles on issue 42".to_string(),
+            due_at: Utc.with_ymd_and_hms(2026, 3, 4, 12, 0, 0).unwrap(),
+        };
+        assert_eq!(
+            render_reminder(&reminder),
+            "**⏰ Reminder for [@alice:localhost](https://matrix.to/#/@alice:localhost):** check subtitles on issue 42"
+        );
+        assert!(
+            render_list(&[reminder], &TimeFormat::default())
+                .contains("- #3 2026-03-04 12:00 by @alice:localhost: check subtitles on issue 42")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use matrix_sdk::ruma::OwnedEventId;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db::{self, StaleIssue};
+use crate::matrix;
+use crate::remediation::EpisodeScope;
+use crate::stats::format_duration;
+
+/// Reminders double their interval up to this many times.
+const MAX_BACKOFF_STEPS: u32 = 5;
+
+/// Whether `issue` should get a reminder: once it has been inactive for
+/// `after`, then again after `after`, `2 × after`, `4 × after`, ... since the
+/// previous reminder.
+fn reminder_due(issue: &StaleIssue, now: DateTime<Utc>, after: Duration) -> bool {
+    let after = chrono::Duration::from_std(after).unwrap_or(chrono::Duration::MAX);
+    match issue.reminded_at {
+        Some(reminded_at) if issue.reminder_count > 0 => {
+            let steps = (issue.reminder_count as u32 - 1).min(MAX_BACKOFF_STEPS);
+            now - reminded_at >= after * 2i32.pow(steps)
+        }
+        _ => now - issue.last_activity_at >= after,
+    }
+}
+
+fn render_reminder(issue: &StaleIssue, now: DateTime<Utc>, mentions: &[String]) -> String {
+    let episodes = match issue.episodes {
+        Some(EpisodeScope::Series) | None => String::new(),
+        Some(episodes) => format!(" ({})", episodes.label()),
+    };
+    let mut markdown = format!(
+        "**⏰ No activity on this issue{episodes} for {}**",
+        format_duration(now - issue.last_activity_at)
+    );
+    if !mentions.is_empty() {
+        markdown.push_str(&format!("  \n{}", mentions.join(", ")));
+    }
+    markdown.push_str("  \nReply `!issues mute` to stop these reminders.");
+    markdown
+}
+
+/// Nudges the admins in the thread of every issue left without activity for
+/// longer than `after`.
+pub async fn send_stale_reminders(state: &AppState, after: Duration) -> Result<()> {
+    let now = Utc::now();
+    let before = now - chrono::Duration::from_std(after)?;
+    let mentions: Vec<String> = state
+        .settings
+        .get()
+        .admin_users
+        .iter()
+        .map(|user_id| format!("[{user_id}](https://matrix.to/#/{user_id})"))
+        .collect();
+
+    for issue in db::list_stale_issues(&state.db, before).await? {
+        if !reminder_due(&issue, now, after) {
+            continue;
+        }
+        let root: OwnedEventId = match issue.matrix_event_id.as_str().try_into() {
+            Ok(root) => root,
+            Err(e) => {
+                warn!(issue_id = issue.issue_id, "Invalid issue event id: {e}");
+                continue;
+            }
+        };
+        let markdown = render_reminder(&issue, now, &mentions);
+        matrix::send_thread_markdown(&state.room, &root, &markdown).await?;
+        db::record_reminder(&state.db, issue.issue_id).await?;
+        info!(
+            issue_id = issue.issue_id,
+            reminders = issue.reminder_count + 1,
+            "Reminded admins of a stale issue"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn stale(reminder_count: i32, reminded_at: Option<DateTime<Utc>>) -> StaleIssue {
+        StaleIssue {
+            issue_id: 1,
+            matrix_event_id: "$issue".to_string(),
+            subject: None,
+            episodes: None,
+            last_activity_at: Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap(),
+            reminded_at,
+            reminder_count,
+        }
+    }
+
+    #[test]
+    fn reminders_back_off() {
+        let after = Duration::from_secs(24 * 3600);
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let hours = chrono::Duration::hours;
+
+        assert!(!reminder_due(&stale(0, None), start + hours(23), after));
+        assert!(reminder_due(&stale(0, None), start + hours(24), after));
+
+        // First reminder sent at +24h, the next one a day later
+        let first = start + hours(24);
+        assert!(!reminder_due(
+            &stale(1, Some(first)),
+            first + hours(23),
+            after
+        ));
+        assert!(reminder_due(
+            &stale(1, Some(first)),
+            first + hours(24),
+            after
+        ));
+
+        // Then two days after the second one
+        let second = first + hours(24);
+        assert!(!reminder_due(
+            &stale(2, Some(second)),
+            second + hours(47),
+            after
+        ));
+        assert!(reminder_due(
+            &stale(2, Some(second)),
+            second + hours(48),
+            after
+        ));
+    }
+
+    #[test]
+    fn reminder_mentions_admins() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 4, 12, 0, 0).unwrap();
+        let markdown = render_reminder(
+            &stale(0, None),
+            now,
+            &["[@alice:localhost](https://matrix.to/#/@alice:localhost)".to_string()],
+        );
+        assert!(markdown.contains("No activity on this issue for 3 days"));
+        assert!(markdown.contains("https://matrix.to/#/@alice:localhost"));
+        assert!(markdown.contains("!issues mute"));
+    }
+
+    #[test]
+    fn reminder_names_episode() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 4, 12, 0, 0).unwrap();
+        let issue = StaleIssue {
+            episodes: Some(EpisodeScope::Episode {
+                season: 1,
+                episode: 4,
+            }),
+            ..stale(0, None)
+        };
+        let markdown = render_reminder(&issue, now, &[]);
+        assert!(markdown.contains("No activity on this issue (S01E04) for 3 days"));
+    }
+}
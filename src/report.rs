@@ -0,0 +1,392 @@
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::ruma::OwnedEventId;
+use matrix_sdk::ruma::events::room::message::OriginalSyncRoomMessageEvent;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::audit;
+use crate::commands::{self, CommandContext};
+use crate::db;
+use crate::issue::IssueCategory;
+use crate::markdown;
+use crate::matrix;
+use crate::remediation::EpisodeScope;
+use crate::seerr::SeerrSearchResult;
+use crate::webhook;
+
+/// How many search results the reporter picks from.
+const MAX_CANDIDATES: usize = 5;
+
+/// Media the reporter can pick.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Candidate {
+    /// Seerr id of the media, not the TMDB one.
+    media_id: i64,
+    media_type: String,
+    title: String,
+}
+
+impl Candidate {
+    /// Issues can only be reported on media Seerr tracks.
+    fn from_search(result: &SeerrSearchResult) -> Option<Self> {
+        if !matches!(result.media_type.as_str(), "movie" | "tv") {
+            return None;
+        }
+        let media_id = result.media_info.as_ref()?.id;
+        let title = result.title.clone().or_else(|| result.name.clone())?;
+        let year = result
+            .release_date
+            .as_deref()
+            .or(result.first_air_date.as_deref())
+            .and_then(|date| date.get(..4));
+        Some(Self {
+            media_id,
+            media_type: result.media_type.clone(),
+            title: match year {
+                Some(year) => format!("{title} ({year})"),
+                None => title,
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Step {
+    Media,
+    Category,
+    Episode,
+    Description,
+}
+
+/// Answers collected so far in a `!report` thread.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Draft {
+    step: Step,
+    candidates: Vec<Candidate>,
+    media: Option<Candidate>,
+    category: Option<IssueCategory>,
+    /// Season and episode of a TV issue, 0 for all of them.
+    episodes: Option<(i64, i64)>,
+}
+
+enum Progress {
+    Next,
+    Invalid,
+    /// All questions answered, the last answer being the description.
+    Complete(String),
+}
+
+impl Draft {
+    fn new(candidates: Vec<Candidate>) -> Self {
+        let mut draft = Self {
+            step: Step::Media,
+            candidates,
+            media: None,
+            category: None,
+            episodes: None,
+        };
+        // Nothing to pick from
+        if let [only] = draft.candidates.as_slice() {
+            draft.media = Some(only.clone());
+            draft.step = Step::Category;
+        }
+        draft
+    }
+
+    fn title(&self) -> &str {
+        self.media
+            .as_ref()
+            .map_or("it", |media| media.title.as_str())
+    }
+
+    /// What the bot asks at this step.
+    fn question(&self) -> String {
+        match self.step {
+            Step::Media => {
+                let mut question =
+                    "**Which one is it?** Reply with its number, or `cancel`\n".to_string();
+                for (n, candidate) in self.candidates.iter().enumerate() {
+                    question.push_str(&format!(
+                        "\n{}. {}",
+                        n + 1,
+                        markdown::escape(&candidate.title)
+                    ));
+                }
+                question
+            }
+            Step::Category => format!(
+                "**What's wrong with {}?** Reply `video`, `audio`, `subtitles` or `other`",
+                markdown::escape(self.title())
+            ),
+            Step::Episode => {
+                "**Which episode?** Reply e.g. `S01E04`, `S01` for a whole season, or `all`"
+                    .to_string()
+            }
+            Step::Description => "**Describe the problem** in one message".to_string(),
+        }
+    }
+
+    fn advance(&mut self, reply: &str) -> Progress {
+        let reply = reply.trim();
+        match self.step {
+            Step::Media => {
+                let picked = reply
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|i| self.candidates.get(i));
+                let Some(media) = picked else {
+                    return Progress::Invalid;
+                };
+                self.media = Some(media.clone());
+                self.step = Step::Category;
+            }
+            Step::Category => {
+                let Some(category) = IssueCategory::parse(reply) else {
+                    return Progress::Invalid;
+                };
+                self.category = Some(category);
+                let is_tv = self.media.as_ref().is_some_and(|m| m.media_type == "tv");
+                self.step = if is_tv {
+                    Step::Episode
+                } else {
+                    Step::Description
+                };
+            }
+            Step::Episode => {
+                let Some(episodes) = parse_episodes(reply) else {
+                    return Progress::Invalid;
+                };
+                self.episodes = Some(episodes);
+                self.step = Step::Description;
+            }
+            Step::Description => return Progress::Complete(reply.to_string()),
+        }
+        Progress::Next
+    }
+}
+
+/// `S01E04`, `S01` for a whole season, or `all`.
+fn parse_episodes(reply: &str) -> Option<(i64, i64)> {
+    let reply = reply.trim().to_lowercase();
+    if reply == "all" {
+        return Some((0, 0));
+    }
+    let rest = reply.strip_prefix('s')?;
+    match rest.split_once('e') {
+        Some((season, episode)) => Some((season.parse().ok()?, episode.parse().ok()?)),
+        None => Some((rest.parse().ok()?, 0)),
+    }
+}
+
+/// `!report <title>`, the command anyone in the room can use.
+pub fn parse(body: &str) -> Option<&str> {
+    let title = body.trim().strip_prefix("!report ")?.trim();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Starts a report in the thread of the `!report` message, with the media
+/// Seerr found for `query` to pick from.
+pub async fn start(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    query: &str,
+) -> Result<()> {
+    let root = &event.event_id;
+    let candidates: Vec<Candidate> = ctx
+        .seerr_client
+        .search(query)
+        .await?
+        .iter()
+        .filter_map(Candidate::from_search)
+        .take(MAX_CANDIDATES)
+        .collect();
+    if candidates.is_empty() {
+        let markdown = format!(
+            "Nothing matching \"{}\" is in Seerr, check the title and `!report` again",
+            markdown::escape(query)
+        );
+        matrix::send_thread_markdown(room, root, &markdown).await?;
+        return Ok(());
+    }
+
+    let draft = Draft::new(candidates);
+    save(ctx, root, event.sender.as_str(), &draft).await?;
+    matrix::send_thread_markdown(room, root, &draft.question()).await?;
+    info!(query, "Report started");
+    Ok(())
+}
+
+/// Takes the reporter's reply in a report thread. Returns `false` when the
+/// message is not an answer to a report, for other handlers to look at.
+pub async fn answer(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    root: &OwnedEventId,
+) -> Result<bool> {
+    let Some(report) = db::get_issue_report(&ctx.db, root.as_str()).await? else {
+        return Ok(false);
+    };
+    if report.reporter != event.sender.as_str() {
+        return Ok(false);
+    }
+    let reply = event.content.body().trim();
+    if reply.starts_with('!') {
+        return Ok(false);
+    }
+    if reply.eq_ignore_ascii_case("cancel") {
+        db::delete_issue_report(&ctx.db, root.as_str()).await?;
+        matrix::send_thread_markdown(room, root, "Report cancelled").await?;
+        return Ok(true);
+    }
+
+    let mut draft: Draft = serde_json::from_str(&report.draft)?;
+    match draft.advance(reply) {
+        Progress::Next => {
+            save(ctx, root, &report.reporter, &draft).await?;
+            matrix::send_thread_markdown(room, root, &draft.question()).await?;
+        }
+        Progress::Invalid => {
+            let markdown = format!("Sorry, I didn't get that. {}", draft.question());
+            matrix::send_thread_markdown(room, root, &markdown).await?;
+        }
+        Progress::Complete(description) => {
+            file(ctx, event, room, root, &draft, &description).await?;
+        }
+    }
+    Ok(true)
+}
+
+async fn save(
+    ctx: &CommandContext,
+    root: &OwnedEventId,
+    reporter: &str,
+    draft: &Draft,
+) -> Result<()> {
+    let draft = serde_json::to_string(draft)?;
+    db::save_issue_report(&ctx.db, root.as_str(), reporter, &draft).await
+}
+
+/// Files the issue in Seerr, whose webhook then posts its card as usual.
+async fn file(
+    ctx: &CommandContext,
+    event: &OriginalSyncRoomMessageEvent,
+    room: &Room,
+    root: &OwnedEventId,
+    draft: &Draft,
+    description: &str,
+) -> Result<()> {
+    let (Some(media), Some(category)) = (&draft.media, draft.category) else {
+        anyhow::bail!("Report completed without media or category");
+    };
+    let sender = event.sender.as_str();
+    let seerr_user = db::get_user_mapping(&ctx.db, sender)
+        .await?
+        .map(|mapping| mapping.seerr_user);
+    let message = commands::attributed_comment(description, sender, seerr_user.as_deref());
+    let episodes = draft.episodes.unwrap_or((0, 0));
+    let result = ctx
+        .seerr_client
+        .create_issue(media.media_id, category, &message, episodes)
+        .await;
+    audit::record(
+        &ctx.db,
+        sender,
+        "seerr.create_issue",
+        result.as_ref().ok().copied(),
+        Some(&media.title),
+        &result,
+    )
+    .await;
+    let issue_id = result?;
+    db::delete_issue_report(&ctx.db, root.as_str()).await?;
+    info!(issue_id, "Issue reported from Matrix");
+
+    let subject = webhook::issue_subject(
+        &media.title,
+        draft
+            .episodes
+            .and_then(|(season, episode)| EpisodeScope::from_columns(Some(season), Some(episode))),
+    );
+    let markdown = format!(
+        "**✅ Issue {issue_id} filed** about {}, thanks! Its card follows in the room{}",
+        markdown::escape(&subject),
+        webhook::seerr_link(ctx.seerr_client.issue_url(issue_id).as_deref())
+    );
+    matrix::send_thread_markdown(room, root, &markdown).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(media_id: i64, media_type: &str, title: &str) -> Candidate {
+        Candidate {
+            media_id,
+            media_type: media_type.to_string(),
+            title: title.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_report_command() {
+        assert_eq!(parse("!report The Expanse "), Some("The Expanse"));
+        assert_eq!(parse("!report  "), None);
+        assert_eq!(parse("!reports"), None);
+    }
+
+    #[test]
+    fn parse_episode_answers() {
+        assert_eq!(parse_episodes("S01E04"), Some((1, 4)));
+        assert_eq!(parse_episodes("s2"), Some((2, 0)));
+        assert_eq!(parse_episodes("All"), Some((0, 0)));
+        assert_eq!(parse_episodes("episode 4"), None);
+    }
+
+    #[test]
+    fn only_tracked_media_can_be_picked() {
+        let results: Vec<SeerrSearchResult> = serde_json::from_str(
+            r#"[{"id": 1, "mediaType": "movie", "title": "Dune", "releaseDate": "2021-09-15",
+                 "mediaInfo": {"id": 12}},
+                {"id": 2, "mediaType": "movie", "title": "Dune", "releaseDate": "1984-12-14"},
+                {"id": 3, "mediaType": "person", "name": "Frank Herbert", "mediaInfo": {"id": 13}}]"#,
+        )
+        .unwrap();
+        let candidates: Vec<Candidate> =
+            results.iter().filter_map(Candidate::from_search).collect();
+        assert_eq!(candidates, vec![candidate(12, "movie", "Dune (2021)")]);
+    }
+
+    #[test]
+    fn tv_reports_ask_for_the_episode() {
+        let mut draft = Draft::new(vec![
+            candidate(12, "movie", "Dune (2021)"),
+            candidate(34, "tv", "The Expanse (2015)"),
+        ]);
+        assert_eq!(draft.step, Step::Media);
+        assert!(matches!(draft.advance("3"), Progress::Invalid));
+        assert!(matches!(draft.advance("2"), Progress::Next));
+        assert!(matches!(draft.advance("audio"), Progress::Next));
+        assert_eq!(draft.step, Step::Episode);
+        assert!(matches!(draft.advance("S02E05"), Progress::Next));
+        assert!(matches!(
+            draft.advance("No sound after 10 minutes"),
+            Progress::Complete(description) if description == "No sound after 10 minutes"
+        ));
+        assert_eq!(draft.category, Some(IssueCategory::Audio));
+        assert_eq!(draft.episodes, Some((2, 5)));
+    }
+
+    #[test]
+    fn single_match_skips_the_choice() {
+        let mut draft = Draft::new(vec![candidate(12, "movie", "Dune (2021)")]);
+        assert_eq!(draft.step, Step::Category);
+        assert!(matches!(draft.advance("video"), Progress::Next));
+        assert_eq!(draft.step, Step::Description);
+    }
+}
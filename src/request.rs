@@ -0,0 +1,72 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RequestStatus {
+    Pending,
+    Approved,
+    Declined,
+    Available,
+    Failed,
+}
+
+impl RequestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "pending",
+            RequestStatus::Approved => "approved",
+            RequestStatus::Declined => "declined",
+            RequestStatus::Available => "available",
+            RequestStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "pending" => Some(RequestStatus::Pending),
+            "approved" => Some(RequestStatus::Approved),
+            "declined" => Some(RequestStatus::Declined),
+            "available" => Some(RequestStatus::Available),
+            "failed" => Some(RequestStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// Status a Seerr media notification moves a request to.
+    pub fn from_notification_type(notification_type: &str) -> Option<Self> {
+        match notification_type {
+            "MEDIA_PENDING" => Some(RequestStatus::Pending),
+            "MEDIA_APPROVED" | "MEDIA_AUTO_APPROVED" => Some(RequestStatus::Approved),
+            "MEDIA_DECLINED" => Some(RequestStatus::Declined),
+            "MEDIA_AVAILABLE" => Some(RequestStatus::Available),
+            "MEDIA_FAILED" => Some(RequestStatus::Failed),
+            _ => None,
+        }
+    }
+
+    /// The Seerr notification announcing a move to this status.
+    pub fn notification_type(&self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "MEDIA_PENDING",
+            RequestStatus::Approved => "MEDIA_APPROVED",
+            RequestStatus::Declined => "MEDIA_DECLINED",
+            RequestStatus::Available => "MEDIA_AVAILABLE",
+            RequestStatus::Failed => "MEDIA_FAILED",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RequestStatus::Pending => "⏳ Waiting for approval",
+            RequestStatus::Approved => "👍 Request approved",
+            RequestStatus::Declined => "🚫 Request declined",
+            RequestStatus::Available => "🎉 Now available",
+            RequestStatus::Failed => "⚠️ Request failed",
+        }
+    }
+}
+
+impl fmt::Display for RequestStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::Result;
+use matrix_sdk::Room;
+use matrix_sdk::deserialized_responses::RawAnySyncOrStrippedState;
+use matrix_sdk::ruma::events::StateEventType;
+use serde::Deserialize;
+
+/// Custom state event room admins can set to tune what the bot posts, e.g.
+/// `{"notification_types": ["ISSUE_CREATED", "ISSUE_RESOLVED"]}`.
+pub const STATE_EVENT_TYPE: &str = "io.michel_bot.config";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RoomConfig {
+    /// Notification types posted in the room, all of them when unset.
+    #[serde(default)]
+    pub notification_types: Option<Vec<String>>,
+    /// Whether the weekly calendar of upcoming releases is posted.
+    #[serde(default)]
+    pub calendar: bool,
+    /// Whether the weekly list of media that became available is posted.
+    #[serde(default)]
+    pub whats_new: bool,
+    /// Language Seerr comments are translated into, overriding
+    /// `TRANSLATION_LANGUAGE`.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl RoomConfig {
+    pub fn allows(&self, notification_type: &str) -> bool {
+        self.notification_types
+            .as_ref()
+            .is_none_or(|types| types.iter().any(|t| t == notification_type))
+    }
+}
+
+/// Reads the room's config from the state store, which sync keeps up to date
+/// so changes apply to the next notification without a restart. `defaults`
+/// applies while the room has no config state event.
+pub async fn load(room: &Room, defaults: &RoomConfig) -> Result<RoomConfig> {
+    let event = room
+        .get_state_event(StateEventType::from(STATE_EVENT_TYPE), "")
+        .await?;
+    let Some(RawAnySyncOrStrippedState::Sync(raw)) = event else {
+        return Ok(defaults.clone());
+    };
+    Ok(raw.get_field("content")?.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_everything_when_unset() {
+        let config: RoomConfig = serde_json::from_str("{}").unwrap();
+        assert!(config.allows("ISSUE_CREATED"));
+        assert!(config.allows("ISSUE_COMMENT"));
+    }
+
+    #[test]
+    fn allows_only_listed_types() {
+        let config: RoomConfig =
+            serde_json::from_str(r#"{"notification_types": ["ISSUE_CREATED"]}"#).unwrap();
+        assert!(config.allows("ISSUE_CREATED"));
+        assert!(!config.allows("ISSUE_COMMENT"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use anyhow::Result;
+use sqlx::PgPool;
+
+use crate::db;
+use crate::request::RequestStatus;
+
+/// Notifications a room can take or leave with `!bot route`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Seerr issue cards and their follow-ups.
+    Issues,
+    /// Seerr request cards and their follow-ups.
+    Requests,
+    /// Sonarr imports, grabs and health tickets.
+    Sonarr,
+    /// Radarr imports, grabs and health tickets.
+    Radarr,
+}
+
+impl Stream {
+    pub const ALL: [Stream; 4] = [
+        Stream::Issues,
+        Stream::Requests,
+        Stream::Sonarr,
+        Stream::Radarr,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|stream| stream.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Issues => "issues",
+            Stream::Requests => "requests",
+            Stream::Sonarr => "sonarr",
+            Stream::Radarr => "radarr",
+        }
+    }
+
+    /// Stream of a Seerr notification, `None` for e.g. test notifications.
+    pub fn of_notification(notification_type: &str) -> Option<Self> {
+        if notification_type.starts_with("ISSUE_") {
+            return Some(Stream::Issues);
+        }
+        RequestStatus::from_notification_type(notification_type).map(|_| Stream::Requests)
+    }
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Whether `!bot route` turned the stream on or off in the room, `None` when
+/// it didn't and the room config (or, for Sonarr and Radarr, nothing) decides.
+pub async fn route(pool: &PgPool, room_id: &str, stream: Stream) -> Result<Option<bool>> {
+    db::get_room_route(pool, room_id, stream.name()).await
+}
+
+/// `!bot route` without arguments: each stream and whether it was routed.
+pub fn render(routes: &[(String, bool)]) -> String {
+    let mut markdown = "**🔀 Notification streams in this room**\n".to_string();
+    for stream in Stream::ALL {
+        let route = routes
+            .iter()
+            .find(|(name, _)| name == stream.name())
+            .map(|(_, enabled)| *enabled);
+        let line = match route {
+            Some(true) => format!("- {stream}: on\n"),
+            Some(false) => format!("- {stream}: off\n"),
+            None => format!("- {stream}: default\n"),
+        };
+        markdown.push_str(&line);
+    }
+    markdown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifications_belong_to_streams() {
+        assert_eq!(
+            Stream::of_notification("ISSUE_COMMENT"),
+            Some(Stream::Issues)
+        );
+        assert_eq!(
+            Stream::of_notification("MEDIA_AUTO_APPROVED"),
+            Some(Stream::Requests)
+        );
+        assert_eq!(Stream::of_notification("TEST_NOTIFICATION"), None);
+        assert_eq!(Stream::parse("Sonarr"), Some(Stream::Sonarr));
+        assert_eq!(Stream::parse("lidarr"), None);
+    }
+
+    #[test]
+    fn render_shows_routed_streams() {
+        let routes = [("sonarr".to_string(), false), ("issues".to_string(), true)];
+        assert_eq!(
+            render(&routes),
+            "**🔀 Notification streams in this room**\n\
+             - issues: on\n\
+             - requests: default\n\
+             - sonarr: off\n\
+             - radarr: default\n"
+        );
+    }
+}
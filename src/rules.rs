@@ -0,0 +1,294 @@
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedEventId;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::AppState;
+use crate::audit;
+use crate::db;
+use crate::markdown;
+use crate::matrix;
+use crate::quota;
+use crate::request::RequestStatus;
+use crate::seerr::SeerrWebhookPayload;
+
+/// Rough size of an hour of video, to estimate what a request takes on disk.
+const GB_PER_HOUR: f64 = 5.0;
+const GB_PER_HOUR_4K: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Approve,
+    Decline,
+}
+
+impl RuleAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RuleAction::Approve => "Auto-approved",
+            RuleAction::Decline => "Auto-declined",
+        }
+    }
+}
+
+/// `[[rules]]` entry of the config file, evaluated against new pending
+/// requests. Every condition it sets must hold for it to fire.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestRule {
+    pub name: String,
+    pub action: RuleAction,
+    /// `movie` or `tv`.
+    pub media_type: Option<String>,
+    /// Seerr display names or emails of the requesters it applies to.
+    #[serde(default)]
+    pub requested_by: Vec<String>,
+    pub is_4k: Option<bool>,
+    /// Fires for requests estimated to take more than this on disk.
+    pub min_estimated_gb: Option<f64>,
+}
+
+/// What the rules know about a pending request.
+#[derive(Debug, Default)]
+pub struct PendingRequest {
+    pub media_type: Option<String>,
+    /// Names and emails the requester goes by.
+    pub requested_by: Vec<String>,
+    pub is_4k: bool,
+    pub runtime_minutes: Option<i64>,
+}
+
+impl PendingRequest {
+    pub fn estimated_gb(&self) -> Option<f64> {
+        let per_hour = if self.is_4k {
+            GB_PER_HOUR_4K
+        } else {
+            GB_PER_HOUR
+        };
+        self.runtime_minutes
+            .map(|minutes| minutes as f64 / 60.0 * per_hour)
+    }
+}
+
+impl RequestRule {
+    /// Why the rule fires for `request`, `None` when it doesn't.
+    pub fn check(&self, request: &PendingRequest) -> Option<String> {
+        let mut reasons = Vec::new();
+        if let Some(media_type) = &self.media_type {
+            if request.media_type.as_deref() != Some(media_type.as_str()) {
+                return None;
+            }
+            reasons.push(match media_type.as_str() {
+                "tv" => "TV request".to_string(),
+                other => format!("{other} request"),
+            });
+        }
+        if !self.requested_by.is_empty() {
+            let requester = request.requested_by.iter().find(|name| {
+                self.requested_by
+                    .iter()
+                    .any(|wanted| wanted.eq_ignore_ascii_case(name))
+            })?;
+            reasons.push(format!("requested by {requester}"));
+        }
+        if let Some(is_4k) = self.is_4k {
+            if request.is_4k != is_4k {
+                return None;
+            }
+            reasons.push(if is_4k { "in 4K" } else { "not in 4K" }.to_string());
+        }
+        if let Some(min_gb) = self.min_estimated_gb {
+            let estimated_gb = request.estimated_gb().filter(|gb| *gb > min_gb)?;
+            reasons.push(format!("about {estimated_gb:.0} GB, over {min_gb} GB"));
+        }
+        Some(reasons.join(", "))
+    }
+
+    /// What is wrong with the rule as configured.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.media_type.is_none()
+            && self.requested_by.is_empty()
+            && self.is_4k.is_none()
+            && self.min_estimated_gb.is_none()
+        {
+            problems.push(format!(
+                "rules: {:?} has no condition and would match every request",
+                self.name
+            ));
+        }
+        if let Some(media_type) = &self.media_type
+            && !matches!(media_type.as_str(), "movie" | "tv")
+        {
+            problems.push(format!(
+                "rules: {:?} media_type must be movie or tv, got {media_type:?}",
+                self.name
+            ));
+        }
+        problems
+    }
+}
+
+/// The first rule firing for `request`, with why.
+pub fn evaluate<'a>(
+    rules: &'a [RequestRule],
+    request: &PendingRequest,
+) -> Option<(&'a RequestRule, String)> {
+    rules
+        .iter()
+        .find_map(|rule| Some((rule, rule.check(request)?)))
+}
+
+/// Runs the rules on a request that just came in pending, approving or
+/// declining it in Seerr and saying in its thread which rule fired and why.
+pub async fn apply(
+    state: &AppState,
+    request_id: i64,
+    root_event_id: &OwnedEventId,
+    payload: &SeerrWebhookPayload,
+) -> Result<()> {
+    let settings = state.settings.get();
+    let rules = &settings.request_rules;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let Some(request) = state.seerr_client.get_request(request_id).await? else {
+        return Ok(());
+    };
+
+    let media = request.media.as_ref();
+    let media_type = payload
+        .media_type
+        .clone()
+        .or_else(|| media.and_then(|m| m.media_type.clone()));
+    let mut requested_by: Vec<String> = payload.requested_by.iter().cloned().collect();
+    if let Some(user) = &request.requested_by {
+        requested_by.extend(user.display_name.iter().chain(&user.email).cloned());
+    }
+    // Only ask for the runtime when a rule needs the size
+    let runtime_minutes = match (media_type.as_deref(), media.and_then(|m| m.tmdb_id)) {
+        (Some(media_type), Some(tmdb_id)) if rules.iter().any(|r| r.min_estimated_gb.is_some()) => {
+            let seasons: Vec<i64> = request.seasons.iter().map(|s| s.season_number).collect();
+            state
+                .seerr_client
+                .runtime_minutes(media_type, tmdb_id, &seasons)
+                .await?
+        }
+        _ => None,
+    };
+    let pending = PendingRequest {
+        media_type,
+        requested_by,
+        is_4k: request.is_4k,
+        runtime_minutes,
+    };
+
+    let Some((rule, reason)) = evaluate(rules, &pending) else {
+        info!(request_id, "No request rule matched");
+        return Ok(());
+    };
+    let (result, action, status) = match rule.action {
+        RuleAction::Approve => (
+            state.seerr_client.approve_request(request_id).await,
+            "seerr.approve_request",
+            RequestStatus::Approved,
+        ),
+        RuleAction::Decline => (
+            state.seerr_client.decline_request(request_id).await,
+            "seerr.decline_request",
+            RequestStatus::Declined,
+        ),
+    };
+    let details = format!("request {request_id}, rule {}", rule.name);
+    audit::record(&state.db, "rules", action, None, Some(&details), &result).await;
+    if let Err(e) = &result
+        && let Some(markdown) = quota::reply(e, &state.settings.get().time_format)
+    {
+        matrix::send_thread_markdown(&state.room, root_event_id, &markdown).await?;
+        return Ok(());
+    }
+    result?;
+
+    db::set_request_status(&state.db, request_id, status).await?;
+    info!(request_id, rule = %rule.name, %status, "Request rule fired");
+
+    let markdown = format!(
+        "**🤖 {} by rule \"{}\":** {}",
+        rule.action.label(),
+        markdown::escape(&rule.name),
+        markdown::escape(&reason)
+    );
+    matrix::send_thread_markdown(&state.room, root_event_id, &markdown).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(toml: &str) -> Vec<RequestRule> {
+        #[derive(Deserialize)]
+        struct File {
+            rules: Vec<RequestRule>,
+        }
+        toml::from_str::<File>(toml).unwrap().rules
+    }
+
+    #[test]
+    fn first_matching_rule_fires() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            name = "Huge 4K movies"
+            action = "decline"
+            media_type = "movie"
+            is_4k = true
+            min_estimated_gb = 40
+
+            [[rules]]
+            name = "Trusted TV"
+            action = "approve"
+            media_type = "tv"
+            requested_by = ["Alice"]
+            "#,
+        );
+
+        let show = PendingRequest {
+            media_type: Some("tv".to_string()),
+            requested_by: vec!["alice".to_string()],
+            ..Default::default()
+        };
+        let (rule, reason) = evaluate(&rules, &show).unwrap();
+        assert_eq!(rule.name, "Trusted TV");
+        assert_eq!(reason, "TV request, requested by alice");
+
+        let movie = PendingRequest {
+            media_type: Some("movie".to_string()),
+            requested_by: vec!["bob".to_string()],
+            is_4k: true,
+            runtime_minutes: Some(180),
+        };
+        let (rule, reason) = evaluate(&rules, &movie).unwrap();
+        assert_eq!(rule.action, RuleAction::Decline);
+        assert_eq!(reason, "movie request, in 4K, about 60 GB, over 40 GB");
+
+        let short_movie = PendingRequest {
+            runtime_minutes: Some(90),
+            ..movie
+        };
+        assert!(evaluate(&rules, &short_movie).is_none());
+    }
+
+    #[test]
+    fn reports_rules_matching_everything() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            name = "Everything"
+            action = "approve"
+            "#,
+        );
+        assert_eq!(rules[0].problems().len(), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, error, info, info_span, warn};
+
+use crate::settings::LiveSettings;
+
+type JobFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+struct Job {
+    name: &'static str,
+    schedule: Schedule,
+    run: Box<dyn Fn() -> JobFuture + Send + Sync>,
+}
+
+/// Parses a cron expression. Both the classic five fields (`30 9 * * Mon`)
+/// and the six or seven field form with seconds and years are accepted.
+pub fn parse_schedule(expression: &str) -> Result<Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {expression}")
+    } else {
+        expression.to_string()
+    };
+    expression
+        .parse()
+        .with_context(|| format!("Invalid cron expression {expression:?}"))
+}
+
+/// Runs periodic jobs on cron schedules, evaluated in the configured timezone.
+pub struct Scheduler {
+    settings: Arc<LiveSettings>,
+    jobs: Vec<Job>,
+}
+
+impl Scheduler {
+    pub fn new(settings: Arc<LiveSettings>) -> Self {
+        Self {
+            settings,
+            jobs: Vec::new(),
+        }
+    }
+
+    pub fn add<F, Fut>(&mut self, name: &'static str, schedule: Schedule, job: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.jobs.push(Job {
+            name,
+            schedule,
+            run: Box::new(move || Box::pin(job())),
+        });
+    }
+
+    /// Runs every job until `shutdown` is cancelled. A job that is running at
+    /// that point is awaited, so its work is not cut in half.
+    pub async fn run(self, shutdown: CancellationToken) {
+        let tasks = TaskTracker::new();
+        for job in self.jobs {
+            info!(job = job.name, "Scheduled job registered");
+            tasks.spawn(run_job(job, self.settings.clone(), shutdown.clone()));
+        }
+        tasks.close();
+        tasks.wait().await;
+    }
+}
+
+async fn run_job(job: Job, settings: Arc<LiveSettings>, shutdown: CancellationToken) {
+    loop {
+        let now = Utc::now();
+        let timezone = settings.get().time_format.timezone;
+        let Some(next) = next_run(&job.schedule, now, timezone) else {
+            warn!(
+                job = job.name,
+                "Schedule has no upcoming run, stopping the job"
+            );
+            return;
+        };
+        let wait = (next - now).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(wait) => {}
+        }
+
+        let span = info_span!("job", job = job.name);
+        match (job.run)().instrument(span).await {
+            Ok(()) => info!(job = job.name, "Scheduled job finished"),
+            Err(e) => error!(job = job.name, "Scheduled job failed: {e:#}"),
+        }
+    }
+}
+
+/// First run of `schedule` strictly after `now`, in `timezone`.
+fn next_run(schedule: &Schedule, now: DateTime<Utc>, timezone: Tz) -> Option<DateTime<Utc>> {
+    schedule
+        .after(&now.with_timezone(&timezone))
+        .next()
+        .map(|next| next.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_five_and_six_field_expressions() {
+        assert!(parse_schedule("0 9 * * Mon").is_ok());
+        assert!(parse_schedule("0 0 9 * * Mon").is_ok());
+        assert!(parse_schedule("every monday").is_err());
+    }
+
+    #[test]
+    fn next_run_is_in_the_configured_timezone() {
+        let schedule = parse_schedule("0 9 * * Mon").unwrap();
+        // Wednesday
+        let now = Utc.with_ymd_and_hms(2025, 3, 5, 12, 0, 0).unwrap();
+        assert_eq!(
+            next_run(&schedule, now, Tz::UTC),
+            Some(Utc.with_ymd_and_hms(2025, 3, 10, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_run(&schedule, now, "Europe/Paris".parse().unwrap()),
+            Some(Utc.with_ymd_and_hms(2025, 3, 10, 8, 0, 0).unwrap())
+        );
+
+        // Exactly at the scheduled time, the next run is a week later
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 9, 0, 0).unwrap();
+        assert_eq!(
+            next_run(&schedule, now, Tz::UTC),
+            Some(Utc.with_ymd_and_hms(2025, 3, 17, 9, 0, 0).unwrap())
+        );
+    }
+}
@@ -1,13 +1,232 @@
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize)]
+use crate::request::RequestStatus;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct SeerrWebhookPayload {
     pub notification_type: String,
     pub subject: String,
     pub message: Option<String>,
     pub image: Option<String>,
     pub issue_id: Option<String>,
+    /// `{{issue_type}}`: Video, Audio, Subtitle or Other.
+    pub issue_type: Option<String>,
     pub reported_by: Option<String>,
     pub comment: Option<String>,
     pub commented_by: Option<String>,
+    pub comment_id: Option<String>,
+    pub request_id: Option<String>,
+    pub requested_by: Option<String>,
+    pub media_type: Option<String>,
+    pub media_tmdbid: Option<String>,
+    pub media_tvdbid: Option<String>,
+}
+
+/// Issue status codes of the Seerr API.
+pub const ISSUE_STATUS_OPEN: i64 = 1;
+pub const ISSUE_STATUS_RESOLVED: i64 = 2;
+
+/// Issue as returned by the Seerr API, only what the bot reads from it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrIssue {
+    #[serde(default)]
+    pub id: i64,
+    #[serde(default)]
+    pub status: i64,
+    /// 1 video, 2 audio, 3 subtitles, 4 other.
+    #[serde(default)]
+    pub issue_type: i64,
+    pub media: Option<SeerrMedia>,
+    pub created_by: Option<SeerrUser>,
+    pub modified_by: Option<SeerrUser>,
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub comments: Vec<SeerrIssueComment>,
+    /// Season of a TV issue, 0 for the whole series.
+    #[serde(default)]
+    pub problem_season: i64,
+    /// Episode of a TV issue, 0 for the whole season.
+    #[serde(default)]
+    pub problem_episode: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrIssueComment {
+    pub id: i64,
+    pub message: String,
+    pub user: Option<SeerrUser>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Movie, show or person found by the Seerr search.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrSearchResult {
+    /// TMDB id.
+    pub id: i64,
+    pub media_type: String,
+    pub title: Option<String>,
+    pub name: Option<String>,
+    pub release_date: Option<String>,
+    pub first_air_date: Option<String>,
+    /// Only set for media Seerr tracks, the only media issues can be about.
+    pub media_info: Option<SeerrMediaInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeerrMediaInfo {
+    pub id: i64,
+}
+
+/// TMDB details of a movie or show, from Seerr's `/movie/{id}` or `/tv/{id}`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrMediaDetails {
+    /// Minutes, for movies.
+    pub runtime: Option<i64>,
+    /// Minutes of an episode, for shows.
+    #[serde(default)]
+    pub episode_run_time: Vec<i64>,
+    /// TMDB rating out of 10.
+    pub vote_average: Option<f64>,
+    #[serde(default)]
+    pub genres: Vec<SeerrGenre>,
+    pub overview: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SeerrGenre {
+    pub name: String,
+}
+
+/// Media request as returned by the Seerr API.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrRequest {
+    pub id: i64,
+    pub status: i64,
+    pub media: Option<SeerrMedia>,
+    pub requested_by: Option<SeerrUser>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default, rename = "is4k")]
+    pub is_4k: bool,
+    /// Seasons of a TV request.
+    #[serde(default)]
+    pub seasons: Vec<SeerrSeason>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrSeason {
+    pub season_number: i64,
+}
+
+impl SeerrRequest {
+    /// The bot's view of the request: Seerr tracks availability on the media.
+    pub fn request_status(&self) -> Option<RequestStatus> {
+        if self
+            .media
+            .as_ref()
+            .is_some_and(|m| m.status == MEDIA_STATUS_AVAILABLE)
+        {
+            return Some(RequestStatus::Available);
+        }
+        match self.status {
+            1 => Some(RequestStatus::Pending),
+            2 => Some(RequestStatus::Approved),
+            3 => Some(RequestStatus::Declined),
+            4 => Some(RequestStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+const MEDIA_STATUS_AVAILABLE: i64 = 5;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrMedia {
+    pub tmdb_id: Option<i64>,
+    pub tvdb_id: Option<i64>,
+    pub media_type: Option<String>,
+    #[serde(default)]
+    pub status: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrUser {
+    #[serde(default)]
+    pub id: i64,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+}
+
+impl SeerrUser {
+    pub fn name(user: Option<&SeerrUser>) -> String {
+        user.and_then(|u| u.display_name.clone())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+}
+
+/// Settings of the Seerr webhook notification agent.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrWebhookSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Bitmask of the `NOTIFICATION_*` types sent.
+    #[serde(default)]
+    pub types: i64,
+    pub options: SeerrWebhookOptions,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrWebhookOptions {
+    #[serde(default)]
+    pub webhook_url: String,
+}
+
+/// Notification type bits of the Seerr notification agents.
+pub const NOTIFICATION_ISSUE_CREATED: i64 = 256;
+pub const NOTIFICATION_ISSUE_COMMENT: i64 = 512;
+pub const NOTIFICATION_ISSUE_RESOLVED: i64 = 1024;
+pub const NOTIFICATION_ISSUE_REOPENED: i64 = 2048;
+
+/// Request quotas of a Seerr user.
+#[derive(Debug, Default, Deserialize)]
+pub struct SeerrQuota {
+    #[serde(default)]
+    pub movie: SeerrQuotaStatus,
+    #[serde(default)]
+    pub tv: SeerrQuotaStatus,
+}
+
+/// Quota of a media type, without a limit when the user has none.
+#[derive(Debug, Default, Deserialize)]
+pub struct SeerrQuotaStatus {
+    /// Length of the rolling window requests count in.
+    pub days: Option<i64>,
+    pub limit: Option<i64>,
+    #[serde(default)]
+    pub used: i64,
+    pub remaining: Option<i64>,
+}
+
+/// A page of a Seerr list endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeerrPage<T> {
+    pub page_info: SeerrPageInfo,
+    pub results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeerrPageInfo {
+    pub pages: i64,
 }
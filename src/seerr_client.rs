@@ -1,11 +1,32 @@
-use anyhow::{Context, Result};
-use reqwest::Client;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use serde_json::json;
 
+use crate::http_metrics::{HttpMetrics, SendRecorded, ServiceMetrics};
+use crate::issue::IssueCategory;
+use crate::quota::{self, QuotaExceeded};
+use crate::seerr::{
+    SeerrIssue, SeerrMedia, SeerrMediaDetails, SeerrPage, SeerrQuota, SeerrRequest,
+    SeerrSearchResult, SeerrWebhookSettings,
+};
+
+const PAGE_SIZE: i64 = 100;
+
+/// Service label of the Seerr API metrics.
+const SERVICE: &str = "seerr";
+
+#[derive(Clone)]
 pub struct SeerrClient {
     base_url: String,
     api_key: String,
+    public_url: Option<String>,
     client: Client,
+    metrics: ServiceMetrics,
 }
 
 impl SeerrClient {
@@ -13,24 +34,129 @@ impl SeerrClient {
         Self {
             base_url: base_url.trim_end_matches('/').to_string(),
             api_key: api_key.to_string(),
+            public_url: None,
             client: Client::new(),
+            metrics: Arc::new(HttpMetrics::default()).service(SERVICE),
         }
     }
 
-    pub async fn add_comment(&self, issue_id: i64, message: &str) -> Result<()> {
-        self.client
+    /// Records the API calls in `metrics`, for `/admin/metrics`.
+    pub fn with_metrics(mut self, metrics: &Arc<HttpMetrics>) -> Self {
+        self.metrics = metrics.service(SERVICE);
+        self
+    }
+
+    /// Address users open Seerr at, which the API one often isn't. Messages
+    /// only link to Seerr when it is set.
+    pub fn with_public_url(mut self, public_url: Option<&str>) -> Self {
+        self.public_url = public_url.map(|url| url.trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn issue_url(&self, issue_id: i64) -> Option<String> {
+        let public_url = self.public_url.as_deref()?;
+        Some(format!("{public_url}/issues/{issue_id}"))
+    }
+
+    /// Page of the requested media, or the request list when the webhook
+    /// didn't say which media it is.
+    pub fn media_url(&self, media_type: Option<&str>, tmdb_id: Option<i64>) -> Option<String> {
+        let public_url = self.public_url.as_deref()?;
+        Some(match (media_type, tmdb_id) {
+            (Some(media_type @ ("movie" | "tv")), Some(tmdb_id)) => {
+                format!("{public_url}/{media_type}/{tmdb_id}")
+            }
+            _ => format!("{public_url}/requests"),
+        })
+    }
+
+    /// Adds a comment to an issue, returning the id Seerr gave it when it can be
+    /// found in the response.
+    pub async fn add_comment(&self, issue_id: i64, message: &str) -> Result<Option<i64>> {
+        let response = self
+            .client
             .post(format!(
                 "{}/api/v1/issue/{}/comment",
                 self.base_url, issue_id
             ))
             .header("X-Api-Key", &self.api_key)
             .json(&json!({ "message": message }))
-            .send()
+            .send_recorded(&self.metrics, "/api/v1/issue/{id}/comment")
             .await
             .context("Failed to send comment to Seerr")?
             .error_for_status()
             .context("Seerr returned error for comment")?;
-        Ok(())
+
+        let issue = response.json::<SeerrIssue>().await.ok();
+        Ok(issue.and_then(|issue| {
+            issue
+                .comments
+                .iter()
+                .rev()
+                .find(|c| c.message == message)
+                .map(|c| c.id)
+        }))
+    }
+
+    /// Files an issue about the media with Seerr id `media_id`, returning the
+    /// id of the new issue. `episodes` are the season and episode of a TV
+    /// issue, 0 for all of them.
+    pub async fn create_issue(
+        &self,
+        media_id: i64,
+        category: IssueCategory,
+        message: &str,
+        episodes: (i64, i64),
+    ) -> Result<i64> {
+        #[derive(Deserialize)]
+        struct Created {
+            id: i64,
+        }
+
+        let (season, episode) = episodes;
+        let created = self
+            .client
+            .post(format!("{}/api/v1/issue", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .json(&json!({
+                "issueType": category.seerr_type(),
+                "message": message,
+                "mediaId": media_id,
+                "problemSeason": season,
+                "problemEpisode": episode,
+            }))
+            .send_recorded(&self.metrics, "/api/v1/issue")
+            .await
+            .context("Failed to create issue in Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for new issue")?
+            .json::<Created>()
+            .await
+            .context("Invalid issue from Seerr")?;
+        Ok(created.id)
+    }
+
+    /// Movies and shows matching `query`, most relevant first.
+    pub async fn search(&self, query: &str) -> Result<Vec<SeerrSearchResult>> {
+        #[derive(Deserialize)]
+        struct Results {
+            results: Vec<SeerrSearchResult>,
+        }
+
+        let results = self
+            .client
+            .get(format!("{}/api/v1/search", self.base_url))
+            .query(&[("query", query), ("page", "1")])
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/search")
+            .await
+            .context("Failed to search Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for search")?
+            .json::<Results>()
+            .await
+            .context("Invalid search results from Seerr")?;
+        Ok(results.results)
     }
 
     pub async fn resolve_issue(&self, issue_id: i64) -> Result<()> {
@@ -40,11 +166,425 @@ impl SeerrClient {
                 self.base_url, issue_id
             ))
             .header("X-Api-Key", &self.api_key)
-            .send()
+            .send_recorded(&self.metrics, "/api/v1/issue/{id}/resolved")
             .await
             .context("Failed to resolve issue in Seerr")?
             .error_for_status()
             .context("Seerr returned error for resolve")?;
         Ok(())
     }
+
+    /// Approves the request, failing with `QuotaExceeded` when Seerr refuses
+    /// it because the requester used up their quota.
+    pub async fn approve_request(&self, request_id: i64) -> Result<()> {
+        #[derive(Deserialize)]
+        struct ApiError {
+            #[serde(default)]
+            message: String,
+        }
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/api/v1/request/{}/approve",
+                self.base_url, request_id
+            ))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/request/{id}/approve")
+            .await
+            .context("Failed to approve request in Seerr")?;
+        if response.status() == StatusCode::FORBIDDEN {
+            let message = response
+                .json::<ApiError>()
+                .await
+                .map(|error| error.message)
+                .unwrap_or_default();
+            if quota::is_quota_error(&message) {
+                return Err(self.quota_exceeded(request_id).await?.into());
+            }
+            bail!("Seerr refused to approve request {request_id}: {message}");
+        }
+        response
+            .error_for_status()
+            .context("Seerr returned error for approve")?;
+        Ok(())
+    }
+
+    /// What is left of the quota of the requester of `request_id`, and when
+    /// it frees a request.
+    async fn quota_exceeded(&self, request_id: i64) -> Result<QuotaExceeded> {
+        let request = self
+            .get_request(request_id)
+            .await?
+            .context("Request not found in Seerr")?;
+        let user_id = request
+            .requested_by
+            .as_ref()
+            .map(|user| user.id)
+            .context("Request has no requester")?;
+        let media_type = request
+            .media
+            .as_ref()
+            .and_then(|media| media.media_type.as_deref())
+            .unwrap_or("movie");
+
+        let quota: SeerrQuota = self
+            .client
+            .get(format!("{}/api/v1/user/{user_id}/quota", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/user/{id}/quota")
+            .await
+            .context("Failed to fetch quota from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for quota")?
+            .json()
+            .await
+            .context("Invalid quota from Seerr")?;
+        let requests: SeerrPage<SeerrRequest> = self
+            .client
+            .get(format!("{}/api/v1/user/{user_id}/requests", self.base_url))
+            .query(&[("take", PAGE_SIZE)])
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/user/{id}/requests")
+            .await
+            .context("Failed to fetch user requests from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for user requests")?
+            .json()
+            .await
+            .context("Invalid user requests from Seerr")?;
+
+        let status = if media_type == "tv" {
+            &quota.tv
+        } else {
+            &quota.movie
+        };
+        Ok(QuotaExceeded::new(
+            request_id,
+            media_type,
+            status,
+            &requests.results,
+            Utc::now(),
+        ))
+    }
+
+    pub async fn decline_request(&self, request_id: i64) -> Result<()> {
+        self.update_request_status(request_id, "decline").await
+    }
+
+    async fn update_request_status(&self, request_id: i64, status: &str) -> Result<()> {
+        self.client
+            .post(format!(
+                "{}/api/v1/request/{}/{}",
+                self.base_url, request_id, status
+            ))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/request/{id}/{status}")
+            .await
+            .with_context(|| format!("Failed to {status} request in Seerr"))?
+            .error_for_status()
+            .with_context(|| format!("Seerr returned error for {status}"))?;
+        Ok(())
+    }
+
+    /// Number of requests waiting for approval, including the ones made
+    /// before the bot was watching.
+    pub async fn pending_request_count(&self) -> Result<i64> {
+        #[derive(Deserialize)]
+        struct RequestCounts {
+            pending: i64,
+        }
+
+        let counts = self
+            .client
+            .get(format!("{}/api/v1/request/count", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/request/count")
+            .await
+            .context("Failed to fetch request counts from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for request counts")?
+            .json::<RequestCounts>()
+            .await
+            .context("Invalid request counts from Seerr")?;
+        Ok(counts.pending)
+    }
+
+    /// Every issue matching a Seerr list `filter` (`open`, `resolved`, `all`).
+    pub async fn list_issues(&self, filter: &str) -> Result<Vec<SeerrIssue>> {
+        self.list(
+            "/api/v1/issue",
+            &[("filter", filter), ("sort", "added")],
+            |_| true,
+        )
+        .await
+        .context("Failed to list issues from Seerr")
+    }
+
+    /// Issues updated since `since`, most recently updated first.
+    pub async fn list_issues_updated_since(&self, since: DateTime<Utc>) -> Result<Vec<SeerrIssue>> {
+        let mut issues: Vec<SeerrIssue> = self
+            .list(
+                "/api/v1/issue",
+                &[("filter", "all"), ("sort", "modified")],
+                |page: &[SeerrIssue]| {
+                    page.last()
+                        .is_some_and(|issue| issue.updated_at.is_none_or(|at| at >= since))
+                },
+            )
+            .await
+            .context("Failed to list recent issues from Seerr")?;
+        issues.retain(|issue| issue.updated_at.is_none_or(|at| at >= since));
+        Ok(issues)
+    }
+
+    /// Requests waiting for approval, oldest first.
+    pub async fn list_pending_requests(&self) -> Result<Vec<SeerrRequest>> {
+        let mut requests: Vec<SeerrRequest> = self
+            .list(
+                "/api/v1/request",
+                &[("filter", "pending"), ("sort", "added")],
+                |_| true,
+            )
+            .await
+            .context("Failed to list pending requests from Seerr")?;
+        requests.reverse();
+        Ok(requests)
+    }
+
+    /// Media requests updated since `since`, most recently updated first.
+    pub async fn list_requests_updated_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<SeerrRequest>> {
+        let mut requests: Vec<SeerrRequest> = self
+            .list(
+                "/api/v1/request",
+                &[("filter", "all"), ("sort", "modified")],
+                |page: &[SeerrRequest]| {
+                    page.last()
+                        .is_some_and(|request| request.updated_at.is_none_or(|at| at >= since))
+                },
+            )
+            .await
+            .context("Failed to list recent requests from Seerr")?;
+        requests.retain(|request| request.updated_at.is_none_or(|at| at >= since));
+        Ok(requests)
+    }
+
+    /// Fetches the pages of a list endpoint until the last one, or until
+    /// `more` says the page just fetched is the last one needed.
+    async fn list<T: DeserializeOwned>(
+        &self,
+        path: &'static str,
+        query: &[(&str, &str)],
+        more: impl Fn(&[T]) -> bool,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut page = 0;
+        loop {
+            let skip = (page * PAGE_SIZE).to_string();
+            let take = PAGE_SIZE.to_string();
+            let response = self
+                .client
+                .get(format!("{}{path}", self.base_url))
+                .query(query)
+                .query(&[("take", take.as_str()), ("skip", skip.as_str())])
+                .header("X-Api-Key", &self.api_key)
+                .send_recorded(&self.metrics, path)
+                .await?
+                .error_for_status()?
+                .json::<SeerrPage<T>>()
+                .await?;
+            page += 1;
+            let done = page >= response.page_info.pages || !more(&response.results);
+            items.extend(response.results);
+            if done {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// How the webhook notification agent is set up, needs an admin API key.
+    pub async fn webhook_settings(&self) -> Result<SeerrWebhookSettings> {
+        self.client
+            .get(format!(
+                "{}/api/v1/settings/notifications/webhook",
+                self.base_url
+            ))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/settings/notifications/webhook")
+            .await
+            .context("Failed to fetch webhook settings from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for webhook settings")?
+            .json()
+            .await
+            .context("Invalid webhook settings from Seerr")
+    }
+
+    /// The issue, or `None` when it was deleted from Seerr.
+    pub async fn get_issue(&self, issue_id: i64) -> Result<Option<SeerrIssue>> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/issue/{}", self.base_url, issue_id))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/issue/{id}")
+            .await
+            .context("Failed to fetch issue from Seerr")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let issue = response
+            .error_for_status()
+            .context("Seerr returned error for issue")?
+            .json()
+            .await
+            .context("Invalid issue from Seerr")?;
+        Ok(Some(issue))
+    }
+
+    /// The request, or `None` when it was deleted from Seerr.
+    pub async fn get_request(&self, request_id: i64) -> Result<Option<SeerrRequest>> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/request/{}", self.base_url, request_id))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/request/{id}")
+            .await
+            .context("Failed to fetch request from Seerr")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let request = response
+            .error_for_status()
+            .context("Seerr returned error for request")?
+            .json()
+            .await
+            .context("Invalid request from Seerr")?;
+        Ok(Some(request))
+    }
+
+    /// Title of a movie or show, as Seerr webhooks put it in their subject.
+    pub async fn media_title(&self, media: &SeerrMedia) -> Result<String> {
+        #[derive(Deserialize)]
+        struct Details {
+            title: Option<String>,
+            name: Option<String>,
+        }
+
+        let tmdb_id = media.tmdb_id.context("Media has no TMDB id")?;
+        let kind = match media.media_type.as_deref() {
+            Some("tv") => "tv",
+            _ => "movie",
+        };
+        let details = self
+            .client
+            .get(format!("{}/api/v1/{kind}/{tmdb_id}", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, media_endpoint(kind))
+            .await
+            .context("Failed to fetch media details from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for media details")?
+            .json::<Details>()
+            .await
+            .context("Invalid media details from Seerr")?;
+        details
+            .title
+            .or(details.name)
+            .context("Media details have no title")
+    }
+
+    /// Runtime, rating, genres and overview TMDB has for a movie or show.
+    pub async fn media_details(&self, media_type: &str, tmdb_id: i64) -> Result<SeerrMediaDetails> {
+        let kind = if media_type == "tv" { "tv" } else { "movie" };
+        self.client
+            .get(format!("{}/api/v1/{kind}/{tmdb_id}", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, media_endpoint(kind))
+            .await
+            .context("Failed to fetch media details from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for media details")?
+            .json::<SeerrMediaDetails>()
+            .await
+            .context("Invalid media details from Seerr")
+    }
+
+    /// Running time of a movie, or of the `seasons` of a show, from the
+    /// runtime TMDB gives. `None` when TMDB doesn't know it.
+    pub async fn runtime_minutes(
+        &self,
+        media_type: &str,
+        tmdb_id: i64,
+        seasons: &[i64],
+    ) -> Result<Option<i64>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Season {
+            season_number: i64,
+            #[serde(default)]
+            episode_count: i64,
+        }
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Details {
+            runtime: Option<i64>,
+            #[serde(default)]
+            episode_run_time: Vec<i64>,
+            #[serde(default)]
+            seasons: Vec<Season>,
+        }
+
+        let kind = if media_type == "tv" { "tv" } else { "movie" };
+        let details = self
+            .client
+            .get(format!("{}/api/v1/{kind}/{tmdb_id}", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, media_endpoint(kind))
+            .await
+            .context("Failed to fetch media details from Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for media details")?
+            .json::<Details>()
+            .await
+            .context("Invalid media details from Seerr")?;
+        if kind == "movie" {
+            return Ok(details.runtime);
+        }
+        let Some(episode_minutes) = details.episode_run_time.first() else {
+            return Ok(None);
+        };
+        let episodes: i64 = details
+            .seasons
+            .iter()
+            .filter(|s| seasons.contains(&s.season_number))
+            .map(|s| s.episode_count)
+            .sum();
+        Ok(Some(episode_minutes * episodes))
+    }
+
+    /// Checks that Seerr is up and answering API calls.
+    pub async fn status(&self) -> Result<()> {
+        self.client
+            .get(format!("{}/api/v1/status", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v1/status")
+            .await
+            .context("Failed to reach Seerr")?
+            .error_for_status()
+            .context("Seerr returned error for status")?;
+        Ok(())
+    }
+}
+
+/// Endpoint of the TMDB details of a movie or show, `kind` being `movie` or
+/// `tv`.
+fn media_endpoint(kind: &str) -> &'static str {
+    if kind == "tv" {
+        "/api/v1/tv/{id}"
+    } else {
+        "/api/v1/movie/{id}"
+    }
 }
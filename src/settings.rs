@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedUserId;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::config::{Config, PriorityConfig, ReactionEmojis};
+use crate::quiet_hours::QuietHours;
+use crate::reactions::ReactionAction;
+use crate::room_config::RoomConfig;
+use crate::rules::RequestRule;
+use crate::time_format::TimeFormat;
+
+/// The part of the configuration that can change while the bot runs. Anything
+/// else, like the Matrix session or the webhook listener, needs a restart.
+pub struct Settings {
+    pub admin_users: Vec<OwnedUserId>,
+    pub reaction_emojis: ReactionEmojis,
+    pub reaction_shortcuts: HashMap<String, ReactionAction>,
+    pub dashboard_enabled: bool,
+    /// Filters from the config file's `[[rooms]]` entry for the bot's room.
+    pub room_defaults: RoomConfig,
+    pub time_format: TimeFormat,
+    pub import_auto_resolve_after: Option<Duration>,
+    pub reporter_follow_up_after: Option<Duration>,
+    pub reporter_follow_up_resolve_after: Duration,
+    pub request_vote_threshold: Option<usize>,
+    pub request_rules: Vec<RequestRule>,
+    pub download_notices_enabled: bool,
+    pub media_details_enabled: bool,
+    pub issue_images_enabled: bool,
+    pub health_tickets_enabled: bool,
+    pub outbox_stuck_attempts: i32,
+    pub outbox_stuck_after: Duration,
+    pub quiet_hours: Option<QuietHours>,
+    pub priorities: PriorityConfig,
+}
+
+impl Settings {
+    /// `room_names` are the alias and id the bot's room is known under.
+    pub fn from_config(config: &Config, room_names: &[&str]) -> Self {
+        Self {
+            admin_users: config.matrix_admin_users.clone(),
+            reaction_emojis: config.reaction_emojis.clone(),
+            reaction_shortcuts: config.reaction_shortcuts.clone(),
+            dashboard_enabled: config.dashboard_enabled,
+            room_defaults: config.room_defaults(room_names),
+            time_format: config.time_format,
+            import_auto_resolve_after: config.import_auto_resolve_after,
+            reporter_follow_up_after: config.reporter_follow_up_after,
+            reporter_follow_up_resolve_after: config.reporter_follow_up_resolve_after,
+            request_vote_threshold: config.request_vote_threshold,
+            request_rules: config.rules.clone(),
+            download_notices_enabled: config.download_notices_enabled,
+            media_details_enabled: config.media_details_enabled,
+            issue_images_enabled: config.issue_images_enabled,
+            health_tickets_enabled: config.health_tickets_enabled,
+            outbox_stuck_attempts: config.outbox_stuck_attempts,
+            outbox_stuck_after: config.outbox_stuck_after,
+            quiet_hours: config.quiet_hours,
+            priorities: config.priorities.clone(),
+        }
+    }
+}
+
+/// Settings shared by the handlers, swapped wholesale on reload so a handler
+/// never sees half of an old config and half of a new one.
+pub struct LiveSettings {
+    current: RwLock<Arc<Settings>>,
+    config_path: Option<PathBuf>,
+    room_names: Vec<String>,
+}
+
+impl LiveSettings {
+    pub fn new(settings: Settings, config_path: Option<PathBuf>, room_names: Vec<String>) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(settings)),
+            config_path,
+            room_names,
+        }
+    }
+
+    pub fn get(&self) -> Arc<Settings> {
+        self.current
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Reads the config file and environment again. The running settings are
+    /// kept when the new config is invalid.
+    pub fn reload(&self) -> Result<()> {
+        let config = Config::load(self.config_path.as_deref())?;
+        let room_names: Vec<&str> = self.room_names.iter().map(String::as_str).collect();
+        let settings = Settings::from_config(&config, &room_names);
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(settings);
+        info!("Configuration reloaded");
+        Ok(())
+    }
+}
+
+/// Reloads the settings every time the process receives SIGHUP.
+pub async fn reload_on_sighup(settings: Arc<LiveSettings>, shutdown: CancellationToken) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(hangup) => hangup,
+            Err(e) => {
+                warn!("Failed to listen for SIGHUP: {e}");
+                return;
+            }
+        };
+        loop {
+            tokio::select! {
+                _ = shutdown.cancelled() => return,
+                received = hangup.recv() => {
+                    if received.is_none() {
+                        return;
+                    }
+                }
+            }
+            info!("Received SIGHUP, reloading configuration");
+            if let Err(e) = settings.reload() {
+                error!("Failed to reload configuration: {e:#}");
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = settings;
+        shutdown.cancelled().await;
+    }
+}
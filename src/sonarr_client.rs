@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::config::ServiceConfig;
+use crate::disk::RootFolder;
+use crate::http_metrics::{HttpMetrics, SendRecorded, ServiceMetrics};
+
+/// Service label of the Sonarr API metrics.
+const SERVICE: &str = "sonarr";
+
+#[derive(Clone)]
+pub struct SonarrClient {
+    base_url: String,
+    api_key: String,
+    client: Client,
+    metrics: ServiceMetrics,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Episode {
+    #[serde(default)]
+    pub id: i64,
+    pub season_number: i64,
+    pub episode_number: i64,
+    pub title: Option<String>,
+    pub air_date_utc: Option<DateTime<Utc>>,
+    pub series: Option<Series>,
+    /// 0 when the episode has no file.
+    #[serde(default)]
+    pub episode_file_id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Series {
+    #[serde(default)]
+    pub id: i64,
+    pub title: String,
+}
+
+impl SonarrClient {
+    pub fn new(config: &ServiceConfig) -> Self {
+        Self {
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            client: Client::new(),
+            metrics: Arc::new(HttpMetrics::default()).service(SERVICE),
+        }
+    }
+
+    /// Records the API calls in `metrics`, for `/admin/metrics`.
+    pub fn with_metrics(mut self, metrics: &Arc<HttpMetrics>) -> Self {
+        self.metrics = metrics.service(SERVICE);
+        self
+    }
+
+    /// Episodes airing between `start` and `end`.
+    pub async fn calendar(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Episode>> {
+        self.client
+            .get(format!("{}/api/v3/calendar", self.base_url))
+            .query(&[
+                ("start", start.to_rfc3339()),
+                ("end", end.to_rfc3339()),
+                ("includeSeries", "true".to_string()),
+            ])
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/calendar")
+            .await
+            .context("Failed to reach Sonarr")?
+            .error_for_status()
+            .context("Sonarr returned error for calendar")?
+            .json()
+            .await
+            .context("Invalid calendar from Sonarr")
+    }
+
+    /// The series of the library with this TVDB id.
+    pub async fn series_by_tvdb_id(&self, tvdb_id: i64) -> Result<Option<Series>> {
+        let series: Vec<Series> = self
+            .client
+            .get(format!("{}/api/v3/series", self.base_url))
+            .query(&[("tvdbId", tvdb_id)])
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/series")
+            .await
+            .context("Failed to reach Sonarr")?
+            .error_for_status()
+            .context("Sonarr returned error for series lookup")?
+            .json()
+            .await
+            .context("Invalid series from Sonarr")?;
+        Ok(series.into_iter().next())
+    }
+
+    /// Episodes of a series, of a single season when given.
+    pub async fn episodes(&self, series_id: i64, season: Option<i64>) -> Result<Vec<Episode>> {
+        let mut query = vec![("seriesId", series_id)];
+        if let Some(season) = season {
+            query.push(("seasonNumber", season));
+        }
+        self.client
+            .get(format!("{}/api/v3/episode", self.base_url))
+            .query(&query)
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/episode")
+            .await
+            .context("Failed to reach Sonarr")?
+            .error_for_status()
+            .context("Sonarr returned error for episodes")?
+            .json()
+            .await
+            .context("Invalid episodes from Sonarr")
+    }
+
+    pub async fn delete_episode_file(&self, episode_file_id: i64) -> Result<()> {
+        self.client
+            .delete(format!(
+                "{}/api/v3/episodefile/{episode_file_id}",
+                self.base_url
+            ))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/episodefile/{id}")
+            .await
+            .context("Failed to reach Sonarr")?
+            .error_for_status()
+            .context("Sonarr returned error for episode file deletion")?;
+        Ok(())
+    }
+
+    /// Starts a search for the missing or upgradable files of the episodes.
+    pub async fn search_episodes(&self, episode_ids: &[i64]) -> Result<()> {
+        self.command(json!({ "name": "EpisodeSearch", "episodeIds": episode_ids }))
+            .await
+    }
+
+    pub async fn search_season(&self, series_id: i64, season: i64) -> Result<()> {
+        self.command(json!({
+            "name": "SeasonSearch",
+            "seriesId": series_id,
+            "seasonNumber": season,
+        }))
+        .await
+    }
+
+    pub async fn search_series(&self, series_id: i64) -> Result<()> {
+        self.command(json!({ "name": "SeriesSearch", "seriesId": series_id }))
+            .await
+    }
+
+    async fn command(&self, body: Value) -> Result<()> {
+        self.client
+            .post(format!("{}/api/v3/command", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .json(&body)
+            .send_recorded(&self.metrics, "/api/v3/command")
+            .await
+            .context("Failed to reach Sonarr")?
+            .error_for_status()
+            .context("Sonarr returned error for command")?;
+        Ok(())
+    }
+
+    pub async fn root_folders(&self) -> Result<Vec<RootFolder>> {
+        self.client
+            .get(format!("{}/api/v3/rootfolder", self.base_url))
+            .header("X-Api-Key", &self.api_key)
+            .send_recorded(&self.metrics, "/api/v3/rootfolder")
+            .await
+            .context("Failed to reach Sonarr")?
+            .error_for_status()
+            .context("Sonarr returned error for root folders")?
+            .json()
+            .await
+            .context("Invalid root folders from Sonarr")
+    }
+}
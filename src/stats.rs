@@ -0,0 +1,156 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use tracing::info;
+
+use crate::AppState;
+use crate::db::{self, WeeklyCount};
+use crate::issue::IssueCategory;
+use crate::markdown;
+use crate::matrix;
+use crate::time_format::TimeFormat;
+
+const TOP_MEDIA_LIMIT: i64 = 5;
+
+pub struct Stats {
+    pub days: i64,
+    pub opened_per_week: Vec<WeeklyCount>,
+    pub mean_time_to_first_response: Option<Duration>,
+    pub mean_time_to_resolution: Option<Duration>,
+    pub top_media: Vec<(String, i64)>,
+    pub by_category: Vec<(String, i64)>,
+    pub resolvers: Vec<(String, i64)>,
+}
+
+pub async fn collect(pool: &PgPool, days: i64) -> Result<Stats> {
+    let since = Utc::now() - Duration::days(days);
+    Ok(Stats {
+        days,
+        opened_per_week: db::issues_opened_per_week(pool, since).await?,
+        mean_time_to_first_response: db::mean_time_to_first_response(pool, since).await?,
+        mean_time_to_resolution: db::mean_time_to_resolution(pool, since).await?,
+        top_media: db::top_media_with_issues(pool, since, TOP_MEDIA_LIMIT).await?,
+        by_category: db::issue_counts_by_category(pool, since).await?,
+        resolvers: db::resolve_counts_by_user(pool, since).await?,
+    })
+}
+
+pub fn render(stats: &Stats, time_format: &TimeFormat) -> String {
+    let opened: i64 = stats.opened_per_week.iter().map(|w| w.count).sum();
+    let mean = |duration: Option<Duration>| {
+        duration
+            .map(format_duration)
+            .unwrap_or_else(|| "n/a".to_string())
+    };
+    let mut markdown = format!(
+        "#### 📊 Issue statistics (last {} days)\n**Opened:** {opened}  \n\
+         **Mean time to first response:** {}  \n**Mean time to resolution:** {}\n",
+        stats.days,
+        mean(stats.mean_time_to_first_response),
+        mean(stats.mean_time_to_resolution),
+    );
+
+    if !stats.opened_per_week.is_empty() {
+        markdown.push_str("\n**Opened per week**\n");
+        for week in &stats.opened_per_week {
+            markdown.push_str(&format!(
+                "- {}: {}\n",
+                time_format.day(week.week_start.date_naive()),
+                week.count
+            ));
+        }
+    }
+    if !stats.top_media.is_empty() {
+        markdown.push_str("\n**Most reported**\n");
+        for (subject, count) in &stats.top_media {
+            markdown.push_str(&format!("- {}: {count}\n", markdown::escape(subject)));
+        }
+    }
+    if !stats.by_category.is_empty() {
+        markdown.push_str("\n**By category**\n");
+        for (category, count) in &stats.by_category {
+            let icon = IssueCategory::parse(category).map_or("", |c| c.icon());
+            markdown.push_str(&format!(
+                "- {icon} {}: {count}\n",
+                markdown::escape(category)
+            ));
+        }
+    }
+    if !stats.resolvers.is_empty() {
+        markdown.push_str("\n**Resolved by**\n");
+        for (user, count) in &stats.resolvers {
+            markdown.push_str(&format!("- {}: {count}\n", markdown::escape(user)));
+        }
+    }
+
+    markdown
+}
+
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let hours = duration.num_hours();
+    match hours {
+        0 => format!("{} min", duration.num_minutes()),
+        1..48 => format!("{hours} h"),
+        _ => format!("{} days", duration.num_days()),
+    }
+}
+
+/// Posts the statistics of the past week in the room.
+pub async fn post_weekly_report(state: &AppState) -> Result<()> {
+    let stats = collect(&state.db, 7).await?;
+    let report = render(&stats, &state.settings.get().time_format);
+    matrix::send_markdown(&state.room, &report).await?;
+    info!("Weekly report posted");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn render_report() {
+        let stats = Stats {
+            days: 7,
+            opened_per_week: vec![WeeklyCount {
+                week_start: Utc.with_ymd_and_hms(2025, 3, 3, 0, 0, 0).unwrap(),
+                count: 4,
+            }],
+            mean_time_to_first_response: Some(Duration::minutes(40)),
+            mean_time_to_resolution: Some(Duration::hours(30)),
+            top_media: vec![("Dune".to_string(), 2)],
+            by_category: vec![("subtitles".to_string(), 3)],
+            resolvers: vec![("@admin:localhost".to_string(), 3)],
+        };
+
+        let markdown = render(&stats, &TimeFormat::default());
+        assert!(markdown.contains("**Opened:** 4"));
+        assert!(markdown.contains("**Mean time to first response:** 40 min"));
+        assert!(markdown.contains("**Mean time to resolution:** 30 h"));
+        assert!(markdown.contains("- 2025-03-03: 4"));
+        assert!(markdown.contains("- Dune: 2"));
+        assert!(markdown.contains("- 🔤 subtitles: 3"));
+        let (plain, _) = markdown::render(&markdown);
+        assert!(plain.contains("- @admin:localhost: 3"));
+    }
+
+    #[test]
+    fn render_report_without_data() {
+        let stats = Stats {
+            days: 30,
+            opened_per_week: vec![],
+            mean_time_to_first_response: None,
+            mean_time_to_resolution: None,
+            top_media: vec![],
+            by_category: vec![],
+            resolvers: vec![],
+        };
+
+        let markdown = render(&stats, &TimeFormat::default());
+        assert!(markdown.contains("**Opened:** 0"));
+        assert!(markdown.contains("n/a"));
+        assert!(!markdown.contains("Most reported"));
+    }
+}
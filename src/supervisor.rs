@@ -0,0 +1,90 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, warn};
+
+const BASE_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(300);
+
+/// A task that ran this long before ending is restarted without the backoff
+/// of its earlier failures.
+const HEALTHY_AFTER: Duration = Duration::from_secs(60);
+
+/// Runs `task` until `shutdown` is cancelled, starting it again with backoff
+/// whenever it ends or fails. The webhook server and the Matrix sync are each
+/// supervised, so webhooks are still accepted (and queued in the outbox)
+/// during a Matrix outage, and sync goes on if the server fails.
+///
+/// `task` must end by itself once `shutdown` is cancelled, or be aborted.
+pub async fn supervise<F, Fut, E>(name: &'static str, shutdown: CancellationToken, mut task: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+    E: Display,
+{
+    let mut failures = 0;
+    loop {
+        let started = Instant::now();
+        let result = task().await;
+        if shutdown.is_cancelled() {
+            return;
+        }
+
+        if started.elapsed() >= HEALTHY_AFTER {
+            failures = 0;
+        }
+        failures += 1;
+        let delay = restart_delay(failures);
+        match result {
+            Ok(()) => warn!(task = name, ?delay, "Task ended, restarting it"),
+            Err(e) => error!(task = name, ?delay, "Task failed, restarting it: {e:#}"),
+        }
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+fn restart_delay(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    BASE_RESTART_DELAY
+        .saturating_mul(2u32.pow(exponent))
+        .min(MAX_RESTART_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn restart_delay_backs_off_exponentially() {
+        assert_eq!(restart_delay(1), Duration::from_secs(1));
+        assert_eq!(restart_delay(2), Duration::from_secs(2));
+        assert_eq!(restart_delay(5), Duration::from_secs(16));
+        assert_eq!(restart_delay(12), MAX_RESTART_DELAY);
+    }
+
+    #[tokio::test]
+    async fn failed_tasks_are_restarted_until_shutdown() {
+        let shutdown = CancellationToken::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counted = runs.clone();
+        let stop = shutdown.clone();
+        supervise("test", shutdown.clone(), move || {
+            let runs = counted.fetch_add(1, Ordering::SeqCst) + 1;
+            if runs == 2 {
+                stop.cancel();
+            }
+            async { Err::<(), _>("sync failed") }
+        })
+        .await;
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+}
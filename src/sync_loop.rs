@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use matrix_sdk::config::SyncSettings;
+use matrix_sdk::ruma::api::client::error::ErrorKind;
+use matrix_sdk::{Client, LoopCtrl};
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+use crate::alerts::{Alerts, Subsystem};
+use crate::config::MatrixAuth;
+use crate::heartbeat::SyncHealth;
+use crate::matrix;
+
+/// How long the sync in flight when shutting down is given to finish handling
+/// its batch. Past it, sync is only waiting for the next one and is dropped.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+/// Syncs with the homeserver until it fails, for the supervisor to start it
+/// again. The sync token stays in the client's store, so the next run resumes
+/// where this one stopped. When the homeserver no longer accepts the access
+/// token, the bot logs in again first. Failures count towards the Matrix
+/// alert, the admins being told once they keep failing and once sync works
+/// again. Once `shutdown` is cancelled, sync stops after the batch it is
+/// handling, so the commands in it are all started.
+pub async fn run(
+    client: Client,
+    user_id: String,
+    auth: MatrixAuth,
+    store_path: Option<PathBuf>,
+    health: Arc<SyncHealth>,
+    alerts: Arc<Alerts>,
+    shutdown: CancellationToken,
+) -> Result<()> {
+    let sync = client.sync_with_callback(SyncSettings::default(), |_| {
+        if health.mark() {
+            info!("Matrix sync recovered");
+            alerts.success(Subsystem::Matrix);
+        }
+        let ctrl = if shutdown.is_cancelled() {
+            LoopCtrl::Break
+        } else {
+            LoopCtrl::Continue
+        };
+        async move { ctrl }
+    });
+    tokio::pin!(sync);
+    let result = tokio::select! {
+        result = &mut sync => result,
+        _ = shutdown.cancelled() => match tokio::time::timeout(SHUTDOWN_GRACE, &mut sync).await {
+            Ok(result) => result,
+            Err(_) => return Ok(()),
+        },
+    };
+    let Err(error) = result else {
+        return Ok(());
+    };
+
+    health.record_failure();
+    let unknown_token = matches!(
+        error.client_api_error_kind(),
+        Some(ErrorKind::UnknownToken { .. })
+    );
+    let mut error = anyhow::Error::new(error).context("Matrix sync failed");
+
+    if unknown_token {
+        let relogin = matrix::relogin(&client, &user_id, &auth, store_path.as_deref()).await;
+        health.record_relogin(relogin.is_ok());
+        if let Err(e) = relogin {
+            error = e;
+        }
+    }
+    alerts.failure(Subsystem::Matrix, &error);
+    Err(error)
+}
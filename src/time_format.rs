@@ -0,0 +1,123 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+/// How timestamps are rendered in messages.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeFormat {
+    pub timezone: Tz,
+    pub locale: Locale,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self {
+            timezone: Tz::UTC,
+            locale: Locale::Iso,
+        }
+    }
+}
+
+/// Date conventions of the supported locales. Only the numeric forms are
+/// covered, so no month or day names need translating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locale {
+    /// `2026-03-09 23:30`
+    Iso,
+    /// `03/09/2026 11:30 PM`
+    EnUs,
+    /// `09/03/2026 23:30`, also used for French, Spanish, Italian, ...
+    DayMonthYear,
+    /// `09.03.2026 23:30`, German style.
+    Dotted,
+}
+
+impl Locale {
+    pub fn parse(locale: &str) -> Option<Self> {
+        let locale = locale.trim().replace('_', "-").to_lowercase();
+        let language = locale.split('-').next().unwrap_or_default();
+        match (locale.as_str(), language) {
+            ("iso", _) => Some(Locale::Iso),
+            ("en-us", _) => Some(Locale::EnUs),
+            (_, "en" | "fr" | "es" | "it" | "pt" | "nl") => Some(Locale::DayMonthYear),
+            (_, "de" | "pl" | "cs" | "ru" | "fi" | "nb" | "da") => Some(Locale::Dotted),
+            (_, "sv" | "ja" | "zh" | "ko" | "lt" | "hu") => Some(Locale::Iso),
+            _ => None,
+        }
+    }
+
+    fn date_pattern(self) -> &'static str {
+        match self {
+            Locale::Iso => "%Y-%m-%d",
+            Locale::EnUs => "%m/%d/%Y",
+            Locale::DayMonthYear => "%d/%m/%Y",
+            Locale::Dotted => "%d.%m.%Y",
+        }
+    }
+
+    fn time_pattern(self) -> &'static str {
+        match self {
+            Locale::EnUs => "%I:%M %p",
+            _ => "%H:%M",
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Date and time of day in the configured timezone.
+    pub fn datetime(&self, time: DateTime<Utc>) -> String {
+        let pattern = format!(
+            "{} {}",
+            self.locale.date_pattern(),
+            self.locale.time_pattern()
+        );
+        time.with_timezone(&self.timezone)
+            .format(&pattern)
+            .to_string()
+    }
+
+    /// Calendar day of `time` in the configured timezone.
+    pub fn date(&self, time: DateTime<Utc>) -> String {
+        self.day(time.with_timezone(&self.timezone).date_naive())
+    }
+
+    /// A calendar day that is not tied to an instant, e.g. a week start.
+    pub fn day(&self, day: NaiveDate) -> String {
+        day.format(self.locale.date_pattern()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn defaults_to_iso_utc() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 9, 23, 30, 0).unwrap();
+        let format = TimeFormat::default();
+
+        assert_eq!(format.datetime(time), "2026-03-09 23:30");
+        assert_eq!(format.date(time), "2026-03-09");
+    }
+
+    #[test]
+    fn renders_in_timezone_and_locale() {
+        let time = Utc.with_ymd_and_hms(2026, 3, 9, 23, 30, 0).unwrap();
+        let format = TimeFormat {
+            timezone: "Europe/Paris".parse().unwrap(),
+            locale: Locale::parse("fr_FR").unwrap(),
+        };
+
+        assert_eq!(format.datetime(time), "10/03/2026 00:30");
+        assert_eq!(format.date(time), "10/03/2026");
+    }
+
+    #[test]
+    fn parses_locales() {
+        assert_eq!(Locale::parse("en-US"), Some(Locale::EnUs));
+        assert_eq!(Locale::parse("en_GB"), Some(Locale::DayMonthYear));
+        assert_eq!(Locale::parse("de_DE.UTF-8"), Some(Locale::Dotted));
+        assert_eq!(Locale::parse("xx"), None);
+    }
+}
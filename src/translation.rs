@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::{TranslationConfig, TranslationProvider};
+
+/// A comment translated into the room's language.
+#[derive(Debug, PartialEq)]
+pub struct Translation {
+    pub text: String,
+    /// Language the original was detected to be in.
+    pub source_language: String,
+}
+
+/// Translates Seerr comments for rooms whose members don't all read the
+/// language they were written in.
+#[derive(Clone)]
+pub struct Translator {
+    config: TranslationConfig,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: String,
+    detected_language: Option<DetectedLanguage>,
+}
+
+#[derive(Deserialize)]
+struct DetectedLanguage {
+    language: String,
+}
+
+impl Translator {
+    pub fn new(config: &TranslationConfig) -> Self {
+        Self {
+            config: config.clone(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Language used when the room doesn't set one.
+    pub fn default_language(&self) -> &str {
+        &self.config.language
+    }
+
+    /// Translates `text` into `target`, or returns `None` when it already is
+    /// in that language.
+    pub async fn translate(&self, text: &str, target: &str) -> Result<Option<Translation>> {
+        match self.config.provider {
+            TranslationProvider::LibreTranslate => self.libretranslate(text, target).await,
+        }
+    }
+
+    async fn libretranslate(&self, text: &str, target: &str) -> Result<Option<Translation>> {
+        let response: LibreTranslateResponse = self
+            .http
+            .post(format!(
+                "{}/translate",
+                self.config.url.trim_end_matches('/')
+            ))
+            .timeout(Duration::from_secs(10))
+            .json(&json!({
+                "q": text,
+                "source": "auto",
+                "target": target,
+                "format": "text",
+                "api_key": self.config.api_key,
+            }))
+            .send()
+            .await
+            .context("Failed to reach LibreTranslate")?
+            .error_for_status()
+            .context("LibreTranslate returned error")?
+            .json()
+            .await
+            .context("Invalid LibreTranslate response")?;
+        Ok(translation(response, text, target))
+    }
+}
+
+fn translation(response: LibreTranslateResponse, text: &str, target: &str) -> Option<Translation> {
+    let source_language = response.detected_language?.language;
+    if source_language.eq_ignore_ascii_case(target)
+        || response.translated_text.trim() == text.trim()
+    {
+        return None;
+    }
+    Some(Translation {
+        text: response.translated_text,
+        source_language,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(text: &str, language: &str) -> LibreTranslateResponse {
+        serde_json::from_value(json!({
+            "translatedText": text,
+            "detectedLanguage": { "confidence": 90.0, "language": language },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn keeps_detected_language() {
+        assert_eq!(
+            translation(
+                response("Subtitles are out of sync", "fr"),
+                "Les sous-titres sont décalés",
+                "en"
+            ),
+            Some(Translation {
+                text: "Subtitles are out of sync".to_string(),
+                source_language: "fr".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn skips_text_already_in_target_language() {
+        assert_eq!(translation(response("Fixed", "en"), "Fixed", "en"), None);
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use futures_util::StreamExt;
+use matrix_sdk::Client;
+use matrix_sdk::encryption::verification::{
+    SasState, SasVerification, Verification, VerificationRequest, VerificationRequestState,
+};
+use matrix_sdk::event_handler::Ctx;
+use matrix_sdk::ruma::UserId;
+use matrix_sdk::ruma::api::client::uiaa::{AuthData, Password, UserIdentifier};
+use matrix_sdk::ruma::events::key::verification::request::ToDeviceKeyVerificationRequestEvent;
+use matrix_sdk::ruma::events::room::message::{MessageType, OriginalSyncRoomMessageEvent};
+use tracing::{info, warn};
+
+use crate::commands::CommandContext;
+use crate::config::MatrixAuth;
+
+/// Creates the cross-signing identity of the bot account if it has none yet.
+/// Uploading the keys needs interactive auth, which only works with a password.
+pub async fn bootstrap_cross_signing(client: &Client, auth: &MatrixAuth) -> Result<()> {
+    let encryption = client.encryption();
+    let Err(e) = encryption.bootstrap_cross_signing_if_needed(None).await else {
+        info!("Cross-signing is set up");
+        return Ok(());
+    };
+
+    let (Some(response), MatrixAuth::Password(password)) = (e.as_uiaa_response(), auth) else {
+        bail!("Failed to bootstrap cross-signing: {e}");
+    };
+    let user_id = client.user_id().map(|u| u.to_string()).unwrap_or_default();
+    let mut password = Password::new(UserIdentifier::UserIdOrLocalpart(user_id), password.clone());
+    password.session = response.session.clone();
+
+    encryption
+        .bootstrap_cross_signing(Some(AuthData::Password(password)))
+        .await?;
+    info!("Cross-signing identity created");
+    Ok(())
+}
+
+pub async fn on_to_device_request(
+    event: ToDeviceKeyVerificationRequestEvent,
+    client: Client,
+    ctx: Ctx<Arc<CommandContext>>,
+) {
+    if !is_admin(&ctx, &event.sender) {
+        return;
+    }
+    let request = client
+        .encryption()
+        .get_verification_request(&event.sender, &event.content.transaction_id)
+        .await;
+    if let Some(request) = request {
+        tokio::spawn(run_verification(request));
+    }
+}
+
+pub async fn on_room_request(
+    event: OriginalSyncRoomMessageEvent,
+    client: Client,
+    ctx: Ctx<Arc<CommandContext>>,
+) {
+    if !matches!(event.content.msgtype, MessageType::VerificationRequest(_))
+        || !is_admin(&ctx, &event.sender)
+    {
+        return;
+    }
+    let request = client
+        .encryption()
+        .get_verification_request(&event.sender, &event.event_id)
+        .await;
+    if let Some(request) = request {
+        tokio::spawn(run_verification(request));
+    }
+}
+
+fn is_admin(ctx: &CommandContext, user_id: &UserId) -> bool {
+    ctx.settings.get().admin_users.iter().any(|u| u == user_id)
+}
+
+async fn run_verification(request: VerificationRequest) {
+    let user_id = request.other_user_id().to_owned();
+    match verify(request).await {
+        Ok(()) => info!(%user_id, "Device verification done"),
+        Err(e) => warn!(%user_id, "Device verification failed: {e:#}"),
+    }
+}
+
+async fn verify(request: VerificationRequest) -> Result<()> {
+    info!(user_id = %request.other_user_id(), "Accepting verification request");
+    request.accept().await?;
+
+    let mut changes = request.changes();
+    while let Some(state) = changes.next().await {
+        match state {
+            VerificationRequestState::Transitioned {
+                verification: Verification::SasV1(sas),
+            } => return verify_sas(sas).await,
+            VerificationRequestState::Transitioned { .. } => {
+                bail!("Only emoji verification is supported")
+            }
+            VerificationRequestState::Done => return Ok(()),
+            VerificationRequestState::Cancelled(info) => bail!("Cancelled: {}", info.reason()),
+            _ => {}
+        }
+    }
+
+    bail!("Verification request went away")
+}
+
+async fn verify_sas(sas: SasVerification) -> Result<()> {
+    sas.accept().await?;
+
+    let mut changes = sas.changes();
+    while let Some(state) = changes.next().await {
+        match state {
+            SasState::KeysExchanged {
+                emojis: Some(emojis),
+                ..
+            } => {
+                // Nobody can compare on the bot side: the admin checks these
+                // against the bot log and cancels on their end if they differ
+                let emojis = emojis
+                    .emojis
+                    .iter()
+                    .map(|e| format!("{} ({})", e.symbol, e.description))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                info!(user_id = %sas.other_user_id(), "Verification emojis: {emojis}");
+                sas.confirm().await?;
+            }
+            SasState::KeysExchanged { emojis: None, .. } => {
+                sas.cancel().await?;
+                bail!("Only emoji verification is supported");
+            }
+            SasState::Done { .. } => return Ok(()),
+            SasState::Cancelled(info) => bail!("Cancelled: {}", info.reason()),
+            _ => {}
+        }
+    }
+
+    bail!("Verification went away")
+}
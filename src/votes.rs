@@ -0,0 +1,178 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use matrix_sdk::event_handler::Ctx;
+use matrix_sdk::ruma::OwnedEventId;
+use matrix_sdk::ruma::events::reaction::OriginalSyncReactionEvent;
+use matrix_sdk::{Client, Room};
+use tracing::{Instrument, error, info, info_span};
+
+use crate::AppState;
+use crate::audit;
+use crate::db::{self, VoteCounts};
+use crate::markdown;
+use crate::matrix;
+use crate::media_details;
+use crate::quota;
+use crate::request::RequestStatus;
+use crate::webhook;
+
+pub const UPVOTE: &str = "👍";
+pub const DOWNVOTE: &str = "👎";
+
+/// Counts 👍 and 👎 reactions of room members on pending request cards.
+pub async fn on_reaction(
+    event: OriginalSyncReactionEvent,
+    room: Room,
+    client: Client,
+    state: Ctx<Arc<AppState>>,
+) {
+    if room.room_id() != state.room.room_id() || client.user_id() == Some(event.sender.as_ref()) {
+        return;
+    }
+    let upvote = match event.content.relates_to.key.as_str() {
+        UPVOTE => true,
+        DOWNVOTE => false,
+        _ => return,
+    };
+
+    let span = info_span!("vote", sender = %event.sender);
+    async {
+        let result = handle_vote(&state, &event, upvote).await;
+        state.alerts.observe(&result);
+        if let Err(e) = result {
+            error!("Error handling vote: {e:#}");
+        }
+    }
+    .instrument(span)
+    .await
+}
+
+async fn handle_vote(
+    state: &AppState,
+    event: &OriginalSyncReactionEvent,
+    upvote: bool,
+) -> Result<()> {
+    let root = &event.content.relates_to.event_id;
+    let Some(request_event) =
+        db::get_request_event_by_matrix_event_id(&state.db, root.as_str()).await?
+    else {
+        return Ok(());
+    };
+    if request_event.status != Some(RequestStatus::Pending) {
+        return Ok(());
+    }
+    let request_id = request_event.request_id;
+    let _permit = state.limiter.acquire(None).await;
+
+    db::upsert_request_vote(
+        &state.db,
+        request_id,
+        event.sender.as_str(),
+        upvote,
+        event.event_id.as_str(),
+    )
+    .await?;
+    info!(request_id, upvote, "Vote recorded");
+    tally(state, request_id).await
+}
+
+/// Forgets the vote cast with a reaction that got redacted. Returns `false`
+/// when `redacted` was not a vote.
+pub async fn retract(state: &AppState, redacted: &str) -> Result<bool> {
+    let _permit = state.limiter.acquire(None).await;
+    let Some(request_id) = db::delete_request_vote(&state.db, redacted).await? else {
+        return Ok(false);
+    };
+    info!(request_id, "Vote retracted");
+    tally(state, request_id).await?;
+    Ok(true)
+}
+
+/// Whether enough distinct room members upvoted to approve the request.
+fn approves(votes: VoteCounts, threshold: Option<usize>) -> bool {
+    threshold.is_some_and(|threshold| votes.up >= threshold as i64)
+}
+
+/// Shows the votes on the request card, and approves the request in Seerr
+/// once `REQUEST_VOTE_THRESHOLD` members upvoted it.
+async fn tally(state: &AppState, request_id: i64) -> Result<()> {
+    let Some(card) = db::get_request_card(&state.db, request_id).await? else {
+        return Ok(());
+    };
+    if card.status != Some(RequestStatus::Pending) {
+        return Ok(());
+    }
+    let root_event_id: OwnedEventId = card.matrix_event_id.as_str().try_into()?;
+    let votes = db::request_vote_counts(&state.db, request_id).await?;
+
+    let mut status = RequestStatus::Pending;
+    let approved = approves(votes, state.settings.get().request_vote_threshold);
+    // Claimed before calling Seerr so concurrent votes approve only once, the
+    // vote that claimed it updates the card
+    if approved && !db::claim_request_approval(&state.db, request_id).await? {
+        return Ok(());
+    }
+    if approved {
+        let result = state.seerr_client.approve_request(request_id).await;
+        let details = format!("request {request_id}, {} votes", votes.up);
+        audit::record(
+            &state.db,
+            "votes",
+            "seerr.approve_request",
+            None,
+            Some(&details),
+            &result,
+        )
+        .await;
+        match result {
+            Ok(()) => {
+                status = RequestStatus::Approved;
+                info!(request_id, votes = votes.up, "Request approved by votes");
+
+                let markdown = format!("**{UPVOTE} Approved by {} votes**", votes.up);
+                matrix::send_thread_markdown(&state.room, &root_event_id, &markdown).await?;
+            }
+            // The card still shows the votes, the request stays pending
+            Err(e) => {
+                db::set_request_status(&state.db, request_id, RequestStatus::Pending).await?;
+                match quota::reply(&e, &state.settings.get().time_format) {
+                    Some(markdown) => {
+                        matrix::send_thread_markdown(&state.room, &root_event_id, &markdown)
+                            .await?;
+                    }
+                    None => return Err(e),
+                }
+            }
+        }
+    }
+
+    let seerr_url = state
+        .seerr_client
+        .media_url(card.media_type.as_deref(), card.media_tmdb_id);
+    let media = media_details::lookup(state, card.media_type.as_deref(), card.media_tmdb_id).await;
+    let markdown = webhook::request_card(
+        card.subject.as_deref().unwrap_or("Unknown title"),
+        card.requested_by.as_deref().unwrap_or("unknown"),
+        status,
+        Some(votes),
+        media.as_ref(),
+        seerr_url.as_deref(),
+    );
+    let (plain, html) = markdown::render(&markdown);
+    matrix::edit_html_message(&state.room, &root_event_id, &plain, &html).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approves_once_the_threshold_is_reached() {
+        let votes = |up| VoteCounts { up, down: 5 };
+        assert!(!approves(votes(10), None));
+        assert!(!approves(votes(2), Some(3)));
+        assert!(approves(votes(3), Some(3)));
+    }
+}
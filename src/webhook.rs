@@ -3,44 +3,157 @@ use std::sync::Arc;
 use axum::Json;
 use axum::extract::State;
 use axum::http::StatusCode;
-use tracing::{error, info, warn};
+use chrono::{DateTime, Utc};
+use matrix_sdk::ruma::OwnedEventId;
+use tracing::{Instrument, error, info, info_span, warn};
 
 use crate::AppState;
-use crate::db;
+use crate::alerts::Subsystem;
+use crate::audit;
+use crate::batching::BatchedRequest;
+use crate::config::PushEvent;
+use crate::dashboard;
+use crate::db::{self, CommentOrigin, IssueDetails, IssueHistory, IssueMedia, VoteCounts};
+use crate::issue::{IssueCategory, IssueState};
+use crate::issue_images;
+use crate::lifecycle::{self, IssueEvent};
+use crate::markdown;
 use crate::matrix;
-use crate::seerr::SeerrWebhookPayload;
+use crate::media_details;
+use crate::outbox;
+use crate::priority::{self, Priority};
+use crate::quiet_hours;
+use crate::reactions;
+use crate::reconcile;
+use crate::remediation::EpisodeScope;
+use crate::request::RequestStatus;
+use crate::room_config;
+use crate::routing::{self, Stream};
+use crate::rules;
+use crate::seerr::{SeerrMediaDetails, SeerrWebhookPayload};
+use crate::time_format::TimeFormat;
 
 pub async fn handle_seerr_webhook(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<SeerrWebhookPayload>,
 ) -> StatusCode {
+    // Gives the logs and error reports of the webhook its context
+    let span = info_span!(
+        "webhook",
+        notification_type = %payload.notification_type,
+        issue_id = payload.issue_id.as_deref(),
+    );
+    receive(&state, &payload).instrument(span).await
+}
+
+async fn receive(state: &AppState, payload: &SeerrWebhookPayload) -> StatusCode {
     info!(
         notification_type = %payload.notification_type,
         subject = %payload.subject,
         "Received Seerr webhook"
     );
 
-    let result = match payload.notification_type.as_str() {
-        "ISSUE_CREATED" => handle_issue_created(&state, &payload).await,
-        "ISSUE_RESOLVED" => handle_issue_resolved(&state, &payload).await,
-        "ISSUE_COMMENT" => handle_issue_comment(&state, &payload).await,
-        "ISSUE_REOPENED" => handle_issue_reopened(&state, &payload).await,
-        other => {
-            warn!("Unknown notification type: {other}");
-            return StatusCode::OK;
+    let received_at = Utc::now();
+    if quiet_hours::should_hold(state).await {
+        match quiet_hours::hold(state, payload).await {
+            Ok(quiet_id) => {
+                info!(quiet_id, "Notification held until the end of quiet hours");
+                if let Err(e) = reconcile::mark_processed(&state.db, received_at).await {
+                    warn!("Failed to record the last processed webhook: {e:#}");
+                }
+                return StatusCode::OK;
+            }
+            Err(e) => error!("Failed to hold notification, posting it now: {e:#}"),
+        }
+    }
+    let Err(e) = process_payload(state, payload).await else {
+        if let Err(e) = reconcile::mark_processed(&state.db, received_at).await {
+            warn!("Failed to record the last processed webhook: {e:#}");
         }
+        return StatusCode::OK;
     };
 
-    match result {
-        Ok(()) => StatusCode::OK,
+    error!("Error handling webhook: {e:#}");
+    match outbox::enqueue(state, payload, &e).await {
+        Ok(outbox_id) => {
+            warn!(outbox_id, "Webhook queued in the outbox for retry");
+            StatusCode::ACCEPTED
+        }
         Err(e) => {
-            error!("Error handling webhook: {e:#}");
+            error!("Failed to queue webhook in the outbox: {e:#}");
             StatusCode::INTERNAL_SERVER_ERROR
         }
     }
 }
 
-async fn handle_issue_created(
+pub async fn process_payload(
+    state: &AppState,
+    payload: &SeerrWebhookPayload,
+) -> anyhow::Result<()> {
+    let _permit = state
+        .limiter
+        .acquire(payload.issue_id.as_deref().and_then(|id| id.parse().ok()))
+        .await;
+    let result = dispatch(state, payload).await;
+    audit::record(
+        &state.db,
+        "seerr",
+        &format!("webhook.{}", payload.notification_type),
+        payload.issue_id.as_deref().and_then(|id| id.parse().ok()),
+        None,
+        &result,
+    )
+    .await;
+    match &result {
+        Ok(()) => state.alerts.success(Subsystem::Matrix),
+        Err(_) => state.alerts.observe(&result),
+    }
+    result
+}
+
+async fn dispatch(state: &AppState, payload: &SeerrWebhookPayload) -> anyhow::Result<()> {
+    let room_config = room_config::load(&state.room, &state.settings.get().room_defaults).await?;
+    let room_id = state.room.room_id().as_str();
+    let route = match Stream::of_notification(&payload.notification_type) {
+        Some(stream) => routing::route(&state.db, room_id, stream).await?,
+        None => None,
+    };
+    if !route.unwrap_or_else(|| room_config.allows(&payload.notification_type)) {
+        info!(notification_type = %payload.notification_type, "Notification type filtered out in this room");
+        return Ok(());
+    }
+    // Follow-ups of issues whose card was filtered out have no thread to go to
+    if !route.unwrap_or_else(|| room_config.allows("ISSUE_CREATED"))
+        && let Some(issue_id) = payload.issue_id.as_deref().and_then(|id| id.parse().ok())
+        && db::get_issue_event(&state.db, issue_id).await?.is_none()
+    {
+        info!(
+            issue_id,
+            "Issue not tracked in this room, skipping notification"
+        );
+        return Ok(());
+    }
+
+    match payload.notification_type.as_str() {
+        "ISSUE_CREATED" => handle_issue_created(state, payload).await,
+        "ISSUE_RESOLVED" => handle_issue_resolved(state, payload).await,
+        "ISSUE_COMMENT" => handle_issue_comment(state, payload).await,
+        "ISSUE_REOPENED" => handle_issue_reopened(state, payload).await,
+        other if RequestStatus::from_notification_type(other).is_some() => {
+            handle_request_event(state, payload).await?;
+            if other == RequestStatus::Available.notification_type() {
+                refresh_library(state).await;
+            }
+            Ok(())
+        }
+        other => {
+            warn!("Unknown notification type: {other}");
+            Ok(())
+        }
+    }
+}
+
+pub(crate) async fn handle_issue_created(
     state: &AppState,
     payload: &SeerrWebhookPayload,
 ) -> anyhow::Result<()> {
@@ -51,30 +164,343 @@ async fn handle_issue_created(
         .parse()
         .map_err(|_| anyhow::anyhow!("Invalid issue_id"))?;
 
-    let reported_by = payload.reported_by.as_deref().unwrap_or("unknown");
-    let message = payload.message.as_deref().unwrap_or("");
+    let details = IssueDetails {
+        subject: payload.subject.clone(),
+        description: payload.message.clone().unwrap_or_default(),
+        reported_by: payload
+            .reported_by
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string()),
+        category: payload.issue_type.as_deref().and_then(IssueCategory::parse),
+        episodes: issue_episodes(state, issue_id, payload).await,
+    };
 
-    let plain_body = format!(
-        "🔴 New Seerr issue\nSubject: {}\nDescription: {}\nReported by: {}",
-        payload.subject, message, reported_by
+    let room_id = state.room.room_id().to_string();
+    let media = issue_media(payload);
+    let is_new = db::begin_issue_event(&state.db, issue_id, &room_id, &details).await?;
+    db::set_issue_media(&state.db, issue_id, &media).await?;
+    if !is_new {
+        return update_issue_card(state, issue_id, &details).await;
+    }
+
+    if let Some(original) = db::find_open_issue_for_media(&state.db, issue_id, &media).await? {
+        return post_duplicate_note(state, issue_id, &details, &original).await;
+    }
+
+    let event_id = post_issue_card(state, issue_id, &details).await?;
+    info!(issue_id, %event_id, "Issue created message sent");
+    if let Some(push) = &state.push {
+        push.send(
+            PushEvent::IssueCreated,
+            &format!("New issue: {}", details.subject),
+            &format!(
+                "{}\nReported by {}",
+                details.description, details.reported_by
+            ),
+        );
+    }
+
+    reactions::transition(
+        &state.room,
+        &state.db,
+        &state.settings.get().reaction_emojis,
+        issue_id,
+        &event_id,
+        IssueState::Open,
+    )
+    .await?;
+    issue_images::post(
+        state,
+        &event_id,
+        payload.image.as_deref(),
+        &details.description,
     );
-    let html_body = format!(
-        "<h4>🔴 New Seerr issue</h4>\
-         <b>Subject:</b> {}<br/>\
-         <b>Description:</b> {}<br/>\
-         <b>Reported by:</b> {}",
-        payload.subject, message, reported_by
+
+    refresh_dashboard(state).await;
+
+    Ok(())
+}
+
+/// Posts a new issue about media that already has an open issue in the thread
+/// of that issue rather than as a card of its own, for an admin to
+/// `!issues merge` it or to answer it there.
+async fn post_duplicate_note(
+    state: &AppState,
+    issue_id: i64,
+    details: &IssueDetails,
+    original: &db::IssueEvent,
+) -> anyhow::Result<()> {
+    let root_event_id: OwnedEventId = original.matrix_event_id.as_str().try_into()?;
+    let sent = async {
+        let reporter = user_mention(state, &details.reported_by).await?;
+        let seerr_url = state.seerr_client.issue_url(issue_id);
+        let note = duplicate_note(
+            details,
+            &reporter,
+            issue_id,
+            original.issue_id,
+            seerr_url.as_deref(),
+        );
+        matrix::send_thread_markdown(&state.room, &root_event_id, &note).await
+    }
+    .await;
+    if let Err(e) = sent {
+        abandon_issue_event(state, issue_id, None).await;
+        return Err(e);
+    }
+
+    db::mark_duplicate(
+        &state.db,
+        issue_id,
+        original.issue_id,
+        root_event_id.as_str(),
+    )
+    .await?;
+    info!(
+        issue_id,
+        duplicate_of = original.issue_id,
+        "Possible duplicate issue posted in thread"
     );
+    refresh_dashboard(state).await;
+    Ok(())
+}
 
-    let event_id = matrix::send_html_message(&state.room, &plain_body, &html_body).await?;
-    let room_id = state.room.room_id().to_string();
+/// Seerr webhooks don't say which episodes a TV issue is about, the issue
+/// details do. The card is posted without them if Seerr can't be reached.
+async fn issue_episodes(
+    state: &AppState,
+    issue_id: i64,
+    payload: &SeerrWebhookPayload,
+) -> Option<EpisodeScope> {
+    if payload.media_type.as_deref() != Some("tv") {
+        return None;
+    }
+    match state.seerr_client.get_issue(issue_id).await {
+        Ok(issue) => issue.map(|issue| EpisodeScope::of(&issue)),
+        Err(e) => {
+            warn!(issue_id, "Failed to fetch the episodes of the issue: {e:#}");
+            None
+        }
+    }
+}
 
-    db::insert_issue_event(&state.db, issue_id, event_id.as_str(), &room_id).await?;
-    info!(issue_id, %event_id, "Issue created message sent");
+fn issue_media(payload: &SeerrWebhookPayload) -> IssueMedia {
+    let id = |id: &Option<String>| id.as_deref().and_then(|id| id.parse().ok());
+    IssueMedia {
+        media_type: payload.media_type.clone(),
+        tmdb_id: id(&payload.media_tmdbid),
+        tvdb_id: id(&payload.media_tvdbid),
+    }
+}
+
+/// Seerr re-sent the creation of an issue that already has a card: edit the
+/// card with the latest details instead of posting a duplicate. Duplicates
+/// only have their details updated.
+async fn update_issue_card(
+    state: &AppState,
+    issue_id: i64,
+    details: &IssueDetails,
+) -> anyhow::Result<()> {
+    let issue_event = db::get_issue_event(&state.db, issue_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No event found for issue {issue_id}"))?;
+    let root_event_id = issue_event.matrix_event_id.as_str().try_into()?;
+
+    db::update_issue_details(&state.db, issue_id, details).await?;
+    // A duplicate shares the card of the issue it duplicates, which stays as is
+    if let Some(original) = db::get_duplicate_of(&state.db, issue_id).await? {
+        info!(
+            issue_id,
+            duplicate_of = original,
+            "Duplicate issue already posted, card left as is"
+        );
+        return Ok(());
+    }
+    let reporter = user_mention(state, &details.reported_by).await?;
+    let seerr_url = state.seerr_client.issue_url(issue_id);
+    let priority = state.settings.get().priorities.of_issue(details.category);
+    let media = issue_media_details(state, issue_id).await;
+    let card = issue_card(
+        details,
+        &reporter,
+        priority,
+        media.as_ref(),
+        seerr_url.as_deref(),
+    );
+    let (plain, html) = priority.render(&card);
+    matrix::edit_html_message(&state.room, &root_event_id, &plain, &html).await?;
+    info!(issue_id, "Issue already posted, card updated");
 
+    refresh_dashboard(state).await;
     Ok(())
 }
 
+/// Sends the card of an issue recorded with [`db::begin_issue_event`] and
+/// stores its event id. On failure the pending row is dropped, and the card
+/// redacted if it was sent, so the webhook can be retried from scratch.
+async fn post_issue_card(
+    state: &AppState,
+    issue_id: i64,
+    details: &IssueDetails,
+) -> anyhow::Result<OwnedEventId> {
+    let sent = async {
+        let reporter = user_mention(state, &details.reported_by).await?;
+        let seerr_url = state.seerr_client.issue_url(issue_id);
+        let media = issue_media_details(state, issue_id).await;
+        let settings = state.settings.get();
+        let priority = settings.priorities.of_issue(details.category);
+        let card = issue_card(
+            details,
+            &reporter,
+            priority,
+            media.as_ref(),
+            seerr_url.as_deref(),
+        );
+        priority::send_card(&state.room, &card, priority, &settings.priorities).await
+    }
+    .await;
+    let event_id = match sent {
+        Ok(event_id) => event_id,
+        Err(e) => {
+            abandon_issue_event(state, issue_id, None).await;
+            return Err(e);
+        }
+    };
+
+    if let Err(e) = db::update_issue_event_id(&state.db, issue_id, event_id.as_str()).await {
+        abandon_issue_event(state, issue_id, Some(&event_id)).await;
+        return Err(e);
+    }
+    Ok(event_id)
+}
+
+async fn abandon_issue_event(state: &AppState, issue_id: i64, sent: Option<&OwnedEventId>) {
+    if let Some(event_id) = sent
+        && let Err(e) =
+            matrix::redact_event(&state.room, event_id, Some("Failed to record issue")).await
+    {
+        warn!(issue_id, %event_id, "Failed to redact orphaned issue card: {e:#}");
+    }
+    if let Err(e) = db::delete_pending_issue_event(&state.db, issue_id).await {
+        // Left for the repair pass on the next start
+        warn!(issue_id, "Failed to drop pending issue row: {e:#}");
+    }
+}
+
+/// Posts the cards of issues left pending by a previous run that stopped
+/// between recording an issue and storing its card.
+pub async fn repair_pending_issues(state: &AppState) -> anyhow::Result<()> {
+    for (issue_id, details) in db::list_pending_issue_events(&state.db).await? {
+        warn!(issue_id, "Repairing issue left without a card");
+        let event_id = post_issue_card(state, issue_id, &details).await?;
+        reactions::transition(
+            &state.room,
+            &state.db,
+            &state.settings.get().reaction_emojis,
+            issue_id,
+            &event_id,
+            IssueState::Open,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// TMDB details for the card of an issue, see [`media_details::lookup`].
+pub(crate) async fn issue_media_details(
+    state: &AppState,
+    issue_id: i64,
+) -> Option<SeerrMediaDetails> {
+    let media = match db::get_issue_media(&state.db, issue_id).await {
+        Ok(media) => media?,
+        Err(e) => {
+            warn!(issue_id, "Failed to look up the media of the issue: {e:#}");
+            return None;
+        }
+    };
+    media_details::lookup(state, media.media_type.as_deref(), media.tmdb_id).await
+}
+
+/// Markdown of the root message an issue thread hangs off, `reporter` being
+/// the reporter as rendered by [`user_mention`].
+pub fn issue_card(
+    details: &IssueDetails,
+    reporter: &str,
+    priority: Priority,
+    media: Option<&SeerrMediaDetails>,
+    seerr_url: Option<&str>,
+) -> String {
+    format!(
+        "#### 🔴 New Seerr issue\n{}{}{}{}",
+        priority.field(),
+        issue_fields(details, reporter),
+        media.map(media_details::fields).unwrap_or_default(),
+        seerr_link(seerr_url)
+    )
+}
+
+/// Markdown of a new issue posted in the thread of an open issue about the
+/// same media.
+fn duplicate_note(
+    details: &IssueDetails,
+    reporter: &str,
+    issue_id: i64,
+    original_issue_id: i64,
+    seerr_url: Option<&str>,
+) -> String {
+    format!(
+        "#### 🔁 New issue {issue_id}, possible duplicate of issue {original_issue_id}\n{}{}\n\n\
+         Answer it in this thread, or `!issues merge {issue_id}` to resolve it as a duplicate.",
+        issue_fields(details, reporter),
+        seerr_link(seerr_url)
+    )
+}
+
+fn issue_fields(details: &IssueDetails, reporter: &str) -> String {
+    let category = details
+        .category
+        .map(|c| format!("**Category:** {} {c}  \n", c.icon()))
+        .unwrap_or_default();
+    format!(
+        "**Subject:** {}  \n\
+         {category}\
+         **Description:** {}  \n\
+         **Reported by:** {reporter}",
+        markdown::escape(&issue_subject(&details.subject, details.episodes)),
+        markdown::escape(&details.description)
+    )
+}
+
+/// Line linking to the issue or request in Seerr, empty without
+/// `SEERR_PUBLIC_URL`.
+pub fn seerr_link(url: Option<&str>) -> String {
+    url.map(|url| format!("  \n[Open in Seerr]({url})"))
+        .unwrap_or_default()
+}
+
+/// Subject of an issue with the episodes it is about, e.g. `The Expanse
+/// S02E05`, since the title alone doesn't say where to look.
+pub fn issue_subject(subject: &str, episodes: Option<EpisodeScope>) -> String {
+    match episodes {
+        Some(episodes) => format!("{subject}{}", episodes.subject_suffix()),
+        None => subject.to_string(),
+    }
+}
+
+/// Mentions the Matrix user linked to a Seerr user with `!users link`, or
+/// falls back to the Seerr name.
+pub async fn user_mention(state: &AppState, seerr_user: &str) -> anyhow::Result<String> {
+    let mapping = db::get_user_mapping_by_seerr_user(&state.db, seerr_user).await?;
+    Ok(match mapping {
+        Some(mapping) => format!(
+            "[{}](https://matrix.to/#/{})",
+            markdown::escape(seerr_user),
+            mapping.matrix_user_id
+        ),
+        None => markdown::escape(seerr_user),
+    })
+}
+
 async fn handle_issue_resolved(
     state: &AppState,
     payload: &SeerrWebhookPayload,
@@ -92,22 +518,152 @@ async fn handle_issue_resolved(
 
     let root_event_id = issue_event.matrix_event_id.as_str().try_into()?;
 
-    let comment = payload.comment.as_deref().unwrap_or("");
-    let commented_by = payload.commented_by.as_deref().unwrap_or("unknown");
+    let comment = markdown::escape(payload.comment.as_deref().unwrap_or(""));
+    let commented_by = markdown::escape(payload.commented_by.as_deref().unwrap_or("unknown"));
 
-    let plain_body = format!("✅ Issue resolved\nComment: {comment}\nBy: {commented_by}");
-    let html_body = format!(
-        "<b>✅ Issue resolved</b><br/>\
-         <b>Comment:</b> {comment}<br/>\
-         <b>By:</b> {commented_by}"
+    let label = shared_thread_label(state, issue_id).await?;
+    let mut markdown = format!(
+        "{label}**✅ Issue resolved**  \n\
+         **Comment:** {comment}  \n\
+         **By:** {commented_by}{}",
+        seerr_link(state.seerr_client.issue_url(issue_id).as_deref())
     );
+    if let Some(history) = db::get_issue_history(&state.db, issue_id).await? {
+        markdown.push_str("\n\n");
+        markdown.push_str(&resolution_summary(
+            &history,
+            Utc::now(),
+            &state.settings.get().time_format,
+        ));
+    }
 
-    matrix::send_thread_reply(&state.room, &root_event_id, &plain_body, &html_body).await?;
-
-    let reaction_event_id = matrix::send_reaction(&state.room, &root_event_id, "✅").await?;
-    db::set_reaction_event_id(&state.db, issue_id, reaction_event_id.as_str()).await?;
+    matrix::send_thread_markdown(&state.room, &root_event_id, &markdown).await?;
+    let resolved = lifecycle::apply(
+        &state.db,
+        issue_id,
+        IssueEvent::Resolved,
+        payload.commented_by.as_deref(),
+    )
+    .await?;
+    update_reaction(state, issue_id, &root_event_id, resolved).await?;
 
     info!(issue_id, "Issue resolved message sent");
+
+    refresh_dashboard(state).await;
+    Ok(())
+}
+
+/// Closing lines of the resolution reply recapping the issue lifecycle.
+fn resolution_summary(
+    history: &IssueHistory,
+    resolved_at: DateTime<Utc>,
+    time_format: &TimeFormat,
+) -> String {
+    let open_days = (resolved_at - history.created_at).num_days();
+    let open_for = match open_days {
+        0 => "less than a day".to_string(),
+        1 => "1 day".to_string(),
+        n => format!("{n} days"),
+    };
+    let comments = match history.comment_count {
+        1 => "1 comment".to_string(),
+        n => format!("{n} comments"),
+    };
+
+    format!(
+        "**📋 Summary:** opened on {} by {}, open for {open_for}, {comments}",
+        time_format.date(history.created_at),
+        markdown::escape(history.reported_by.as_deref().unwrap_or("unknown")),
+    )
+}
+
+fn comment_markdown(label: &str, commented_by: &str, comment: &str) -> String {
+    format!(
+        "{label}**💬 {} :** {}",
+        markdown::escape(commented_by),
+        markdown::escape(comment)
+    )
+}
+
+/// The comment reply, translated into the room's language with the original
+/// collapsed under it when translation is set up. A failed translation only
+/// costs the translation.
+async fn render_comment(
+    state: &AppState,
+    label: &str,
+    commented_by: &str,
+    comment: &str,
+) -> (String, String) {
+    let markdown = comment_markdown(label, commented_by, comment);
+    let Some(translator) = &state.translator else {
+        return markdown::render(&markdown);
+    };
+    let room_language =
+        match room_config::load(&state.room, &state.settings.get().room_defaults).await {
+            Ok(room_config) => room_config.language,
+            Err(e) => {
+                warn!("Failed to read the room config: {e:#}");
+                None
+            }
+        };
+    let language = room_language
+        .as_deref()
+        .unwrap_or(translator.default_language());
+    match translator.translate(comment, language).await {
+        Ok(Some(translation)) => markdown::render_with_details(
+            &comment_markdown(label, commented_by, &translation.text),
+            &format!("Original ({})", translation.source_language),
+            &markdown::escape(comment),
+        ),
+        Ok(None) => markdown::render(&markdown),
+        Err(e) => {
+            warn!("Failed to translate comment: {e:#}");
+            markdown::render(&markdown)
+        }
+    }
+}
+
+/// Reflects a comment edited in Seerr on its mirror in the issue thread.
+pub async fn edit_comment(
+    state: &AppState,
+    seerr_comment_id: i64,
+    commented_by: &str,
+    comment: &str,
+) -> anyhow::Result<()> {
+    let Some(comment_event) = db::get_comment_event(&state.db, seerr_comment_id).await? else {
+        return Ok(());
+    };
+    if comment_event.origin == CommentOrigin::Matrix {
+        // The Matrix message is the original, it can't be edited on the user's behalf
+        return Ok(());
+    }
+
+    let event_id = comment_event.matrix_event_id.as_str().try_into()?;
+    let label = shared_thread_label(state, comment_event.issue_id).await?;
+    let (plain, html) = render_comment(state, &label, commented_by, comment).await;
+    matrix::edit_html_message(&state.room, &event_id, &plain, &html).await?;
+    db::update_comment_message(&state.db, seerr_comment_id, comment).await?;
+    info!(
+        issue_id = comment_event.issue_id,
+        seerr_comment_id, "Comment edited"
+    );
+    Ok(())
+}
+
+/// Redacts the mirror of a comment deleted in Seerr.
+pub async fn delete_comment(state: &AppState, seerr_comment_id: i64) -> anyhow::Result<()> {
+    let Some(comment_event) = db::get_comment_event(&state.db, seerr_comment_id).await? else {
+        return Ok(());
+    };
+    if comment_event.origin == CommentOrigin::Seerr {
+        let event_id = comment_event.matrix_event_id.as_str().try_into()?;
+        matrix::redact_event(&state.room, &event_id, Some("Comment deleted in Seerr")).await?;
+    }
+    db::delete_comment_event(&state.db, seerr_comment_id).await?;
+    info!(
+        issue_id = comment_event.issue_id,
+        seerr_comment_id, "Comment deleted"
+    );
     Ok(())
 }
 
@@ -131,10 +687,54 @@ async fn handle_issue_comment(
     let comment = payload.comment.as_deref().unwrap_or("");
     let commented_by = payload.commented_by.as_deref().unwrap_or("unknown");
 
-    let plain_body = format!("💬 {commented_by} : {comment}");
-    let html_body = format!("<b>💬 {commented_by} :</b> {comment}");
+    if state.forwarded_comments.is_echo(issue_id, comment) {
+        info!(issue_id, "Comment sent from the thread, skipping");
+        return Ok(());
+    }
+
+    let seerr_comment_id = payload
+        .comment_id
+        .as_deref()
+        .and_then(|id| id.parse::<i64>().ok());
+    if let Some(comment_id) = seerr_comment_id
+        && db::get_comment_event(&state.db, comment_id)
+            .await?
+            .is_some()
+    {
+        info!(
+            issue_id,
+            comment_id, "Comment already in the thread, skipping"
+        );
+        return Ok(());
+    }
+
+    let label = shared_thread_label(state, issue_id).await?;
+    let (plain, html) = render_comment(state, &label, commented_by, comment).await;
+    let event_id = matrix::send_thread_reply(&state.room, &root_event_id, &plain, &html).await?;
+    db::insert_comment_event(
+        &state.db,
+        issue_id,
+        seerr_comment_id,
+        event_id.as_str(),
+        CommentOrigin::Seerr,
+        comment,
+    )
+    .await?;
+    db::increment_comment_count(&state.db, issue_id).await?;
+    issue_images::post(state, &root_event_id, None, comment);
+    // Reporters following up on their own issue don't answer it
+    if payload.commented_by.is_some() && payload.commented_by != payload.reported_by {
+        db::record_first_response(&state.db, issue_id).await?;
+        db::set_awaiting_reporter(&state.db, issue_id, true).await?;
+    } else if payload.commented_by.is_some() {
+        db::set_awaiting_reporter(&state.db, issue_id, false).await?;
+    }
 
-    matrix::send_thread_reply(&state.room, &root_event_id, &plain_body, &html_body).await?;
+    let commented = lifecycle::apply(&state.db, issue_id, IssueEvent::Commented, None).await?;
+    update_reaction(state, issue_id, &root_event_id, commented).await?;
+    if commented.is_some() {
+        refresh_dashboard(state).await;
+    }
 
     info!(issue_id, "Issue comment sent");
     Ok(())
@@ -157,22 +757,395 @@ async fn handle_issue_reopened(
 
     let root_event_id = issue_event.matrix_event_id.as_str().try_into()?;
 
-    let reported_by = payload.reported_by.as_deref().unwrap_or("unknown");
+    let reported_by = markdown::escape(payload.reported_by.as_deref().unwrap_or("unknown"));
+
+    let label = shared_thread_label(state, issue_id).await?;
+    let markdown = format!(
+        "{label}**🔄 Issue reopened**  \n\
+         **By:** {reported_by}{}",
+        seerr_link(state.seerr_client.issue_url(issue_id).as_deref())
+    );
+
+    matrix::send_thread_markdown(&state.room, &root_event_id, &markdown).await?;
+    let reopened = lifecycle::apply(&state.db, issue_id, IssueEvent::Reopened, None).await?;
+    update_reaction(state, issue_id, &root_event_id, reopened).await?;
+
+    info!(issue_id, "Issue reopened message sent");
+
+    refresh_dashboard(state).await;
+    Ok(())
+}
+
+/// Line naming the issue a thread message is about when the thread is the one
+/// of the issue it duplicates, empty otherwise.
+async fn shared_thread_label(state: &AppState, issue_id: i64) -> anyhow::Result<String> {
+    Ok(db::get_duplicate_of(&state.db, issue_id)
+        .await?
+        .map(|original| duplicate_label(issue_id, original))
+        .unwrap_or_default())
+}
+
+fn duplicate_label(issue_id: i64, original_issue_id: i64) -> String {
+    format!("**Issue {issue_id} (duplicate of {original_issue_id})**  \n")
+}
+
+/// Moves the reaction on the issue card to the state the issue just entered,
+/// if it entered one.
+async fn update_reaction(
+    state: &AppState,
+    issue_id: i64,
+    root_event_id: &OwnedEventId,
+    issue_state: Option<IssueState>,
+) -> anyhow::Result<()> {
+    let Some(issue_state) = issue_state else {
+        return Ok(());
+    };
+    reactions::transition(
+        &state.room,
+        &state.db,
+        &state.settings.get().reaction_emojis,
+        issue_id,
+        root_event_id,
+        issue_state,
+    )
+    .await
+}
+
+/// Markdown of the root message a media request thread hangs off, with the
+/// room's votes once somebody voted.
+pub(crate) fn request_card(
+    subject: &str,
+    requested_by: &str,
+    status: RequestStatus,
+    votes: Option<VoteCounts>,
+    media: Option<&SeerrMediaDetails>,
+    seerr_url: Option<&str>,
+) -> String {
+    let votes = votes
+        .map(|v| format!("  \n**Votes:** 👍 {} · 👎 {}", v.up, v.down))
+        .unwrap_or_default();
+    format!(
+        "#### 📥 New media request\n\
+         **Title:** {}  \n\
+         **Requested by:** {}  \n\
+         **Status:** {}{votes}{}{}",
+        markdown::escape(subject),
+        markdown::escape(requested_by),
+        status.label(),
+        media.map(media_details::fields).unwrap_or_default(),
+        seerr_link(seerr_url)
+    )
+}
+
+/// Posts the request card on the first notification about a request, and
+/// threads status changes under it afterwards.
+async fn handle_request_event(
+    state: &AppState,
+    payload: &SeerrWebhookPayload,
+) -> anyhow::Result<()> {
+    let status = RequestStatus::from_notification_type(&payload.notification_type)
+        .ok_or_else(|| anyhow::anyhow!("Not a request notification"))?;
+    let request_id: i64 = payload
+        .request_id
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Missing request_id"))?
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid request_id"))?;
+
+    if let Some(request_event) = db::get_request_event(&state.db, request_id).await? {
+        let root_event_id = request_event.matrix_event_id.as_str().try_into()?;
+        let markdown = format!("**{}**", status.label());
+        matrix::send_thread_markdown(&state.room, &root_event_id, &markdown).await?;
+        db::set_request_status(&state.db, request_id, status).await?;
+        info!(request_id, %status, "Request status message sent");
+        return Ok(());
+    }
+
+    // Pending requests keep their own card, they are voted on and approved from it
+    if status != RequestStatus::Pending
+        && let Some(batcher) = &state.batcher
+    {
+        let request = BatchedRequest {
+            request_id,
+            payload: payload.clone(),
+        };
+        if batcher.hold(status, request) {
+            info!(request_id, %status, "Request notification batched");
+            return Ok(());
+        }
+    }
 
-    let plain_body = format!("🔄 Issue reopened\nBy: {reported_by}");
-    let html_body = format!(
-        "<b>🔄 Issue reopened</b><br/>\
-         <b>By:</b> {reported_by}"
+    let requested_by = payload.requested_by.as_deref().unwrap_or("unknown");
+    let media_tmdb_id = payload
+        .media_tmdbid
+        .as_deref()
+        .and_then(|id| id.parse().ok());
+    let seerr_url = state
+        .seerr_client
+        .media_url(payload.media_type.as_deref(), media_tmdb_id);
+    let media = media_details::lookup(state, payload.media_type.as_deref(), media_tmdb_id).await;
+    let markdown = request_card(
+        &payload.subject,
+        requested_by,
+        status,
+        None,
+        media.as_ref(),
+        seerr_url.as_deref(),
     );
+    let event_id = matrix::send_markdown(&state.room, &markdown).await?;
+    let room_id = state.room.room_id().to_string();
 
-    matrix::send_thread_reply(&state.room, &root_event_id, &plain_body, &html_body).await?;
+    db::insert_request_event(
+        &state.db,
+        &db::NewRequestEvent {
+            request_id,
+            media_tmdb_id,
+            media_type: payload.media_type.as_deref(),
+            subject: &payload.subject,
+            requested_by,
+            image: payload.image.as_deref(),
+            matrix_event_id: event_id.as_str(),
+            matrix_room_id: &room_id,
+            status,
+        },
+    )
+    .await?;
+    info!(request_id, %event_id, "Request message sent");
 
-    if let Some(reaction_event_id_str) = &issue_event.reaction_event_id {
-        let reaction_event_id = reaction_event_id_str.as_str().try_into()?;
-        matrix::redact_event(&state.room, &reaction_event_id, Some("Issue reopened")).await?;
-        db::clear_reaction_event_id(&state.db, issue_id).await?;
+    if status == RequestStatus::Pending
+        && let Err(e) = rules::apply(state, request_id, &event_id, payload).await
+    {
+        warn!(request_id, "Failed to apply request rules: {e:#}");
     }
 
-    info!(issue_id, "Issue reopened message sent");
     Ok(())
 }
+
+/// Lets new media show up for viewers without waiting for the next scheduled
+/// library scan.
+async fn refresh_library(state: &AppState) {
+    let Some(jellyfin) = &state.jellyfin_client else {
+        return;
+    };
+    match jellyfin.refresh_library().await {
+        Ok(()) => info!("Jellyfin library refresh started"),
+        Err(e) => warn!("Failed to refresh Jellyfin library: {e:#}"),
+    }
+}
+
+pub(crate) async fn refresh_dashboard(state: &AppState) {
+    if !state.settings.get().dashboard_enabled {
+        return;
+    }
+    if let Err(e) = dashboard::refresh(&state.room, &state.db).await {
+        warn!("Failed to refresh open issues dashboard: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::seerr_client::SeerrClient;
+
+    #[test]
+    fn resolution_summary_recaps_lifecycle() {
+        let history = IssueHistory {
+            created_at: Utc.with_ymd_and_hms(2025, 3, 1, 10, 0, 0).unwrap(),
+            reported_by: Some("alice".to_string()),
+            comment_count: 3,
+        };
+        let resolved_at = Utc.with_ymd_and_hms(2025, 3, 4, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            resolution_summary(&history, resolved_at, &TimeFormat::default()),
+            "**📋 Summary:** opened on 2025-03-01 by alice, open for 3 days, 3 comments"
+        );
+    }
+
+    #[test]
+    fn issue_card_shows_category() {
+        let mut details = IssueDetails {
+            subject: "Dune".to_string(),
+            description: "Subs out of sync".to_string(),
+            reported_by: "alice".to_string(),
+            category: Some(IssueCategory::Subtitles),
+            episodes: None,
+        };
+        let card = issue_card(&details, "alice", Priority::Normal, None, None);
+        assert!(card.contains("**Category:** 🔤 subtitles  \n"));
+        assert!(!card.contains("Priority"));
+
+        details.category = None;
+        let card = issue_card(&details, "alice", Priority::High, None, None);
+        assert!(!card.contains("Category"));
+        assert!(card.contains("**Priority:** 🟠 high  \n**Subject:** Dune"));
+    }
+
+    #[test]
+    fn issue_card_shows_episodes() {
+        let details = IssueDetails {
+            subject: "The Expanse".to_string(),
+            description: "Subs out of sync".to_string(),
+            reported_by: "alice".to_string(),
+            category: None,
+            episodes: Some(EpisodeScope::Episode {
+                season: 2,
+                episode: 5,
+            }),
+        };
+        let card = issue_card(&details, "alice", Priority::Normal, None, None);
+        let (plain, _) = markdown::render(&card);
+        assert!(plain.contains("Subject: The Expanse S02E05"));
+
+        assert_eq!(
+            issue_subject("The Expanse", Some(EpisodeScope::Season(3))),
+            "The Expanse season 3"
+        );
+        assert_eq!(
+            issue_subject("The Expanse", Some(EpisodeScope::Series)),
+            "The Expanse"
+        );
+    }
+
+    #[test]
+    fn cards_show_media_details() {
+        let details = IssueDetails {
+            subject: "Dune".to_string(),
+            description: "No sound".to_string(),
+            reported_by: "alice".to_string(),
+            category: None,
+            episodes: None,
+        };
+        let media = SeerrMediaDetails {
+            runtime: Some(155),
+            ..Default::default()
+        };
+        let url = Some("https://seerr.example.com/issues/42");
+
+        let card = issue_card(&details, "alice", Priority::Normal, Some(&media), url);
+        assert!(
+            card.contains("**Reported by:** alice  \n**Runtime:** 2 h 35 min  \n[Open in Seerr]")
+        );
+        let card = request_card(
+            "Dune",
+            "bob",
+            RequestStatus::Approved,
+            None,
+            Some(&media),
+            None,
+        );
+        assert!(card.ends_with("**Runtime:** 2 h 35 min"));
+    }
+
+    #[test]
+    fn duplicate_note_points_at_the_open_issue() {
+        let details = IssueDetails {
+            subject: "Dune".to_string(),
+            description: "No sound".to_string(),
+            reported_by: "bob".to_string(),
+            category: None,
+            episodes: None,
+        };
+
+        let note = duplicate_note(&details, "bob", 57, 42, None);
+        assert!(note.starts_with("#### 🔁 New issue 57, possible duplicate of issue 42\n"));
+        assert!(note.contains("**Description:** No sound  \n**Reported by:** bob\n\n"));
+        assert!(note.ends_with("`!issues merge 57` to resolve it as a duplicate."));
+    }
+
+    #[test]
+    fn comments_of_a_duplicate_name_it_in_the_shared_thread() {
+        assert_eq!(
+            comment_markdown(&duplicate_label(57, 42), "bob", "Still no sound"),
+            "**Issue 57 (duplicate of 42)**  \n**💬 bob :** Still no sound"
+        );
+        assert_eq!(comment_markdown("", "bob", "Hi"), "**💬 bob :** Hi");
+    }
+
+    #[test]
+    fn cards_link_to_seerr() {
+        let seerr = SeerrClient::new("http://seerr:5055/api/v1", "key")
+            .with_public_url(Some("https://seerr.example.com/"));
+        let details = IssueDetails {
+            subject: "Dune".to_string(),
+            description: String::new(),
+            reported_by: "alice".to_string(),
+            category: None,
+            episodes: None,
+        };
+
+        let url = seerr.issue_url(42);
+        assert!(
+            issue_card(&details, "alice", Priority::Normal, None, url.as_deref())
+                .ends_with("  \n[Open in Seerr](https://seerr.example.com/issues/42)")
+        );
+
+        let url = seerr.media_url(Some("movie"), Some(438631));
+        assert!(
+            request_card(
+                "Dune",
+                "bob",
+                RequestStatus::Pending,
+                None,
+                None,
+                url.as_deref()
+            )
+            .ends_with("[Open in Seerr](https://seerr.example.com/movie/438631)")
+        );
+        let votes = VoteCounts { up: 3, down: 1 };
+        assert!(
+            request_card(
+                "Dune",
+                "bob",
+                RequestStatus::Pending,
+                Some(votes),
+                None,
+                url.as_deref()
+            )
+            .contains("**Status:** ⏳ Waiting for approval  \n**Votes:** 👍 3 · 👎 1  \n")
+        );
+        assert_eq!(
+            seerr.media_url(None, None).as_deref(),
+            Some("https://seerr.example.com/requests")
+        );
+        assert_eq!(
+            SeerrClient::new("http://seerr:5055/api/v1", "key").issue_url(42),
+            None
+        );
+    }
+
+    #[test]
+    fn issue_media_parses_ids() {
+        let payload = SeerrWebhookPayload {
+            media_type: Some("tv".to_string()),
+            media_tmdbid: Some("1399".to_string()),
+            media_tvdbid: Some("".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            issue_media(&payload),
+            IssueMedia {
+                media_type: Some("tv".to_string()),
+                tmdb_id: Some(1399),
+                tvdb_id: None,
+            }
+        );
+    }
+
+    #[test]
+    fn resolution_summary_same_day() {
+        let created_at = Utc.with_ymd_and_hms(2025, 3, 1, 10, 0, 0).unwrap();
+        let history = IssueHistory {
+            created_at,
+            reported_by: None,
+            comment_count: 1,
+        };
+
+        assert_eq!(
+            resolution_summary(&history, created_at, &TimeFormat::default()),
+            "**📋 Summary:** opened on 2025-03-01 by unknown, open for less than a day, 1 comment"
+        );
+    }
+}
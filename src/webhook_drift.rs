@@ -0,0 +1,131 @@
+use anyhow::Result;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db;
+use crate::markdown;
+use crate::matrix;
+use crate::seerr::{
+    NOTIFICATION_ISSUE_COMMENT, NOTIFICATION_ISSUE_CREATED, NOTIFICATION_ISSUE_REOPENED,
+    NOTIFICATION_ISSUE_RESOLVED, SeerrWebhookSettings,
+};
+
+/// Problems last reported in the room, so a drift is only reported once.
+const REPORTED_SETTING: &str = "webhook_drift";
+
+/// Notification types the bot can't work without.
+const REQUIRED_TYPES: [(i64, &str); 4] = [
+    (NOTIFICATION_ISSUE_CREATED, "Issue Reported"),
+    (NOTIFICATION_ISSUE_COMMENT, "Issue Comment"),
+    (NOTIFICATION_ISSUE_RESOLVED, "Issue Resolved"),
+    (NOTIFICATION_ISSUE_REOPENED, "Issue Reopened"),
+];
+
+/// What is wrong with the Seerr webhook agent, `expected_url` being where
+/// the bot receives webhooks, when known.
+pub fn problems(settings: &SeerrWebhookSettings, expected_url: Option<&str>) -> Vec<String> {
+    let mut problems = Vec::new();
+    if !settings.enabled {
+        problems.push("the webhook agent is disabled".to_string());
+    }
+    if let Some(expected_url) = expected_url
+        && settings.options.webhook_url.trim_end_matches('/') != expected_url.trim_end_matches('/')
+    {
+        problems.push(format!(
+            "the webhook URL is {:?} instead of {expected_url:?}",
+            settings.options.webhook_url
+        ));
+    }
+    let missing: Vec<&str> = REQUIRED_TYPES
+        .iter()
+        .filter(|(bit, _)| settings.types & bit == 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if !missing.is_empty() {
+        problems.push(format!(
+            "these notification types are not sent: {}",
+            missing.join(", ")
+        ));
+    }
+    problems
+}
+
+/// Checks the webhook agent settings in Seerr and warns the admins in the
+/// room when they changed for the worse, e.g. after a Seerr upgrade, and once
+/// they are fixed.
+pub async fn check(state: &AppState, expected_url: Option<&str>) -> Result<()> {
+    let settings = state.seerr_client.webhook_settings().await?;
+    let problems = problems(&settings, expected_url);
+    let reported = db::get_setting(&state.db, REPORTED_SETTING)
+        .await?
+        .unwrap_or_default();
+    let current = problems.join("\n");
+    if current == reported {
+        return Ok(());
+    }
+
+    let markdown = if problems.is_empty() {
+        info!("Seerr webhook settings fixed");
+        "**✅ The Seerr webhook settings are fine again**".to_string()
+    } else {
+        warn!(?problems, "Seerr webhook settings drifted");
+        let mentions: Vec<String> = state
+            .settings
+            .get()
+            .admin_users
+            .iter()
+            .map(|user_id| format!("[{user_id}](https://matrix.to/#/{user_id})"))
+            .collect();
+        let mut markdown = "**⚠️ Seerr may not be sending notifications to the bot**\n".to_string();
+        for problem in &problems {
+            markdown.push_str(&format!("- {}\n", markdown::escape(problem)));
+        }
+        markdown.push_str("\nCheck Settings → Notifications → Webhook in Seerr.");
+        if !mentions.is_empty() {
+            markdown.push_str(&format!("  \n{}", mentions.join(", ")));
+        }
+        markdown
+    };
+    matrix::send_markdown(&state.room, &markdown).await?;
+    db::set_setting(&state.db, REPORTED_SETTING, &current).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(enabled: bool, types: i64, url: &str) -> SeerrWebhookSettings {
+        serde_json::from_value(serde_json::json!({
+            "enabled": enabled,
+            "types": types,
+            "options": { "webhookUrl": url },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn no_problems_when_configured() {
+        let all = NOTIFICATION_ISSUE_CREATED
+            | NOTIFICATION_ISSUE_COMMENT
+            | NOTIFICATION_ISSUE_RESOLVED
+            | NOTIFICATION_ISSUE_REOPENED
+            | 2;
+        let settings = settings(true, all, "http://michel:8080/webhook/seerr/");
+        assert!(problems(&settings, Some("http://michel:8080/webhook/seerr")).is_empty());
+        assert!(problems(&settings, None).is_empty());
+    }
+
+    #[test]
+    fn reports_every_drift() {
+        let settings = settings(false, NOTIFICATION_ISSUE_CREATED, "http://old:8080/webhook");
+        let problems = problems(&settings, Some("http://michel:8080/webhook/seerr"));
+        assert_eq!(
+            problems,
+            vec![
+                "the webhook agent is disabled".to_string(),
+                "the webhook URL is \"http://old:8080/webhook\" instead of \"http://michel:8080/webhook/seerr\"".to_string(),
+                "these notification types are not sent: Issue Comment, Issue Resolved, Issue Reopened".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use matrix_sdk::ruma::OwnedMxcUri;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::db::{self, AvailableMedia};
+use crate::markdown;
+use crate::matrix;
+use crate::room_config;
+
+/// One media of the list, with its poster uploaded to the homeserver since
+/// clients only show `mxc://` images.
+struct NewMedia<'a> {
+    media: &'a AvailableMedia,
+    poster: Option<OwnedMxcUri>,
+}
+
+fn title(media: &AvailableMedia) -> &str {
+    media.subject.as_deref().unwrap_or("Unknown title")
+}
+
+/// Several requests, e.g. one per season, can make the same media available.
+fn dedup(media: &[AvailableMedia]) -> Vec<&AvailableMedia> {
+    let mut seen = HashSet::new();
+    media
+        .iter()
+        .filter(|m| match m.media_tmdb_id {
+            Some(tmdb_id) => seen.insert((m.media_type.clone(), tmdb_id.to_string())),
+            None => seen.insert((m.media_type.clone(), title(m).to_string())),
+        })
+        .collect()
+}
+
+fn render(media: &[NewMedia]) -> String {
+    let mut markdown = "#### 🍿 New this week\n".to_string();
+    let sections = [
+        ("🎬 Movies", Some("movie")),
+        ("📺 Series", Some("tv")),
+        ("📦 Other", None),
+    ];
+    for (heading, media_type) in sections {
+        let entries: Vec<&NewMedia> = media
+            .iter()
+            .filter(|m| match media_type {
+                Some(media_type) => m.media.media_type.as_deref() == Some(media_type),
+                None => !matches!(m.media.media_type.as_deref(), Some("movie" | "tv")),
+            })
+            .collect();
+        if entries.is_empty() {
+            continue;
+        }
+        markdown.push_str(&format!("\n**{heading}**\n"));
+        for entry in entries {
+            let title = markdown::escape(title(entry.media));
+            match &entry.poster {
+                Some(poster) => markdown.push_str(&format!("- ![{title}]({poster}) {title}\n")),
+                None => markdown.push_str(&format!("- {title}\n")),
+            }
+        }
+    }
+    markdown
+}
+
+/// Posts the media that became available in the past seven days, grouped by
+/// type, in rooms that opted in with `whats_new` in their config.
+pub async fn post_whats_new(state: &AppState) -> Result<()> {
+    let room_config = room_config::load(&state.room, &state.settings.get().room_defaults).await?;
+    if !room_config.whats_new {
+        info!("What's new not enabled for the room, skipping");
+        return Ok(());
+    }
+
+    let available = db::list_available_media(&state.db, Utc::now() - Duration::days(7)).await?;
+    if available.is_empty() {
+        info!("Nothing became available this week, skipping");
+        return Ok(());
+    }
+    let mut media = Vec::new();
+    for entry in dedup(&available) {
+        // A missing poster only costs the picture
+        let poster = match &entry.image {
+            Some(url) => matrix::upload_image(&state.room.client(), url)
+                .await
+                .inspect_err(|e| warn!(url, "Failed to upload poster: {e:#}"))
+                .ok()
+                .map(|poster| poster.uri),
+            None => None,
+        };
+        media.push(NewMedia {
+            media: entry,
+            poster,
+        });
+    }
+
+    matrix::send_markdown(&state.room, &render(&media)).await?;
+    info!(media = media.len(), "What's new posted");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available(media_type: &str, tmdb_id: i64, subject: &str) -> AvailableMedia {
+        AvailableMedia {
+            media_type: Some(media_type.to_string()),
+            media_tmdb_id: Some(tmdb_id),
+            subject: Some(subject.to_string()),
+            image: None,
+            available_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn render_groups_media_by_type() {
+        let movie = available("movie", 1, "Dune");
+        let show = available("tv", 2, "Some Show");
+        let markdown = render(&[
+            NewMedia {
+                media: &show,
+                poster: None,
+            },
+            NewMedia {
+                media: &movie,
+                poster: Some("mxc://example.com/poster".into()),
+            },
+        ]);
+
+        let movies = markdown.find("**🎬 Movies**").unwrap();
+        let series = markdown.find("**📺 Series**").unwrap();
+        assert!(movies < series);
+        assert!(markdown.contains("- ![Dune](mxc://example.com/poster) Dune\n"));
+        assert!(markdown.contains("- Some Show\n"));
+        assert!(!markdown.contains("Other"));
+    }
+
+    #[test]
+    fn dedup_keeps_one_entry_per_media() {
+        let media = [
+            available("tv", 2, "Some Show"),
+            available("tv", 2, "Some Show"),
+            available("movie", 2, "Dune"),
+        ];
+        assert_eq!(dedup(&media).len(), 2);
+    }
+}
@@ -5,10 +5,19 @@ mod world;
 
 use crate::world::TestWorld;
 use cucumber::{World, writer};
+use futures_util::FutureExt;
 
 #[tokio::main]
 async fn main() {
     TestWorld::cucumber()
+        .after(|_feature, _rule, _scenario, _event, world| {
+            async move {
+                if let Some(world) = world {
+                    world.stop_bot().await;
+                }
+            }
+            .boxed_local()
+        })
         .with_writer(writer::Libtest::or_basic())
         .run("tests/features")
         .await;
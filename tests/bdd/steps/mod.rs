@@ -1 +1,2 @@
 pub mod seerr_issues;
+pub mod seerr_requests;
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use cucumber::gherkin::Step;
 use cucumber::{given, then, when};
-use wiremock::matchers::{method, path_regex};
+use wiremock::matchers::{method, path, path_regex};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use crate::world::{self, ADMIN_USERNAME, BOT_PASSWORD, OBSERVER_USERNAME, TestWorld};
@@ -77,116 +77,31 @@ async fn the_bot_is_started(world: &mut TestWorld, room_alias: String) {
     let admin_user_id = format!("@{ADMIN_USERNAME}:localhost");
     let matrix_room_alias = room_alias;
 
-    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
-    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
-
-    let handle = tokio::spawn(async move {
-        let config = michel_bot::config::Config {
-            matrix_homeserver_url: homeserver_url,
-            matrix_user_id: bot_username.to_string(),
-            matrix_password: BOT_PASSWORD.to_string(),
-            matrix_room_alias,
-            database_url,
-            webhook_listen_addr: listen_addr,
-            seerr_api_url,
-            seerr_api_key: "test-api-key".to_string(),
-            matrix_admin_users: vec![admin_user_id],
-        };
-
-        let pool = match sqlx::PgPool::connect(&config.database_url).await {
-            Ok(p) => p,
-            Err(e) => {
-                let _ = ready_tx.send(Err(format!("Failed to connect to DB: {e}")));
-                return;
-            }
-        };
-        if let Err(e) = michel_bot::db::run_migrations(&pool).await {
-            let _ = ready_tx.send(Err(format!("Failed to run migrations: {e}")));
-            return;
-        }
-
-        let client = match michel_bot::matrix::create_and_login(
-            &config.matrix_homeserver_url,
-            &config.matrix_user_id,
-            &config.matrix_password,
-        )
+    let config = michel_bot::config::Config {
+        matrix_homeserver_url: homeserver_url,
+        matrix_user_id: bot_username.to_string(),
+        matrix_auth: michel_bot::config::MatrixAuth::Password(BOT_PASSWORD.to_string()),
+        matrix_room_alias,
+        database_url,
+        webhook_listen_addr: listen_addr,
+        seerr_api_url,
+        seerr_api_key: "test-api-key".to_string(),
+        matrix_admin_users: vec![admin_user_id.try_into().unwrap()],
+        shutdown_timeout: std::time::Duration::from_secs(5),
+        pending_actions_poll: std::time::Duration::from_secs(1),
+        ..Default::default()
+    };
+
+    let bot = michel_bot::bot::Bot::builder()
+        .config(config)
+        .build()
         .await
-        {
-            Ok(c) => c,
-            Err(e) => {
-                let _ = ready_tx.send(Err(format!("Failed to login bot: {e}")));
-                return;
-            }
-        };
-
-        let (room, _room_id) =
-            match michel_bot::matrix::join_room(&client, &config.matrix_room_alias).await {
-                Ok(r) => r,
-                Err(e) => {
-                    let _ = ready_tx.send(Err(format!("Failed to join room: {e}")));
-                    return;
-                }
-            };
-
-        let seerr_client = michel_bot::seerr_client::SeerrClient::new(
-            &config.seerr_api_url,
-            &config.seerr_api_key,
-        );
-
-        let admin_users: Vec<matrix_sdk::ruma::OwnedUserId> = config
-            .matrix_admin_users
-            .iter()
-            .filter_map(|u| matrix_sdk::ruma::OwnedUserId::try_from(u.as_str()).ok())
-            .collect();
-
-        let cmd_ctx = std::sync::Arc::new(michel_bot::commands::CommandContext {
-            db: pool.clone(),
-            seerr_client,
-            admin_users,
-        });
-
-        client.add_event_handler_context(cmd_ctx);
-        client.add_event_handler(michel_bot::commands::on_room_message);
-
-        let state = std::sync::Arc::new(michel_bot::AppState { room, db: pool });
-
-        let app = axum::Router::new()
-            .route(
-                "/webhook/seerr",
-                axum::routing::post(michel_bot::webhook::handle_seerr_webhook),
-            )
-            .with_state(state);
-
-        let listener = match tokio::net::TcpListener::bind(&config.webhook_listen_addr).await {
-            Ok(l) => l,
-            Err(e) => {
-                let _ = ready_tx.send(Err(format!("Failed to bind: {e}")));
-                return;
-            }
-        };
-
-        let _ = ready_tx.send(Ok(()));
-
-        let sync_client = client.clone();
-        tokio::select! {
-            result = axum::serve(listener, app) => {
-                result.expect("Server error");
-            }
-            _ = sync_client.sync(matrix_sdk::config::SyncSettings::default()) => {}
-            _ = shutdown_rx.changed() => {}
-        }
+        .unwrap_or_else(|e| panic!("Bot startup failed: {e:#}"));
+    world.bot = Some(bot.handle());
+    tokio::spawn(async move {
+        bot.run().await.expect("Bot stopped with an error");
     });
 
-    world.bot_handle = Some(handle);
-    world.bot_shutdown = Some(shutdown_tx);
-
-    // Wait for the bot to signal readiness
-    match ready_rx.await {
-        Ok(Ok(())) => {}
-        Ok(Err(e)) => panic!("Bot startup failed: {e}"),
-        Err(_) => panic!("Bot task exited before signaling readiness"),
-    }
-
     // Small extra delay for sync to start
     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
 }
@@ -266,6 +181,11 @@ async fn seerr_sends_webhook(world: &mut TestWorld, step: &Step, notification_ty
         "reported_by": data.get("reported_by").cloned(),
         "comment": data.get("comment").cloned(),
         "commented_by": data.get("commented_by").cloned(),
+        "comment_id": data.get("comment_id").cloned(),
+        "request_id": data.get("request_id").cloned(),
+        "requested_by": data.get("requested_by").cloned(),
+        "media_type": data.get("media_type").cloned(),
+        "media_tmdbid": data.get("media_tmdbid").cloned(),
     });
 
     let resp = http
@@ -523,6 +443,22 @@ async fn no_longer_has_reaction(world: &mut TestWorld, emoji: String) {
         .await;
 }
 
+#[then("the original message was not edited")]
+async fn original_message_not_edited(world: &mut TestWorld) {
+    let http = http_client();
+    let edits = world::get_relations(
+        &http,
+        world.synapse_port,
+        &world.observer_access_token,
+        &world.room_id,
+        &world.last_root_event_id,
+        "m.replace",
+    )
+    .await;
+
+    assert!(edits.is_empty(), "Original message was edited: {edits:?}");
+}
+
 // -- Admin command steps --
 
 #[when(regex = r#"^the admin sends '([^']*)' as a thread reply$"#)]
@@ -571,7 +507,7 @@ async fn admin_sends_thread_reply(world: &mut TestWorld, command: String) {
     );
 }
 
-#[then(regex = r#"^Seerr received a comment "([^"]*)" for issue (\d+)$"#)]
+#[then(regex = r#"^Seerr received a comment "([^"]*)" from the admin for issue (\d+)$"#)]
 async fn seerr_received_comment(world: &mut TestWorld, comment: String, issue_id: u64) {
     let mock_server = world
         .seerr_mock
@@ -579,7 +515,8 @@ async fn seerr_received_comment(world: &mut TestWorld, comment: String, issue_id
         .expect("Wiremock not started")
         .clone();
     let expected_path = format!("/api/v1/issue/{}/comment", issue_id);
-    let comment_clone = comment.clone();
+    // Comments forwarded from Matrix name their sender
+    let comment_clone = format!("{comment}\n\n— @{ADMIN_USERNAME}:localhost via Matrix");
 
     awaitility::at_most(std::time::Duration::from_secs(10))
         .poll_interval(std::time::Duration::from_millis(500))
@@ -607,6 +544,84 @@ async fn seerr_received_comment(world: &mut TestWorld, comment: String, issue_id
         .await;
 }
 
+#[when(regex = r"^Seerr sends back the comment the admin forwarded for issue (\d+)$")]
+async fn seerr_echoes_forwarded_comment(world: &mut TestWorld, issue_id: u64) {
+    let mock_server = world.seerr_mock.as_ref().expect("Wiremock not started");
+    let expected_path = format!("/api/v1/issue/{issue_id}/comment");
+    let received = mock_server.received_requests().await.unwrap_or_default();
+    let forwarded = received
+        .iter()
+        .rev()
+        .find(|req| req.url.path() == expected_path)
+        .expect("No comment forwarded to Seerr");
+    let body: serde_json::Value =
+        serde_json::from_slice(&forwarded.body).expect("Invalid comment body");
+
+    // Seerr notifies of every comment, those the bot sent included
+    let payload = serde_json::json!({
+        "notification_type": "ISSUE_COMMENT",
+        "subject": "",
+        "issue_id": issue_id.to_string(),
+        "comment": body["message"],
+        "commented_by": "admin",
+    });
+    let resp = http_client()
+        .post(format!(
+            "http://127.0.0.1:{}/webhook/seerr",
+            world.webhook_port
+        ))
+        .json(&payload)
+        .send()
+        .await
+        .expect("Failed to send webhook");
+
+    assert!(
+        resp.status().is_success(),
+        "Webhook returned error: {}",
+        resp.status()
+    );
+}
+
+#[then(regex = r#"^no threaded reply on the original message contains "([^"]*)"$"#)]
+async fn no_threaded_reply_contains(world: &mut TestWorld, unexpected_text: String) {
+    let http = http_client();
+    let thread_messages = world::get_relations(
+        &http,
+        world.synapse_port,
+        &world.observer_access_token,
+        &world.room_id,
+        &world.last_root_event_id,
+        "m.thread",
+    )
+    .await;
+
+    let found = thread_messages.iter().find(|msg| {
+        let body = msg["content"]["body"].as_str().unwrap_or("");
+        let formatted = msg["content"]["formatted_body"].as_str().unwrap_or("");
+        body.contains(&unexpected_text) || formatted.contains(&unexpected_text)
+    });
+
+    assert!(
+        found.is_none(),
+        "Threaded reply contains '{unexpected_text}': {found:?}"
+    );
+}
+
+#[given(regex = r"^Seerr is unreachable for the next resolution of issue (\d+)$")]
+async fn seerr_unreachable_for_resolution(world: &mut TestWorld, issue_id: u64) {
+    let mock_server = world.seerr_mock.as_ref().expect("Wiremock not started");
+
+    // Takes precedence over the mock mounted when the bot started, which
+    // answers again afterwards
+    Mock::given(method("POST"))
+        .and(path(format!("/api/v1/issue/{issue_id}/resolved")))
+        .respond_with(ResponseTemplate::new(503))
+        .with_priority(1)
+        .up_to_n_times(1)
+        .mount(mock_server)
+        .await;
+}
+
 #[then(regex = r#"^Seerr received a resolve request for issue (\d+)$"#)]
 async fn seerr_received_resolve(world: &mut TestWorld, issue_id: u64) {
     let mock_server = world
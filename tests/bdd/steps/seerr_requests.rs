@@ -0,0 +1,52 @@
+use cucumber::given;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::world::TestWorld;
+
+#[given(regex = r"^Seerr refuses to approve request (\d+) over the requester's movie quota$")]
+async fn seerr_refuses_over_quota(world: &mut TestWorld, request_id: u64) {
+    let mock_server = world.seerr_mock.as_ref().expect("Wiremock not started");
+    let user_id = 5;
+    let created_at = chrono::Utc::now() - chrono::Duration::days(2);
+
+    Mock::given(method("POST"))
+        .and(path(format!("/api/v1/request/{request_id}/approve")))
+        .respond_with(
+            ResponseTemplate::new(403)
+                .set_body_json(serde_json::json!({ "message": "Movie Quota exceeded." })),
+        )
+        .mount(mock_server)
+        .await;
+
+    let request = serde_json::json!({
+        "id": request_id,
+        "status": 1,
+        "media": { "mediaType": "movie", "tmdbId": 329865 },
+        "requestedBy": { "id": user_id, "displayName": "erin" },
+        "createdAt": created_at,
+    });
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v1/request/{request_id}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&request))
+        .mount(mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v1/user/{user_id}/quota")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "movie": { "days": 7, "limit": 2, "used": 2, "remaining": 0, "restricted": true },
+            "tv": { "used": 0, "restricted": false },
+        })))
+        .mount(mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path(format!("/api/v1/user/{user_id}/requests")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "pageInfo": { "pages": 1 },
+            "results": [request],
+        })))
+        .mount(mock_server)
+        .await;
+}
@@ -134,8 +134,7 @@ pub async fn stop_shared_infra() {
 pub struct TestWorld {
     pub synapse_port: u16,
     pub postgres_port: u16,
-    pub bot_handle: Option<tokio::task::JoinHandle<()>>,
-    pub bot_shutdown: Option<tokio::sync::watch::Sender<bool>>,
+    pub bot: Option<michel_bot::bot::BotHandle>,
     pub bot_username: String,
     pub webhook_port: u16,
     pub observer_access_token: String,
@@ -148,14 +147,12 @@ pub struct TestWorld {
     pub issue_admin_access_token: String,
 }
 
-impl Drop for TestWorld {
-    fn drop(&mut self) {
-        // Signal bot to shut down
-        if let Some(tx) = self.bot_shutdown.take() {
-            let _ = tx.send(true);
-        }
-        if let Some(handle) = self.bot_handle.take() {
-            handle.abort();
+impl TestWorld {
+    /// Shuts the scenario's bot down and waits until it has drained, so the
+    /// next scenario starts from a quiet database.
+    pub async fn stop_bot(&mut self) {
+        if let Some(bot) = self.bot.take() {
+            bot.shutdown().await;
         }
     }
 }
@@ -14,6 +14,7 @@ Feature: Seerr issue management via Matrix
       | reported_by | alice                     |
     Then a message appears in "#test-issue-created" containing "Video playback problem"
     And the message contains "alice"
+    And the original message has a "🔴" reaction
 
   Scenario: Resolving an issue posts in the thread and adds a reaction
     Given a room "#test-issue-resolved" exists
@@ -48,6 +49,7 @@ Feature: Seerr issue management via Matrix
       | commented_by | admin                     |
     Then a threaded reply appears on the original message containing "Looking into the problem"
     And the threaded reply contains "admin"
+    And the original message has a "🟡" reaction
 
   Scenario: Admin resolves issue via Matrix command
     Given a room "#test-admin-resolve" exists
@@ -59,7 +61,7 @@ Feature: Seerr issue management via Matrix
       | reported_by | alice                  |
     And a message appears in "#test-admin-resolve" containing "Broken subtitles"
     When the admin sends '!issues resolve "Subtitles fixed"' as a thread reply
-    Then Seerr received a comment "Subtitles fixed" for issue 50
+    Then Seerr received a comment "Subtitles fixed" from the admin for issue 50
     And Seerr received a resolve request for issue 50
     And a threaded reply appears on the original message containing "resolved"
 
@@ -84,3 +86,105 @@ Feature: Seerr issue management via Matrix
       | reported_by | dave                   |
     Then a threaded reply appears on the original message containing "reopened"
     And the original message no longer has a "✅" reaction
+    And the original message has a "🔴" reaction
+
+  Scenario: Media request notifications are threaded under the request message
+    Given a room "#test-media-request" exists
+    And the bot is started and connected to room "#test-media-request:localhost"
+    And Seerr sends an "MEDIA_PENDING" webhook with:
+      | request_id   | 7              |
+      | subject      | Dune Part Two  |
+      | requested_by | erin           |
+      | media_type   | movie          |
+      | media_tmdbid | 693134         |
+    And a message appears in "#test-media-request" containing "Dune Part Two"
+    When Seerr sends an "MEDIA_APPROVED" webhook with:
+      | request_id   | 7              |
+      | subject      | Dune Part Two  |
+    Then a threaded reply appears on the original message containing "Request approved"
+
+  Scenario: Approving a request over the requester's quota tells what is left of it
+    Given a room "#test-request-quota" exists
+    And the bot is started and connected to room "#test-request-quota:localhost"
+    And Seerr refuses to approve request 8 over the requester's movie quota
+    And Seerr sends an "MEDIA_PENDING" webhook with:
+      | request_id   | 8              |
+      | subject      | Arrival        |
+      | requested_by | erin           |
+      | media_type   | movie          |
+      | media_tmdbid | 329865         |
+    And a message appears in "#test-request-quota" containing "Arrival"
+    When the admin sends '!requests approve' as a thread reply
+    Then a threaded reply appears on the original message containing "used up their movie quota"
+    And the threaded reply contains "Remaining: 0 of 2 requests per 7 days"
+
+  Scenario: A merged duplicate re-sent by Seerr leaves the card of the issue kept as is
+    Given a room "#test-issue-duplicate" exists
+    And the bot is started and connected to room "#test-issue-duplicate:localhost"
+    And Seerr sends an "ISSUE_CREATED" webhook with:
+      | issue_id     | 60                     |
+      | subject      | The Matrix             |
+      | message      | Audio out of sync      |
+      | reported_by  | frank                  |
+      | media_type   | movie                  |
+      | media_tmdbid | 603                    |
+    And a message appears in "#test-issue-duplicate" containing "Audio out of sync"
+    And Seerr sends an "ISSUE_CREATED" webhook with:
+      | issue_id     | 61                     |
+      | subject      | The Matrix             |
+      | message      | Sound lags behind      |
+      | reported_by  | grace                  |
+      | media_type   | movie                  |
+      | media_tmdbid | 603                    |
+    And a threaded reply appears on the original message containing "possible duplicate of issue 60"
+    When the admin sends '!issues merge 61' as a thread reply
+    Then a threaded reply appears on the original message containing "merged into issue 60"
+    When Seerr sends an "ISSUE_CREATED" webhook with:
+      | issue_id     | 61                     |
+      | subject      | The Matrix             |
+      | message      | Sound still lags       |
+      | reported_by  | grace                  |
+      | media_type   | movie                  |
+      | media_tmdbid | 603                    |
+    And Seerr sends an "ISSUE_COMMENT" webhook with:
+      | issue_id     | 61                     |
+      | subject      | The Matrix             |
+      | comment      | Any news?              |
+      | commented_by | grace                  |
+    Then a threaded reply appears on the original message containing "Any news?"
+    And the threaded reply contains "Issue 61 (duplicate of 60)"
+    And the original message was not edited
+
+  Scenario: Resolving an issue while Seerr is unreachable queues the resolution until it is back
+    Given a room "#test-issue-queued-resolve" exists
+    And the bot is started and connected to room "#test-issue-queued-resolve:localhost"
+    And Seerr is unreachable for the next resolution of issue 52
+    And Seerr sends an "ISSUE_CREATED" webhook with:
+      | issue_id    | 52                     |
+      | subject     | Wrong language         |
+      | message     | Audio is in German     |
+      | reported_by | henry                  |
+    And a message appears in "#test-issue-queued-resolve" containing "Wrong language"
+    When the admin sends '!issues resolve' as a thread reply
+    Then a threaded reply appears on the original message containing "issue 52 will be resolved once it is back"
+    And a threaded reply appears on the original message containing "now that Seerr is back"
+
+  Scenario: A comment sent from the thread isn't posted back when Seerr notifies of it
+    Given a room "#test-issue-comment-echo" exists
+    And the bot is started and connected to room "#test-issue-comment-echo:localhost"
+    And Seerr sends an "ISSUE_CREATED" webhook with:
+      | issue_id    | 53                     |
+      | subject     | Flickering image       |
+      | message     | The picture flickers   |
+      | reported_by | ivan                   |
+    And a message appears in "#test-issue-comment-echo" containing "Flickering image"
+    When the admin sends '!issues resolve "Replaced the file"' as a thread reply
+    Then Seerr received a comment "Replaced the file" from the admin for issue 53
+    When Seerr sends back the comment the admin forwarded for issue 53
+    And Seerr sends an "ISSUE_COMMENT" webhook with:
+      | issue_id     | 53                     |
+      | subject      | Flickering image       |
+      | comment      | Looks good now         |
+      | commented_by | ivan                   |
+    Then a threaded reply appears on the original message containing "Looks good now"
+    And no threaded reply on the original message contains "via Matrix"